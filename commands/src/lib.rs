@@ -0,0 +1,121 @@
+// every command the backend handles, in one place so the frontend stubs and the backend's handler can't drift.
+// the callback macro is invoked with a list of `name(argument: Type, ...) -> Output;`, where Output is what the
+// command returns on success and arguments can carry the ui macro's #[optional] and #[name = "..."] attributes.
+// types are resolved where the callback expands, so they're written as the ui imports them. tauri's build script
+// fails when an entry stops matching its #[tauri::command]
+#[macro_export]
+macro_rules! with_commands {
+    ($callback:ident) => {
+        $callback! {
+            add_conversation(
+                exchanges: Vec<(usize, Exchange)>,
+                carry_over: Option<CarryOver>,
+                settings: ConversationSettings,
+                parameters: ConversationParameters
+            ) -> uuid::Uuid;
+            add_conversation_tag(conversation_uuid: uuid::Uuid, tag: String) -> ();
+            add_folder(name: String, parent: Option<i32>) -> Folder;
+            attach_files() -> Vec<Attachment>;
+            build_token_stream(
+                stream_id: uuid::Uuid,
+                conversation_uuid: Option<uuid::Uuid>,
+                prompt: &str,
+                config: Config,
+                exchanges: Vec<Exchange>,
+                #[optional] body: Option<String>
+            ) -> impl Stream<Item = Result<ResponseChunk>> + Unpin;
+            clear_error_counts() -> ();
+            copy_to_clipboard(text: String) -> ();
+            count_tokens(texts: Vec<String>, model: String) -> Vec<usize>;
+            delete_conversation(conversation_uuid: uuid::Uuid) -> ();
+            duplicate_conversation(conversation_uuid: uuid::Uuid) -> uuid::Uuid;
+            encrypt_database(passphrase: String) -> ();
+            estimate_cost(model: String, usage: Usage) -> Option<f64>;
+            export_all() -> Option<String>;
+            export_conversation(conversation_uuid: uuid::Uuid, format: ConversationFormat) -> Option<String>;
+            export_filtered_conversations(
+                directory: String,
+                filter: ExportFilter,
+                layout: ExportLayout
+            ) -> String;
+            export_settings(include_keys: bool) -> Option<String>;
+            find_duplicate_conversations() -> Vec<Vec<Conversation>>;
+            get_conversation_json(conversation_uuid: uuid::Uuid) -> ConversationRecord;
+            import_all() -> Option<ImportSummary>;
+            import_chatgpt() -> Option<ImportSummary>;
+            import_env_keys(path: String, api_keys: Vec<APIKey>) -> Vec<APIKey>;
+            import_settings(config: Config) -> Option<Config>;
+            list_conversations_since(since: chrono::DateTime<chrono::Utc>) -> Vec<ConversationSummary>;
+            list_models(api_key: APIKey) -> Vec<String>;
+            load_archived_conversations() -> Vec<Conversation>;
+            load_carry_over(conversation_uuid: uuid::Uuid) -> Option<CarryOver>;
+            load_config() -> Config;
+            load_conversation(conversation_uuid: uuid::Uuid) -> Conversation;
+            load_conversation_settings(conversation_uuid: uuid::Uuid) -> ConversationSettings;
+            load_conversations() -> Vec<Conversation>;
+            load_draft(conversation_uuid: Option<uuid::Uuid>) -> String;
+            load_edit_log(conversation_uuid: uuid::Uuid) -> Vec<EditLogEntry>;
+            load_encryption_status() -> EncryptionStatus;
+            load_error_counts() -> Vec<ErrorCount>;
+            load_exchanges(conversation_uuid: uuid::Uuid) -> Vec<(usize, Exchange)>;
+            load_folders() -> Vec<Folder>;
+            load_image(name: String) -> String;
+            load_last_request() -> Option<String>;
+            load_tags() -> Vec<String>;
+            load_usage(period: UsagePeriod) -> Vec<UsageTotal>;
+            merge_conversations(keep_uuid: uuid::Uuid, merge_uuids: Vec<uuid::Uuid>) -> ();
+            preview_request(
+                conversation_uuid: Option<uuid::Uuid>,
+                prompt: &str,
+                config: Config,
+                exchanges: Vec<Exchange>
+            ) -> String;
+            remove_conversation_tag(conversation_uuid: uuid::Uuid, tag: String) -> ();
+            remove_folder(id: i32) -> ();
+            request_attention() -> ();
+            restore_conversation(conversation_uuid: uuid::Uuid) -> ();
+            restore_edit(conversation_uuid: uuid::Uuid, edit_id: i32) -> Option<uuid::Uuid>;
+            retitle_conversations() -> usize;
+            save_config(config: Config) -> ();
+            save_conversation_parameters(
+                conversation_uuid: uuid::Uuid,
+                parameters: ConversationParameters
+            ) -> ();
+            save_conversation_settings(
+                conversation_uuid: uuid::Uuid,
+                settings: ConversationSettings
+            ) -> ();
+            save_draft(conversation_uuid: Option<uuid::Uuid>, prompt: String) -> ();
+            save_image(name: String) -> String;
+            scan_prompt(prompt: String, config: Config) -> Vec<GuardrailFinding>;
+            set_conversation_archived(conversation_uuid: uuid::Uuid, archived: bool) -> ();
+            set_conversation_folder(conversation_uuid: uuid::Uuid, folder: Option<i32>) -> ();
+            set_conversation_icon(conversation_uuid: uuid::Uuid, icon: Option<String>) -> ();
+            set_exchanges(
+                conversation_uuid: uuid::Uuid,
+                exchanges: Vec<(usize, Exchange)>
+            ) -> Option<uuid::Uuid>;
+            set_folder_name(id: i32, name: String) -> ();
+            set_window_title(title: String) -> ();
+            store_secret(secret: String) -> String;
+            summarize_conversation(conversation_uuid: uuid::Uuid) -> CarryOver;
+            tail_logs(lines: usize) -> Vec<String>;
+            toggle_pin(conversation_uuid: uuid::Uuid) -> bool;
+            unlock(passphrase: String) -> ();
+            validate_api_key(api_key: APIKey) -> ();
+            validate_request(config: Config) -> ();
+        }
+    };
+}
+
+// the key an argument is sent under by default, tauri commands take their arguments in camelCase.
+// used by the ui macro to send arguments and by tauri's build script to check them
+pub fn camel_case(name: &str) -> String {
+    let mut words = name.split('_').filter(|word| !word.is_empty());
+    let first = words.next().unwrap_or_default().to_string();
+    words.fold(first, |camel_case, word| {
+        let mut chars = word.chars();
+        camel_case + &chars.next().map(|first| first.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default()
+    })
+}
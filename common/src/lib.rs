@@ -9,7 +9,69 @@ pub fn to_serde_err(error: anyhow::Error) -> serde_error::Error {
 pub enum Provider {
     OpenAI { base_url: String },
     Anthropic,
-    Google
+    Google,
+    Groq,
+    XAI,
+    Ollama { base_url: String },
+    // base_url is the resource endpoint, e.g. https://<resource>.openai.azure.com. keys saved before the deployment
+    // was set separately have none, and are routed to the deployment named by the model
+    Azure {
+        base_url: String,
+        #[serde(default)]
+        deployment: String,
+        api_version: String
+    },
+    // the key is an OAuth access token, or empty to use the gcloud CLI's credentials
+    VertexAI { project_id: String, location: String }
+}
+
+impl Provider {
+    pub fn default_ollama() -> Self {
+        Provider::Ollama { base_url: "http://localhost:11434".into() }
+    }
+
+    pub fn default_azure() -> Self {
+        Provider::Azure { base_url: "".into(), deployment: "".into(), api_version: "2024-10-21".into() }
+    }
+
+    pub fn default_vertex_ai() -> Self {
+        Provider::VertexAI { project_id: "".into(), location: "us-central1".into() }
+    }
+
+    // the provider with a variant's name, with the defaults a new key starts with
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "OpenAI" => Some(Provider::default()),
+            "Anthropic" => Some(Provider::Anthropic),
+            "Google" => Some(Provider::Google),
+            "Groq" => Some(Provider::Groq),
+            "XAI" => Some(Provider::XAI),
+            "Ollama" => Some(Provider::default_ollama()),
+            "Azure" => Some(Provider::default_azure()),
+            "VertexAI" => Some(Provider::default_vertex_ai()),
+            _ => None
+        }
+    }
+
+    pub fn base_url(&self) -> Option<&str> {
+        match self {
+            Provider::OpenAI { base_url }
+            | Provider::Ollama { base_url }
+            | Provider::Azure { base_url, .. } => Some(base_url),
+            Provider::Anthropic | Provider::Google | Provider::Groq | Provider::XAI
+            | Provider::VertexAI { .. } => None
+        }
+    }
+
+    pub fn base_url_mut(&mut self) -> Option<&mut String> {
+        match self {
+            Provider::OpenAI { base_url }
+            | Provider::Ollama { base_url }
+            | Provider::Azure { base_url, .. } => Some(base_url),
+            Provider::Anthropic | Provider::Google | Provider::Groq | Provider::XAI
+            | Provider::VertexAI { .. } => None
+        }
+    }
 }
 
 impl Default for Provider {
@@ -18,6 +80,167 @@ impl Default for Provider {
     }
 }
 
+// payload of the events emitted by the backend's file watchers
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    pub kind: FileChangeKind,
+    pub origin: ChangeOrigin
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum FileChangeKind {
+    Modified,
+    Removed,
+    // the watch was re-created after an error, changes in between may have been missed
+    Reconnected
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ChangeOrigin {
+    // written by this process, e.g. saving the settings, with the label of the window it was written for
+    App { window: Option<String> },
+    External
+}
+
+// kinds of failures counted locally for the diagnostics page, nothing is sent anywhere
+#[derive(Clone, Copy, Debug, Deserialize, strum_macros::Display, Eq, Hash, PartialEq, Serialize)]
+pub enum ErrorCategory {
+    #[strum(serialize = "Parse errors")]
+    Parse,
+    #[strum(serialize = "Rate limited (429)")]
+    RateLimited,
+    #[strum(serialize = "Overloaded")]
+    Overloaded,
+    #[strum(serialize = "Timeouts")]
+    Timeout,
+    #[strum(serialize = "Network errors")]
+    Network,
+    #[strum(serialize = "Other provider errors")]
+    Provider,
+    #[strum(serialize = "Database failures")]
+    Database,
+    #[strum(serialize = "Other")]
+    Other
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ErrorCount {
+    pub category: ErrorCategory,
+    pub count: u64,
+    pub last_occurred: chrono::DateTime<chrono::Utc>,
+    pub last_message: String,
+    // the provider involved in the last occurrence, if any
+    pub provider: Option<String>
+}
+
+// quota reported in a provider's x-ratelimit-* response headers, resets are durations like "2m59.56s"
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RateLimit {
+    pub provider: String,
+    pub limit_requests: Option<u64>,
+    pub remaining_requests: Option<u64>,
+    pub reset_requests: Option<String>,
+    pub limit_tokens: Option<u64>,
+    pub remaining_tokens: Option<u64>,
+    pub reset_tokens: Option<String>
+}
+
+impl RateLimit {
+    // whether either quota is under a tenth of its limit
+    pub fn is_low(&self) -> bool {
+        let is_low = |remaining: Option<u64>, limit: Option<u64>| match (remaining, limit) {
+            (Some(remaining), Some(limit)) => remaining * 10 < limit,
+            _ => false
+        };
+        is_low(self.remaining_requests, self.limit_requests) || is_low(self.remaining_tokens, self.limit_tokens)
+    }
+}
+
+// errors reported by a provider, parsed so the user knows what to do about them
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ProviderError {
+    Overloaded { provider: String, retry_after: Option<u64> },
+    RateLimited { provider: String, retry_after: Option<u64> },
+    InvalidRequest { provider: String, message: String },
+    Authentication { provider: String, message: String },
+    NotFound { provider: String, message: String },
+    Other { provider: String, kind: String, message: String }
+}
+
+impl ProviderError {
+    // seconds the provider asked to wait before retrying
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            ProviderError::Overloaded { retry_after, .. }
+            | ProviderError::RateLimited { retry_after, .. } => *retry_after,
+            _ => None
+        }
+    }
+
+    pub fn with_retry_after(mut self, seconds: Option<u64>) -> Self {
+        if let ProviderError::Overloaded { retry_after, .. }
+                | ProviderError::RateLimited { retry_after, .. } = &mut self {
+            *retry_after = seconds;
+        }
+
+        return self;
+    }
+}
+
+impl std::fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let retry = |retry_after: &Option<u64>| match retry_after {
+            Some(0) => "Try again now.".to_string(),
+            Some(seconds) => format!("Try again in {seconds} seconds."),
+            None => "Try again shortly.".to_string()
+        };
+
+        match self {
+            ProviderError::Overloaded { provider, retry_after } =>
+                write!(f, "{provider} is overloaded. {}", retry(retry_after)),
+            ProviderError::RateLimited { provider, retry_after } =>
+                write!(f, "{provider} rate limit reached. {}", retry(retry_after)),
+            ProviderError::InvalidRequest { provider, message } =>
+                write!(f, "{provider} rejected the request: {message} Check the model and settings."),
+            ProviderError::Authentication { provider, message } =>
+                write!(f, "{provider} rejected the API key: {message} Check the selected key in settings."),
+            ProviderError::NotFound { provider, message } =>
+                write!(f, "{provider} couldn't find the model: {message} Check the model name."),
+            ProviderError::Other { provider, kind, message } =>
+                write!(f, "{provider} error ({kind}): {message}")
+        }
+    }
+}
+
+impl std::error::Error for ProviderError {}
+
+// errors in a streamed command's events. provider errors are sent as is so the frontend can act on them
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum StreamError {
+    Provider(ProviderError),
+    Other(serde_error::Error)
+}
+
+impl From<anyhow::Error> for StreamError {
+    fn from(error: anyhow::Error) -> Self {
+        match error.downcast::<ProviderError>() {
+            Ok(error) => StreamError::Provider(error),
+            Err(error) => StreamError::Other(to_serde_err(error))
+        }
+    }
+}
+
+impl From<StreamError> for anyhow::Error {
+    fn from(error: StreamError) -> Self {
+        match error {
+            StreamError::Provider(error) => error.into(),
+            StreamError::Other(error) => error.into()
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct APIKey {
     pub name: String,
@@ -25,6 +248,238 @@ pub struct APIKey {
     pub provider: Provider
 }
 
+// the model last used with a provider
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ProviderModel {
+    pub provider: Provider,
+    pub model: String
+}
+
+// use the API key named api_key for models starting with model_prefix
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct KeyRoute {
+    pub model_prefix: String,
+    pub api_key: String
+}
+
+// a saved piece of prompt, inserted by typing "/" and its name in the prompt box
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Snippet {
+    pub name: String,
+    pub body: String
+}
+
+// gemini's built-in tools
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct GoogleTools {
+    pub code_execution: bool,
+    pub search_grounding: bool
+}
+
+// gemini's harm block threshold, applied to every harm category
+#[derive(Clone, Copy, Debug, Default, Deserialize, strum_macros::Display, strum_macros::EnumString, PartialEq)]
+#[derive(Serialize, VariantNames)]
+pub enum SafetyThreshold {
+    #[default]
+    #[strum(serialize = "BLOCK_NONE")]
+    BlockNone,
+    #[strum(serialize = "BLOCK_ONLY_HIGH")]
+    BlockOnlyHigh,
+    #[strum(serialize = "BLOCK_MEDIUM_AND_ABOVE")]
+    BlockMediumAndAbove,
+    #[strum(serialize = "BLOCK_LOW_AND_ABOVE")]
+    BlockLowAndAbove
+}
+
+// applied in the respective provider's request builder on every request
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ProviderDefaults {
+    // appended to the system prompt for anthropic models
+    pub anthropic_system_suffix: String,
+    pub google_safety_threshold: SafetyThreshold
+}
+
+// openai's reasoning_effort for o-series and gpt-5 models
+#[derive(Clone, Copy, Debug, Deserialize, strum_macros::Display, strum_macros::EnumString, PartialEq, Serialize)]
+#[derive(VariantNames)]
+#[strum(serialize_all = "lowercase")]
+pub enum ReasoningEffort {
+    Low,
+    Medium,
+    High
+}
+
+// which key combination sends the prompt, the other inserts a newline
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum SendKey {
+    Enter,
+    #[default]
+    ShiftEnter
+}
+
+// the schema is json schema source, validated when the settings are applied
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum ResponseFormat {
+    #[default]
+    Text,
+    JsonObject,
+    JsonSchema(String)
+}
+
+// character/word counters under message boxes, highlighted past the soft limits
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct MessageCounter {
+    pub enabled: bool,
+    pub character_limit: Option<usize>,
+    pub word_limit: Option<usize>
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum ExportCadence {
+    #[default]
+    Daily,
+    Weekly
+}
+
+impl ExportCadence {
+    pub fn interval(&self) -> chrono::Duration {
+        match self {
+            ExportCadence::Daily => chrono::Duration::days(1),
+            ExportCadence::Weekly => chrono::Duration::weeks(1)
+        }
+    }
+}
+
+// periodic export to a directory of the conversations updated since the last one
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ExportSchedule {
+    pub enabled: bool,
+    pub cadence: ExportCadence,
+    pub directory: String,
+    pub format: ConversationFormat,
+    pub layout: ExportLayout
+}
+
+impl Default for ExportSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cadence: ExportCadence::Daily,
+            directory: "".into(),
+            // json so the backups can be imported again
+            format: ConversationFormat::Json,
+            layout: ExportLayout::Combined
+        }
+    }
+}
+
+// how requests failing with rate limits, server errors or dropped connections are sent again
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RetryPolicy {
+    // including the first, 1 disables retrying
+    pub attempts: u32,
+    // doubled after every retry unless the provider says how long to wait
+    pub initial_backoff_ms: u64
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { attempts: 3, initial_backoff_ms: 1000 }
+    }
+}
+
+impl RetryPolicy {
+    // the wait before the given retry, starting from 1
+    pub fn backoff(&self, retry: u32) -> std::time::Duration {
+        std::time::Duration::from_millis(self.initial_backoff_ms.saturating_mul(1 << retry.saturating_sub(1).min(16)))
+    }
+}
+
+// deleted conversations can be restored until they're purged
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TrashPolicy {
+    pub retention_days: u32
+}
+
+impl Default for TrashPolicy {
+    fn default() -> Self {
+        Self { retention_days: 30 }
+    }
+}
+
+// emitted as "retrying" when a request failed and will be sent again
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RetryStatus {
+    pub provider: String,
+    pub attempt: u32,
+    pub attempts: u32,
+    pub delay_ms: u64,
+    pub reason: String
+}
+
+// the payload of notices about one response, e.g. "retrying", so a window shows them only with the
+// conversation the response is for
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct StreamNotice<T> {
+    pub stream_id: uuid::Uuid,
+    pub notice: T
+}
+
+// emitted as "context_summarized" when a conversation's first exchanges were sent as their summary
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ContextSummary {
+    pub conversation_uuid: uuid::Uuid,
+    pub summarized: usize
+}
+
+// cleanup applied to a response before it's added to the conversation and saved, all off unless enabled
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct OutputCleanup {
+    pub trim: bool,
+    pub collapse_blank_lines: bool,
+    pub strip_invisible: bool
+}
+
+impl OutputCleanup {
+    pub fn apply(&self, message: &str) -> String {
+        let mut message = message.to_string();
+        if self.strip_invisible {
+            // byte order mark and zero-width space. the zero-width joiner and non-joiner are kept, emoji
+            // sequences and scripts like persian and hindi depend on them
+            message.retain(|c| !matches!(c, '\u{FEFF}' | '\u{200B}'));
+        }
+
+        if self.collapse_blank_lines {
+            // blank lines in fenced code blocks are left as they are
+            let mut lines = Vec::<&str>::new();
+            let mut in_fence = false;
+            for line in message.lines() {
+                if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+                    in_fence = !in_fence;
+                }
+                let blank = !in_fence && line.trim().is_empty();
+                if blank && lines.last().is_some_and(|last| last.is_empty()) {
+                    continue;
+                }
+                lines.push(if blank { "" } else { line });
+            }
+            let trailing_newline = message.ends_with('\n');
+            message = lines.join("\n");
+            if trailing_newline {
+                message.push('\n');
+            }
+        }
+
+        if self.trim {
+            message = message.trim().to_string();
+        }
+
+        return message;
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Config {
     pub system_prompt: String,
@@ -32,7 +487,130 @@ pub struct Config {
     pub max_tokens: u32,
     pub model: String,
     pub api_key: Option<usize>,
-    pub api_keys: Vec<APIKey>
+    pub api_keys: Vec<APIKey>,
+    #[serde(default)]
+    pub output_cleanup: OutputCleanup,
+    #[serde(default)]
+    pub key_routes: Vec<KeyRoute>,
+    #[serde(default)]
+    pub google_tools: GoogleTools,
+    // temperature 0 and a fixed seed where the provider supports one
+    #[serde(default)]
+    pub deterministic: bool,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub message_counter: MessageCounter,
+    #[serde(default)]
+    pub export_schedule: ExportSchedule,
+    // show the request body for review before it's sent
+    #[serde(default)]
+    pub preview_requests: bool,
+    #[serde(default)]
+    pub provider_defaults: ProviderDefaults,
+    // check prompts for likely secrets and personal details before sending
+    #[serde(default)]
+    pub scan_prompts: bool,
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+    // None leaves the provider's default
+    #[serde(default)]
+    pub reasoning_effort: Option<ReasoningEffort>,
+    // tokens for anthropic's extended thinking and gemini's thinking, None disables it
+    #[serde(default)]
+    pub thinking_budget: Option<u32>,
+    // completions requested in parallel per prompt, 0 is treated as 1
+    #[serde(default)]
+    pub candidates: usize,
+    // reveal streamed responses at a steady pace rather than chunk by chunk
+    #[serde(default)]
+    pub smooth_streaming: bool,
+    // milliseconds between token events, tokens streamed in between are sent together. 0 is treated as 25
+    #[serde(default)]
+    pub token_interval_ms: u64,
+    // defaults to shift+enter so that enter keeps inserting newlines as before
+    #[serde(default)]
+    pub send_key: SendKey,
+    // the start of the response for the model to continue, set per request by the chat and never saved
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub prefill: String,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub snippets: Vec<Snippet>,
+    #[serde(default)]
+    pub trash: TrashPolicy,
+    // switched to when a key of that provider is selected, since the model names differ between providers
+    #[serde(default)]
+    pub provider_models: Vec<ProviderModel>,
+    // record each request's body and raw response to <config-dir>/captures, for debugging provider changes
+    #[serde(default)]
+    pub capture_requests: bool,
+    // keep a summary of long conversations' older exchanges, sent in their place once the context window fills
+    #[serde(default)]
+    pub summarize_long_conversations: bool
+}
+
+impl Config {
+    pub fn effective_temperature(&self) -> f64 {
+        if self.deterministic { 0.0 } else { self.temperature }
+    }
+
+    // tauri can't process events much faster than the default
+    pub fn token_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(if self.token_interval_ms == 0 { 25 } else { self.token_interval_ms })
+    }
+
+    // the first routing rule matching the model takes precedence over the selected key
+    pub fn request_api_key(&self) -> Option<&APIKey> {
+        let routed_key = self.key_routes.iter()
+            .filter(|route| !route.model_prefix.is_empty() && self.model.starts_with(&route.model_prefix))
+            .find_map(|route| self.api_keys.iter().find(|api_key| api_key.name == route.api_key));
+        routed_key.or_else(|| self.api_keys.get(self.api_key?))
+    }
+
+    // remembers the model for the selected key's provider, then switches to the new key's provider's last model
+    pub fn switch_api_key(&mut self, api_key: Option<usize>) {
+        let provider = self.api_key.and_then(|api_key| self.api_keys.get(api_key)).map(|key| key.provider.clone());
+        if let Some(provider) = provider {
+            let model = self.model.clone();
+            match self.provider_models.iter_mut().find(|provider_model| provider_model.provider == provider) {
+                Some(provider_model) => provider_model.model = model,
+                None => self.provider_models.push(ProviderModel { provider, model })
+            }
+        }
+
+        self.api_key = api_key;
+        let provider = api_key.and_then(|api_key| self.api_keys.get(api_key)).map(|key| &key.provider);
+        let provider_model = self.provider_models.iter()
+            .find(|provider_model| Some(&provider_model.provider) == provider);
+        if let Some(provider_model) = provider_model {
+            self.model = provider_model.model.clone();
+        }
+    }
+
+    // keys are identified by name, so repeated names are suffixed with " (2)", " (3)", etc.
+    fn unique_key_name(&self, name: &str) -> String {
+        let is_taken = |name: &str| self.api_keys.iter().any(|api_key| api_key.name == name);
+        if !is_taken(name) {
+            return name.to_string();
+        }
+
+        return (2..).map(|suffix| format!("{name} ({suffix})")).find(|name| !is_taken(name))
+            .unwrap_or_else(|| name.to_string());
+    }
+
+    pub fn add_api_key(&mut self, mut api_key: APIKey) {
+        api_key.name = self.unique_key_name(&api_key.name);
+        self.api_keys.push(api_key);
+    }
+
+    // for configs edited by hand
+    pub fn dedupe_key_names(&mut self) {
+        for api_key in std::mem::take(&mut self.api_keys) {
+            self.add_api_key(api_key);
+        }
+    }
 }
 
 impl Default for Config {
@@ -43,20 +621,507 @@ impl Default for Config {
             max_tokens: 1024,
             model: "".into(),
             api_key: None,
-            api_keys: vec![]
+            api_keys: vec![],
+            output_cleanup: OutputCleanup::default(),
+            key_routes: vec![],
+            google_tools: GoogleTools::default(),
+            deterministic: false,
+            seed: 0,
+            message_counter: MessageCounter::default(),
+            export_schedule: ExportSchedule::default(),
+            preview_requests: false,
+            provider_defaults: ProviderDefaults::default(),
+            scan_prompts: false,
+            response_format: ResponseFormat::default(),
+            reasoning_effort: None,
+            thinking_budget: None,
+            candidates: 1,
+            smooth_streaming: false,
+            token_interval_ms: 25,
+            send_key: SendKey::default(),
+            prefill: "".into(),
+            retry: RetryPolicy::default(),
+            snippets: vec![],
+            trash: TrashPolicy::default(),
+            provider_models: vec![],
+            capture_requests: false,
+            summarize_long_conversations: false
         }
     }
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+// rough count for display, most tokenizers average about four characters per token in english
+pub fn estimate_tokens(text: &str) -> usize {
+    (text.chars().count() + 3) / 4
+}
+
+// a likely secret or personal detail in an outgoing prompt, start and end are byte offsets
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct GuardrailFinding {
+    pub rule: String,
+    pub start: usize,
+    pub end: usize
+}
+
+// the findings' offsets are into scanned, the prompt with its attachments, so each match is replaced wherever it
+// appears in text, whether that's the prompt or an attachment
+pub fn redact(text: &str, scanned: &str, findings: &[GuardrailFinding]) -> String {
+    findings.iter().fold(text.to_string(), |redacted, finding| match scanned.get(finding.start..finding.end) {
+        Some(matched) if !matched.is_empty() =>
+            redacted.replace(matched, &format!("[REDACTED {}]", finding.rule.to_uppercase())),
+        _ => redacted
+    })
+}
+
+// text extracted from an attached file, long files are split into chunks labeled separately in the prompt
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Attachment {
+    pub name: String,
+    pub chunks: Vec<String>,
+    // whether the text was cut short to fit in the context
+    pub truncated: bool
+}
+
+// the attachments' text goes before the prompt so the prompt can refer to it
+pub fn with_attachments(prompt: &str, attachments: &[Attachment]) -> String {
+    let mut message = String::new();
+    for attachment in attachments {
+        let chunk_count = attachment.chunks.len();
+        for (index, chunk) in attachment.chunks.iter().enumerate() {
+            let label = match chunk_count {
+                1 => attachment.name.clone(),
+                _ => format!("{} (part {} of {chunk_count})", attachment.name, index + 1)
+            };
+            message += &format!("<attachment name=\"{label}\">\n{chunk}\n</attachment>\n\n");
+        }
+        if attachment.truncated {
+            message += &format!("(The rest of {} was left out.)\n\n", attachment.name);
+        }
+    }
+    message += prompt;
+
+    return message;
+}
+
+// token counts reported by the provider, some report them cumulatively over several chunks
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Usage {
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>
+}
+
+impl Usage {
+    pub fn merge(&mut self, other: Usage) {
+        self.prompt_tokens = other.prompt_tokens.or(self.prompt_tokens);
+        self.completion_tokens = other.completion_tokens.or(self.completion_tokens);
+    }
+}
+
+// the spans the usage page totals responses over, in local time
+#[derive(Clone, Copy, Debug, Default, Deserialize, strum_macros::Display, strum_macros::EnumString, PartialEq)]
+#[derive(Serialize, VariantNames)]
+pub enum UsagePeriod {
+    #[default]
+    Day,
+    Week,
+    Month
+}
+
+// the responses from one model over one period
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct UsageTotal {
+    // e.g. 2024-08-06 for a day, 2024-W32 for a week, and 2024-08 for a month
+    pub period: String,
+    pub provider: String,
+    pub model: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    // responses from models without a known price, or without reported token counts, add nothing
+    pub cost: f64
+}
+
+// details reported by the provider alongside a response, used to reproduce it later
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ResponseMetadata {
+    pub seed: Option<u64>,
+    // the snapshot that answered, e.g. gpt-4o-2024-08-06 for a request to gpt-4o
+    pub model: Option<String>,
+    pub system_fingerprint: Option<String>,
+    // file names of images returned with the response, stored in the config dir
+    pub images: Vec<String>,
+    pub usage: Usage,
+    // as reported by the provider, e.g. "length" or "content_filter"
+    pub finish_reason: Option<String>,
+    // the model declined, its explanation is the response text
+    pub refusal: bool,
+    // anthropic's thinking and gemini's thought summaries, streamed in pieces and kept out of the response text
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub reasoning: String
+}
+
+// the reasons providers give for a response that ended on its own
+const NATURAL_FINISH_REASONS: [&str; 6] = ["stop", "end_turn", "stop_sequence", "tool_use", "tool_calls", "STOP"];
+
+impl ResponseMetadata {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    // fields reported later in the stream take precedence
+    pub fn merge(&mut self, other: ResponseMetadata) {
+        self.seed = other.seed.or(self.seed);
+        self.model = other.model.or(self.model.take());
+        self.system_fingerprint = other.system_fingerprint.or(self.system_fingerprint.take());
+        self.images.extend(other.images);
+        self.usage.merge(other.usage);
+        self.finish_reason = other.finish_reason.or(self.finish_reason.take());
+        self.refusal |= other.refusal;
+        self.reasoning += &other.reasoning;
+    }
+
+    // why the response was cut short or declined, None if it ended normally
+    pub fn stopped_reason(&self) -> Option<String> {
+        if self.refusal {
+            return Some("refusal".into());
+        }
+        self.finish_reason.clone()
+            .filter(|finish_reason| !NATURAL_FINISH_REASONS.contains(&finish_reason.as_str()))
+            .map(|finish_reason| finish_reason.to_lowercase())
+    }
+}
+
+// a piece of a streamed response
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ResponseChunk {
+    pub tokens: String,
+    pub metadata: ResponseMetadata,
+    // which of the parallel candidates this belongs to
+    #[serde(default)]
+    pub candidate: usize
+}
+
+impl ResponseChunk {
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty() && self.metadata.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
 pub struct Exchange {
     pub user_message: String,
-    pub assistant_message: String
+    pub assistant_message: String,
+    #[serde(default)]
+    pub metadata: ResponseMetadata,
+    // estimated in dollars once the response finishes, None if the model's prices or the usage are unknown
+    #[serde(default)]
+    pub cost: Option<f64>,
+    // the model requested and its provider, None for exchanges saved before they were recorded
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub provider: Option<String>
+}
+
+// the parameters a conversation was started with, used instead of the config's when it's loaded again
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ConversationParameters {
+    pub model: String,
+    pub temperature: f64,
+    pub max_tokens: u32,
+    pub system_prompt: String
+}
+
+impl ConversationParameters {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            model: config.model.clone(),
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+            system_prompt: config.system_prompt.clone()
+        }
+    }
+
+    pub fn apply(&self, config: &mut Config) {
+        config.model = self.model.clone();
+        config.temperature = self.temperature;
+        config.max_tokens = self.max_tokens;
+        config.system_prompt = self.system_prompt.clone();
+    }
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct Conversation {
     pub uuid: uuid::Uuid,
     pub last_updated: chrono::DateTime<chrono::Utc>,
-    pub title: String
-}
\ No newline at end of file
+    pub title: String,
+    // None for conversations saved before parameters were recorded
+    #[serde(default)]
+    pub parameters: Option<ConversationParameters>,
+    #[serde(default)]
+    pub workspace: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    // a short title generated from the first exchange, titles otherwise are the first prompt
+    #[serde(default)]
+    pub generated_title: Option<String>,
+    // the total of its exchanges' estimated costs, only filled in for the history
+    #[serde(default)]
+    pub cost: Option<f64>,
+    // sorted by name, only filled in for the history
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // None for conversations at the top level of the history
+    #[serde(default)]
+    pub folder: Option<i32>,
+    // pinned conversations are listed first in the history
+    #[serde(default)]
+    pub pinned: bool,
+    // archived conversations are only listed in the history's archived view
+    #[serde(default)]
+    pub archived: bool
+}
+
+// a folder in the history, None parent for top-level folders
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Folder {
+    pub id: i32,
+    pub name: String,
+    pub parent: Option<i32>
+}
+
+// icons offered for marking conversations so they stand out in long lists
+pub const CONVERSATION_ICONS: [&str; 16] = [
+    "⭐", "📌", "💡", "🐛", "🔧", "📚", "✍️", "🧪", "📊", "🎨", "🚀", "🔒", "💬", "🧠", "📝", "❓"
+];
+
+impl Conversation {
+    // the title as shown in lists and the window title
+    pub fn display_title(&self) -> String {
+        let title = self.generated_title.as_ref().unwrap_or(&self.title);
+        match &self.icon {
+            Some(icon) => format!("{icon} {title}"),
+            None => title.clone()
+        }
+    }
+}
+
+// context pulled from a previous conversation into a new one's system prompt
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CarryOver {
+    pub conversation_uuid: uuid::Uuid,
+    pub summary: String
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, strum_macros::Display, strum_macros::EnumString, PartialEq, Serialize)]
+pub enum Edit {
+    Added,
+    Edited,
+    Deleted
+}
+
+// an entry in a conversation's append-only edit log, replaying entries reconstructs past states
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EditLogEntry {
+    pub id: i32,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub edit: Edit,
+    pub key: usize,
+    pub exchange: Exchange
+}
+
+// settings saved with a single conversation
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ConversationSettings {
+    // BCP 47 tag used for spellcheck and font selection, e.g. "ja"
+    pub language: Option<String>,
+    // the project directory the conversation is about
+    pub workspace: Option<String>,
+    // one of CONVERSATION_ICONS, shown before the title
+    pub icon: Option<String>,
+    // set by retitle_conversations
+    pub title: Option<String>
+}
+
+// emitted as "retitle_progress" while conversations are retitled in the background
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct RetitleProgress {
+    pub retitled: usize,
+    pub failed: usize,
+    pub total: usize,
+    pub last_error: Option<String>
+}
+
+impl RetitleProgress {
+    pub fn is_done(&self) -> bool {
+        self.retitled + self.failed >= self.total
+    }
+}
+
+// a conversation with its exchanges, as written to exports
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ExportedConversation {
+    pub conversation: Conversation,
+    pub exchanges: Vec<(usize, Exchange)>
+}
+
+// bumped when archives change in a way older versions can't read
+pub const ARCHIVE_VERSION: u32 = 1;
+
+// every conversation, written by export_all and read by import_all to move the history between machines
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ConversationArchive {
+    pub version: u32,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    pub conversations: Vec<ExportedConversation>
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ImportSummary {
+    pub imported: usize,
+    // their uuids were taken by different conversations, so they were given new ones
+    pub reassigned: usize,
+    // already in the history
+    pub skipped: usize
+}
+
+impl ImportSummary {
+    pub fn describe(&self) -> String {
+        let mut description = format!("Imported {} conversations.", self.imported);
+        if self.reassigned > 0 {
+            description += &format!(" {} were given new ids since theirs were taken.", self.reassigned);
+        }
+        if self.skipped > 0 {
+            description += &format!(" Skipped {} already in the history.", self.skipped);
+        }
+        description
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct EncryptionStatus {
+    // the conversations database is encrypted with a passphrase
+    pub encrypted: bool,
+    // the passphrase hasn't been entered yet, so the database can't be read
+    pub locked: bool
+}
+
+// the read-only shapes served to external tools, kept stable independently of the ui's types
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    User,
+    Assistant
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Message {
+    pub role: Role,
+    pub content: String,
+    // only responses carry metadata
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ResponseMetadata>
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConversationSummary {
+    pub uuid: uuid::Uuid,
+    pub title: String,
+    pub last_updated: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub workspace: Option<String>
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ConversationRecord {
+    #[serde(flatten)]
+    pub summary: ConversationSummary,
+    #[serde(default)]
+    pub parameters: Option<ConversationParameters>,
+    pub messages: Vec<Message>
+}
+
+impl From<&Conversation> for ConversationSummary {
+    fn from(conversation: &Conversation) -> Self {
+        ConversationSummary {
+            uuid: conversation.uuid,
+            title: conversation.title.clone(),
+            last_updated: conversation.last_updated,
+            workspace: conversation.workspace.clone()
+        }
+    }
+}
+
+impl ConversationRecord {
+    pub fn new(conversation: &Conversation, exchanges: Vec<Exchange>) -> Self {
+        let messages = exchanges.into_iter()
+            .flat_map(|exchange| [
+                Message { role: Role::User, content: exchange.user_message, metadata: None },
+                Message {
+                    role: Role::Assistant,
+                    content: exchange.assistant_message,
+                    metadata: Some(exchange.metadata)
+                }
+            ])
+            .collect();
+
+        ConversationRecord {
+            summary: conversation.into(),
+            parameters: conversation.parameters.clone(),
+            messages
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum ExportLayout {
+    // a single file with every matching conversation
+    #[default]
+    Combined,
+    // a directory with one file per conversation
+    PerConversation
+}
+
+// how conversations are written when exported from the history or on a schedule
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum ConversationFormat {
+    // a readable transcript
+    #[default]
+    Markdown,
+    Json
+}
+
+impl ConversationFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ConversationFormat::Markdown => "md",
+            ConversationFormat::Json => "json"
+        }
+    }
+}
+
+// selects conversations to export, None leaves that side of the range or that filter open
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct ExportFilter {
+    pub since: Option<chrono::NaiveDate>,
+    pub until: Option<chrono::NaiveDate>,
+    pub tag: Option<String>,
+    // conversations with a response from the provider, whatever its base url or other settings
+    pub provider: Option<Provider>
+}
+
+impl ExportFilter {
+    // dates are inclusive and compared in local time
+    pub fn matches(&self, conversation: &Conversation, exchanges: &[(usize, Exchange)]) -> bool {
+        let date = conversation.last_updated.with_timezone(&chrono::Local).date_naive();
+        let provider = self.provider.as_ref().map(Provider::to_string);
+        self.since.map_or(true, |since| since <= date) && self.until.map_or(true, |until| date <= until)
+            && self.tag.as_ref().map_or(true, |tag| conversation.tags.contains(tag))
+            && provider.map_or(true, |provider| exchanges.iter()
+                .any(|(_, exchange)| exchange.provider.as_ref() == Some(&provider)))
+    }
+}
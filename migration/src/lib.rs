@@ -1,12 +1,48 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
+mod m20220101_000002_add_carry_over;
+mod m20220101_000003_create_edit_log;
+mod m20220101_000004_add_exchange_metadata;
+mod m20220101_000005_add_conversation_settings;
+mod m20220101_000006_add_conversation_parameters;
+mod m20220101_000007_add_exchange_cost;
+mod m20220101_000008_create_tags;
+mod m20220101_000009_create_folders;
+mod m20220101_000010_add_pinned;
+mod m20220101_000011_add_deleted_at;
+mod m20220101_000012_add_archived;
+mod m20220101_000013_add_exchange_model;
+mod m20220101_000014_create_secrets;
+mod m20220101_000015_create_usage;
+mod m20220101_000016_create_drafts;
+mod m20220101_000017_create_summaries;
+mod m20220101_000018_add_edit_log_details;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(m20220101_000001_create_table::Migration)]
+        vec![
+            Box::new(m20220101_000001_create_table::Migration),
+            Box::new(m20220101_000002_add_carry_over::Migration),
+            Box::new(m20220101_000003_create_edit_log::Migration),
+            Box::new(m20220101_000004_add_exchange_metadata::Migration),
+            Box::new(m20220101_000005_add_conversation_settings::Migration),
+            Box::new(m20220101_000006_add_conversation_parameters::Migration),
+            Box::new(m20220101_000007_add_exchange_cost::Migration),
+            Box::new(m20220101_000008_create_tags::Migration),
+            Box::new(m20220101_000009_create_folders::Migration),
+            Box::new(m20220101_000010_add_pinned::Migration),
+            Box::new(m20220101_000011_add_deleted_at::Migration),
+            Box::new(m20220101_000012_add_archived::Migration),
+            Box::new(m20220101_000013_add_exchange_model::Migration),
+            Box::new(m20220101_000014_create_secrets::Migration),
+            Box::new(m20220101_000015_create_usage::Migration),
+            Box::new(m20220101_000016_create_drafts::Migration),
+            Box::new(m20220101_000017_create_summaries::Migration),
+            Box::new(m20220101_000018_add_edit_log_details::Migration)
+        ]
     }
 }
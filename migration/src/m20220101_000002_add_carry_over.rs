@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // sqlite only supports one column change per alter statement
+        manager.alter_table(Table::alter()
+            .table(Conversations::Table)
+            .add_column(ColumnDef::new(Conversations::ContinuedFrom).binary_len(16).null())
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(Conversations::Table)
+            .add_column(ColumnDef::new(Conversations::CarryOverSummary).text().null())
+            .to_owned()).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(Table::alter()
+            .table(Conversations::Table)
+            .drop_column(Conversations::CarryOverSummary)
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(Conversations::Table)
+            .drop_column(Conversations::ContinuedFrom)
+            .to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Conversations {
+    Table,
+    ContinuedFrom,
+    CarryOverSummary
+}
@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // raw sql schema for the deferrable foreign key, see the exchanges table
+        manager.get_connection().execute_unprepared("
+            create table if not exists edit_log (
+                id integer primary key autoincrement not null,
+                conversation integer not null,
+                timestamp integer not null,
+                edit text not null,
+                key integer not null,
+                user_message text not null,
+                assistant_message text not null,
+                foreign key (conversation) references conversations(id) on delete cascade deferrable initially deferred
+            );
+        ").await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(EditLog::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EditLog {
+    Table
+}
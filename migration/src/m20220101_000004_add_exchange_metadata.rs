@@ -0,0 +1,42 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // json serialized common::ResponseMetadata
+        manager.alter_table(Table::alter()
+            .table(Exchanges::Table)
+            .add_column(ColumnDef::new(Exchanges::Metadata).text().null())
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(EditLog::Table)
+            .add_column(ColumnDef::new(EditLog::Metadata).text().null())
+            .to_owned()).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(Table::alter()
+            .table(EditLog::Table)
+            .drop_column(EditLog::Metadata)
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(Exchanges::Table)
+            .drop_column(Exchanges::Metadata)
+            .to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Exchanges {
+    Table,
+    Metadata
+}
+
+#[derive(DeriveIden)]
+enum EditLog {
+    Table,
+    Metadata
+}
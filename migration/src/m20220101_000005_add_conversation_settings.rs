@@ -0,0 +1,28 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // json serialized common::ConversationSettings
+        manager.alter_table(Table::alter()
+            .table(Conversations::Table)
+            .add_column(ColumnDef::new(Conversations::Settings).text().null())
+            .to_owned()).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(Table::alter()
+            .table(Conversations::Table)
+            .drop_column(Conversations::Settings)
+            .to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Conversations {
+    Table,
+    Settings
+}
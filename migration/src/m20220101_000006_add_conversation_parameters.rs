@@ -0,0 +1,49 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // null for conversations saved before parameters were recorded
+        // sqlite only supports one column change per alter statement
+        manager.alter_table(Table::alter()
+            .table(Conversations::Table)
+            .add_column(ColumnDef::new(Conversations::Model).text().null())
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(Conversations::Table)
+            .add_column(ColumnDef::new(Conversations::Temperature).double().null())
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(Conversations::Table)
+            .add_column(ColumnDef::new(Conversations::MaxTokens).integer().null())
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(Conversations::Table)
+            .add_column(ColumnDef::new(Conversations::SystemPrompt).text().null())
+            .to_owned()).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        for column in [Conversations::SystemPrompt, Conversations::MaxTokens,
+                Conversations::Temperature, Conversations::Model] {
+            manager.alter_table(Table::alter()
+                .table(Conversations::Table)
+                .drop_column(column)
+                .to_owned()).await?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[derive(DeriveIden)]
+enum Conversations {
+    Table,
+    Model,
+    Temperature,
+    MaxTokens,
+    SystemPrompt
+}
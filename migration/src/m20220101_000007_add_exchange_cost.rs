@@ -0,0 +1,28 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // estimated in dollars from the usage the provider reported, null when unknown
+        manager.alter_table(Table::alter()
+            .table(Exchanges::Table)
+            .add_column(ColumnDef::new(Exchanges::Cost).double().null())
+            .to_owned()).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(Table::alter()
+            .table(Exchanges::Table)
+            .drop_column(Exchanges::Cost)
+            .to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Exchanges {
+    Table,
+    Cost
+}
@@ -0,0 +1,42 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // raw sql schema for the composite primary key and cascading deletes
+        manager.get_connection().execute_unprepared("
+            create table if not exists tags (
+                id integer primary key autoincrement not null,
+                name text unique not null
+            );
+
+            create table if not exists conversation_tags (
+                conversation integer not null,
+                tag integer not null,
+                primary key (conversation, tag),
+                foreign key (conversation) references conversations(id) on delete cascade,
+                foreign key (tag) references tags(id) on delete cascade
+            );
+        ").await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(ConversationTags::Table).to_owned()).await?;
+        manager.drop_table(Table::drop().table(Tags::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Tags {
+    Table
+}
+
+#[derive(DeriveIden)]
+enum ConversationTags {
+    Table
+}
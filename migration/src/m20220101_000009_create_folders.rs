@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // raw sql schema for the foreign keys, deleting a folder deletes its subfolders
+        // and moves its conversations to the top level
+        manager.get_connection().execute_unprepared("
+            create table if not exists folders (
+                id integer primary key autoincrement not null,
+                name text not null,
+                parent integer,
+                foreign key (parent) references folders(id) on delete cascade
+            );
+
+            alter table conversations add column folder integer references folders(id) on delete set null;
+        ").await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(Table::alter()
+            .table(Conversations::Table)
+            .drop_column(Conversations::Folder)
+            .to_owned()).await?;
+        manager.drop_table(Table::drop().table(Folders::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Conversations {
+    Table,
+    Folder
+}
+
+#[derive(DeriveIden)]
+enum Folders {
+    Table
+}
@@ -0,0 +1,37 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // sqlite only supports one column change per alter statement
+        manager.alter_table(Table::alter()
+            .table(Exchanges::Table)
+            .add_column(ColumnDef::new(Exchanges::Model).text().null())
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(Exchanges::Table)
+            .add_column(ColumnDef::new(Exchanges::Provider).text().null())
+            .to_owned()).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(Table::alter()
+            .table(Exchanges::Table)
+            .drop_column(Exchanges::Provider)
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(Exchanges::Table)
+            .drop_column(Exchanges::Model)
+            .to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Exchanges {
+    Table,
+    Model,
+    Provider
+}
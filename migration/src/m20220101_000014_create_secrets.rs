@@ -0,0 +1,28 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // API keys are only kept here when the database is encrypted and there is no OS keychain
+        manager.create_table(Table::create()
+            .table(Secrets::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(Secrets::Name).text().not_null().primary_key())
+            .col(ColumnDef::new(Secrets::Secret).text().not_null())
+            .to_owned()).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(Secrets::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Secrets {
+    Table,
+    Name,
+    Secret
+}
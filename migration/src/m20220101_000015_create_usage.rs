@@ -0,0 +1,38 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // one row per response, kept apart from the exchanges so deleting a conversation doesn't erase its spend
+        manager.create_table(Table::create()
+            .table(Usage::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(Usage::Id).integer().not_null().auto_increment().primary_key())
+            .col(ColumnDef::new(Usage::Timestamp).big_integer().not_null())
+            .col(ColumnDef::new(Usage::Provider).text().not_null())
+            .col(ColumnDef::new(Usage::Model).text().not_null())
+            .col(ColumnDef::new(Usage::PromptTokens).big_integer().null())
+            .col(ColumnDef::new(Usage::CompletionTokens).big_integer().null())
+            .col(ColumnDef::new(Usage::Cost).double().null())
+            .to_owned()).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(Usage::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Usage {
+    Table,
+    Id,
+    Timestamp,
+    Provider,
+    Model,
+    PromptTokens,
+    CompletionTokens,
+    Cost
+}
@@ -0,0 +1,28 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // the nil uuid holds the draft of the conversation that hasn't been saved yet
+        manager.create_table(Table::create()
+            .table(Drafts::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(Drafts::ConversationUuid).binary_len(16).not_null().primary_key())
+            .col(ColumnDef::new(Drafts::Prompt).text().not_null())
+            .to_owned()).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(Drafts::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Drafts {
+    Table,
+    ConversationUuid,
+    Prompt
+}
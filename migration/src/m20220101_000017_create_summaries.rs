@@ -0,0 +1,34 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // exchange_ids are the comma separated ids of the exchanges summarized, since unchanged exchanges keep
+        // their ids a summary is stale once they no longer match. content_hash is a sha256 of their messages,
+        // for exchanges edited in place
+        manager.create_table(Table::create()
+            .table(Summaries::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(Summaries::ConversationUuid).binary_len(16).not_null().primary_key())
+            .col(ColumnDef::new(Summaries::Summary).text().not_null())
+            .col(ColumnDef::new(Summaries::ExchangeIds).text().not_null())
+            .col(ColumnDef::new(Summaries::ContentHash).text().not_null())
+            .to_owned()).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.drop_table(Table::drop().table(Summaries::Table).to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Summaries {
+    Table,
+    ConversationUuid,
+    Summary,
+    ExchangeIds,
+    ContentHash
+}
@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // logged with each exchange so restoring an edit keeps them, null for entries logged before
+        manager.alter_table(Table::alter()
+            .table(EditLog::Table)
+            .add_column(ColumnDef::new(EditLog::Cost).double().null())
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(EditLog::Table)
+            .add_column(ColumnDef::new(EditLog::Model).text().null())
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(EditLog::Table)
+            .add_column(ColumnDef::new(EditLog::Provider).text().null())
+            .to_owned()).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager.alter_table(Table::alter()
+            .table(EditLog::Table)
+            .drop_column(EditLog::Provider)
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(EditLog::Table)
+            .drop_column(EditLog::Model)
+            .to_owned()).await?;
+        manager.alter_table(Table::alter()
+            .table(EditLog::Table)
+            .drop_column(EditLog::Cost)
+            .to_owned()).await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EditLog {
+    Table,
+    Cost,
+    Model,
+    Provider
+}
@@ -1,3 +1,245 @@
+use std::collections::HashMap;
+use commands::camel_case;
+use quote::ToTokens;
+use syn::{parse::{Parse, ParseStream}, visit_mut::VisitMut, Token};
+
+const COMMANDS: &str = "../commands/src/lib.rs";
+
+struct Argument {
+    attributes: Vec<syn::Attribute>,
+    name: syn::Ident,
+    ty: syn::Type
+}
+
+impl Parse for Argument {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attributes = input.call(syn::Attribute::parse_outer)?;
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        Ok(Argument { attributes, name, ty: input.parse()? })
+    }
+}
+
+// an entry in the shared list, `name(argument: Type, ...) -> Output;`
+struct Signature {
+    name: syn::Ident,
+    arguments: Vec<Argument>,
+    output: syn::Type
+}
+
+impl Parse for Signature {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let arguments;
+        syn::parenthesized!(arguments in input);
+        let arguments = arguments.parse_terminated(Argument::parse, Token![,])?.into_iter().collect();
+        input.parse::<Token![->]>()?;
+        let output = input.parse()?;
+        input.parse::<Token![;]>()?;
+        Ok(Signature { name, arguments, output })
+    }
+}
+
+struct Signatures(Vec<Signature>);
+
+impl Parse for Signatures {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut signatures = vec![];
+        while !input.is_empty() {
+            signatures.push(input.parse()?);
+        }
+        Ok(Signatures(signatures))
+    }
+}
+
+// the list the callback macro is invoked with
+fn shared_signatures() -> Vec<Signature> {
+    let source = std::fs::read_to_string(COMMANDS).expect("Unable to read the shared command list.");
+    let start = source.find("$callback! {").expect("Shared command list not found.") + "$callback! {".len();
+    let mut depth = 1;
+    let end = source[start..].find(|char| {
+        match char {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => ()
+        }
+        depth == 0
+    }).expect("Shared command list isn't closed.");
+
+    syn::parse_str::<Signatures>(&source[start..start + end]).expect("Unable to parse the shared command list.").0
+}
+
+fn backend_commands() -> HashMap<String, syn::Signature> {
+    let mut commands = HashMap::new();
+    for entry in std::fs::read_dir("src").expect("Unable to read src.").flatten() {
+        let Ok(source) = std::fs::read_to_string(entry.path()) else {
+            continue;
+        };
+        let file = syn::parse_file(&source)
+            .unwrap_or_else(|error| panic!("Unable to parse {}: {error}", entry.path().display()));
+
+        for item in file.items {
+            let syn::Item::Fn(function) = item else {
+                continue;
+            };
+            let is_command = function.attrs.iter()
+                .any(|attribute| attribute.path().segments.last().is_some_and(|segment| segment.ident == "command"));
+            if is_command {
+                commands.insert(function.sig.ident.to_string(), function.sig);
+            }
+        }
+    }
+
+    return commands;
+}
+
+// types are compared by their last path segments, since the ui and the backend import them differently,
+// and &str deserializes the same as String
+struct Normalize;
+
+impl VisitMut for Normalize {
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        if let syn::Type::Reference(reference) = &*ty {
+            if reference.elem.to_token_stream().to_string() == "str" {
+                *ty = syn::parse_quote!(String);
+            }
+        }
+        syn::visit_mut::visit_type_mut(self, ty);
+    }
+
+    fn visit_type_path_mut(&mut self, path: &mut syn::TypePath) {
+        path.qself = None;
+        path.path.leading_colon = None;
+        path.path.segments = path.path.segments.iter().last().cloned().into_iter().collect();
+        syn::visit_mut::visit_type_path_mut(self, path);
+    }
+}
+
+fn normalize(ty: &syn::Type) -> String {
+    let mut ty = ty.clone();
+    Normalize.visit_type_mut(&mut ty);
+    ty.to_token_stream().to_string()
+}
+
+// the T in Result<T, Error>
+fn ok_type(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    let segment = path.path.segments.last().filter(|segment| segment.ident == "Result")?;
+    let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+
+    arguments.args.iter().find_map(|argument| match argument {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None
+    })
+}
+
+fn last_segment(ty: &syn::Type) -> Option<String> {
+    let syn::Type::Path(path) = ty else {
+        return None;
+    };
+    path.path.segments.last().map(|segment| segment.ident.to_string())
+}
+
+// the key the ui macro sends an argument under, and whether it's #[optional]
+fn ui_key(argument: &Argument) -> (String, bool) {
+    let mut key = camel_case(&argument.name.to_string());
+    let mut optional = false;
+    for attribute in &argument.attributes {
+        if attribute.path().is_ident("optional") {
+            optional = true;
+        } else if attribute.path().is_ident("name") {
+            let syn::Meta::NameValue(syn::MetaNameValue {
+                value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(name), .. }), ..
+            }) = &attribute.meta else {
+                panic!("{} is renamed with #[name = \"...\"].", argument.name);
+            };
+            key = name.value();
+        }
+    }
+
+    return (key, optional);
+}
+
+fn compare(shared: &Signature, backend: &syn::Signature) -> Vec<String> {
+    let name = &shared.name;
+    let mut mismatches = vec![];
+
+    // the window, app handle and managed state are passed by tauri rather than the ui
+    let backend_arguments = backend.inputs.iter()
+        .filter_map(|argument| match argument {
+            syn::FnArg::Typed(argument) => Some(argument),
+            syn::FnArg::Receiver(_) => None
+        })
+        .filter(|argument| !matches!(last_segment(&argument.ty).as_deref(), Some("Window" | "AppHandle" | "State")))
+        .map(|argument| {
+            let name = match &*argument.pat {
+                syn::Pat::Ident(pattern) => pattern.ident.to_string(),
+                pattern => pattern.to_token_stream().to_string()
+            };
+            (camel_case(&name), &*argument.ty)
+        })
+        .collect::<Vec<_>>();
+
+    if backend_arguments.len() != shared.arguments.len() {
+        mismatches.push(format!("{name} takes {} arguments in the backend but {} in the shared list",
+            backend_arguments.len(), shared.arguments.len()));
+    }
+    for (argument, (backend_key, backend_type)) in shared.arguments.iter().zip(&backend_arguments) {
+        let (key, optional) = ui_key(argument);
+        if key != *backend_key {
+            mismatches.push(format!("{name} is sent {key} but the backend expects {backend_key}"));
+        }
+        if normalize(&argument.ty) != normalize(backend_type) {
+            mismatches.push(format!("{name}'s {key} is {} in the shared list but {} in the backend",
+                argument.ty.to_token_stream(), backend_type.to_token_stream()));
+        }
+        if optional && last_segment(backend_type).as_deref() != Some("Option") {
+            mismatches.push(format!("{name}'s {key} is #[optional] but not an Option in the backend"));
+        }
+    }
+
+    // streams are emitted as events, so the command itself returns nothing to compare
+    if matches!(shared.output, syn::Type::ImplTrait(_)) {
+        return mismatches;
+    }
+    let output = match &backend.output {
+        syn::ReturnType::Type(_, output) => output.as_ref().clone(),
+        syn::ReturnType::Default => syn::parse_quote!(())
+    };
+    let output = ok_type(&output).unwrap_or(&output);
+    if normalize(&shared.output) != normalize(output) {
+        mismatches.push(format!("{name} returns {} in the shared list but {} in the backend",
+            shared.output.to_token_stream(), output.to_token_stream()));
+    }
+
+    return mismatches;
+}
+
+// fails the build when a command in the shared list no longer matches its #[tauri::command], rather than the
+// ui's invoke failing at runtime
+fn check_commands() {
+    println!("cargo:rerun-if-changed={COMMANDS}");
+    println!("cargo:rerun-if-changed=src");
+
+    let backend = backend_commands();
+    let mut mismatches = vec![];
+    for shared in shared_signatures() {
+        match backend.get(&shared.name.to_string()) {
+            Some(signature) => mismatches.extend(compare(&shared, signature)),
+            None => mismatches.push(format!("{} has no #[tauri::command]", shared.name))
+        }
+    }
+
+    if !mismatches.is_empty() {
+        panic!("The shared command list doesn't match the backend:\n{}", mismatches.join("\n"));
+    }
+}
+
 fn main() {
+    check_commands();
     tauri_build::build()
 }
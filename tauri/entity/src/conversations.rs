@@ -2,7 +2,7 @@
 
 use sea_orm::entity::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "conversations")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -12,6 +12,23 @@ pub struct Model {
     pub last_updated: i64,
     #[sea_orm(unique)]
     pub first_exchange: i32,
+    #[sea_orm(column_type = "Binary(BlobSize::Blob(None))", nullable)]
+    pub continued_from: Option<Vec<u8>>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub carry_over_summary: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub settings: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub model: Option<String>,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<i32>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub system_prompt: Option<String>,
+    pub folder: Option<i32>,
+    pub pinned: bool,
+    pub deleted_at: Option<i64>,
+    pub archived: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
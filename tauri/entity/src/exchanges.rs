@@ -2,7 +2,7 @@
 
 use sea_orm::entity::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "exchanges")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -10,7 +10,15 @@ pub struct Model {
     pub key: i32,
     pub user_message: String,
     pub assistant_message: String,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub metadata: Option<String>,
     pub conversation: i32,
+    #[sea_orm(column_type = "Double", nullable)]
+    pub cost: Option<f64>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub model: Option<String>,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub provider: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
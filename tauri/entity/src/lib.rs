@@ -2,5 +2,13 @@
 
 pub mod prelude;
 
+pub mod conversation_tags;
 pub mod conversations;
+pub mod drafts;
+pub mod edit_log;
 pub mod exchanges;
+pub mod folders;
+pub mod secrets;
+pub mod summaries;
+pub mod tags;
+pub mod usage;
@@ -1,4 +1,12 @@
 //! `SeaORM` Entity. Generated by sea-orm-codegen 0.12.15
 
+pub use super::conversation_tags::Entity as ConversationTags;
 pub use super::conversations::Entity as Conversations;
+pub use super::drafts::Entity as Drafts;
+pub use super::edit_log::Entity as EditLog;
 pub use super::exchanges::Entity as Exchanges;
+pub use super::folders::Entity as Folders;
+pub use super::secrets::Entity as Secrets;
+pub use super::summaries::Entity as Summaries;
+pub use super::tags::Entity as Tags;
+pub use super::usage::Entity as Usage;
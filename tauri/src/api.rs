@@ -0,0 +1,58 @@
+use anyhow::{bail, Context, Result};
+use common::{to_serde_err, ConversationRecord, ConversationSummary};
+use serde_error::Error;
+use crate::{_load_conversation, _load_conversations, _load_exchanges};
+
+// read-only commands for external tools, returning common's stable record types rather than the ui's. they're also
+// run from the command line, e.g. `llm-playground conversations-since 2024-08-01T00:00:00Z`, printing json
+
+async fn _get_conversation_json(conversation_uuid: uuid::Uuid) -> Result<ConversationRecord> {
+    let conversation = _load_conversation(conversation_uuid).await?;
+    let exchanges = _load_exchanges(conversation_uuid).await?
+        .into_iter()
+        .map(|(_, exchange)| exchange)
+        .collect();
+    return Ok(ConversationRecord::new(&conversation, exchanges));
+}
+
+#[tauri::command]
+pub async fn get_conversation_json(conversation_uuid: uuid::Uuid) -> Result<ConversationRecord, Error> {
+    _get_conversation_json(conversation_uuid).await.map_err(to_serde_err)
+}
+
+// most recently updated first, since is inclusive
+async fn _list_conversations_since(since: chrono::DateTime<chrono::Utc>) -> Result<Vec<ConversationSummary>> {
+    let summaries = _load_conversations().await?
+        .iter()
+        .filter(|conversation| conversation.last_updated >= since)
+        .map(ConversationSummary::from)
+        .collect();
+    return Ok(summaries);
+}
+
+#[tauri::command]
+pub async fn list_conversations_since(
+    since: chrono::DateTime<chrono::Utc>
+) -> Result<Vec<ConversationSummary>, Error> {
+    _list_conversations_since(since).await.map_err(to_serde_err)
+}
+
+const USAGE: &str = "Usage: llm-playground conversation <uuid> | conversations-since <RFC 3339 time>";
+
+// None when the arguments aren't a command, e.g. when the app is launched normally
+pub async fn run_command(args: &[String]) -> Result<Option<String>> {
+    let output = match args {
+        [command, uuid] if command == "conversation" => {
+            let conversation_uuid = uuid.parse().context("Invalid conversation uuid")?;
+            serde_json::to_string_pretty(&_get_conversation_json(conversation_uuid).await?)?
+        },
+        [command, since] if command == "conversations-since" => {
+            let since = chrono::DateTime::parse_from_rfc3339(since).context("Invalid RFC 3339 time")?;
+            serde_json::to_string_pretty(&_list_conversations_since(since.with_timezone(&chrono::Utc)).await?)?
+        },
+        [command, ..] if command == "conversation" || command == "conversations-since" => bail!(USAGE),
+        _ => return Ok(None)
+    };
+
+    return Ok(Some(output));
+}
@@ -0,0 +1,90 @@
+use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use common::{to_serde_err, ConversationArchive, ExportFilter, ExportedConversation, ImportSummary, ARCHIVE_VERSION};
+use sea_orm::DatabaseTransaction;
+use serde_error::Error;
+use tauri::api::dialog::blocking::FileDialogBuilder;
+use crate::{conversations, initiate_transaction};
+use crate::export::export_conversations;
+
+// returns the path written to, None if the dialog is canceled
+async fn _export_all() -> Result<Option<PathBuf>> {
+    let archive = ConversationArchive {
+        version: ARCHIVE_VERSION,
+        exported_at: chrono::Utc::now(),
+        conversations: export_conversations(&ExportFilter::default()).await?
+    };
+
+    let file_name = format!("llm-playground-archive-{}.json", chrono::Local::now().format("%Y-%m-%d"));
+    // the dialog blocks
+    let path = tokio::task::spawn_blocking(move || FileDialogBuilder::new()
+        .set_file_name(&file_name)
+        .add_filter("Archive", &["json"])
+        .save_file()).await?;
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    tokio::fs::write(&path, serde_json::to_string_pretty(&archive)?).await.context("Error writing archive")?;
+    return Ok(Some(path));
+}
+
+#[tauri::command]
+pub async fn export_all() -> Result<Option<String>, Error> {
+    _export_all().await
+        .map(|path| path.map(|path| path.display().to_string()))
+        .map_err(to_serde_err)
+}
+
+// conversations already in the history are skipped, so importing the same file twice is harmless.
+// the caller commits, all or nothing so a bad conversation doesn't leave half a file imported
+pub async fn import_conversations(
+    txn: &DatabaseTransaction,
+    exported_conversations: Vec<ExportedConversation>
+) -> Result<ImportSummary> {
+    let mut summary = ImportSummary::default();
+    for exported_conversation in exported_conversations {
+        let original_uuid = exported_conversation.conversation.uuid;
+        if let Ok(exchanges) = conversations::load_exchanges(txn, original_uuid).await {
+            if exchanges == exported_conversation.exchanges {
+                summary.skipped += 1;
+                continue;
+            }
+        }
+
+        let conversation_uuid = conversations::import_conversation(txn, exported_conversation).await?;
+        summary.imported += 1;
+        if conversation_uuid != original_uuid {
+            summary.reassigned += 1;
+        }
+    }
+
+    return Ok(summary);
+}
+
+// None if the dialog is canceled
+async fn _import_all() -> Result<Option<ImportSummary>> {
+    let path = tokio::task::spawn_blocking(|| FileDialogBuilder::new()
+        .add_filter("Archive", &["json"])
+        .pick_file()).await?;
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let archive = tokio::fs::read_to_string(&path).await.context("Error reading archive")?;
+    let archive = serde_json::from_str::<ConversationArchive>(&archive).context("Not a conversation archive")?;
+    if archive.version > ARCHIVE_VERSION {
+        bail!("The archive is from a newer version of the app.");
+    }
+
+    let txn = initiate_transaction().await?;
+    let summary = import_conversations(&txn, archive.conversations).await?;
+    txn.commit().await?;
+
+    return Ok(Some(summary));
+}
+
+#[tauri::command]
+pub async fn import_all() -> Result<Option<ImportSummary>, Error> {
+    _import_all().await.map_err(to_serde_err)
+}
@@ -0,0 +1,82 @@
+use std::path::Path;
+use anyhow::{bail, Context, Result};
+use common::{to_serde_err, Attachment};
+use serde_error::Error;
+use tauri::api::dialog::blocking::FileDialogBuilder;
+
+const EXTENSIONS: [&str; 3] = ["pdf", "txt", "md"];
+// chunks break at paragraphs where possible, the total is capped so one file can't fill the context window
+const CHUNK_CHARS: usize = 8000;
+const MAX_CHUNKS: usize = 12;
+
+fn extract_text(path: &Path) -> Result<String> {
+    let extension = path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    let bytes = std::fs::read(path).with_context(|| format!("Error reading {}", path.display()))?;
+    return match extension.as_str() {
+        "pdf" => pdf_extract::extract_text_from_mem(&bytes)
+            .with_context(|| format!("Error extracting text from {}", path.display())),
+        "txt" | "md" => Ok(String::from_utf8_lossy(&bytes).into_owned()),
+        _ => bail!("Only {} files can be attached.", EXTENSIONS.join(", "))
+    };
+}
+
+// splits between paragraphs, and within a paragraph only when it's longer than a chunk by itself
+fn chunk_text(text: &str) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut chunk = String::new();
+    for paragraph in text.split("\n\n").map(str::trim).filter(|paragraph| !paragraph.is_empty()) {
+        if !chunk.is_empty() && chunk.chars().count() + paragraph.chars().count() + 2 > CHUNK_CHARS {
+            chunks.push(std::mem::take(&mut chunk));
+        }
+
+        let mut characters = paragraph.chars().peekable();
+        while characters.peek().is_some() {
+            if !chunk.is_empty() {
+                chunk += "\n\n";
+            }
+            let room = CHUNK_CHARS.saturating_sub(chunk.chars().count()).max(1);
+            chunk.extend(characters.by_ref().take(room));
+            if characters.peek().is_some() {
+                chunks.push(std::mem::take(&mut chunk));
+            }
+        }
+    }
+    if !chunk.is_empty() {
+        chunks.push(chunk);
+    }
+
+    return chunks;
+}
+
+fn attachment(path: &Path) -> Result<Attachment> {
+    let name = path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .context("Invalid file name")?;
+    let mut chunks = chunk_text(&extract_text(path)?);
+    if chunks.is_empty() {
+        bail!("No text found in {name}.");
+    }
+
+    let truncated = chunks.len() > MAX_CHUNKS;
+    chunks.truncate(MAX_CHUNKS);
+    return Ok(Attachment { name, chunks, truncated });
+}
+
+async fn _attach_files() -> Result<Vec<Attachment>> {
+    // the dialog and pdf extraction both block
+    tokio::task::spawn_blocking(|| -> Result<Vec<Attachment>> {
+        let Some(paths) = FileDialogBuilder::new().add_filter("Documents", &EXTENSIONS).pick_files() else {
+            return Ok(vec![]);
+        };
+        paths.iter().map(|path| attachment(path)).collect()
+    }).await?
+}
+
+// asks for files to attach and returns their text, empty if the dialog is canceled
+#[tauri::command]
+pub async fn attach_files() -> Result<Vec<Attachment>, Error> {
+    _attach_files().await.map_err(to_serde_err)
+}
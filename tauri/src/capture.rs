@@ -0,0 +1,82 @@
+use std::{io::Write, path::PathBuf};
+use anyhow::{Context, Result};
+use common::{to_serde_err, APIKey};
+use serde_error::Error;
+use serde_json::Value;
+use crate::config_dir;
+
+// older captures are removed so that leaving capture on doesn't fill the disk
+const MAX_CAPTURES: usize = 20;
+const RESPONSE_SEPARATOR: &str = "\n\n--- raw response ---\n";
+
+// a request's body followed by the raw events streamed back, in <config-dir>/captures.
+// headers aren't recorded since that's where the keys are, and the key is redacted anywhere else it shows up
+pub struct Capture {
+    file: std::fs::File,
+    key: String
+}
+
+async fn captures_dir() -> Result<PathBuf> {
+    let captures_dir = config_dir().await?.join("captures");
+    tokio::fs::create_dir_all(&captures_dir).await.context("Error creating the captures directory")?;
+    return Ok(captures_dir);
+}
+
+// the timestamps in the file names sort them oldest first
+async fn capture_files() -> Result<Vec<PathBuf>> {
+    let mut capture_files = vec![];
+    let mut entries = tokio::fs::read_dir(captures_dir().await?).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        capture_files.push(entry.path());
+    }
+
+    capture_files.sort();
+    return Ok(capture_files);
+}
+
+impl Capture {
+    pub async fn start(api_key: &APIKey, body: &str, candidate: usize) -> Result<Self> {
+        let capture_files = capture_files().await?;
+        for capture_file in capture_files.iter().rev().skip(MAX_CAPTURES - 1) {
+            tokio::fs::remove_file(capture_file).await?;
+        }
+
+        let file_name = format!("{}-{candidate}.txt", chrono::Local::now().format("%Y%m%d-%H%M%S%.3f"));
+        let file = std::fs::File::create(captures_dir().await?.join(file_name)).context("Error creating capture")?;
+        let mut capture = Capture { file, key: api_key.key.clone() };
+
+        let body = serde_json::from_str::<Value>(body)
+            .and_then(|body| serde_json::to_string_pretty(&body))
+            .unwrap_or_else(|_| body.to_string());
+        capture.write(&format!("{} request\n{body}{RESPONSE_SEPARATOR}", api_key.provider));
+        return Ok(capture);
+    }
+
+    // failing to capture never fails the request
+    pub fn write(&mut self, text: &str) {
+        let text = match self.key.is_empty() {
+            true => text.to_string(),
+            false => text.replace(&self.key, "[redacted]")
+        };
+        if let Err(error) = self.file.write_all(text.as_bytes()) {
+            tracing::warn!("Error writing capture: {error}");
+        }
+    }
+}
+
+// the body of the most recently captured request, None if nothing has been captured
+async fn _load_last_request() -> Result<Option<String>> {
+    let Some(capture_file) = capture_files().await?.pop() else {
+        return Ok(None);
+    };
+
+    let capture = tokio::fs::read_to_string(capture_file).await.context("Error reading capture")?;
+    let request = capture.split(RESPONSE_SEPARATOR).next().unwrap_or_default();
+    // after the "<provider> request" line
+    return Ok(Some(request.split_once('\n').map(|(_, body)| body).unwrap_or(request).to_string()));
+}
+
+#[tauri::command]
+pub async fn load_last_request() -> Result<Option<String>, Error> {
+    _load_last_request().await.map_err(to_serde_err)
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use anyhow::{Context, Result};
+use common::{to_serde_err, Conversation, Exchange, ExportedConversation, ImportSummary, ResponseMetadata};
+use serde::Deserialize;
+use serde_error::Error;
+use tauri::api::dialog::blocking::FileDialogBuilder;
+use crate::archive::import_conversations;
+use crate::initiate_transaction;
+
+// the parts of conversations.json from a ChatGPT data export that are imported, the rest is ignored
+
+#[derive(Deserialize)]
+struct ChatGPTConversation {
+    conversation_id: Option<String>,
+    update_time: Option<f64>,
+    mapping: HashMap<String, ChatGPTNode>,
+    // the last message of the branch that was shown, earlier edits and regenerations are on other branches
+    current_node: Option<String>
+}
+
+#[derive(Deserialize)]
+struct ChatGPTNode {
+    message: Option<ChatGPTMessage>,
+    parent: Option<String>
+}
+
+#[derive(Deserialize)]
+struct ChatGPTMessage {
+    author: ChatGPTAuthor,
+    content: ChatGPTContent,
+    #[serde(default)]
+    metadata: ChatGPTMetadata
+}
+
+#[derive(Deserialize)]
+struct ChatGPTAuthor {
+    role: String
+}
+
+#[derive(Deserialize)]
+struct ChatGPTContent {
+    content_type: String,
+    #[serde(default)]
+    parts: Vec<serde_json::Value>
+}
+
+#[derive(Default, Deserialize)]
+struct ChatGPTMetadata {
+    model_slug: Option<String>
+}
+
+// only text, images and other attachments are left out
+fn message_text(message: &ChatGPTMessage) -> Option<String> {
+    if message.content.content_type != "text" {
+        return None;
+    }
+
+    let text = message.content.parts.iter().filter_map(serde_json::Value::as_str).collect::<Vec<_>>().join("\n");
+    Some(text).filter(|text| !text.trim().is_empty())
+}
+
+// follows the shown branch back to the root
+fn branch(conversation: &ChatGPTConversation) -> Vec<&ChatGPTMessage> {
+    let mut messages = vec![];
+    let mut node_id = conversation.current_node.as_ref();
+    while let Some(node) = node_id.and_then(|node_id| conversation.mapping.get(node_id)) {
+        messages.extend(&node.message);
+        node_id = node.parent.as_ref();
+        // a malformed export could loop
+        if messages.len() > conversation.mapping.len() {
+            break;
+        }
+    }
+
+    messages.reverse();
+    messages
+}
+
+// consecutive messages from the same side are joined, tool and system messages are dropped
+fn exchanges(messages: Vec<&ChatGPTMessage>) -> Vec<Exchange> {
+    let mut exchanges = Vec::<Exchange>::new();
+    for message in messages {
+        let Some(text) = message_text(message) else {
+            continue;
+        };
+
+        match message.author.role.as_str() {
+            "user" => match exchanges.last_mut() {
+                Some(exchange) if exchange.assistant_message.is_empty() => {
+                    exchange.user_message += &format!("\n\n{text}");
+                },
+                _ => exchanges.push(Exchange { user_message: text, ..Default::default() })
+            },
+            "assistant" => {
+                let Some(exchange) = exchanges.last_mut() else {
+                    continue;
+                };
+                if !exchange.assistant_message.is_empty() {
+                    exchange.assistant_message += "\n\n";
+                }
+                exchange.assistant_message += &text;
+                exchange.metadata = ResponseMetadata {
+                    model: message.metadata.model_slug.clone(),
+                    ..Default::default()
+                };
+                exchange.model = message.metadata.model_slug.clone();
+                exchange.provider = Some("OpenAI".into());
+            },
+            _ => ()
+        }
+    }
+
+    exchanges
+}
+
+fn exported_conversation(conversation: ChatGPTConversation) -> Option<ExportedConversation> {
+    let exchanges = exchanges(branch(&conversation));
+    let title = exchanges.first()?.user_message.clone();
+    let last_updated = conversation.update_time
+        .and_then(|update_time| chrono::DateTime::from_timestamp(update_time as i64, 0))
+        .unwrap_or_else(chrono::Utc::now);
+    // ChatGPT's ids are uuids, so importing the same export again finds the conversations already imported
+    let uuid = conversation.conversation_id
+        .and_then(|conversation_id| uuid::Uuid::parse_str(&conversation_id).ok())
+        .unwrap_or_else(uuid::Uuid::new_v4);
+
+    Some(ExportedConversation {
+        conversation: Conversation { uuid, last_updated, title, ..Default::default() },
+        exchanges: exchanges.into_iter().enumerate().collect()
+    })
+}
+
+// None if the dialog is canceled
+async fn _import_chatgpt() -> Result<Option<ImportSummary>> {
+    let path = tokio::task::spawn_blocking(|| FileDialogBuilder::new()
+        .add_filter("ChatGPT export", &["json"])
+        .pick_file()).await?;
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let chatgpt_conversations = tokio::fs::read_to_string(&path).await.context("Error reading ChatGPT export")?;
+    let chatgpt_conversations = serde_json::from_str::<Vec<ChatGPTConversation>>(&chatgpt_conversations)
+        .context("Not a ChatGPT conversations.json")?;
+    // conversations without any text, e.g. only images, are left out
+    let exported_conversations = chatgpt_conversations.into_iter().filter_map(exported_conversation).collect();
+
+    let txn = initiate_transaction().await?;
+    let summary = import_conversations(&txn, exported_conversations).await?;
+    txn.commit().await?;
+
+    return Ok(Some(summary));
+}
+
+#[tauri::command]
+pub async fn import_chatgpt() -> Result<Option<ImportSummary>, Error> {
+    _import_chatgpt().await.map_err(to_serde_err)
+}
@@ -0,0 +1,567 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, bail, Result};
+use common::{CarryOver, Conversation, ConversationParameters, ConversationSettings, Exchange, ExportedConversation};
+use common::ResponseMetadata;
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseTransaction, EntityTrait, IntoActiveModel};
+use sea_orm::{sea_query::Expr, QueryFilter, QueryOrder, QuerySelect, Set};
+use crate::edit_log::log_edits;
+use crate::tags::add_tag;
+
+// the conversation history's queries, kept apart from tauri so they work with any connection,
+// e.g. an in-memory database. callers own the transactions and commit them
+
+// the parameters' columns are set together, so the model being null means none were recorded
+fn parameters_from_model(conversation: &entity::conversations::Model) -> Option<ConversationParameters> {
+    Some(ConversationParameters {
+        model: conversation.model.clone()?,
+        temperature: conversation.temperature?,
+        max_tokens: conversation.max_tokens?.try_into().ok()?,
+        system_prompt: conversation.system_prompt.clone()?
+    })
+}
+
+fn conversation_from_model(
+    conversation: entity::conversations::Model,
+    first_exchange: Option<entity::exchanges::Model>
+) -> Option<Conversation> {
+    let settings = conversation.settings.as_deref()
+        .and_then(|settings| serde_json::from_str::<ConversationSettings>(settings).ok())
+        .unwrap_or_default();
+
+    Some(Conversation {
+        uuid: uuid::Uuid::from_slice(&conversation.uuid).ok()?,
+        last_updated: chrono::DateTime::from_timestamp(conversation.last_updated, 0)?,
+        parameters: parameters_from_model(&conversation),
+        title: first_exchange?.user_message,
+        workspace: settings.workspace,
+        icon: settings.icon,
+        generated_title: settings.title,
+        cost: None,
+        tags: vec![],
+        folder: conversation.folder,
+        pinned: conversation.pinned,
+        archived: conversation.archived
+    })
+}
+
+// metadata is stored as json so new fields don't need a migration
+pub fn serialize_metadata(metadata: &ResponseMetadata) -> Result<Option<String>> {
+    if metadata.is_empty() {
+        return Ok(None);
+    }
+
+    return Ok(Some(serde_json::to_string(metadata)?));
+}
+
+pub fn deserialize_metadata(metadata: Option<String>) -> ResponseMetadata {
+    metadata.and_then(|metadata| serde_json::from_str(&metadata).ok()).unwrap_or_default()
+}
+
+pub fn exchange_from_model(exchange: entity::exchanges::Model) -> (usize, Exchange) {
+    (exchange.key as usize, Exchange {
+        user_message: exchange.user_message,
+        assistant_message: exchange.assistant_message,
+        metadata: deserialize_metadata(exchange.metadata),
+        cost: exchange.cost,
+        model: exchange.model,
+        provider: exchange.provider
+    })
+}
+
+async fn add_exchanges(
+    conversation_id: i32,
+    exchanges: Vec<(usize, Exchange)>,
+    txn: &DatabaseTransaction
+) -> Result<Vec<entity::exchanges::Model>> {
+    futures::future::join_all(exchanges.into_iter().map(|(key, exchange)| async move {
+        entity::exchanges::ActiveModel {
+            key: Set(key.try_into()?),
+            metadata: Set(serialize_metadata(&exchange.metadata)?),
+            cost: Set(exchange.cost),
+            model: Set(exchange.model),
+            provider: Set(exchange.provider),
+            user_message: Set(exchange.user_message),
+            assistant_message: Set(exchange.assistant_message),
+            conversation: Set(conversation_id),
+            ..Default::default()
+        }.insert(txn).await.map_err(anyhow::Error::from)
+    })).await.into_iter().collect::<Result<Vec<_>, _>>()
+}
+
+// either the archived conversations or the rest
+pub async fn load_conversations(conn: &impl ConnectionTrait, archived: bool) -> Result<Vec<Conversation>> {
+    // summed by the database rather than loading every exchange
+    let costs = entity::exchanges::Entity::find()
+        .select_only()
+        .column(entity::exchanges::Column::Conversation)
+        .column_as(Expr::col(entity::exchanges::Column::Cost).sum(), "cost")
+        .group_by(entity::exchanges::Column::Conversation)
+        .into_tuple::<(i32, Option<f64>)>()
+        .all(conn).await?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    let mut tags = crate::tags::tags_by_conversation(conn).await?;
+
+    // deleted conversations are left out until they're restored or purged
+    let conversations = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::DeletedAt.is_null())
+        .filter(entity::conversations::Column::Archived.eq(archived))
+        .find_also_related(entity::exchanges::Entity)
+        .order_by_desc(entity::conversations::Column::LastUpdated)
+        .all(conn).await?
+        .into_iter()
+        .filter_map(|(conversation, exchange)| {
+            let cost = costs.get(&conversation.id).copied().flatten();
+            let tags = tags.remove(&conversation.id).unwrap_or_default();
+            Some(Conversation { cost, tags, ..conversation_from_model(conversation, exchange)? })
+        })
+        .collect();
+
+    return Ok(conversations);
+}
+
+pub async fn load_conversation(conn: &impl ConnectionTrait, conversation_uuid: uuid::Uuid) -> Result<Conversation> {
+    entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .find_also_related(entity::exchanges::Entity)
+        .one(conn).await?
+        .and_then(|(conversation, exchange)| conversation_from_model(conversation, exchange))
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))
+}
+
+pub async fn load_exchanges(
+    conn: &impl ConnectionTrait,
+    conversation_uuid: uuid::Uuid
+) -> Result<Vec<(usize, Exchange)>> {
+    // find the conversation
+    let conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(conn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+
+    // load all exchanges in the conversation
+    let exchanges = entity::exchanges::Entity::find()
+        .filter(entity::exchanges::Column::Conversation.eq(conversation.id))
+        .order_by_asc(entity::exchanges::Column::Key)
+        .all(conn).await?
+        .into_iter()
+        .map(exchange_from_model)
+        .collect();
+
+    return Ok(exchanges);
+}
+
+// every conversation's exchanges that hasn't been deleted, by the conversation's uuid, in one query
+pub async fn load_all_exchanges(conn: &impl ConnectionTrait) -> Result<HashMap<uuid::Uuid, Vec<(usize, Exchange)>>> {
+    let conversations = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::DeletedAt.is_null())
+        .find_with_related(entity::exchanges::Entity)
+        .order_by_asc(entity::exchanges::Column::Key)
+        .all(conn).await?
+        .into_iter()
+        .filter_map(|(conversation, exchanges)| Some((
+            uuid::Uuid::from_slice(&conversation.uuid).ok()?,
+            exchanges.into_iter().map(exchange_from_model).collect()
+        )))
+        .collect();
+
+    return Ok(conversations);
+}
+
+pub async fn add_conversation(
+    txn: &DatabaseTransaction,
+    mut exchanges: Vec<(usize, Exchange)>,
+    carry_over: Option<CarryOver>,
+    settings: ConversationSettings,
+    parameters: Option<ConversationParameters>
+) -> Result<uuid::Uuid> {
+    if exchanges.is_empty() {
+        bail!("Conversation cannot be set empty.");
+    }
+    let logged_exchanges = exchanges.clone();
+    let (first_exchange_key, first_exchange) = exchanges.remove(0);
+    let first_exchange = entity::exchanges::ActiveModel {
+        key: Set(first_exchange_key.try_into()?),
+        metadata: Set(serialize_metadata(&first_exchange.metadata)?),
+        cost: Set(first_exchange.cost),
+        model: Set(first_exchange.model),
+        provider: Set(first_exchange.provider),
+        user_message: Set(first_exchange.user_message),
+        assistant_message: Set(first_exchange.assistant_message),
+        // the foreign key constraint is deferred until transaction is committed
+        // so this is okay as long as it's changed later
+        conversation: Set(-1),
+        ..Default::default()
+    }.insert(txn).await?;
+
+    let conversation_uuid = uuid::Uuid::new_v4();
+    let conversation = entity::conversations::ActiveModel {
+        uuid: Set(conversation_uuid.into()),
+        last_updated: Set(chrono::Utc::now().timestamp()),
+        first_exchange: Set(first_exchange.id),
+        continued_from: Set(carry_over.as_ref().map(|carry_over| carry_over.conversation_uuid.into())),
+        carry_over_summary: Set(carry_over.map(|carry_over| carry_over.summary)),
+        settings: Set(Some(serde_json::to_string(&settings)?)),
+        model: Set(parameters.as_ref().map(|parameters| parameters.model.clone())),
+        temperature: Set(parameters.as_ref().map(|parameters| parameters.temperature)),
+        max_tokens: Set(parameters.as_ref().map(|parameters| parameters.max_tokens.try_into()).transpose()?),
+        system_prompt: Set(parameters.map(|parameters| parameters.system_prompt)),
+        ..Default::default()
+    }.insert(txn).await?;
+
+    add_exchanges(conversation.id, exchanges, txn).await?;
+    log_edits(conversation.id, vec![], &logged_exchanges, txn).await?;
+    let mut first_exchange = first_exchange.into_active_model();
+    first_exchange.conversation = Set(conversation.id);     // fixed first_exchange foreign key
+    first_exchange.update(txn).await?;
+
+    return Ok(conversation_uuid);
+}
+
+// keeps the exported uuid unless it's taken, returns the uuid it was imported under
+pub async fn import_conversation(
+    txn: &DatabaseTransaction,
+    exported_conversation: ExportedConversation
+) -> Result<uuid::Uuid> {
+    let ExportedConversation { conversation, exchanges } = exported_conversation;
+    let settings = ConversationSettings {
+        workspace: conversation.workspace,
+        icon: conversation.icon,
+        title: conversation.generated_title,
+        ..Default::default()
+    };
+    let new_uuid = add_conversation(txn, exchanges, None, settings, conversation.parameters).await?;
+
+    let is_taken = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation.uuid))
+        .one(txn).await?
+        .is_some();
+    let conversation_uuid = if is_taken { new_uuid } else { conversation.uuid };
+
+    // folders aren't exported since their ids only mean something in the database they came from
+    let mut imported_conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(new_uuid))
+        .one(txn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", new_uuid))?
+        .into_active_model();
+    imported_conversation.uuid = Set(conversation_uuid.into());
+    imported_conversation.last_updated = Set(conversation.last_updated.timestamp());
+    imported_conversation.pinned = Set(conversation.pinned);
+    imported_conversation.archived = Set(conversation.archived);
+    imported_conversation.update(txn).await?;
+
+    for tag in &conversation.tags {
+        add_tag(txn, conversation_uuid, tag).await?;
+    }
+
+    return Ok(conversation_uuid);
+}
+
+// a deep copy with its own exchanges, so editing it leaves the original alone. returns the copy's uuid
+pub async fn duplicate_conversation(txn: &DatabaseTransaction, conversation_uuid: uuid::Uuid) -> Result<uuid::Uuid> {
+    let model = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(txn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+    let conversation = load_conversation(txn, conversation_uuid).await?;
+
+    let mut settings = model.settings.as_deref()
+        .and_then(|settings| serde_json::from_str::<ConversationSettings>(settings).ok())
+        .unwrap_or_default();
+    settings.title = Some(format!("Copy of {}", conversation.generated_title.as_ref().unwrap_or(&conversation.title)));
+    let carry_over = match (model.continued_from, model.carry_over_summary) {
+        (Some(continued_from), Some(summary)) =>
+            Some(CarryOver { conversation_uuid: uuid::Uuid::from_slice(&continued_from)?, summary }),
+        _ => None
+    };
+
+    let exchanges = load_exchanges(txn, conversation_uuid).await?;
+    let copy_uuid = add_conversation(txn, exchanges, carry_over, settings, conversation.parameters).await?;
+
+    let mut copy = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(copy_uuid))
+        .one(txn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", copy_uuid))?
+        .into_active_model();
+    copy.folder = Set(conversation.folder);
+    copy.update(txn).await?;
+
+    let tags = crate::tags::tags_by_conversation(txn).await?.remove(&model.id).unwrap_or_default();
+    for tag in tags {
+        add_tag(txn, copy_uuid, &tag).await?;
+    }
+
+    return Ok(copy_uuid);
+}
+
+// only marks the conversation deleted, it's removed by purge_deleted once the retention period is over
+pub async fn delete_conversation(conn: &impl ConnectionTrait, conversation_uuid: uuid::Uuid) -> Result<()> {
+    set_deleted_at(conn, conversation_uuid, Some(chrono::Utc::now().timestamp())).await
+}
+
+pub async fn restore_conversation(conn: &impl ConnectionTrait, conversation_uuid: uuid::Uuid) -> Result<()> {
+    set_deleted_at(conn, conversation_uuid, None).await
+}
+
+async fn set_deleted_at(
+    conn: &impl ConnectionTrait,
+    conversation_uuid: uuid::Uuid,
+    deleted_at: Option<i64>
+) -> Result<()> {
+    let mut conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(conn).await?
+        .map(entity::conversations::Model::into_active_model)
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+    conversation.deleted_at = Set(deleted_at);
+    conversation.update(conn).await?;
+
+    return Ok(());
+}
+
+// their exchanges, edit logs and tags are deleted with them
+pub async fn purge_deleted(conn: &impl ConnectionTrait, retention_days: u32) -> Result<()> {
+    let cutoff = chrono::Utc::now().timestamp() - i64::from(retention_days) * 24 * 60 * 60;
+    entity::conversations::Entity::delete_many()
+        .filter(entity::conversations::Column::DeletedAt.lte(cutoff))
+        .exec(conn).await?;
+    // the nil uuid's draft belongs to the unsaved conversation
+    conn.execute_unprepared("delete from drafts where conversation_uuid != zeroblob(16)
+        and conversation_uuid not in (select uuid from conversations)").await?;
+    conn.execute_unprepared("delete from summaries where conversation_uuid not in (select uuid from conversations)")
+        .await?;
+
+    return Ok(());
+}
+
+pub async fn set_archived(conn: &impl ConnectionTrait, conversation_uuid: uuid::Uuid, archived: bool) -> Result<()> {
+    let mut conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(conn).await?
+        .map(entity::conversations::Model::into_active_model)
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+    conversation.archived = Set(archived);
+    conversation.update(conn).await?;
+
+    return Ok(());
+}
+
+// returns whether the conversation is now pinned
+pub async fn toggle_pin(conn: &impl ConnectionTrait, conversation_uuid: uuid::Uuid) -> Result<bool> {
+    let conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(conn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+
+    let pinned = !conversation.pinned;
+    let mut conversation = conversation.into_active_model();
+    conversation.pinned = Set(pinned);
+    conversation.update(conn).await?;
+
+    return Ok(pinned);
+}
+
+// returns the new uuid if the conversation had to be added again
+pub async fn set_exchanges(
+    txn: &DatabaseTransaction,
+    conversation_uuid: uuid::Uuid,
+    exchanges: Vec<(usize, Exchange)>
+) -> Result<Option<uuid::Uuid>> {
+    let conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(txn).await?;
+    let Some(conversation) = conversation else {
+        // add conversation if doesn't exist (i.e. another window deleted it
+        // when the current window still had it loaded and expected it to exist
+        return Ok(Some(add_conversation(txn, exchanges, None, Default::default(), None).await?));
+    };
+
+    let old_exchanges = entity::exchanges::Entity::find()
+        .filter(entity::exchanges::Column::Conversation.eq(conversation.id))
+        .all(txn).await?;
+
+    log_edits(conversation.id, old_exchanges.iter().cloned().map(exchange_from_model).collect(),
+        &exchanges, txn).await?;
+
+    // rows are matched up by key, so only the exchanges that changed are written and the rest keep their ids
+    let mut old_exchanges = old_exchanges.into_iter()
+        .map(|exchange| (exchange.key, exchange))
+        .collect::<HashMap<_, _>>();
+    let mut first_exchange = None;
+    let mut changed = false;
+    for (key, exchange) in exchanges {
+        let saved_exchange = match old_exchanges.remove(&key.try_into()?) {
+            Some(old_exchange) if exchange_from_model(old_exchange.clone()).1 == exchange => old_exchange,
+            Some(old_exchange) => {
+                changed = true;
+                let mut old_exchange = old_exchange.into_active_model();
+                old_exchange.metadata = Set(serialize_metadata(&exchange.metadata)?);
+                old_exchange.cost = Set(exchange.cost);
+                old_exchange.model = Set(exchange.model);
+                old_exchange.provider = Set(exchange.provider);
+                old_exchange.user_message = Set(exchange.user_message);
+                old_exchange.assistant_message = Set(exchange.assistant_message);
+                old_exchange.update(txn).await?
+            },
+            None => {
+                changed = true;
+                add_exchanges(conversation.id, vec![(key, exchange)], txn).await?.remove(0)
+            }
+        };
+        first_exchange.get_or_insert(saved_exchange.id);
+    }
+    changed |= !old_exchanges.is_empty();
+
+    // like one deleted outright, a conversation edited after another window deleted it is kept
+    let first_exchange = first_exchange.ok_or(anyhow!("Conversation cannot be set empty."))?;
+    if changed || conversation.first_exchange != first_exchange || conversation.deleted_at.is_some() {
+        let mut conversation = conversation.into_active_model();
+        conversation.first_exchange = Set(first_exchange);
+        conversation.last_updated = Set(chrono::Utc::now().timestamp());
+        conversation.deleted_at = Set(None);
+        conversation.update(txn).await?;
+    }
+
+    // removed only after the conversation stops referencing them as its first exchange
+    futures::future::join_all(old_exchanges.into_values()
+        .map(entity::exchanges::Model::into_active_model)
+        .map(|exchange| entity::exchanges::Entity::delete(exchange).exec(txn))
+    ).await.into_iter().collect::<Result<Vec<_>, _>>()?;
+
+    return Ok(None);
+}
+
+// only the title changes, so settings saved from a window in the meantime aren't undone
+pub async fn set_generated_title(
+    conn: &impl ConnectionTrait,
+    conversation_uuid: uuid::Uuid,
+    title: String
+) -> Result<()> {
+    let conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(conn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+    let mut settings = conversation.settings.as_deref()
+        .map(serde_json::from_str::<ConversationSettings>)
+        .transpose()?
+        .unwrap_or_default();
+    settings.title = Some(title);
+
+    let mut conversation = conversation.into_active_model();
+    conversation.settings = Set(Some(serde_json::to_string(&settings)?));
+    conversation.update(conn).await?;
+
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ConnectOptions, Database, DatabaseConnection, TransactionTrait};
+    use super::*;
+
+    async fn memory_database() -> DatabaseConnection {
+        let mut options = ConnectOptions::new("sqlite::memory:");
+        // every connection to :memory: opens a database of its own
+        options.max_connections(1).min_connections(1).sqlx_logging(false);
+        let conn = Database::connect(options).await.unwrap();
+        Migrator::up(&conn, None).await.unwrap();
+        conn
+    }
+
+    fn exchange(user_message: &str, assistant_message: &str) -> Exchange {
+        Exchange {
+            user_message: user_message.into(),
+            assistant_message: assistant_message.into(),
+            ..Default::default()
+        }
+    }
+
+    async fn add(conn: &DatabaseConnection, exchanges: Vec<(usize, Exchange)>) -> uuid::Uuid {
+        let txn = conn.begin().await.unwrap();
+        let conversation_uuid = add_conversation(&txn, exchanges, None, Default::default(), None).await.unwrap();
+        txn.commit().await.unwrap();
+        conversation_uuid
+    }
+
+    #[tokio::test]
+    async fn adds_conversation() {
+        let conn = memory_database().await;
+        let exchanges = vec![(0, exchange("Hello", "Hi")), (1, exchange("How are you?", "Well"))];
+        let conversation_uuid = add(&conn, exchanges.clone()).await;
+
+        let conversation = load_conversation(&conn, conversation_uuid).await.unwrap();
+        assert_eq!(conversation.title, "Hello");
+        assert_eq!(load_exchanges(&conn, conversation_uuid).await.unwrap(), exchanges);
+        assert_eq!(load_conversations(&conn, false).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn renames_conversation() {
+        let conn = memory_database().await;
+        let conversation_uuid = add(&conn, vec![(0, exchange("Hello", "Hi"))]).await;
+
+        set_generated_title(&conn, conversation_uuid, "Greetings".into()).await.unwrap();
+        let conversation = load_conversation(&conn, conversation_uuid).await.unwrap();
+        assert_eq!(conversation.generated_title.as_deref(), Some("Greetings"));
+        assert_eq!(conversation.title, "Hello");
+    }
+
+    #[tokio::test]
+    async fn sets_exchanges() {
+        let conn = memory_database().await;
+        let conversation_uuid = add(&conn, vec![(0, exchange("Hello", "Hi")), (1, exchange("Bye", "Bye"))]).await;
+
+        let exchanges = vec![(0, exchange("Hello", "Hello there")), (2, exchange("What's new?", "Not much"))];
+        let txn = conn.begin().await.unwrap();
+        assert_eq!(set_exchanges(&txn, conversation_uuid, exchanges.clone()).await.unwrap(), None);
+        txn.commit().await.unwrap();
+
+        assert_eq!(load_exchanges(&conn, conversation_uuid).await.unwrap(), exchanges);
+    }
+
+    #[tokio::test]
+    async fn sets_exchanges_of_missing_conversation() {
+        let conn = memory_database().await;
+        let exchanges = vec![(0, exchange("Hello", "Hi"))];
+
+        let txn = conn.begin().await.unwrap();
+        let new_uuid = set_exchanges(&txn, uuid::Uuid::new_v4(), exchanges.clone()).await.unwrap();
+        txn.commit().await.unwrap();
+
+        let new_uuid = new_uuid.expect("the conversation is added again");
+        assert_eq!(load_exchanges(&conn, new_uuid).await.unwrap(), exchanges);
+    }
+
+    #[tokio::test]
+    async fn deletes_conversation() {
+        let conn = memory_database().await;
+        let conversation_uuid = add(&conn, vec![(0, exchange("Hello", "Hi"))]).await;
+
+        delete_conversation(&conn, conversation_uuid).await.unwrap();
+        assert!(load_conversations(&conn, false).await.unwrap().is_empty());
+
+        restore_conversation(&conn, conversation_uuid).await.unwrap();
+        assert_eq!(load_conversations(&conn, false).await.unwrap().len(), 1);
+
+        delete_conversation(&conn, conversation_uuid).await.unwrap();
+        purge_deleted(&conn, 0).await.unwrap();
+        assert!(load_conversation(&conn, conversation_uuid).await.is_err());
+        assert!(entity::exchanges::Entity::find().all(&conn).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn rolls_back_on_error() {
+        let conn = memory_database().await;
+        let conversation_uuid = add(&conn, vec![(0, exchange("Hello", "Hi"))]).await;
+
+        let txn = conn.begin().await.unwrap();
+        add_conversation(&txn, vec![(0, exchange("Another", "One"))], None, Default::default(), None).await.unwrap();
+        set_exchanges(&txn, conversation_uuid, vec![(0, exchange("Hello", "Edited"))]).await.unwrap();
+        assert!(set_exchanges(&txn, conversation_uuid, vec![]).await.is_err());
+        txn.rollback().await.unwrap();
+
+        assert_eq!(load_conversations(&conn, false).await.unwrap().len(), 1);
+        assert_eq!(load_exchanges(&conn, conversation_uuid).await.unwrap(), vec![(0, exchange("Hello", "Hi"))]);
+    }
+}
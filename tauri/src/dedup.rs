@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+use anyhow::{bail, Result};
+use common::{to_serde_err, Conversation, Exchange};
+use serde_error::Error;
+use crate::{_load_conversations, conversations, database, initiate_transaction, purge_deleted_conversations};
+use crate::summaries;
+
+// conversations with word sets at least this similar are considered duplicates
+const SIMILARITY_THRESHOLD: f64 = 0.8;
+
+fn words(exchanges: &[(usize, Exchange)]) -> HashSet<String> {
+    exchanges.iter()
+        .flat_map(|(_, exchange)| [&exchange.user_message, &exchange.assistant_message])
+        .flat_map(|message| message.split_whitespace())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+// jaccard similarity of the two conversations' words
+fn similarity(words: &HashSet<String>, other_words: &HashSet<String>) -> f64 {
+    let union = words.union(other_words).count();
+    if union == 0 {
+        return 1.0;
+    }
+
+    return words.intersection(other_words).count() as f64 / union as f64;
+}
+
+// conversations with the same first prompt are clustered by how similar their words are
+async fn _find_duplicate_conversations() -> Result<Vec<Vec<Conversation>>> {
+    let mut exchanges = conversations::load_all_exchanges(&database()?).await?;
+
+    // each cluster is compared by the first prompt and words of its first (most recent) conversation
+    let mut clusters = Vec::<(String, HashSet<String>, Vec<Conversation>)>::new();
+    for conversation in _load_conversations().await? {
+        let Some(conversation_exchanges) = exchanges.remove(&conversation.uuid) else { continue };
+        let Some((_, first_exchange)) = conversation_exchanges.first() else { continue };
+        let first_prompt = first_exchange.user_message.trim().to_string();
+        let conversation_words = words(&conversation_exchanges);
+        let cluster = clusters.iter_mut().find(|(cluster_prompt, cluster_words, _)|
+            *cluster_prompt == first_prompt && similarity(cluster_words, &conversation_words) >= SIMILARITY_THRESHOLD);
+        match cluster {
+            Some((_, _, cluster)) => cluster.push(conversation),
+            None => clusters.push((first_prompt, conversation_words, vec![conversation]))
+        }
+    }
+
+    return Ok(clusters.into_iter()
+        .map(|(_, _, cluster)| cluster)
+        .filter(|cluster| cluster.len() > 1)
+        .collect());
+}
+
+#[tauri::command]
+pub async fn find_duplicate_conversations() -> Result<Vec<Vec<Conversation>>, Error> {
+    _find_duplicate_conversations().await.map_err(to_serde_err)
+}
+
+// append the exchanges missing from the kept conversation, then delete the rest
+async fn _merge_conversations(keep_uuid: uuid::Uuid, merge_uuids: Vec<uuid::Uuid>) -> Result<()> {
+    if merge_uuids.contains(&keep_uuid) {
+        bail!("Cannot merge a conversation into itself.");
+    }
+
+    // all or nothing, and read in the same transaction, so a failure or another write can't leave the exchanges
+    // in both conversations or lose any
+    let txn = initiate_transaction().await?;
+    let mut exchanges = conversations::load_exchanges(&txn, keep_uuid).await?;
+    for &merge_uuid in &merge_uuids {
+        for (_, exchange) in conversations::load_exchanges(&txn, merge_uuid).await? {
+            let is_duplicate = exchanges.iter().any(|(_, kept_exchange)|
+                kept_exchange.user_message == exchange.user_message
+                    && kept_exchange.assistant_message == exchange.assistant_message);
+            if !is_duplicate {
+                let key = exchanges.iter().map(|(key, _)| key + 1).max().unwrap_or(0);
+                exchanges.push((key, exchange));
+            }
+        }
+    }
+
+    let new_uuid = conversations::set_exchanges(&txn, keep_uuid, exchanges).await?;
+    for merge_uuid in merge_uuids {
+        conversations::delete_conversation(&txn, merge_uuid).await?;
+    }
+    txn.commit().await?;
+
+    summaries::schedule(new_uuid.unwrap_or(keep_uuid));
+    return purge_deleted_conversations().await;
+}
+
+#[tauri::command]
+pub async fn merge_conversations(keep_uuid: uuid::Uuid, merge_uuids: Vec<uuid::Uuid>) -> Result<(), Error> {
+    _merge_conversations(keep_uuid, merge_uuids).await.map_err(to_serde_err)
+}
@@ -0,0 +1,46 @@
+use anyhow::Result;
+use common::to_serde_err;
+use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+use serde_error::Error;
+use crate::{database, initiate_window_transaction};
+
+// None is the conversation that hasn't been saved yet, kept under the nil uuid
+fn draft_key(conversation_uuid: Option<uuid::Uuid>) -> Vec<u8> {
+    conversation_uuid.unwrap_or_default().as_bytes().to_vec()
+}
+
+async fn _load_draft(conversation_uuid: Option<uuid::Uuid>) -> Result<String> {
+    let draft = entity::drafts::Entity::find_by_id(draft_key(conversation_uuid))
+        .one(&database()?)
+        .await?;
+    return Ok(draft.map(|draft| draft.prompt).unwrap_or_default());
+}
+
+#[tauri::command]
+pub async fn load_draft(conversation_uuid: Option<uuid::Uuid>) -> Result<String, Error> {
+    _load_draft(conversation_uuid).await.map_err(to_serde_err)
+}
+
+// an empty draft is removed
+async fn _save_draft(window: &str, conversation_uuid: Option<uuid::Uuid>, prompt: String) -> Result<()> {
+    let txn = initiate_window_transaction(Some(window)).await?;
+    entity::drafts::Entity::delete_by_id(draft_key(conversation_uuid)).exec(&txn).await?;
+    if !prompt.trim().is_empty() {
+        entity::drafts::ActiveModel {
+            conversation_uuid: Set(draft_key(conversation_uuid)),
+            prompt: Set(prompt)
+        }.insert(&txn).await?;
+    }
+    txn.commit().await?;
+
+    return Ok(());
+}
+
+#[tauri::command]
+pub async fn save_draft(
+    window: tauri::Window,
+    conversation_uuid: Option<uuid::Uuid>,
+    prompt: String
+) -> Result<(), Error> {
+    _save_draft(window.label(), conversation_uuid, prompt).await.map_err(to_serde_err)
+}
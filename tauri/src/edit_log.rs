@@ -0,0 +1,121 @@
+use std::{collections::BTreeMap, str::FromStr};
+use anyhow::{anyhow, Result};
+use common::{to_serde_err, Edit, EditLogEntry, Exchange};
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use serde_error::Error;
+use crate::conversations::{deserialize_metadata, serialize_metadata};
+use crate::{database, _load_exchanges, _set_exchanges};
+
+// append the differences between the old and new exchanges to the conversation's edit log
+pub async fn log_edits(
+    conversation_id: i32,
+    old_exchanges: Vec<(usize, Exchange)>,
+    new_exchanges: &[(usize, Exchange)],
+    txn: &sea_orm::DatabaseTransaction
+) -> Result<()> {
+    let old_exchanges = old_exchanges.into_iter().collect::<BTreeMap<_, _>>();
+    let new_keys = new_exchanges.iter().map(|(key, _)| *key).collect::<Vec<_>>();
+
+    let mut edits = vec![];
+    for (key, exchange) in new_exchanges {
+        match old_exchanges.get(key) {
+            // the cost, model and provider are filled in after the response, not edited
+            Some(old_exchange) if old_exchange.user_message == exchange.user_message
+                && old_exchange.assistant_message == exchange.assistant_message
+                && old_exchange.metadata == exchange.metadata => continue,
+            Some(_) => edits.push((Edit::Edited, *key, exchange.clone())),
+            None => edits.push((Edit::Added, *key, exchange.clone()))
+        }
+    }
+    for (key, exchange) in old_exchanges {
+        if !new_keys.contains(&key) {
+            edits.push((Edit::Deleted, key, exchange));
+        }
+    }
+
+    let timestamp = chrono::Utc::now().timestamp();
+    for (edit, key, exchange) in edits {
+        entity::edit_log::ActiveModel {
+            conversation: Set(conversation_id),
+            timestamp: Set(timestamp),
+            edit: Set(edit.to_string()),
+            key: Set(key.try_into()?),
+            metadata: Set(serialize_metadata(&exchange.metadata)?),
+            user_message: Set(exchange.user_message),
+            assistant_message: Set(exchange.assistant_message),
+            cost: Set(exchange.cost),
+            model: Set(exchange.model),
+            provider: Set(exchange.provider),
+            ..Default::default()
+        }.insert(txn).await?;
+    }
+
+    return Ok(());
+}
+
+async fn _load_edit_log(conversation_uuid: uuid::Uuid) -> Result<Vec<EditLogEntry>> {
+    let conn = &database()?;
+
+    let conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(conn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+
+    entity::edit_log::Entity::find()
+        .filter(entity::edit_log::Column::Conversation.eq(conversation.id))
+        .order_by_asc(entity::edit_log::Column::Id)
+        .all(conn).await?
+        .into_iter()
+        .map(|entry| Ok(EditLogEntry {
+            id: entry.id,
+            timestamp: chrono::DateTime::from_timestamp(entry.timestamp, 0)
+                .ok_or(anyhow!("Invalid timestamp in edit log."))?,
+            edit: Edit::from_str(&entry.edit)?,
+            key: entry.key as usize,
+            exchange: Exchange {
+                user_message: entry.user_message,
+                assistant_message: entry.assistant_message,
+                metadata: deserialize_metadata(entry.metadata),
+                cost: entry.cost,
+                model: entry.model,
+                provider: entry.provider
+            }
+        }))
+        .collect()
+}
+
+#[tauri::command]
+pub async fn load_edit_log(conversation_uuid: uuid::Uuid) -> Result<Vec<EditLogEntry>, Error> {
+    _load_edit_log(conversation_uuid).await.map_err(to_serde_err)
+}
+
+// set the conversation to its state right after the given edit, which is itself logged
+async fn _restore_edit(conversation_uuid: uuid::Uuid, edit_id: i32) -> Result<Option<uuid::Uuid>> {
+    let mut exchanges = BTreeMap::new();
+    for entry in _load_edit_log(conversation_uuid).await? {
+        if entry.id > edit_id {
+            break;
+        }
+
+        match entry.edit {
+            Edit::Added | Edit::Edited => exchanges.insert(entry.key, entry.exchange),
+            Edit::Deleted => exchanges.remove(&entry.key)
+        };
+    }
+
+    // entries logged before the cost, model and provider were recorded keep the conversation's current ones
+    for (key, exchange) in _load_exchanges(conversation_uuid).await? {
+        if let Some(restored) = exchanges.get_mut(&key) {
+            restored.cost = restored.cost.or(exchange.cost);
+            restored.model = restored.model.take().or(exchange.model);
+            restored.provider = restored.provider.take().or(exchange.provider);
+        }
+    }
+
+    _set_exchanges(None, conversation_uuid, exchanges.into_iter().collect()).await
+}
+
+#[tauri::command]
+pub async fn restore_edit(conversation_uuid: uuid::Uuid, edit_id: i32) -> Result<Option<uuid::Uuid>, Error> {
+    _restore_edit(conversation_uuid, edit_id).await.map_err(to_serde_err)
+}
@@ -0,0 +1,243 @@
+use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use common::{to_serde_err, ConversationFormat, ExportFilter, ExportLayout, ExportSchedule, ExportedConversation};
+use serde_error::Error;
+use tauri::api::dialog::blocking::FileDialogBuilder;
+use crate::{_load_archived_conversations, _load_conversation, _load_conversations, _load_exchanges};
+use crate::{config_dir, load_config};
+
+// archived conversations are exported too
+pub async fn export_conversations(filter: &ExportFilter) -> Result<Vec<ExportedConversation>> {
+    let mut exported_conversations = vec![];
+    for conversation in _load_conversations().await?.into_iter().chain(_load_archived_conversations().await?) {
+        let exchanges = _load_exchanges(conversation.uuid).await?;
+        if !filter.matches(&conversation, &exchanges) {
+            continue;
+        }
+
+        exported_conversations.push(ExportedConversation { conversation, exchanges });
+    }
+
+    return Ok(exported_conversations);
+}
+
+pub fn expand_home(directory: &str) -> Result<PathBuf> {
+    match directory.strip_prefix("~/") {
+        Some(directory) => Ok(dirs::home_dir().context("Unable to find the home directory")?.join(directory)),
+        None => Ok(PathBuf::from(directory))
+    }
+}
+
+// e.g. 2024-08-01-how-do-i-center-a-div-1a2b3c4d.json, the uuid prefix keeps names unique
+fn conversation_file_name(exported_conversation: &ExportedConversation, extension: &str) -> String {
+    let conversation = &exported_conversation.conversation;
+    let date = conversation.last_updated.with_timezone(&chrono::Local).format("%Y-%m-%d");
+    let title = conversation.title.chars()
+        .map(|char| if char.is_alphanumeric() { char.to_ascii_lowercase() } else { '-' })
+        .collect::<String>()
+        .split('-')
+        .filter(|word| !word.is_empty())
+        .take(8)
+        .collect::<Vec<_>>()
+        .join("-");
+    let uuid = conversation.uuid.simple().to_string();
+    format!("{date}-{title}-{}.{extension}", &uuid[..8])
+}
+
+// the conversations of a combined markdown export are separated by a horizontal rule
+fn conversations_contents(
+    exported_conversations: &[ExportedConversation],
+    format: ConversationFormat
+) -> Result<String> {
+    Ok(match format {
+        ConversationFormat::Markdown => exported_conversations.iter()
+            .map(conversation_markdown)
+            .collect::<Vec<_>>()
+            .join("\n---\n\n"),
+        ConversationFormat::Json => serde_json::to_string_pretty(exported_conversations)?
+    })
+}
+
+fn conversation_contents(exported_conversation: &ExportedConversation, format: ConversationFormat) -> Result<String> {
+    Ok(match format {
+        ConversationFormat::Markdown => conversation_markdown(exported_conversation),
+        ConversationFormat::Json => serde_json::to_string_pretty(exported_conversation)?
+    })
+}
+
+async fn export_to_directory(
+    directory: &str,
+    exported_conversations: &[ExportedConversation],
+    layout: ExportLayout,
+    format: ConversationFormat
+) -> Result<PathBuf> {
+    if directory.trim().is_empty() {
+        bail!("No export directory set.");
+    }
+
+    let directory = expand_home(directory.trim())?;
+    tokio::fs::create_dir_all(&directory).await.context("Error creating export directory")?;
+
+    let timestamp = chrono::Local::now().format("%Y-%m-%d-%H%M%S");
+    match layout {
+        ExportLayout::Combined => {
+            let path = directory.join(format!("llm-playground-export-{timestamp}.{}", format.extension()));
+            let contents = conversations_contents(exported_conversations, format)?;
+            tokio::fs::write(&path, contents).await.context("Error writing export")?;
+            return Ok(path);
+        },
+        ExportLayout::PerConversation => {
+            let path = directory.join(format!("llm-playground-export-{timestamp}"));
+            tokio::fs::create_dir(&path).await.context("Error creating export directory")?;
+            for exported_conversation in exported_conversations {
+                let contents = conversation_contents(exported_conversation, format)?;
+                let file_name = conversation_file_name(exported_conversation, format.extension());
+                tokio::fs::write(path.join(file_name), contents)
+                    .await
+                    .context("Error writing export")?;
+            }
+            return Ok(path);
+        }
+    }
+}
+
+async fn _export_filtered_conversations(
+    directory: &str,
+    filter: &ExportFilter,
+    layout: ExportLayout
+) -> Result<PathBuf> {
+    let exported_conversations = export_conversations(filter).await?;
+    if exported_conversations.is_empty() {
+        bail!("No conversations match the filter.");
+    }
+
+    export_to_directory(directory, &exported_conversations, layout, ConversationFormat::Json).await
+}
+
+// returns the path of the written file or directory
+#[tauri::command]
+pub async fn export_filtered_conversations(
+    directory: String,
+    filter: ExportFilter,
+    layout: ExportLayout
+) -> Result<String, Error> {
+    _export_filtered_conversations(&directory, &filter, layout).await
+        .map(|path| path.display().to_string())
+        .map_err(to_serde_err)
+}
+
+fn conversation_markdown(exported_conversation: &ExportedConversation) -> String {
+    let ExportedConversation { conversation, exchanges } = exported_conversation;
+    let mut markdown = format!("# {}\n\n", conversation.generated_title.as_ref().unwrap_or(&conversation.title));
+
+    let date = conversation.last_updated.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M");
+    markdown += &format!("- Date: {date}\n");
+    // conversations saved before parameters were recorded fall back to the snapshot that answered
+    let model = conversation.parameters.as_ref()
+        .map(|parameters| parameters.model.clone())
+        .or_else(|| exchanges.first().and_then(|(_, exchange)| exchange.metadata.model.clone()));
+    if let Some(model) = model {
+        markdown += &format!("- Model: {model}\n");
+    }
+    if let Some(parameters) = &conversation.parameters {
+        markdown += &format!("- Temperature: {}\n- Max tokens: {}\n", parameters.temperature, parameters.max_tokens);
+    }
+    if !conversation.tags.is_empty() {
+        markdown += &format!("- Tags: {}\n", conversation.tags.join(", "));
+    }
+
+    let system_prompt = conversation.parameters.as_ref().map_or("", |parameters| parameters.system_prompt.trim());
+    if !system_prompt.is_empty() {
+        markdown += "\n## System prompt\n\n";
+        markdown += system_prompt;
+        markdown += "\n";
+    }
+
+    for (_, exchange) in exchanges {
+        markdown += &format!("\n## User\n\n{}\n\n## Assistant\n\n{}\n",
+            exchange.user_message.trim(), exchange.assistant_message.trim());
+    }
+
+    markdown
+}
+
+// returns the path written to, None if the dialog is canceled
+async fn _export_conversation(conversation_uuid: uuid::Uuid, format: ConversationFormat) -> Result<Option<PathBuf>> {
+    let conversation = _load_conversation(conversation_uuid).await?;
+    let exchanges = _load_exchanges(conversation_uuid).await?;
+    let exported_conversation = ExportedConversation { conversation, exchanges };
+
+    let file_name = conversation_file_name(&exported_conversation, format.extension());
+    // the dialog blocks
+    let path = tokio::task::spawn_blocking(move || FileDialogBuilder::new()
+        .set_file_name(&file_name)
+        .add_filter(format!("{format:?}"), &[format.extension()])
+        .save_file()).await?;
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let contents = conversation_contents(&exported_conversation, format)?;
+    tokio::fs::write(&path, contents).await.context("Error writing export")?;
+
+    return Ok(Some(path));
+}
+
+#[tauri::command]
+pub async fn export_conversation(
+    conversation_uuid: uuid::Uuid,
+    format: ConversationFormat
+) -> Result<Option<String>, Error> {
+    _export_conversation(conversation_uuid, format).await
+        .map(|path| path.map(|path| path.display().to_string()))
+        .map_err(to_serde_err)
+}
+
+// the time of the last scheduled export is kept next to the config so it survives restarts
+async fn last_export_path() -> Result<PathBuf> {
+    Ok(config_dir().await?.join("last_export"))
+}
+
+async fn last_export() -> Option<chrono::DateTime<chrono::Utc>> {
+    let last_export = tokio::fs::read_to_string(last_export_path().await.ok()?).await.ok()?;
+    chrono::DateTime::from_timestamp(last_export.trim().parse().ok()?, 0)
+}
+
+async fn export_if_due(schedule: &ExportSchedule) -> Result<()> {
+    if !schedule.enabled {
+        return Ok(());
+    }
+
+    let last_export = last_export().await;
+    if let Some(last_export) = last_export {
+        if chrono::Utc::now() - last_export < schedule.cadence.interval() {
+            return Ok(());
+        }
+    }
+
+    // taken before loading, so a conversation updated during the export is in the next one too
+    let started = chrono::Utc::now();
+    // the conversations updated since the last export, or all of them the first time
+    let mut exported_conversations = export_conversations(&ExportFilter::default()).await?;
+    exported_conversations.retain(|exported_conversation| last_export
+        .map_or(true, |last_export| exported_conversation.conversation.last_updated >= last_export));
+    if !exported_conversations.is_empty() {
+        export_to_directory(&schedule.directory, &exported_conversations, schedule.layout, schedule.format).await?;
+    }
+    tokio::fs::write(last_export_path().await?, started.timestamp().to_string()).await?;
+
+    return Ok(());
+}
+
+// checks every minute so schedule changes in the config take effect without a restart
+pub async fn run_export_scheduler() {
+    loop {
+        match load_config().await {
+            Ok(config) => export_if_due(&config.export_schedule).await
+                .unwrap_or_else(|error| tracing::error!("Error exporting conversations: {error}")),
+            Err(error) => tracing::error!("Error loading config for export: {error}")
+        }
+
+        tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+    }
+}
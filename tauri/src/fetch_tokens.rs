@@ -1,12 +1,111 @@
+use std::collections::HashMap;
 use anyhow::{anyhow, bail, Context, Result};
-use common::{APIKey, Config, Exchange, Provider, to_serde_err};
+use common::{APIKey, Config, Exchange, Provider, ProviderError, RateLimit, ResponseChunk, ResponseFormat};
+use common::{ContextSummary, ErrorCategory, ResponseMetadata, RetryStatus, StreamError, StreamNotice, Usage};
+use common::to_serde_err;
 use eventsource_stream::{Event, Eventsource};
-use futures::{FutureExt, Stream, StreamExt};
+use futures::{Stream, StreamExt};
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use serde_error::Error;
 use serde_json::{json, Value};
+use crate::capture::Capture;
+use crate::guardrail;
+use crate::images::store_image;
+use crate::limits;
+use crate::secrets;
+use crate::service_accounts;
+use crate::summaries;
+use crate::telemetry::record_error;
+use crate::usage::record_usage;
+
+fn error_message(error: &Value) -> String {
+    error["message"].as_str().unwrap_or("Error with request.").to_string()
+}
+
+// error.type in https://docs.anthropic.com/en/api/errors
+fn parse_anthropic_error(error: &Value) -> ProviderError {
+    let provider = Provider::Anthropic.to_string();
+    let message = error_message(error);
+    match error["type"].as_str().unwrap_or_default() {
+        "overloaded_error" => ProviderError::Overloaded { provider, retry_after: None },
+        "rate_limit_error" => ProviderError::RateLimited { provider, retry_after: None },
+        "invalid_request_error" | "request_too_large" => ProviderError::InvalidRequest { provider, message },
+        "authentication_error" | "permission_error" => ProviderError::Authentication { provider, message },
+        "not_found_error" => ProviderError::NotFound { provider, message },
+        kind => ProviderError::Other { provider, kind: kind.into(), message }
+    }
+}
+
+// error.status in https://ai.google.dev/gemini-api/docs/troubleshooting
+fn parse_google_error(error: &Value) -> ProviderError {
+    let provider = Provider::Google.to_string();
+    let message = error_message(error);
+    match error["status"].as_str().unwrap_or_default() {
+        "UNAVAILABLE" => ProviderError::Overloaded { provider, retry_after: None },
+        "RESOURCE_EXHAUSTED" => ProviderError::RateLimited { provider, retry_after: None },
+        "INVALID_ARGUMENT" | "FAILED_PRECONDITION" => ProviderError::InvalidRequest { provider, message },
+        "UNAUTHENTICATED" | "PERMISSION_DENIED" => ProviderError::Authentication { provider, message },
+        "NOT_FOUND" => ProviderError::NotFound { provider, message },
+        kind => ProviderError::Other { provider, kind: kind.into(), message }
+    }
+}
+
+// parse the error of a response with a non-OK status code
+fn parse_status_error(
+    provider: &Provider,
+    status: reqwest::StatusCode,
+    retry_after: Option<u64>,
+    body: &str
+) -> ProviderError {
+    let response = serde_json::from_str::<Value>(body).unwrap_or_default();
+    let error = match response {
+        // gemini wraps streamed responses in an array
+        Value::Array(responses) => responses.get(0).map(|response| response["error"].clone()).unwrap_or_default(),
+        response => response["error"].clone()
+    };
+
+    let provider_name = provider.to_string();
+    match status.as_u16() {
+        429 => return ProviderError::RateLimited { provider: provider_name, retry_after },
+        503 | 529 => return ProviderError::Overloaded { provider: provider_name, retry_after },
+        _ => ()
+    }
+
+    match provider {
+        Provider::Anthropic if error.is_object() => parse_anthropic_error(&error),
+        Provider::Google | Provider::VertexAI { .. } if error.is_object() => parse_google_error(&error),
+        _ => {
+            let message = error["message"].as_str().unwrap_or(body).to_string();
+            match status.as_u16() {
+                400 | 422 => ProviderError::InvalidRequest { provider: provider_name, message },
+                401 | 403 => ProviderError::Authentication { provider: provider_name, message },
+                404 => ProviderError::NotFound { provider: provider_name, message },
+                _ => ProviderError::Other { provider: provider_name, kind: status.to_string(), message }
+            }
+        }
+    }
+}
+
+// an invalid schema falls back to requiring any json object
+fn parse_schema(schema: &str) -> Value {
+    serde_json::from_str(schema).unwrap_or_else(|_| json!({ "type": "object" }))
+}
+
+// o1, o3-mini, o4-mini, gpt-5, gpt-5-mini, etc. gpt-5-chat doesn't reason
+fn is_openai_reasoning_model(model: &str) -> bool {
+    model.strip_prefix('o').is_some_and(|model| model.starts_with(|char: char| char.is_ascii_digit()))
+        || (model.starts_with("gpt-5") && !model.starts_with("gpt-5-chat"))
+}
+
+// openai itself, rather than a compatible server at another base url
+fn is_openai(provider: &Provider) -> bool {
+    let default_base_url = Provider::default().base_url().map(|base_url| base_url.trim_end_matches('/').to_string());
+    matches!(provider, Provider::OpenAI { base_url }
+        if Some(base_url.trim_end_matches('/')) == default_base_url.as_deref())
+}
 
 fn build_openai_request_body(
+    provider: &Provider,
     config: &Config,
     exchanges: Vec<Exchange>,
     prompt: &str
@@ -32,27 +131,69 @@ fn build_openai_request_body(
         "role": "user",
         "content": prompt
     }));
+    // openai has no prefill, so it's sent as a trailing assistant message. the ui prefixes it to the stored response
+    if !config.prefill.trim().is_empty() {
+        messages.push(json!({
+            "role": "assistant",
+            "content": config.prefill
+        }));
+    }
 
-    return json!({
+    let mut body = json!({
         "model": config.model,
         "max_completion_tokens": config.max_tokens,
-        "temperature": config.temperature,
-        "stream": !config.model.starts_with("o1"),  // TODO: change to true when o1 supports streaming
+        "temperature": config.effective_temperature(),
+        "stream": true,
         "messages": messages
     });
+    // usage is sent in a final chunk without choices. compatible servers may reject the option, groq reports usage
+    // without it
+    if is_openai(provider) {
+        body["stream_options"] = json!({ "include_usage": true });
+    }
+    // reasoning models reject any temperature but the default, and other models reject a reasoning effort
+    if is_openai_reasoning_model(&config.model) {
+        body.as_object_mut().map(|body| body.remove("temperature"));
+        if let Some(reasoning_effort) = config.reasoning_effort {
+            body["reasoning_effort"] = json!(reasoning_effort.to_string());
+        }
+    }
+    if config.deterministic {
+        body["seed"] = json!(config.seed);
+    }
+    match &config.response_format {
+        ResponseFormat::Text => (),
+        ResponseFormat::JsonObject => body["response_format"] = json!({ "type": "json_object" }),
+        ResponseFormat::JsonSchema(schema) => body["response_format"] = json!({
+            "type": "json_schema",
+            "json_schema": { "name": "response", "strict": true, "schema": parse_schema(schema) }
+        })
+    }
+
+    return body;
 }
 
-fn parse_openai_nonstreaming_response(response_text: String) -> Result<String> {
-    let response = serde_json::from_str::<Value>(&response_text)
-        .context("Error parsing response.")?;
+fn parse_openai_metadata(response: &Value) -> ResponseMetadata {
+    // groq reports usage under x_groq instead
+    let usage = match &response["usage"] {
+        Value::Null => &response["x_groq"]["usage"],
+        usage => usage
+    };
 
-    return response["choices"][0]["message"]["content"].as_str()
-        .map(str::to_string)
-        .ok_or(anyhow!("Error parsing response."));
+    ResponseMetadata {
+        model: response["model"].as_str().map(str::to_string),
+        system_fingerprint: response["system_fingerprint"].as_str().map(str::to_string),
+        usage: Usage {
+            prompt_tokens: usage["prompt_tokens"].as_u64(),
+            completion_tokens: usage["completion_tokens"].as_u64()
+        },
+        finish_reason: response["choices"][0]["finish_reason"].as_str().map(str::to_string),
+        ..Default::default()
+    }
 }
 
 // Ok(None) represents response end
-fn parse_openai_response(message: Event) -> Result<Option<String>> {
+fn parse_openai_response(message: Event) -> Result<Option<ResponseChunk>> {
     if message.event == "error" {
         bail!("{}", message.data);
     }
@@ -64,12 +205,34 @@ fn parse_openai_response(message: Event) -> Result<Option<String>> {
     let response = serde_json::from_str::<Value>(&message.data)
         .context("Error parsing response.")?;
 
+    // azure sends content filter results and the usage chunk has no choices
+    if response["choices"].as_array().is_some_and(Vec::is_empty) {
+        return Ok(Some(ResponseChunk { metadata: parse_openai_metadata(&response), ..Default::default() }));
+    }
+
+    // the usage chunk still follows, the stream ends at [DONE]
     if !response["choices"][0]["finish_reason"].is_null() {
-        return Ok(None);
+        let tokens = response["choices"][0]["delta"]["content"].as_str().unwrap_or_default();
+        return Ok(Some(ResponseChunk {
+            tokens: tokens.into(),
+            metadata: parse_openai_metadata(&response),
+            ..Default::default()
+        }));
     }
 
     if let Some(tokens) = response["choices"][0]["delta"]["content"].as_str() {
-        return Ok(Some(tokens.into()));
+        return Ok(Some(ResponseChunk {
+            tokens: tokens.into(),
+            metadata: parse_openai_metadata(&response),
+            ..Default::default()
+        }));
+    // structured outputs stream a refusal in place of the content
+    } else if let Some(refusal) = response["choices"][0]["delta"]["refusal"].as_str() {
+        return Ok(Some(ResponseChunk {
+            tokens: refusal.into(),
+            metadata: ResponseMetadata { refusal: true, ..parse_openai_metadata(&response) },
+            ..Default::default()
+        }));
     } else {
         bail!("Error parsing response.");
     }
@@ -95,33 +258,105 @@ fn build_anthropic_request_body(
             "role": "user",
             "content": prompt
         })))
+        // the response continues from the prefill, which can't end with whitespace
+        .chain(Some(config.prefill.trim_end()).filter(|prefill| !prefill.is_empty()).map(|prefill| json!({
+            "role": "assistant",
+            "content": prefill
+        })))
         .collect::<Vec<Value>>();
 
-    return json!({
+    let system_prompt = format!("{}\n\n{}", config.system_prompt, config.provider_defaults.anthropic_system_suffix);
+
+    let mut body = json!({
         "model": config.model,
         "max_tokens": config.max_tokens,
-        "temperature": config.temperature,
+        "temperature": config.effective_temperature(),
         "stream": true,
-        "system": config.system_prompt,
+        "system": system_prompt.trim(),
         "messages": messages
     });
+
+    // anthropic has no json mode, so a tool taking the response as its input is forced instead
+    let input_schema = match &config.response_format {
+        ResponseFormat::Text => None,
+        ResponseFormat::JsonObject => Some(json!({ "type": "object" })),
+        ResponseFormat::JsonSchema(schema) => Some(parse_schema(schema))
+    };
+    // the budget comes out of max_tokens, so it's added on top to keep room for the response
+    if let Some(thinking_budget) = config.thinking_budget {
+        body["thinking"] = json!({ "type": "enabled", "budget_tokens": thinking_budget });
+        body["max_tokens"] = json!(config.max_tokens.saturating_add(thinking_budget));
+        // extended thinking isn't compatible with temperature changes
+        body.as_object_mut().map(|body| body.remove("temperature"));
+    }
+
+    if let Some(input_schema) = input_schema {
+        body["tools"] = json!([{
+            "name": "respond",
+            "description": "Respond to the user with JSON.",
+            "input_schema": input_schema
+        }]);
+        body["tool_choice"] = json!({ "type": "tool", "name": "respond" });
+    }
+
+    return body;
 }
 
 // Ok(None) represents response end
-fn parse_anthropic_response(message: Event) -> Result<Option<String>> {
+fn parse_anthropic_response(message: Event) -> Result<Option<ResponseChunk>> {
+    let response = serde_json::from_str::<Value>(&message.data)
+        .context("Error parsing response.");
+
     if message.event == "error" {
-        bail!("{}", message.data);
+        match response {
+            Ok(response) if response["error"].is_object() =>
+                return Err(parse_anthropic_error(&response["error"]).into()),
+            _ => bail!("{}", message.data)
+        }
     }
+    let response = response?;
 
-    let response = serde_json::from_str::<Value>(&message.data)
-        .context("Error parsing response.")?;
+    // input tokens are reported when the message starts and output tokens when it ends
+    match message.event.as_str() {
+        "message_start" => return Ok(Some(ResponseChunk {
+            metadata: ResponseMetadata {
+                model: response["message"]["model"].as_str().map(str::to_string),
+                usage: Usage {
+                    prompt_tokens: response["message"]["usage"]["input_tokens"].as_u64(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        })),
+        "message_delta" => return Ok(Some(ResponseChunk {
+            metadata: ResponseMetadata {
+                usage: Usage {
+                    completion_tokens: response["usage"]["output_tokens"].as_u64(),
+                    ..Default::default()
+                },
+                finish_reason: response["delta"]["stop_reason"].as_str().map(str::to_string),
+                ..Default::default()
+            },
+            ..Default::default()
+        })),
+        "content_block_delta" => (),
+        _ => return Ok(Some(ResponseChunk::default()))
+    }
 
-    if message.event != "content_block_delta" {
-        return Ok(Some("".into()));
+    // thinking is shown apart from the response, its signature only matters for multi-turn tool use
+    if let Some(thinking) = response["delta"]["thinking"].as_str() {
+        let metadata = ResponseMetadata { reasoning: thinking.into(), ..Default::default() };
+        return Ok(Some(ResponseChunk { metadata, ..Default::default() }));
+    }
+    if response["delta"]["type"] == "signature_delta" {
+        return Ok(Some(ResponseChunk::default()));
     }
 
-    if let Some(tokens) = response["delta"]["text"].as_str() {
-        return Ok(Some(tokens.into()));
+    // partial_json streams the input of the tool forced for json responses
+    let tokens = response["delta"]["text"].as_str().or(response["delta"]["partial_json"].as_str());
+    if let Some(tokens) = tokens {
+        return Ok(Some(ResponseChunk { tokens: tokens.into(), ..Default::default() }));
     } else {
         bail!("Error parsing response.");        
     }
@@ -147,11 +382,25 @@ fn build_google_request_body(
             "role": "user",
             "parts": [{ "text": prompt }]
         })))
+        .chain(Some(config.prefill.trim_end()).filter(|prefill| !prefill.is_empty()).map(|prefill| json!({
+            "role": "model",
+            "parts": [{ "text": prefill }]
+        })))
         .collect::<Vec<Value>>();
 
-    return json!({
+    let mut tools = vec![];
+    if config.google_tools.code_execution {
+        tools.push(json!({ "code_execution": {} }));
+    }
+    if config.google_tools.search_grounding {
+        tools.push(json!({ "google_search": {} }));
+    }
+
+    let threshold = config.provider_defaults.google_safety_threshold.to_string();
+
+    let mut body = json!({
         "generation_config": {
-            "temperature": config.temperature,
+            "temperature": config.effective_temperature(),
             "max_output_tokens": config.max_tokens
         },
         "system_instruction": {
@@ -160,27 +409,47 @@ fn build_google_request_body(
         "safety_settings": [
             {
                 "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT",
-                "threshold": "BLOCK_NONE"
+                "threshold": threshold
             },
             {
                 "category": "HARM_CATEGORY_HATE_SPEECH",
-                "threshold": "BLOCK_NONE"
+                "threshold": threshold
             },
             {
                 "category": "HARM_CATEGORY_HARASSMENT",
-                "threshold": "BLOCK_NONE"
+                "threshold": threshold
             },
             {
                 "category": "HARM_CATEGORY_DANGEROUS_CONTENT",
-                "threshold": "BLOCK_NONE"
+                "threshold": threshold
             }
         ],
         "contents": messages
     });
+    if !tools.is_empty() {
+        body["tools"] = Value::Array(tools);
+    }
+    if config.deterministic {
+        body["generation_config"]["seed"] = json!(config.seed);
+    }
+    if let Some(thinking_budget) = config.thinking_budget {
+        body["generation_config"]["thinking_config"] =
+            json!({ "thinking_budget": thinking_budget, "include_thoughts": true });
+    }
+    match &config.response_format {
+        ResponseFormat::Text => (),
+        ResponseFormat::JsonObject => body["generation_config"]["response_mime_type"] = json!("application/json"),
+        ResponseFormat::JsonSchema(schema) => {
+            body["generation_config"]["response_mime_type"] = json!("application/json");
+            body["generation_config"]["response_schema"] = parse_schema(schema);
+        }
+    }
+
+    return body;
 }
 
 // Ok(None) represents response end
-fn parse_google_response(message: bytes::Bytes) -> Result<Option<String>> {
+fn parse_google_response(message: bytes::Bytes) -> Result<Option<ResponseChunk>> {
     let message = String::from_utf8(message.into())?;
     let mut message = message.trim();
     if message.starts_with("[") || message.starts_with(",") {
@@ -197,77 +466,235 @@ fn parse_google_response(message: bytes::Bytes) -> Result<Option<String>> {
         .context("Error parsing response.")?;
 
     if !response["error"].is_null() {
-        let error_message = response["error"]["message"].as_str()
-            .unwrap_or("Error with request.");
-        return Err(anyhow!("{error_message}"));
+        return Err(parse_google_error(&response["error"]).into());
     }
     
-    if let Some(tokens) = response["candidates"][0]["content"]["parts"][0]["text"].as_str() {
-        return Ok(Some(tokens.into()));
-    } else {
-        bail!("Error parsing response.");        
+    // usage metadata is cumulative, so the last chunk's counts are the totals
+    let mut metadata = ResponseMetadata {
+        usage: Usage {
+            prompt_tokens: response["usageMetadata"]["promptTokenCount"].as_u64(),
+            completion_tokens: response["usageMetadata"]["candidatesTokenCount"].as_u64()
+        },
+        model: response["modelVersion"].as_str().map(str::to_string),
+        ..Default::default()
+    };
+
+    let candidate = &response["candidates"][0];
+    // a blocked prompt has no candidates, only the feedback says why
+    metadata.finish_reason = candidate["finishReason"].as_str()
+        .or(response["promptFeedback"]["blockReason"].as_str())
+        .map(str::to_string);
+    let Some(parts) = candidate["content"]["parts"].as_array() else {
+        if !metadata.is_empty() {
+            return Ok(Some(ResponseChunk { metadata, ..Default::default() }));
+        }
+        bail!("Error parsing response.");
+    };
+
+    // executed code and its results are rendered as their own blocks
+    let mut tokens = String::new();
+    for part in parts {
+        if let Some(thought) = part["text"].as_str().filter(|_| part["thought"].as_bool() == Some(true)) {
+            metadata.reasoning += thought;
+        } else if let Some(text) = part["text"].as_str() {
+            tokens += text;
+        } else if let Some(data) = part["inlineData"]["data"].as_str() {
+            let mime_type = part["inlineData"]["mimeType"].as_str().unwrap_or("image/png");
+            metadata.images.push(store_image(mime_type, data)?);
+        } else if !part["executableCode"].is_null() {
+            let language = part["executableCode"]["language"].as_str().unwrap_or_default().to_lowercase();
+            let code = part["executableCode"]["code"].as_str().unwrap_or_default();
+            tokens += &format!("\n\n```{language}\n{}\n```\n\n", code.trim_end());
+        } else if !part["codeExecutionResult"].is_null() {
+            let outcome = part["codeExecutionResult"]["outcome"].as_str().unwrap_or("OUTCOME_UNSPECIFIED");
+            let output = part["codeExecutionResult"]["output"].as_str().unwrap_or_default();
+            tokens += &format!("Output ({outcome}):\n```\n{}\n```\n\n", output.trim_end());
+        }
+    }
+
+    // grounding sources are only attached to the last chunk of the response
+    if let Some(sources) = candidate["groundingMetadata"]["groundingChunks"].as_array() {
+        let sources = sources.iter()
+            .filter_map(|source| Some(format!("- {}: {}",
+                source["web"]["title"].as_str()?, source["web"]["uri"].as_str()?)))
+            .collect::<Vec<_>>();
+        if !sources.is_empty() {
+            tokens += &format!("\n\nSources:\n{}", sources.join("\n"));
+        }
+    }
+
+    return Ok(Some(ResponseChunk { tokens, metadata, ..Default::default() }));
+}
+
+fn build_ollama_request_body(
+    config: &Config,
+    exchanges: Vec<Exchange>,
+    prompt: &str
+) -> serde_json::Value {
+    let mut messages = vec![];
+    if !config.system_prompt.is_empty() {
+        messages.push(json!({
+            "role": "system",
+            "content": config.system_prompt
+        }));
+    }
+    for exchange in exchanges {
+        messages.push(json!({
+            "role": "user",
+            "content": exchange.user_message
+        }));
+        messages.push(json!({
+            "role": "assistant",
+            "content": exchange.assistant_message
+        }));
+    }
+    messages.push(json!({
+        "role": "user",
+        "content": prompt
+    }));
+    // ollama continues a trailing assistant message
+    if !config.prefill.trim().is_empty() {
+        messages.push(json!({
+            "role": "assistant",
+            "content": config.prefill
+        }));
+    }
+
+    let mut options = json!({
+        "temperature": config.effective_temperature(),
+        "num_predict": config.max_tokens
+    });
+    if config.deterministic {
+        options["seed"] = json!(config.seed);
+    }
+
+    let mut body = json!({
+        "model": config.model,
+        "stream": true,
+        "options": options,
+        "messages": messages
+    });
+    match &config.response_format {
+        ResponseFormat::Text => (),
+        ResponseFormat::JsonObject => body["format"] = json!("json"),
+        ResponseFormat::JsonSchema(schema) => body["format"] = parse_schema(schema)
     }
+
+    return body;
 }
 
-async fn rate_limit<T>(
-    tokens_stream: &mut (impl Stream<Item = T> + std::marker::Unpin),
-    last_event_timestamp: std::time::Instant
-) -> Option<T> {
-    // tauri can't process events much faster than this
-    let bottleneck = 25;
+// Ok(None) represents response end
+fn parse_ollama_response(line: String) -> Result<Option<ResponseChunk>> {
+    if line.trim().is_empty() {
+        return Ok(Some(ResponseChunk::default()));
+    }
+
+    let response = serde_json::from_str::<Value>(&line)
+        .context("Error parsing response.")?;
+
+    if let Some(error) = response["error"].as_str() {
+        bail!("{error}");
+    }
+
+    // the final line has the token counts, the stream ends after it
+    if response["done"].as_bool().unwrap_or(false) {
+        let metadata = ResponseMetadata {
+            usage: Usage {
+                prompt_tokens: response["prompt_eval_count"].as_u64(),
+                completion_tokens: response["eval_count"].as_u64()
+            },
+            finish_reason: response["done_reason"].as_str().map(str::to_string),
+            model: response["model"].as_str().map(str::to_string),
+            ..Default::default()
+        };
+        return Ok(Some(ResponseChunk { metadata, ..Default::default() }));
+    }
 
-    let elapsed = std::time::Instant::now().duration_since(last_event_timestamp).as_millis();
-    if elapsed > bottleneck as u128 {
-        return tokens_stream.next().await;
+    if let Some(tokens) = response["message"]["content"].as_str() {
+        return Ok(Some(ResponseChunk { tokens: tokens.into(), ..Default::default() }));
+    } else {
+        bail!("Error parsing response.");
     }
-    let elapsed = elapsed as u64;
+}
 
-    let (event, _) = futures::join!(
-        tokens_stream.next(),
-        tokio::time::sleep(tokio::time::Duration::from_millis(bottleneck - elapsed))
-    );
+// split a newline delimited stream into lines since chunks don't necessarily end on one
+fn lines(
+    bytes_stream: impl Stream<Item = reqwest::Result<bytes::Bytes>> + std::marker::Unpin + Send
+) -> impl Stream<Item = Result<String>> + std::marker::Unpin + Send {
+    let mut buffer = Vec::<u8>::new();
+    bytes_stream
+        .map(move |chunk| {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(error) => return vec![Err(error.into())]
+            };
+            buffer.extend_from_slice(&chunk);
 
-    return event;
+            let mut lines = vec![];
+            while let Some(newline) = buffer.iter().position(|&byte| byte == b'\n') {
+                let line = buffer.drain(..=newline).collect::<Vec<_>>();
+                lines.push(String::from_utf8(line).map_err(Into::into));
+            }
+            lines
+        })
+        .flat_map(futures::stream::iter)
 }
 
+// chunks are sent at most once per interval, each event carrying the chunks streamed since the last.
+// None marks the end of the response
 async fn collect_tokens(
     cancel: std::sync::Arc<tokio::sync::Notify>,
-    mut tokens_stream: impl Stream<Item = Result<Option<String>>> + std::marker::Unpin,
-    window: &tauri::Window
+    mut tokens_stream: impl Stream<Item = Result<Option<ResponseChunk>>> + std::marker::Unpin,
+    window: &tauri::Window,
+    event: &str,
+    interval: std::time::Duration
 ) {
-    let mut last_event_timestamp = std::time::Instant::now();
+    let emit = |payload: Result<Option<&[ResponseChunk]>, StreamError>| match window.emit(event, &payload) {
+        Ok(_) => true,
+        Err(error) => {
+            tracing::error!("{error}");
+            false
+        }
+    };
+
+    let mut batch = Vec::<ResponseChunk>::new();
+    let mut next_event = tokio::time::Instant::now();
     loop {
         tokio::select! {
             _ = cancel.notified() => {
-                if let Err(error) = window.emit("token", Ok::<_, String>(None::<String>)) {
-                    eprintln!("{error}");
+                if !batch.is_empty() {
+                    emit(Ok(Some(&batch)));
                 }
+                emit(Ok(None));
                 break;
             }
 
-            tokens = rate_limit(&mut tokens_stream, last_event_timestamp) => {
-                let Some(tokens) = tokens else {
-                    if let Err(error) = window.emit("token", Ok::<_, String>(None::<String>)) {
-                        eprintln!("{error}");
-                    }
+            _ = tokio::time::sleep_until(next_event), if !batch.is_empty() => {
+                if !emit(Ok(Some(&batch))) {
                     break;
-                };
-
-                // skip if empty token
-                if tokens.as_ref().map(|tokens| tokens == &Some("".into())).unwrap_or(false) {
-                    continue;
                 }
+                batch.clear();
+                next_event = tokio::time::Instant::now() + interval;
+            }
 
-                let tokens = tokens.map_err(to_serde_err);
-                match window.emit("token", &tokens) {
-                    Ok(_) => last_event_timestamp = std::time::Instant::now(),
-                    Err(error) => {
-                        eprintln!("{error}");
+            tokens = tokens_stream.next() => match tokens {
+                // skip if empty token
+                Some(Ok(Some(chunk))) => if !chunk.is_empty() {
+                    batch.push(chunk);
+                },
+                Some(Err(error)) => {
+                    if !batch.is_empty() && emit(Ok(Some(&batch))) {
+                        batch.clear();
+                    }
+                    if !emit(Err(StreamError::from(error))) {
                         break;
                     }
-                }
-
-                if let Ok(None) = tokens {
+                },
+                None | Some(Ok(None)) => {
+                    if !batch.is_empty() {
+                        emit(Ok(Some(&batch)));
+                    }
+                    emit(Ok(None));
                     break;
                 }
             }
@@ -275,23 +702,51 @@ async fn collect_tokens(
     }
 }
 
-fn build_request(
-    api_key: &APIKey,
-    config: &Config,
-    exchanges: Vec<Exchange>,
-    prompt: &str,
-) -> Result<reqwest::RequestBuilder> {
+fn build_request_body(provider: &Provider, config: &Config, exchanges: Vec<Exchange>, prompt: &str) -> Value {
+    match provider {
+        Provider::OpenAI { .. } | Provider::Azure { .. } | Provider::Groq | Provider::XAI =>
+            build_openai_request_body(provider, config, exchanges, prompt),
+        Provider::Anthropic => build_anthropic_request_body(config, exchanges, prompt),
+        Provider::Google | Provider::VertexAI { .. } => build_google_request_body(config, exchanges, prompt),
+        Provider::Ollama { .. } => build_ollama_request_body(config, exchanges, prompt)
+    }
+}
+
+fn build_request(api_key: &APIKey, config: &Config, body: String) -> Result<reqwest::RequestBuilder> {
     let mut headers = HeaderMap::new();
     headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
 
     let request_builder = match &api_key.provider {
         Provider::OpenAI { base_url } => {
+            // compatible servers like vLLM and LM Studio often run without a key
+            if !api_key.key.is_empty() {
+                headers.insert("Authorization", HeaderValue::from_str(&format!("Bearer {}", api_key.key))?);
+            }
+
+            let base_url = match base_url.trim() {
+                "" => Provider::default().base_url().unwrap_or_default().to_string(),
+                base_url => base_url.to_string()
+            };
+            reqwest::Client::new()
+                .post(base_url.trim_end_matches('/').to_string() + "/chat/completions")
+                .headers(headers)
+                .body(body)
+        },
+        Provider::Groq => {
             headers.insert("Authorization", HeaderValue::from_str(&format!("Bearer {}", api_key.key))?);
 
             reqwest::Client::new()
-                .post(base_url.to_string() + "/chat/completions")
+                .post("https://api.groq.com/openai/v1/chat/completions")
                 .headers(headers)
-                .body(build_openai_request_body(config, exchanges, prompt).to_string())
+                .body(body)
+        },
+        Provider::XAI => {
+            headers.insert("Authorization", HeaderValue::from_str(&format!("Bearer {}", api_key.key))?);
+
+            reqwest::Client::new()
+                .post("https://api.x.ai/v1/chat/completions")
+                .headers(headers)
+                .body(body)
         },
         Provider::Anthropic => {
             headers.insert("x-api-key", HeaderValue::from_str(&api_key.key)?);
@@ -300,8 +755,43 @@ fn build_request(
             reqwest::Client::new()
                 .post("https://api.anthropic.com/v1/messages")
                 .headers(headers)
-                .body(build_anthropic_request_body(config, exchanges, prompt).to_string())
+                .body(body)
         }
+        Provider::Azure { base_url, deployment, api_version } => {
+            headers.insert("api-key", HeaderValue::from_str(&api_key.key)?);
+
+            let deployment = match deployment.trim() {
+                "" => &config.model,
+                deployment => deployment
+            };
+            let url = format!("{}/openai/deployments/{deployment}/chat/completions", base_url.trim_end_matches('/'));
+            reqwest::Client::new()
+                .post(url)
+                .query(&[("api-version", api_version)])
+                .headers(headers)
+                .body(body)
+        },
+        Provider::Ollama { base_url } => {
+            // local servers don't need a key, but one may be set for a proxy in front of it
+            if !api_key.key.is_empty() {
+                headers.insert("Authorization", HeaderValue::from_str(&format!("Bearer {}", api_key.key))?);
+            }
+
+            reqwest::Client::new()
+                .post(base_url.trim_end_matches('/').to_string() + "/api/chat")
+                .headers(headers)
+                .body(body)
+        },
+        Provider::VertexAI { project_id, location } => {
+            headers.insert("Authorization", HeaderValue::from_str(&format!("Bearer {}", api_key.key))?);
+
+            let url = format!("https://{location}-aiplatform.googleapis.com/v1/projects/{project_id}\
+                /locations/{location}/publishers/google/models/{}:streamGenerateContent", config.model);
+            reqwest::Client::new()
+                .post(url)
+                .headers(headers)
+                .body(body)
+        },
         Provider::Google => {
             headers.insert("x-goog-api-key", HeaderValue::from_str(&api_key.key)?);
 
@@ -309,63 +799,518 @@ fn build_request(
             reqwest::Client::new()
                 .post(format!("https://{domain}/v1beta/models/{}:streamGenerateContent", config.model))
                 .headers(headers)
-                .body(build_google_request_body(config, exchanges, prompt).to_string())
+                .body(body)
         },
     };
 
     return Ok(request_builder);
 }
 
+// sent by groq and openai, missing headers are left as None
+fn parse_rate_limit(provider: &Provider, headers: &HeaderMap) -> Option<RateLimit> {
+    let header = |name: &str| headers.get(format!("x-ratelimit-{name}"))?.to_str().ok().map(str::to_string);
+    let number = |name: &str| header(name)?.parse::<u64>().ok();
+
+    let rate_limit = RateLimit {
+        provider: provider.to_string(),
+        limit_requests: number("limit-requests"),
+        remaining_requests: number("remaining-requests"),
+        reset_requests: header("reset-requests"),
+        limit_tokens: number("limit-tokens"),
+        remaining_tokens: number("remaining-tokens"),
+        reset_tokens: header("reset-tokens")
+    };
+    (rate_limit.remaining_requests.is_some() || rate_limit.remaining_tokens.is_some()).then_some(rate_limit)
+}
+
+fn select_api_key(config: &Config) -> Result<&APIKey> {
+    config.request_api_key().ok_or_else(|| match config.api_key {
+        Some(_) => anyhow!("Invalid selection."),
+        None => anyhow!("No API key selected.")
+    })
+}
+
+// keys in the keychain are read per request. vertex ai keys are a service account's json key or the path of its
+// file, exchanged for an access token, or else an access token itself. without either they fall back to the gcloud
+// CLI's application credentials
+async fn resolve_access_token(api_key: &APIKey) -> Result<APIKey> {
+    let api_key = secrets::resolve_api_key(api_key).await?;
+    if !matches!(api_key.provider, Provider::VertexAI { .. }) {
+        return Ok(api_key);
+    }
+    if let Some(service_account) = service_accounts::read_key_file(&api_key.key).await? {
+        return Ok(APIKey { key: service_accounts::access_token(&service_account).await?, ..api_key });
+    }
+    if service_accounts::is_service_account(&api_key.key) {
+        return Ok(APIKey { key: service_accounts::access_token(&api_key.key).await?, ..api_key });
+    }
+    if !api_key.key.trim().is_empty() {
+        return Ok(api_key);
+    }
+
+    let output = tauri::async_runtime::spawn_blocking(||
+        std::process::Command::new("gcloud").args(["auth", "print-access-token"]).output()).await?
+        .context("Unable to run gcloud for a Vertex AI access token")?;
+    if !output.status.success() {
+        bail!("gcloud auth print-access-token failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    return Ok(APIKey { key: String::from_utf8(output.stdout)?.trim().to_string(), ..api_key });
+}
+
+// variables are expanded per request so prompts like "today is {{today}}" don't go stale
+fn expand_system_prompt(config: &Config, exchanges: &[Exchange], prompt: &str) -> String {
+    // conversations are titled by their first prompt
+    let conversation_title = exchanges.first()
+        .map(|exchange| exchange.user_message.as_str())
+        .unwrap_or(prompt);
+    config.system_prompt
+        .replace("{{today}}", &chrono::Local::now().format("%Y-%m-%d").to_string())
+        .replace("{{model}}", &config.model)
+        .replace("{{conversation_title}}", conversation_title.lines().next().unwrap_or_default())
+}
+
+async fn _preview_request(
+    conversation_uuid: Option<uuid::Uuid>,
+    prompt: &str,
+    mut config: Config,
+    exchanges: Vec<Exchange>
+) -> Result<String> {
+    config.system_prompt = expand_system_prompt(&config, &exchanges, prompt);
+    let api_key = select_api_key(&config)?;
+    let (exchanges, _) = summaries::condense(conversation_uuid, &mut config, exchanges, prompt).await;
+    let body = build_request_body(&api_key.provider, &config, exchanges, prompt);
+    return Ok(serde_json::to_string_pretty(&body)?);
+}
+
+// the exact body build_token_stream would send, so it can be reviewed and edited first
 #[tauri::command]
-pub async fn build_token_stream(
-    window: tauri::Window,
+pub async fn preview_request(
+    conversation_uuid: Option<uuid::Uuid>,
     prompt: &str,
     config: Config,
     exchanges: Vec<Exchange>
-) -> Result<bool, Error> {
-    let api_key_index = config.api_key.ok_or(to_serde_err(anyhow!("No API key selected.")))?;
-    let api_key = &config.api_keys.get(api_key_index).ok_or(to_serde_err(anyhow!("Invalid selection.")))?;
+) -> Result<String, Error> {
+    _preview_request(conversation_uuid, prompt, config, exchanges).await.map_err(to_serde_err)
+}
+
+// lists the provider's models, an authenticated request that costs nothing. azure lists base models rather
+// than the deployments requests are routed by, and vertex ai lists its endpoints
+fn build_models_request(api_key: &APIKey) -> reqwest::RequestBuilder {
+    let client = reqwest::Client::new();
+    match &api_key.provider {
+        Provider::OpenAI { base_url } => {
+            let base_url = match base_url.trim() {
+                "" => Provider::default().base_url().unwrap_or_default().to_string(),
+                base_url => base_url.to_string()
+            };
+            let request_builder = client.get(base_url.trim_end_matches('/').to_string() + "/models");
+            match api_key.key.is_empty() {
+                true => request_builder,
+                false => request_builder.bearer_auth(&api_key.key)
+            }
+        },
+        Provider::Groq => client.get("https://api.groq.com/openai/v1/models").bearer_auth(&api_key.key),
+        Provider::XAI => client.get("https://api.x.ai/v1/models").bearer_auth(&api_key.key),
+        Provider::Anthropic => client.get("https://api.anthropic.com/v1/models")
+            .query(&[("limit", "1000")])
+            .header("x-api-key", &api_key.key)
+            .header("anthropic-version", "2023-06-01"),
+        Provider::Azure { base_url, api_version, .. } =>
+            client.get(format!("{}/openai/models", base_url.trim_end_matches('/')))
+                .query(&[("api-version", api_version)])
+                .header("api-key", &api_key.key),
+        Provider::Ollama { base_url } => {
+            let request_builder = client.get(base_url.trim_end_matches('/').to_string() + "/api/tags");
+            match api_key.key.is_empty() {
+                true => request_builder,
+                false => request_builder.bearer_auth(&api_key.key)
+            }
+        },
+        Provider::VertexAI { project_id, location } => client
+            .get(format!("https://{location}-aiplatform.googleapis.com/v1/projects/{project_id}\
+                /locations/{location}/endpoints"))
+            .bearer_auth(&api_key.key),
+        Provider::Google => client.get("https://generativelanguage.googleapis.com/v1beta/models")
+            .query(&[("pageSize", "1000")])
+            .header("x-goog-api-key", &api_key.key)
+    }
+}
 
-    let request = build_request(api_key, &config, exchanges, prompt).map_err(to_serde_err)?;
+async fn send_models_request(api_key: &APIKey) -> Result<reqwest::Response> {
+    let api_key = resolve_access_token(api_key).await?;
+    let response = build_models_request(&api_key).send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(parse_status_error(&api_key.provider, status, None, &body).into());
+    }
 
-    let cancel = std::sync::Arc::new(tokio::sync::Notify::new());
-    let cancel_listener_id = window.listen("cancel", {
-        let cancel = cancel.clone();
-        move |_| cancel.notify_one()
-    });
+    return Ok(response);
+}
 
-    let response = tokio::select! {
-        response = request.send() => response.map_err(|error| Error::new(&error))?,
-        _ = cancel.notified() => return Ok(true)
+// an error describes why the key was rejected, e.g. an authentication error or an unreachable server
+#[tauri::command]
+pub async fn validate_api_key(api_key: APIKey) -> Result<(), Error> {
+    send_models_request(&api_key).await.map(|_| ()).map_err(to_serde_err)
+}
+
+// the names the model field takes
+fn parse_models(provider: &Provider, body: &Value) -> Vec<String> {
+    let list = |field: &str| body[field].as_array().cloned().unwrap_or_default();
+    let mut models = match provider {
+        Provider::OpenAI { .. } | Provider::Groq | Provider::XAI | Provider::Anthropic => list("data").iter()
+            .filter_map(|model| model["id"].as_str().map(str::to_string))
+            .collect::<Vec<_>>(),
+        // embedding and other models that can't chat are left out
+        Provider::Google => list("models").iter()
+            .filter(|model| model["supportedGenerationMethods"].as_array()
+                .is_some_and(|methods| methods.iter().any(|method| method == "generateContent")))
+            .filter_map(|model| model["name"].as_str())
+            .map(|name| name.strip_prefix("models/").unwrap_or(name).to_string())
+            .collect(),
+        Provider::Ollama { .. } => list("models").iter()
+            .filter_map(|model| model["name"].as_str().map(str::to_string))
+            .collect(),
+        Provider::Azure { .. } | Provider::VertexAI { .. } => vec![]
     };
+
+    models.sort();
+    return models;
+}
+
+// empty for providers whose models can't be listed, where the model is still entered by hand
+async fn _list_models(api_key: APIKey) -> Result<Vec<String>> {
+    if matches!(api_key.provider, Provider::Azure { .. } | Provider::VertexAI { .. }) {
+        return Ok(vec![]);
+    }
+
+    let body = serde_json::from_str::<Value>(&send_models_request(&api_key).await?.text().await?)?;
+    return Ok(parse_models(&api_key.provider, &body));
+}
+
+#[tauri::command]
+pub async fn list_models(api_key: APIKey) -> Result<Vec<String>, Error> {
+    _list_models(api_key).await.map_err(to_serde_err)
+}
+
+type TokenStream = Box<dyn Stream<Item = Result<Option<ResponseChunk>>> + std::marker::Unpin + Send>;
+
+// retry-after is either a number of seconds or an http date, openai also sends retry-after-ms
+fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    if let Some(milliseconds) = header("retry-after-ms").and_then(|value| value.parse::<u64>().ok()) {
+        return Some(milliseconds.div_ceil(1000));
+    }
+
+    let retry_after = header(reqwest::header::RETRY_AFTER.as_str())?.trim();
+    retry_after.parse::<u64>().ok().or_else(|| {
+        let date = chrono::DateTime::parse_from_rfc2822(retry_after).ok()?;
+        Some((date.timestamp() - chrono::Utc::now().timestamp()).max(0) as u64)
+    })
+}
+
+async fn check_status(provider: &Provider, response: reqwest::Response) -> Result<reqwest::Response> {
     if response.status() != reqwest::StatusCode::OK {
-        return Err(to_serde_err(anyhow!("Invalid status code: {}: {}", response.status(),
-            response.text().await.unwrap_or_else(|error| error.to_string()))));
-    }
-
-    let tokens_stream: Box<dyn Stream<Item = Result<Option<String>>> + std::marker::Unpin + Send>;
-    match api_key.provider {
-        // TODO: delete this spaghetti once o1 supports streaming
-        Provider::OpenAI { .. } if config.model.starts_with("o1") => {
-            let response_future = Box::pin(response.text().map(|result|
-                result.map_err(Into::into).and_then(parse_openai_nonstreaming_response).map(Some)));
-            tokens_stream = Box::new(futures::stream::once(response_future)
-                .chain(futures::stream::once(std::future::ready(Ok(None)))));
-        },
-        Provider::OpenAI { .. } => tokens_stream = Box::new(response.bytes_stream()
+        let status = response.status();
+        let retry_after = parse_retry_after(response.headers());
+        let body = response.text().await.unwrap_or_else(|error| error.to_string());
+        return Err(parse_status_error(provider, status, retry_after, &body).into());
+    }
+
+    return Ok(response);
+}
+
+fn parse_token_stream(
+    provider: &Provider,
+    bytes_stream: impl Stream<Item = reqwest::Result<bytes::Bytes>> + std::marker::Unpin + Send + 'static
+) -> TokenStream {
+    match provider {
+        Provider::OpenAI { .. } | Provider::Azure { .. } | Provider::Groq | Provider::XAI =>
+            Box::new(bytes_stream
             .eventsource()
             .map(|event| event.map_err(Into::into).map(parse_openai_response).unwrap_or_else(Err))),
-        Provider::Anthropic => tokens_stream = Box::new(response.bytes_stream()
+        Provider::Anthropic => Box::new(bytes_stream
             .eventsource()
             .map(|event| event.map_err(Into::into).map(parse_anthropic_response).unwrap_or_else(Err))),
-        Provider::Google => tokens_stream = Box::new(response.bytes_stream()
-            .map(|event| event.map_err(Into::into).map(parse_google_response).unwrap_or_else(Err)))
+        Provider::Google | Provider::VertexAI { .. } => Box::new(bytes_stream
+            .map(|event| event.map_err(Into::into).map(parse_google_response).unwrap_or_else(Err))),
+        Provider::Ollama { .. } => Box::new(lines(bytes_stream)
+            .map(|line| line.map(parse_ollama_response).unwrap_or_else(Err)))
+    }
+}
+
+async fn send_request(
+    window: &tauri::Window,
+    stream_id: uuid::Uuid,
+    api_key: &APIKey,
+    config: &Config,
+    body: &str
+) -> Result<reqwest::Response> {
+    tracing::info!(provider = %api_key.provider, model = %config.model, "Sending request");
+    let response = build_request(api_key, config, body.to_string())?.send().await?;
+    if let Some(rate_limit) = parse_rate_limit(&api_key.provider, response.headers()) {
+        if let Err(error) = window.emit("rate_limit", &StreamNotice { stream_id, notice: rate_limit }) {
+            tracing::error!("{error}");
+        }
+    }
+
+    check_status(&api_key.provider, response).await
+}
+
+// rate limits, overloaded or failing servers and dropped connections are likely to succeed if sent again
+fn is_transient(error: &anyhow::Error) -> bool {
+    if let Some(error) = error.downcast_ref::<ProviderError>() {
+        return match error {
+            ProviderError::RateLimited { .. } | ProviderError::Overloaded { .. } => true,
+            // server errors without a more specific parse keep the status as their kind, e.g. "502 Bad Gateway"
+            ProviderError::Other { kind, .. } => kind.starts_with('5') || kind == "api_error",
+            _ => false
+        };
+    }
+    if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        return error.is_connect() || error.is_timeout() || error.is_request();
+    }
+
+    return false;
+}
+
+// longest retry-after worth waiting out, anything longer is reported as an error
+const MAX_RETRY_AFTER: u64 = 120;
+
+async fn send_with_retries(
+    window: &tauri::Window,
+    stream_id: uuid::Uuid,
+    api_key: &APIKey,
+    config: &Config,
+    body: &str
+) -> Result<reqwest::Response> {
+    let attempts = config.retry.attempts.max(1);
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let error = match send_request(window, stream_id, api_key, config, body).await {
+            Ok(response) => return Ok(response),
+            Err(error) => error
+        };
+        let retry_after = error.downcast_ref::<ProviderError>()
+            .and_then(ProviderError::retry_after)
+            .filter(|retry_after| *retry_after <= MAX_RETRY_AFTER);
+        // a rate limit that says when it expires is waited out once even if retries are disabled
+        let rate_limited = matches!(error.downcast_ref::<ProviderError>(), Some(ProviderError::RateLimited { .. }))
+            && retry_after.is_some();
+        let attempts = if rate_limited { attempts.max(2) } else { attempts };
+        if attempt >= attempts || !is_transient(&error) {
+            tracing::error!(provider = %api_key.provider, attempt, "Request failed: {error}");
+            return Err(error);
+        }
+
+        // the provider's retry-after takes precedence over the backoff
+        let delay = retry_after.map(std::time::Duration::from_secs)
+            .unwrap_or_else(|| config.retry.backoff(attempt));
+        tracing::warn!(provider = %api_key.provider, attempt, ?delay, "Retrying: {error}");
+
+        // counts down the wait, once a second
+        let mut remaining = delay;
+        loop {
+            let retry_status = RetryStatus {
+                provider: api_key.provider.to_string(),
+                attempt: attempt + 1,
+                attempts,
+                delay_ms: remaining.as_millis().try_into().unwrap_or(u64::MAX),
+                reason: error.to_string()
+            };
+            if let Err(error) = window.emit("retrying", &StreamNotice { stream_id, notice: retry_status }) {
+                tracing::error!("{error}");
+            }
+
+            let tick = remaining.min(std::time::Duration::from_secs(1));
+            tokio::time::sleep(tick).await;
+            remaining -= tick;
+            if remaining.is_zero() {
+                break;
+            }
+        }
+    }
+}
+
+// sends one request and parses its response, tagging every chunk with candidate
+async fn request_candidate(
+    window: &tauri::Window,
+    stream_id: uuid::Uuid,
+    api_key: &APIKey,
+    config: &Config,
+    body: String,
+    candidate: usize
+) -> Result<TokenStream> {
+    let response = send_with_retries(window, stream_id, api_key, config, &body).await?;
+    let mut capture = match config.capture_requests {
+        true => Capture::start(api_key, &body, candidate).await
+            .inspect_err(|error| tracing::warn!("Error starting capture: {error}"))
+            .ok(),
+        false => None
+    };
+    let bytes_stream = response.bytes_stream().inspect(move |chunk| {
+        if let (Some(capture), Ok(chunk)) = (&mut capture, chunk) {
+            capture.write(&String::from_utf8_lossy(chunk));
+        }
+    });
+    let mut tokens_stream = parse_token_stream(&api_key.provider, bytes_stream);
+
+    // record the seed with the response so it can be reproduced, anthropic doesn't support one
+    if config.deterministic && !matches!(api_key.provider, Provider::Anthropic) {
+        let metadata = ResponseMetadata { seed: Some(config.seed), ..Default::default() };
+        tokens_stream = Box::new(futures::stream::once(std::future::ready(Ok(Some(ResponseChunk {
+            tokens: "".into(),
+            metadata,
+            candidate
+        })))).chain(tokens_stream));
     }
 
+    // the merged stream ends once every candidate has, rather than with the first to finish
+    return Ok(Box::new(tokens_stream
+        .take_while(|chunk| std::future::ready(!matches!(chunk, Ok(None))))
+        .map(move |chunk| chunk.map(|chunk| chunk.map(|chunk| ResponseChunk { candidate, ..chunk })))));
+}
+
+// body overrides the request built from the prompt and exchanges, e.g. after editing a preview,
+// with config.candidates above one the same request is sent that many times in parallel.
+// tokens are emitted on build_token_stream:<stream_id>, as streamed commands expect, and the stream is canceled on
+// cancel:<stream_id>.
+// a conversation too long for the context window is sent with its saved summary, if there is one
+#[tauri::command]
+pub async fn build_token_stream(
+    window: tauri::Window,
+    stream_id: uuid::Uuid,
+    conversation_uuid: Option<uuid::Uuid>,
+    prompt: &str,
+    mut config: Config,
+    exchanges: Vec<Exchange>,
+    body: Option<String>
+) -> Result<(), Error> {
+    let event = format!("build_token_stream:{stream_id}");
+    guardrail::check_prompt(prompt, &config).await.map_err(to_serde_err)?;
+    config.system_prompt = expand_system_prompt(&config, &exchanges, prompt);
+    let api_key = select_api_key(&config).map_err(to_serde_err)?;
+    let api_key = &resolve_access_token(api_key).await.map_err(to_serde_err)?;
+
+    let body = match body {
+        Some(body) => serde_json::from_str::<Value>(&body)
+            .context("The edited request isn't valid JSON.")
+            .map_err(to_serde_err)?
+            .to_string(),
+        None => {
+            limits::_validate_request(&config).map_err(to_serde_err)?;
+            let (exchanges, summarized) = summaries::condense(conversation_uuid, &mut config, exchanges, prompt).await;
+            if let Some(conversation_uuid) = conversation_uuid.filter(|_| summarized > 0) {
+                let summary = ContextSummary { conversation_uuid, summarized };
+                if let Err(error) = window.emit("context_summarized", &StreamNotice { stream_id, notice: summary }) {
+                    tracing::error!("{error}");
+                }
+            }
+            build_request_body(&api_key.provider, &config, exchanges, prompt).to_string()
+        }
+    };
+
+    let cancel = std::sync::Arc::new(tokio::sync::Notify::new());
+    let cancel_listener_id = window.listen(format!("cancel:{stream_id}"), {
+        let cancel = cancel.clone();
+        move |_| cancel.notify_one()
+    });
+
+    let requests = (0..config.candidates.max(1))
+        .map(|candidate| request_candidate(&window, stream_id, api_key, &config, body.clone(), candidate));
+    let results = tokio::select! {
+        results = futures::future::join_all(requests) => results,
+        _ = cancel.notified() => {
+            // the stream ends before any tokens
+            window.unlisten(cancel_listener_id);
+            if let Err(error) = window.emit(&event, &Ok::<Option<&[ResponseChunk]>, StreamError>(None)) {
+                tracing::error!("{error}");
+            }
+            return Ok(());
+        }
+    };
+    for error in results.iter().filter_map(|result| result.as_ref().err()) {
+        record_error(error, ErrorCategory::Other, Some(&api_key.provider));
+    }
+    // the request only fails if every candidate did, otherwise a failed candidate ends with its error as its
+    // finish reason alongside the others
+    if results.iter().all(Result::is_err) {
+        window.unlisten(cancel_listener_id);
+        let error = results.into_iter().find_map(Result::err).unwrap_or(anyhow!("No candidates were requested."));
+        return Err(to_serde_err(error));
+    }
+    let tokens_streams = results.into_iter().enumerate().map(|(candidate, result)| match result {
+        Ok(tokens_stream) => tokens_stream,
+        Err(error) => {
+            let metadata = ResponseMetadata { finish_reason: Some(format!("error: {error}")), ..Default::default() };
+            let chunk = ResponseChunk { candidate, metadata, ..Default::default() };
+            Box::new(futures::stream::iter([Ok(Some(chunk))])) as TokenStream
+        }
+    });
+    let tokens_stream = futures::stream::select_all(tokens_streams);
+
+    let provider = api_key.provider.clone();
+    let model = config.model.clone();
+    let interval = config.token_interval();
     tokio::spawn(async move {
-        collect_tokens(cancel, tokens_stream, &window).await;
+        // usage is reported cumulatively over several chunks, so it's recorded per candidate once the response ends
+        let usages = std::sync::Arc::new(std::sync::Mutex::new(HashMap::<usize, Usage>::new()));
+        let tokens_stream = tokens_stream.inspect({
+            let provider = provider.clone();
+            let usages = usages.clone();
+            move |tokens| match tokens {
+                // errors partway through a response are mostly unparseable chunks
+                Err(error) => record_error(error, ErrorCategory::Parse, Some(&provider)),
+                Ok(Some(chunk)) => if let Ok(mut usages) = usages.lock() {
+                    usages.entry(chunk.candidate).or_default().merge(chunk.metadata.usage.clone());
+                },
+                Ok(None) => ()
+            }
+        });
+        collect_tokens(cancel, tokens_stream, &window, &event, interval).await;
         window.unlisten(cancel_listener_id);
+
+        let usages = usages.lock().map(|mut usages| std::mem::take(&mut *usages)).unwrap_or_default();
+        for usage in usages.into_values() {
+            record_usage(&provider, &model, &usage).await;
+        }
     });
 
-    Ok(false)
-}
\ No newline at end of file
+    Ok(())
+}
+
+// the user's model and key for a request made outside a chat, without the response format, reasoning, tools or
+// prefill set for chatting
+pub fn background_config(config: Config, system_prompt: &str) -> Config {
+    Config {
+        system_prompt: system_prompt.into(),
+        reasoning_effort: None,
+        thinking_budget: None,
+        candidates: 1,
+        response_format: Default::default(),
+        google_tools: Default::default(),
+        prefill: "".into(),
+        ..config
+    }
+}
+
+// a whole response for requests made outside a chat, e.g. generating titles
+pub async fn complete(mut config: Config, prompt: &str, exchanges: Vec<Exchange>) -> Result<String> {
+    config.system_prompt = expand_system_prompt(&config, &exchanges, prompt);
+    let api_key = &resolve_access_token(select_api_key(&config)?).await?;
+    let body = build_request_body(&api_key.provider, &config, exchanges, prompt).to_string();
+
+    let response = check_status(&api_key.provider, build_request(api_key, &config, body)?.send().await?).await?;
+    let mut tokens_stream = parse_token_stream(&api_key.provider, response.bytes_stream());
+    let mut response = String::new();
+    while let Some(chunk) = tokens_stream.next().await {
+        match chunk? {
+            Some(chunk) => response += &chunk.tokens,
+            None => break
+        }
+    }
+
+    return Ok(response);
+}
@@ -0,0 +1,146 @@
+use anyhow::{anyhow, bail, Result};
+use common::{to_serde_err, Folder};
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, IntoActiveModel};
+use sea_orm::{QueryFilter, QueryOrder, Set};
+use serde_error::Error;
+use crate::{database, initiate_transaction};
+
+fn folder_from_model(folder: entity::folders::Model) -> Folder {
+    Folder { id: folder.id, name: folder.name, parent: folder.parent }
+}
+
+async fn find_folder(conn: &impl ConnectionTrait, id: i32) -> Result<entity::folders::Model> {
+    entity::folders::Entity::find_by_id(id)
+        .one(conn).await?
+        .ok_or(anyhow!("Folder {} not found", id))
+}
+
+fn validate_name(name: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("Folder names cannot be empty.");
+    }
+
+    return Ok(name.to_string());
+}
+
+pub async fn list_folders(conn: &impl ConnectionTrait) -> Result<Vec<Folder>> {
+    let folders = entity::folders::Entity::find()
+        .order_by_asc(entity::folders::Column::Name)
+        .all(conn).await?
+        .into_iter()
+        .map(folder_from_model)
+        .collect();
+
+    return Ok(folders);
+}
+
+pub async fn create_folder(conn: &impl ConnectionTrait, name: &str, parent: Option<i32>) -> Result<Folder> {
+    if let Some(parent) = parent {
+        find_folder(conn, parent).await?;
+    }
+
+    let folder = entity::folders::ActiveModel {
+        name: Set(validate_name(name)?),
+        parent: Set(parent),
+        ..Default::default()
+    }.insert(conn).await?;
+
+    return Ok(folder_from_model(folder));
+}
+
+pub async fn rename_folder(conn: &impl ConnectionTrait, id: i32, name: &str) -> Result<()> {
+    let mut folder = find_folder(conn, id).await?.into_active_model();
+    folder.name = Set(validate_name(name)?);
+    folder.update(conn).await?;
+
+    return Ok(());
+}
+
+// its subfolders are deleted with it and their conversations move to the top level
+pub async fn delete_folder(conn: &impl ConnectionTrait, id: i32) -> Result<()> {
+    entity::folders::Entity::delete(find_folder(conn, id).await?.into_active_model()).exec(conn).await?;
+    return Ok(());
+}
+
+// None moves the conversation to the top level
+pub async fn move_to_folder(
+    conn: &impl ConnectionTrait,
+    conversation_uuid: uuid::Uuid,
+    folder: Option<i32>
+) -> Result<()> {
+    if let Some(folder) = folder {
+        find_folder(conn, folder).await?;
+    }
+
+    let mut conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(conn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?
+        .into_active_model();
+    conversation.folder = Set(folder);
+    conversation.update(conn).await?;
+
+    return Ok(());
+}
+
+async fn _load_folders() -> Result<Vec<Folder>> {
+    list_folders(&database()?).await
+}
+
+#[tauri::command]
+pub async fn load_folders() -> Result<Vec<Folder>, Error> {
+    _load_folders().await.map_err(to_serde_err)
+}
+
+async fn _add_folder(name: String, parent: Option<i32>) -> Result<Folder> {
+    let txn = initiate_transaction().await?;
+    let folder = create_folder(&txn, &name, parent).await?;
+    txn.commit().await?;
+
+    return Ok(folder);
+}
+
+#[tauri::command]
+pub async fn add_folder(name: String, parent: Option<i32>) -> Result<Folder, Error> {
+    _add_folder(name, parent).await.map_err(to_serde_err)
+}
+
+async fn _set_folder_name(id: i32, name: String) -> Result<()> {
+    let txn = initiate_transaction().await?;
+    rename_folder(&txn, id, &name).await?;
+    txn.commit().await?;
+
+    return Ok(());
+}
+
+#[tauri::command]
+pub async fn set_folder_name(id: i32, name: String) -> Result<(), Error> {
+    _set_folder_name(id, name).await.map_err(to_serde_err)
+}
+
+async fn _remove_folder(id: i32) -> Result<()> {
+    let txn = initiate_transaction().await?;
+    delete_folder(&txn, id).await?;
+    txn.commit().await?;
+
+    return Ok(());
+}
+
+#[tauri::command]
+pub async fn remove_folder(id: i32) -> Result<(), Error> {
+    _remove_folder(id).await.map_err(to_serde_err)
+}
+
+async fn _set_conversation_folder(conversation_uuid: uuid::Uuid, folder: Option<i32>) -> Result<()> {
+    let txn = initiate_transaction().await?;
+    move_to_folder(&txn, conversation_uuid, folder).await?;
+    txn.commit().await?;
+
+    return Ok(());
+}
+
+#[tauri::command]
+pub async fn set_conversation_folder(conversation_uuid: uuid::Uuid, folder: Option<i32>) -> Result<(), Error> {
+    _set_conversation_folder(conversation_uuid, folder).await.map_err(to_serde_err)
+}
@@ -0,0 +1,80 @@
+use anyhow::{bail, Result};
+use common::{to_serde_err, Config, GuardrailFinding};
+use regex::Regex;
+use serde_error::Error;
+use crate::secrets;
+
+// earlier rules win when matches overlap, so specific key formats come before generic ones
+const RULES: [(&str, &str); 8] = [
+    ("private key", r"-----BEGIN [A-Z ]*PRIVATE KEY-----"),
+    ("Anthropic key", r"sk-ant-[A-Za-z0-9_-]{20,}"),
+    ("OpenAI key", r"sk-[A-Za-z0-9_-]{20,}"),
+    ("Google key", r"AIza[0-9A-Za-z_-]{35}"),
+    ("AWS access key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    ("GitHub token", r"\bgh[pousr]_[A-Za-z0-9]{36,}\b"),
+    ("Slack token", r"\bxox[abprs]-[A-Za-z0-9-]{10,}\b"),
+    ("email", r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}\b")
+];
+
+lazy_static::lazy_static! {
+    static ref RULE_SET: Vec<(&'static str, Regex)> = RULES.iter()
+        .map(|&(rule, pattern)| (rule, Regex::new(pattern).expect("invalid guardrail pattern")))
+        .collect();
+}
+
+fn scan(prompt: &str, config: &Config) -> Vec<GuardrailFinding> {
+    let mut findings = vec![];
+    let mut add_finding = |rule: &str, start: usize, end: usize| {
+        let overlaps = findings.iter().any(|finding: &GuardrailFinding| start < finding.end && finding.start < end);
+        if !overlaps {
+            findings.push(GuardrailFinding { rule: rule.to_string(), start, end });
+        }
+    };
+
+    // the configured keys are caught even when they don't match a known format
+    for api_key in config.api_keys.iter().filter(|api_key| api_key.key.len() >= 8) {
+        for (start, key) in prompt.match_indices(&api_key.key) {
+            add_finding("API key", start, start + key.len());
+        }
+    }
+
+    for (rule, pattern) in RULE_SET.iter() {
+        for found in pattern.find_iter(prompt) {
+            add_finding(rule, found.start(), found.end());
+        }
+    }
+
+    findings.sort_by_key(|finding| finding.start);
+    return findings;
+}
+
+// the configured keys are stored as references to the keyring, so they're looked up to be matched
+async fn resolve_keys(mut config: Config) -> Result<Config> {
+    for api_key in config.api_keys.iter_mut() {
+        api_key.key = secrets::get(&api_key.key).await?;
+    }
+    Ok(config)
+}
+
+// refuses a prompt with findings when scanning is on, for requests sent without the interface's confirmation
+pub async fn check_prompt(prompt: &str, config: &Config) -> Result<()> {
+    if !config.scan_prompts {
+        return Ok(());
+    }
+
+    let findings = scan(prompt, &resolve_keys(config.clone()).await?);
+    if !findings.is_empty() {
+        let mut rules = findings.iter().map(|finding| finding.rule.as_str()).collect::<Vec<_>>();
+        rules.sort_unstable();
+        rules.dedup();
+        bail!("This prompt may contain sensitive information ({}).", rules.join(", "));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn scan_prompt(prompt: String, config: Config) -> Result<Vec<GuardrailFinding>, Error> {
+    let config = resolve_keys(config).await.map_err(to_serde_err)?;
+    Ok(scan(&prompt, &config))
+}
@@ -0,0 +1,76 @@
+use std::path::PathBuf;
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::to_serde_err;
+use serde_error::Error;
+
+// images returned by providers are stored here and referenced by file name from the exchange's metadata
+fn images_dir() -> Result<PathBuf> {
+    let images_dir = dirs::config_dir()
+        .context("Unable to find the config directory")?
+        .join("llm-playground")
+        .join("images");
+    std::fs::create_dir_all(&images_dir).context("Error creating images directory")?;
+    return Ok(images_dir);
+}
+
+fn extension(mime_type: &str) -> &str {
+    match mime_type {
+        "image/jpeg" => "jpg",
+        "image/webp" => "webp",
+        "image/gif" => "gif",
+        _ => "png"
+    }
+}
+
+fn mime_type(name: &str) -> &str {
+    match name.rsplit_once('.').map(|(_, extension)| extension) {
+        Some("jpg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        Some("gif") => "image/gif",
+        _ => "image/png"
+    }
+}
+
+// names come from the frontend so they can't be allowed to escape the images directory
+fn image_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) || name.starts_with('.') {
+        bail!("Invalid image name {name}.");
+    }
+
+    return Ok(images_dir()?.join(name));
+}
+
+// decode a base64 image from a response and save it, returning the file name
+pub fn store_image(mime_type: &str, data: &str) -> Result<String> {
+    let image = STANDARD.decode(data).context("Error decoding image")?;
+    let name = format!("{}.{}", uuid::Uuid::new_v4(), extension(mime_type));
+    std::fs::write(images_dir()?.join(&name), image).context("Error saving image")?;
+    return Ok(name);
+}
+
+async fn _load_image(name: &str) -> Result<String> {
+    let image = tokio::fs::read(image_path(name)?).await.context("Error reading image")?;
+    return Ok(format!("data:{};base64,{}", mime_type(name), STANDARD.encode(image)));
+}
+
+// returns the image as a data url since the webview can't read from the config dir
+#[tauri::command]
+pub async fn load_image(name: String) -> Result<String, Error> {
+    _load_image(&name).await.map_err(to_serde_err)
+}
+
+async fn _save_image(name: &str) -> Result<PathBuf> {
+    let directory = dirs::download_dir().context("Unable to find the downloads directory")?;
+    let path = directory.join(name);
+    tokio::fs::copy(image_path(name)?, &path).await.context("Error saving image")?;
+    return Ok(path);
+}
+
+// copies the image to the downloads directory, returning where it was saved
+#[tauri::command]
+pub async fn save_image(name: String) -> Result<String, Error> {
+    _save_image(&name).await
+        .map(|path| path.display().to_string())
+        .map_err(to_serde_err)
+}
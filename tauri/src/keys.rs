@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use common::{to_serde_err, APIKey, Provider};
+use serde_error::Error;
+use crate::secrets;
+
+// environment variables conventionally holding each provider's key
+fn provider_for_variable(variable: &str) -> Option<Provider> {
+    match variable {
+        "OPENAI_API_KEY" => Some(Provider::default()),
+        "OPENROUTER_API_KEY" => Some(Provider::OpenAI { base_url: "https://openrouter.ai/api/v1".into() }),
+        "ANTHROPIC_API_KEY" => Some(Provider::Anthropic),
+        "GEMINI_API_KEY" | "GOOGLE_API_KEY" => Some(Provider::Google),
+        "GROQ_API_KEY" => Some(Provider::Groq),
+        "XAI_API_KEY" => Some(Provider::XAI),
+        "OLLAMA_API_KEY" => Some(Provider::default_ollama()),
+        _ => None
+    }
+}
+
+// parse KEY=value lines, ignoring comments, blank lines, and `export` prefixes
+fn parse_env(contents: &str) -> Vec<(String, String)> {
+    contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (variable, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = value.strip_prefix('"').and_then(|value| value.strip_suffix('"'))
+                .or_else(|| value.strip_prefix('\'').and_then(|value| value.strip_suffix('\'')))
+                .unwrap_or(value);
+            Some((variable.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+// returns the keys not already in api_keys, moved out of plaintext when there's somewhere to store them
+async fn _import_env_keys(path: String, api_keys: Vec<APIKey>) -> Result<Vec<APIKey>> {
+    let path = match path.strip_prefix("~/") {
+        Some(path) => dirs::home_dir().context("Unable to find the home directory")?.join(path),
+        None => PathBuf::from(path)
+    };
+    let contents = tokio::fs::read_to_string(&path).await
+        .context(format!("Unable to read {}", path.display()))?;
+
+    let variables = parse_env(&contents);
+    // OPENAI_BASE_URL points the OpenAI key at a compatible provider
+    let openai_base_url = variables.iter()
+        .find(|(variable, _)| variable == "OPENAI_BASE_URL")
+        .map(|(_, base_url)| base_url.clone());
+
+    let mut existing_keys = vec![];
+    for api_key in &api_keys {
+        existing_keys.push(secrets::resolve_api_key(api_key).await?);
+    }
+
+    let mut imported_keys = variables.into_iter()
+        .filter(|(_, value)| !value.is_empty())
+        .filter_map(|(variable, key)| {
+            let provider = match (provider_for_variable(&variable)?, &openai_base_url) {
+                (Provider::OpenAI { .. }, Some(base_url)) if variable == "OPENAI_API_KEY" =>
+                    Provider::OpenAI { base_url: base_url.clone() },
+                (provider, _) => provider
+            };
+            Some(APIKey { name: variable, key, provider })
+        })
+        .filter(|imported_key| !existing_keys.iter().any(|api_key|
+            api_key.key == imported_key.key && api_key.provider == imported_key.provider))
+        .collect::<Vec<_>>();
+
+    for imported_key in imported_keys.iter_mut() {
+        if let Ok(reference) = secrets::store(&imported_key.key).await {
+            imported_key.key = reference;
+        }
+    }
+
+    return Ok(imported_keys);
+}
+
+#[tauri::command]
+pub async fn import_env_keys(path: String, api_keys: Vec<APIKey>) -> Result<Vec<APIKey>, Error> {
+    _import_env_keys(path, api_keys).await.map_err(to_serde_err)
+}
@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use common::{Config, Provider};
+use serde_json::{json, Map, Value};
+use crate::config_dir;
+
+// the older src-tauri builds saved providers as bare names and a single key as api_key/provider strings
+fn upgrade_provider(provider: &Value) -> Option<Value> {
+    let upgraded = match provider.as_str()? {
+        "OpenAI" => Provider::OpenAI { base_url: "".into() },
+        "Anthropic" => Provider::Anthropic,
+        "Google" => Provider::Google,
+        "Ollama" => Provider::default_ollama(),
+        _ => return None
+    };
+    serde_json::to_value(upgraded).ok()
+}
+
+// fields missing from the legacy config take the current defaults
+fn upgrade_config(mut legacy: Map<String, Value>) -> Result<Config> {
+    let Value::Object(mut config) = serde_json::to_value(Config::default())? else {
+        unreachable!("Config serializes to an object");
+    };
+    let legacy_provider = legacy.remove("provider").unwrap_or(json!("OpenAI"));
+
+    for (field, value) in legacy {
+        match (field.as_str(), value) {
+            ("api_keys", Value::Array(api_keys)) => {
+                let api_keys = api_keys.into_iter()
+                    .map(|mut api_key| {
+                        if let Some(provider) = upgrade_provider(&api_key["provider"]) {
+                            api_key["provider"] = provider;
+                        }
+                        api_key
+                    })
+                    .collect();
+                config.insert(field, Value::Array(api_keys));
+            },
+            // a single key, becoming the selected entry of api_keys
+            ("api_key", Value::String(key)) => {
+                config.insert("api_keys".into(), json!([{
+                    "name": legacy_provider.as_str().unwrap_or("OpenAI"),
+                    "key": key,
+                    "provider": upgrade_provider(&legacy_provider).unwrap_or(json!({ "OpenAI": { "base_url": "" } }))
+                }]));
+                config.insert("api_key".into(), json!(0));
+            },
+            (_, value) => {
+                config.insert(field, value);
+            }
+        }
+    }
+
+    serde_json::from_value(Value::Object(config)).context("Unable to upgrade the legacy config")
+}
+
+// run once at startup, the legacy config is kept alongside as config.json.legacy
+pub async fn upgrade_legacy_config() -> Result<()> {
+    let config_path = config_dir().await?.join("config.json");
+    let Ok(config_str) = tokio::fs::read_to_string(&config_path).await else {
+        return Ok(());
+    };
+    if serde_json::from_str::<Config>(&config_str).is_ok() {
+        return Ok(());
+    }
+    let Ok(Value::Object(legacy)) = serde_json::from_str::<Value>(&config_str) else {
+        return Ok(());
+    };
+
+    let config = upgrade_config(legacy)?;
+    tokio::fs::write(config_path.with_extension("json.legacy"), &config_str).await
+        .context("Error backing up the legacy config")?;
+    tokio::fs::write(&config_path, serde_json::to_string(&config)?).await
+        .context("Error saving the upgraded config")?;
+
+    return Ok(());
+}
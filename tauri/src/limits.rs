@@ -0,0 +1,86 @@
+use anyhow::{anyhow, bail, Result};
+use common::{to_serde_err, Config, ResponseFormat};
+use serde_error::Error;
+
+struct ModelLimits {
+    context_window: u32,
+    max_output_tokens: u32
+}
+
+// matched by the longest prefix, so dated snapshots like gpt-4o-2024-08-06 share their family's limits
+const MODEL_LIMITS: [(&str, ModelLimits); 20] = [
+    ("gpt-3.5-turbo", ModelLimits { context_window: 16_385, max_output_tokens: 4_096 }),
+    ("gpt-4", ModelLimits { context_window: 8_192, max_output_tokens: 8_192 }),
+    ("gpt-4-turbo", ModelLimits { context_window: 128_000, max_output_tokens: 4_096 }),
+    ("gpt-4o", ModelLimits { context_window: 128_000, max_output_tokens: 16_384 }),
+    ("gpt-4o-mini", ModelLimits { context_window: 128_000, max_output_tokens: 16_384 }),
+    ("o1", ModelLimits { context_window: 200_000, max_output_tokens: 100_000 }),
+    ("o1-mini", ModelLimits { context_window: 128_000, max_output_tokens: 65_536 }),
+    ("o3-mini", ModelLimits { context_window: 200_000, max_output_tokens: 100_000 }),
+    ("claude-3-haiku", ModelLimits { context_window: 200_000, max_output_tokens: 4_096 }),
+    ("claude-3-sonnet", ModelLimits { context_window: 200_000, max_output_tokens: 4_096 }),
+    ("claude-3-opus", ModelLimits { context_window: 200_000, max_output_tokens: 4_096 }),
+    ("claude-3-5-haiku", ModelLimits { context_window: 200_000, max_output_tokens: 8_192 }),
+    ("claude-3-5-sonnet", ModelLimits { context_window: 200_000, max_output_tokens: 8_192 }),
+    ("claude-3-7-sonnet", ModelLimits { context_window: 200_000, max_output_tokens: 64_000 }),
+    ("gemini-1.0-pro", ModelLimits { context_window: 32_760, max_output_tokens: 8_192 }),
+    ("gemini-1.5-flash", ModelLimits { context_window: 1_048_576, max_output_tokens: 8_192 }),
+    ("gemini-1.5-pro", ModelLimits { context_window: 2_097_152, max_output_tokens: 8_192 }),
+    ("gemini-2.0-flash", ModelLimits { context_window: 1_048_576, max_output_tokens: 8_192 }),
+    ("gemini-2.5-flash", ModelLimits { context_window: 1_048_576, max_output_tokens: 65_536 }),
+    ("gemini-2.5-pro", ModelLimits { context_window: 1_048_576, max_output_tokens: 65_536 })
+];
+
+fn model_limits(model: &str) -> Option<&'static ModelLimits> {
+    MODEL_LIMITS.iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, limits)| limits)
+}
+
+pub fn context_window(model: &str) -> Option<u32> {
+    model_limits(model).map(|limits| limits.context_window)
+}
+
+// the token limits of models missing from the table are left for the provider to validate
+pub fn _validate_request(config: &Config) -> Result<()> {
+    // anthropic rejects a forced tool or a prefilled response while extended thinking is on, and json responses are
+    // made by forcing a tool
+    if config.thinking_budget.is_some() && config.model.starts_with("claude") {
+        if config.response_format != ResponseFormat::Text {
+            bail!("{} can't use a JSON response format with a thinking budget.", config.model);
+        }
+        if !config.prefill.trim().is_empty() {
+            bail!("{} can't continue a prefilled response with a thinking budget.", config.model);
+        }
+    }
+
+    let Some(limits) = model_limits(&config.model) else { return Ok(()) };
+
+    if config.max_tokens > limits.max_output_tokens {
+        bail!("Max tokens of {} exceeds {}'s output limit of {} tokens.",
+            config.max_tokens, config.model, limits.max_output_tokens);
+    }
+
+    // anthropic counts the thinking budget towards max_tokens
+    if let Some(thinking_budget) = config.thinking_budget.filter(|_| config.model.starts_with("claude")) {
+        let total = config.max_tokens.checked_add(thinking_budget)
+            .ok_or(anyhow!("Max tokens plus the thinking budget is too large."))?;
+        if total > limits.max_output_tokens {
+            bail!("Max tokens plus the thinking budget ({total}) exceeds {}'s output limit of {} tokens.",
+                config.model, limits.max_output_tokens);
+        }
+    }
+
+    if config.max_tokens >= limits.context_window {
+        bail!("Max tokens of {} leaves no room for the prompt in {}'s context window of {} tokens.",
+            config.max_tokens, config.model, limits.context_window);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn validate_request(config: Config) -> Result<(), Error> {
+    _validate_request(&config).map_err(to_serde_err)
+}
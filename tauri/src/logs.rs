@@ -0,0 +1,70 @@
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use common::to_serde_err;
+use serde_error::Error;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{Builder, Rotation};
+use tracing_subscriber::{fmt::writer::MakeWriterExt, EnvFilter};
+use crate::config_dir;
+
+const LOG_FILE_PREFIX: &str = "llm-playground";
+// a week of daily files
+const MAX_LOG_FILES: usize = 7;
+
+async fn logs_dir() -> Result<PathBuf> {
+    Ok(config_dir().await?.join("logs"))
+}
+
+// logs to <config-dir>/logs as well as stderr. RUST_LOG overrides the level, e.g. RUST_LOG=debug.
+// the guard flushes the file when dropped, so it's held for as long as the app runs
+pub async fn init() -> Result<WorkerGuard> {
+    let appender = Builder::new()
+        .rotation(Rotation::DAILY)
+        .filename_prefix(LOG_FILE_PREFIX)
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(logs_dir().await?)
+        .context("Unable to create the log file")?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+
+    tracing_subscriber::fmt()
+        .with_writer(writer.and(std::io::stderr))
+        .with_ansi(false)
+        .with_env_filter(EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| EnvFilter::new("warn,llm_playground=info")))
+        .init();
+    return Ok(guard);
+}
+
+// the last lines across the log files, oldest first
+async fn _tail_logs(lines: usize) -> Result<Vec<String>> {
+    let mut log_files = vec![];
+    let mut entries = match tokio::fs::read_dir(logs_dir().await?).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(error) => return Err(error).context("Unable to read the logs directory")
+    };
+    while let Some(entry) = entries.next_entry().await? {
+        if entry.file_name().to_string_lossy().starts_with(LOG_FILE_PREFIX) {
+            log_files.push(entry.path());
+        }
+    }
+    // the dates in the file names sort them oldest first
+    log_files.sort();
+
+    let mut tail = Vec::<String>::new();
+    for log_file in log_files.iter().rev() {
+        let contents = tokio::fs::read_to_string(log_file).await.context("Unable to read the log file")?;
+        tail.splice(0..0, contents.lines().map(str::to_string));
+        if tail.len() >= lines {
+            break;
+        }
+    }
+
+    return Ok(tail.split_off(tail.len().saturating_sub(lines)));
+}
+
+#[tauri::command]
+pub async fn tail_logs(lines: usize) -> Result<Vec<String>, Error> {
+    _tail_logs(lines).await.map_err(to_serde_err)
+}
@@ -1,18 +1,70 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::{ops::Deref, path::Path};
+use std::{io::Read, path::Path};
 use anyhow::{anyhow, bail, Context, Result};
-use common::{to_serde_err, Config, Conversation, Exchange};
+use common::{to_serde_err, CarryOver, Config, Conversation, ConversationParameters, ConversationSettings, Exchange};
+use common::{EncryptionStatus, ErrorCategory, CONVERSATION_ICONS};
 use migration::{Migrator, MigratorTrait};
-use notify::{RecommendedWatcher, RecursiveMode, Watcher};
-use sea_orm::{ActiveModelTrait, ColumnTrait, Database, EntityTrait, IntoActiveModel};
-use sea_orm::{QueryFilter, QueryOrder, Set, TransactionTrait};
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, Database, EntityTrait, IntoActiveModel};
+use sea_orm::{ConnectOptions, QueryFilter, Set, TransactionTrait};
+use sea_orm::sqlx::sqlite::SqliteJournalMode;
 use serde_error::Error;     // necessary for tauri comamnds since anyhow::Error isn't serializable
-use tauri::Manager;
-use fetch_tokens::build_token_stream;
-
+use tauri::ClipboardManager;
+use api::{get_conversation_json, list_conversations_since};
+use archive::{export_all, import_all};
+use attachments::attach_files;
+use capture::load_last_request;
+use chatgpt::import_chatgpt;
+use dedup::{find_duplicate_conversations, merge_conversations};
+use drafts::{load_draft, save_draft};
+use edit_log::{load_edit_log, restore_edit};
+use export::{expand_home, export_conversation, export_filtered_conversations};
+use fetch_tokens::{build_token_stream, list_models, preview_request, validate_api_key};
+use folders::{add_folder, load_folders, remove_folder, set_conversation_folder, set_folder_name};
+use guardrail::scan_prompt;
+use images::{load_image, save_image};
+use keys::import_env_keys;
+use limits::validate_request;
+use logs::tail_logs;
+use pricing::estimate_cost;
+use secrets::store_secret;
+use settings::{export_settings, import_settings};
+use tags::{add_conversation_tag, load_tags, remove_conversation_tag};
+use telemetry::{clear_error_counts, load_error_counts};
+use titles::retitle_conversations;
+use tokens::count_tokens;
+use usage::load_usage;
+
+mod api;
+mod archive;
+mod attachments;
+mod capture;
+mod chatgpt;
+mod conversations;
+mod dedup;
+mod drafts;
+mod edit_log;
+mod export;
 mod fetch_tokens;
+mod folders;
+mod guardrail;
+mod images;
+mod keys;
+mod legacy;
+mod limits;
+mod logs;
+mod pricing;
+mod secrets;
+mod service_accounts;
+mod settings;
+mod summaries;
+mod tags;
+mod telemetry;
+mod titles;
+mod tokens;
+mod usage;
+mod watcher;
 
 async fn config_dir() -> Result<std::path::PathBuf, Error> {
     let config_dir = dirs::config_dir()
@@ -30,7 +82,7 @@ async fn config_dir() -> Result<std::path::PathBuf, Error> {
 
 #[tauri::command]
 async fn load_config() -> Result<Config, Error> {
-    let config: Config;
+    let mut config: Config;
     let config_path = config_dir().await?.join("config.json");
     match tokio::fs::read_to_string(config_path).await {
         Ok(config_str) => {
@@ -42,60 +94,242 @@ async fn load_config() -> Result<Config, Error> {
             if matches!(error.kind(), std::io::ErrorKind::NotFound) {
                 // create if doesn't exist
                 config = Config::default();
-                save_config(config.clone()).await?;
+                _save_config(None, config.clone()).await.map_err(to_serde_err)?;
             } else {
                 return Err(Error::new(&error));
             }
         }
     }
+    config.dedupe_key_names();
+    if secrets::migrate_plaintext_keys(&mut config).await {
+        _save_config(None, config.clone()).await.map_err(to_serde_err)?;
+    }
 
     return Ok(config);
 }
 
-#[tauri::command]
-async fn save_config(config: Config) -> Result<(), Error> {
+// origin is the window the config was saved from, None for the backend's own writes
+async fn _save_config(origin: Option<&str>, config: Config) -> Result<()> {
     let config_path = config_dir().await?.join("config.json");
-    let serialized_config = serde_json::to_string(&config)
-        .map_err(|error| Error::new(&error))?;
-    tokio::fs::write(config_path, &serialized_config).await
-        .map_err(|error| Error::new(&error))
+    // keys stored by the settings that are never saved stay in the keychain, they can't be told apart
+    if let Ok(saved_config) = tokio::fs::read_to_string(&config_path).await {
+        if let Ok(saved_config) = serde_json::from_str::<Config>(&saved_config) {
+            secrets::delete_removed(&saved_config, &config).await?;
+        }
+    }
+    let serialized_config = serde_json::to_string(&config)?;
+    watcher::mark_own_write(&config_path, origin);
+    tokio::fs::write(config_path, &serialized_config).await.map_err(Into::into)
+}
+
+#[tauri::command]
+async fn save_config(window: tauri::Window, config: Config) -> Result<(), Error> {
+    _save_config(Some(window.label()), config).await.map_err(to_serde_err)
 }
 
-async fn connect_to_database() -> Result<sea_orm::DatabaseConnection> {
+// sqlcipher encrypts the header too, so an encrypted database doesn't start with sqlite's magic string
+fn database_is_encrypted(db_path: &Path) -> bool {
+    let mut header = [0; 16];
+    let read = std::fs::File::open(db_path).and_then(|mut file| file.read_exact(&mut header));
+    read.is_ok() && &header != b"SQLite format 3\0"
+}
+
+// for pragmas and attach statements, which don't take bound parameters
+fn quote_sql(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
+
+async fn connect_to_database(passphrase: Option<&str>) -> Result<sea_orm::DatabaseConnection> {
     let db_path = config_dir().await?
         .join("conversations.db")
         .to_str()
         .map(str::to_string)
         .ok_or(anyhow!("Unable to connect to database."))?;
 
-    Database::connect(&format!("sqlite://{}?mode=rwc", db_path)).await.map_err(Into::into)
+    // WAL lets windows and the file watcher read while another window writes, and the busy timeout
+    // waits out short writes instead of failing with "database is locked"
+    let key = passphrase.map(quote_sql);
+    let mut options = ConnectOptions::new(format!("sqlite://{}?mode=rwc", db_path));
+    options.map_sqlx_sqlite_opts(move |options| {
+        // sqlx runs the key pragma before the others, as sqlcipher requires
+        let options = match &key {
+            Some(key) => options.pragma("key", key.clone()),
+            None => options
+        };
+        options
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(std::time::Duration::from_secs(5))
+            .foreign_keys(true)
+    });
+    Database::connect(options).await.map_err(Into::into)
 }
 
-// the database connection to <config-dir>/conversations.db
+// the database connection to <config-dir>/conversations.db, None while an encrypted database is locked
 // I chose sqlite over json for data consistency
 lazy_static::lazy_static! {
-    static ref CONN: Result<sea_orm::DatabaseConnection> = futures::executor::block_on(connect_to_database());
+    static ref CONN: std::sync::RwLock<Option<sea_orm::DatabaseConnection>> = std::sync::RwLock::new(None);
+}
+
+pub fn database() -> Result<sea_orm::DatabaseConnection> {
+    CONN.read()
+        .map_err(|error| anyhow!("{error}"))?
+        .clone()
+        .ok_or(anyhow!("The database is locked."))
+}
+
+// with the wrong passphrase sqlcipher fails the first read, so a bad unlock never replaces the connection
+async fn open_database(passphrase: Option<&str>) -> Result<()> {
+    let conn = connect_to_database(passphrase).await?;
+    Migrator::up(&conn, None).await?;
+    *CONN.write().map_err(|error| anyhow!("{error}"))? = Some(conn);
+    purge_deleted_conversations().await
+}
+
+async fn _load_encryption_status() -> Result<EncryptionStatus> {
+    Ok(EncryptionStatus {
+        encrypted: database_is_encrypted(&config_dir().await?.join("conversations.db")),
+        locked: CONN.read().map_err(|error| anyhow!("{error}"))?.is_none()
+    })
+}
+
+#[tauri::command]
+async fn load_encryption_status() -> Result<EncryptionStatus, Error> {
+    _load_encryption_status().await.map_err(to_serde_err)
+}
+
+async fn _unlock(passphrase: String) -> Result<()> {
+    if !cfg!(feature = "encryption") {
+        bail!("The database is encrypted, but this build doesn't have the encryption feature.");
+    }
+    if database().is_ok() {
+        return Ok(());
+    }
+
+    open_database(Some(&passphrase)).await.context("Unable to unlock, check the passphrase")?;
+    // API keys kept in plaintext for lack of a keychain move into the encrypted database
+    load_config().await?;
+    return Ok(());
+}
+
+#[tauri::command]
+async fn unlock(passphrase: String) -> Result<(), Error> {
+    _unlock(passphrase).await.map_err(to_serde_err)
+}
+
+// sqlcipher can't encrypt a database in place, so an encrypted copy is exported and replaces it
+async fn _encrypt_database(passphrase: String) -> Result<()> {
+    if !cfg!(feature = "encryption") {
+        bail!("This build doesn't have the encryption feature, build with `--features encryption` to enable it.");
+    }
+    if passphrase.is_empty() {
+        bail!("The passphrase must be non-empty.");
+    }
+    let db_path = config_dir().await?.join("conversations.db");
+    if database_is_encrypted(&db_path) {
+        bail!("The database is already encrypted.");
+    }
+    let encrypted_path = db_path.with_extension("db.encrypted");
+    let encrypted_path_str = encrypted_path.to_str().ok_or(anyhow!("Unable to encrypt the database."))?;
+    // left over from an interrupted attempt
+    if encrypted_path.exists() {
+        tokio::fs::remove_file(&encrypted_path).await?;
+    }
+
+    // commands fail as if locked while the file is swapped, rather than writing to the old one
+    let conn = CONN.write()
+        .map_err(|error| anyhow!("{error}"))?
+        .take()
+        .ok_or(anyhow!("The database is locked."))?;
+    let export = conn.execute_unprepared(&format!("
+        attach database {} as encrypted key {};
+        select sqlcipher_export('encrypted');
+        detach database encrypted;
+    ", quote_sql(encrypted_path_str), quote_sql(&passphrase))).await;
+    if let Err(error) = export {
+        *CONN.write().map_err(|error| anyhow!("{error}"))? = Some(conn);
+        return Err(error).context("Unable to encrypt the database");
+    }
+
+    conn.close().await?;
+    tokio::fs::rename(&encrypted_path, &db_path).await?;
+    // the old write-ahead log doesn't belong to the encrypted database
+    for suffix in ["db-wal", "db-shm"] {
+        let path = db_path.with_extension(suffix);
+        if path.exists() {
+            tokio::fs::remove_file(path).await?;
+        }
+    }
+
+    open_database(Some(&passphrase)).await?;
+    load_config().await?;
+    return Ok(());
+}
+
+#[tauri::command]
+async fn encrypt_database(passphrase: String) -> Result<(), Error> {
+    _encrypt_database(passphrase).await.map_err(to_serde_err)
+}
+
+// other windows and the api write to the same file, so the write lock can be taken for a moment
+const BUSY_DEADLINE: std::time::Duration = std::time::Duration::from_secs(10);
+const INITIAL_BUSY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(25);
+const MAX_BUSY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(500);
+
+// SQLITE_BUSY or one of its extended codes, which sqlx reports as the error's code
+fn is_busy(error: &sea_orm::DbErr) -> bool {
+    let (sea_orm::DbErr::Exec(sea_orm::RuntimeErr::SqlxError(error))
+        | sea_orm::DbErr::Query(sea_orm::RuntimeErr::SqlxError(error))
+        | sea_orm::DbErr::Conn(sea_orm::RuntimeErr::SqlxError(error))) = error else {
+        return false;
+    };
+
+    error.as_database_error()
+        .and_then(|error| error.code())
+        .and_then(|code| code.parse::<i32>().ok())
+        .is_some_and(|code| code & 0xff == 5)
+}
+
+// writes show up as changes to the database and its write-ahead log
+async fn mark_database_write(window: Option<&str>) -> Result<()> {
+    let config_dir = config_dir().await?;
+    watcher::mark_own_write(&config_dir.join("conversations.db"), window);
+    watcher::mark_own_write(&config_dir.join("conversations.db-wal"), window);
+    return Ok(());
+}
+
+// the write lock is taken up front, like BEGIN IMMEDIATE, so a busy database is retried here until the deadline
+// rather than failing a write partway through the transaction. sqlx 0.7 only begins deferred transactions,
+// so the lock is taken by a write that changes nothing
+async fn begin_immediate(conn: &sea_orm::DatabaseConnection) -> Result<sea_orm::DatabaseTransaction> {
+    let deadline = std::time::Instant::now() + BUSY_DEADLINE;
+    let mut backoff = INITIAL_BUSY_BACKOFF;
+    loop {
+        let txn = conn.begin().await?;
+        match txn.execute_unprepared("update conversations set id = id where 0").await {
+            Ok(_) => return Ok(txn),
+            Err(error) if is_busy(&error) && std::time::Instant::now() + backoff < deadline => {
+                txn.rollback().await?;
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BUSY_BACKOFF);
+            },
+            Err(error) => return Err(error).context("Unable to start a database transaction")
+        }
+    }
 }
 
 async fn initiate_transaction() -> Result<sea_orm::DatabaseTransaction> {
-    CONN.as_ref().map_err(Deref::deref)?.begin().await.map_err(Into::into)
+    initiate_window_transaction(None).await
+}
+
+// for writes made from a window, so its history can tell them from other windows' writes
+async fn initiate_window_transaction(window: Option<&str>) -> Result<sea_orm::DatabaseTransaction> {
+    mark_database_write(window).await?;
+    begin_immediate(&database()?).await
 }
 
+// archived conversations are left out
 async fn _load_conversations() -> Result<Vec<Conversation>> {
-    let conn = CONN.as_ref().map_err(Deref::deref)?;
-    let conversations = entity::conversations::Entity::find()
-        .find_also_related(entity::exchanges::Entity)
-        .order_by_desc(entity::conversations::Column::LastUpdated)
-        .all(conn).await?
-        .into_iter()
-        .filter_map(|(conversation, exchange)| Some(Conversation {
-            uuid: uuid::Uuid::from_slice(&conversation.uuid).ok()?,
-            last_updated: chrono::DateTime::from_timestamp(conversation.last_updated, 0)?,
-            title: exchange?.user_message,
-        }))
-        .collect();
-
-    return Ok(conversations);
+    conversations::load_conversations(&database()?, false).await
 }
 
 #[tauri::command]
@@ -103,225 +337,481 @@ async fn load_conversations() -> Result<Vec<Conversation>, Error> {
     _load_conversations().await.map_err(to_serde_err)
 }
 
-async fn add_exchanges(
-    conversation_id: i32,
-    exchanges: Vec<(usize, Exchange)>,
-    txn: &sea_orm::DatabaseTransaction
-) -> Result<Vec<entity::exchanges::Model>> {
-    futures::future::join_all(exchanges.into_iter().map(|(key, exchange)| async move {
-        entity::exchanges::ActiveModel {
-            key: Set(key.try_into()?),
-            user_message: Set(exchange.user_message),
-            assistant_message: Set(exchange.assistant_message),
-            conversation: Set(conversation_id),
-            ..Default::default()
-        }.insert(txn).await.map_err(anyhow::Error::from)
-    })).await.into_iter().collect::<Result<Vec<_>, _>>()
+async fn _load_archived_conversations() -> Result<Vec<Conversation>> {
+    conversations::load_conversations(&database()?, true).await
+}
+
+#[tauri::command]
+async fn load_archived_conversations() -> Result<Vec<Conversation>, Error> {
+    _load_archived_conversations().await.map_err(to_serde_err)
+}
+
+async fn _set_conversation_archived(conversation_uuid: uuid::Uuid, archived: bool) -> Result<()> {
+    let txn = initiate_transaction().await?;
+    conversations::set_archived(&txn, conversation_uuid, archived).await?;
+    txn.commit().await?;
+
+    return Ok(());
+}
+
+#[tauri::command]
+async fn set_conversation_archived(conversation_uuid: uuid::Uuid, archived: bool) -> Result<(), Error> {
+    _set_conversation_archived(conversation_uuid, archived).await.map_err(to_serde_err)
+}
+
+async fn _load_conversation(conversation_uuid: uuid::Uuid) -> Result<Conversation> {
+    conversations::load_conversation(&database()?, conversation_uuid).await
+}
+
+#[tauri::command]
+async fn load_conversation(conversation_uuid: uuid::Uuid) -> Result<Conversation, Error> {
+    _load_conversation(conversation_uuid).await.map_err(to_serde_err)
+}
+
+async fn _save_conversation_parameters(
+    window: Option<&str>,
+    conversation_uuid: uuid::Uuid,
+    parameters: ConversationParameters
+) -> Result<()> {
+    let txn = initiate_window_transaction(window).await?;
+    let mut conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(&txn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?
+        .into_active_model();
+
+    conversation.model = Set(Some(parameters.model));
+    conversation.temperature = Set(Some(parameters.temperature));
+    conversation.max_tokens = Set(Some(parameters.max_tokens.try_into()?));
+    conversation.system_prompt = Set(Some(parameters.system_prompt));
+    conversation.update(&txn).await?;
+    txn.commit().await?;
+
+    return Ok(());
+}
+
+#[tauri::command]
+async fn save_conversation_parameters(
+    window: tauri::Window,
+    conversation_uuid: uuid::Uuid,
+    parameters: ConversationParameters
+) -> Result<(), Error> {
+    _save_conversation_parameters(Some(window.label()), conversation_uuid, parameters).await.map_err(to_serde_err)
 }
 
 async fn _add_conversation(
-    mut exchanges: Vec<(usize, Exchange)>,
-    txn: sea_orm::DatabaseTransaction
+    window: Option<&str>,
+    exchanges: Vec<(usize, Exchange)>,
+    carry_over: Option<CarryOver>,
+    settings: ConversationSettings,
+    parameters: Option<ConversationParameters>
 ) -> Result<uuid::Uuid> {
-    if exchanges.is_empty() {
-        bail!("Conversation cannot be set empty.");
-    }
-    let (first_exchange_key, first_exchange) = exchanges.remove(0);
-    let first_exchange = entity::exchanges::ActiveModel {
-        key: Set(first_exchange_key.try_into()?),
-        user_message: Set(first_exchange.user_message),
-        assistant_message: Set(first_exchange.assistant_message),
-        // the foreign key constraint is deferred until transaction is committed
-        // so this is okay as long as it's changed later
-        conversation: Set(-1),
-        ..Default::default()
-    }.insert(&txn).await?;
-
-    let conversation_uuid = uuid::Uuid::new_v4();
-    let conversation = entity::conversations::ActiveModel {
-        uuid: Set(conversation_uuid.into()),
-        last_updated: Set(chrono::Utc::now().timestamp()),
-        first_exchange: Set(first_exchange.id),
-        ..Default::default()
-    }.insert(&txn).await?;
-
-    add_exchanges(conversation.id, exchanges, &txn).await?;
-    let mut first_exchange = first_exchange.into_active_model();
-    first_exchange.conversation = Set(conversation.id);     // fixed first_exchange foreign key
-    first_exchange.update(&txn).await?;
-
+    let txn = initiate_window_transaction(window).await?;
+    let conversation_uuid = conversations::add_conversation(&txn, exchanges, carry_over, settings, parameters).await?;
     txn.commit().await?;
 
     return Ok(conversation_uuid);
 }
 
 #[tauri::command]
-async fn add_conversation(exchanges: Vec<(usize, Exchange)>) -> Result<uuid::Uuid, Error> {
-    let txn = initiate_transaction().await.map_err(to_serde_err)?;
-    _add_conversation(exchanges, txn).await.map_err(to_serde_err)
+async fn add_conversation(
+    window: tauri::Window,
+    exchanges: Vec<(usize, Exchange)>,
+    carry_over: Option<CarryOver>,
+    settings: ConversationSettings,
+    parameters: ConversationParameters
+) -> Result<uuid::Uuid, Error> {
+    _add_conversation(Some(window.label()), exchanges, carry_over, settings, Some(parameters)).await
+        .inspect_err(|error| telemetry::record_error(error, ErrorCategory::Database, None)).map_err(to_serde_err)
+}
+
+async fn purge_deleted_conversations() -> Result<()> {
+    let retention_days = load_config().await?.trash.retention_days;
+    conversations::purge_deleted(&database()?, retention_days).await
 }
 
+// restorable with restore_conversation until the trash's retention period is over
 async fn _delete_conversation(conversation_uuid: uuid::Uuid) -> Result<()> {
     let txn = initiate_transaction().await?;
+    conversations::delete_conversation(&txn, conversation_uuid).await?;
+    txn.commit().await?;
 
-    let conversation = entity::conversations::Entity::find()
-        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
-        .one(&txn).await?
-        .map(entity::conversations::Model::into_active_model)
-        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+    // purged here too so the trash is emptied without restarting
+    purge_deleted_conversations().await
+}
+
+#[tauri::command]
+async fn delete_conversation(conversation_uuid: uuid::Uuid) -> Result<(), Error> {
+    _delete_conversation(conversation_uuid).await.map_err(to_serde_err)
+}
+
+async fn _duplicate_conversation(conversation_uuid: uuid::Uuid) -> Result<uuid::Uuid> {
+    let txn = initiate_transaction().await?;
+    let copy_uuid = conversations::duplicate_conversation(&txn, conversation_uuid).await?;
+    txn.commit().await?;
 
-    entity::conversations::Entity::delete(conversation).exec(&txn).await?;
+    return Ok(copy_uuid);
+}
+
+#[tauri::command]
+async fn duplicate_conversation(conversation_uuid: uuid::Uuid) -> Result<uuid::Uuid, Error> {
+    _duplicate_conversation(conversation_uuid).await.map_err(to_serde_err)
+}
 
+async fn _restore_conversation(conversation_uuid: uuid::Uuid) -> Result<()> {
+    let txn = initiate_transaction().await?;
+    conversations::restore_conversation(&txn, conversation_uuid).await?;
     txn.commit().await?;
 
     return Ok(());
 }
 
-#[tauri::command(rename_all = "snake_case")]
-async fn delete_conversation(conversation_uuid: uuid::Uuid) -> Result<(), Error> {
-    _delete_conversation(conversation_uuid).await.map_err(to_serde_err)
+#[tauri::command]
+async fn restore_conversation(conversation_uuid: uuid::Uuid) -> Result<(), Error> {
+    _restore_conversation(conversation_uuid).await.map_err(to_serde_err)
+}
+
+async fn _toggle_pin(conversation_uuid: uuid::Uuid) -> Result<bool> {
+    let txn = initiate_transaction().await?;
+    let pinned = conversations::toggle_pin(&txn, conversation_uuid).await?;
+    txn.commit().await?;
+
+    return Ok(pinned);
+}
+
+#[tauri::command]
+async fn toggle_pin(conversation_uuid: uuid::Uuid) -> Result<bool, Error> {
+    _toggle_pin(conversation_uuid).await.map_err(to_serde_err)
 }
 
 async fn _load_exchanges(conversation_uuid: uuid::Uuid) -> Result<Vec<(usize, Exchange)>> {
-    let conn = CONN.as_ref().map_err(Deref::deref)?;
+    conversations::load_exchanges(&database()?, conversation_uuid).await
+}
+
+#[tauri::command]
+async fn load_exchanges(conversation_uuid: uuid::Uuid) -> Result<Vec<(usize, Exchange)>, Error> {
+    _load_exchanges(conversation_uuid).await.map_err(to_serde_err)
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let mut chars = text.trim().chars();
+    let truncated = chars.by_ref().take(max_chars).collect::<String>();
+    if chars.next().is_some() {
+        return truncated + "...";
+    }
+
+    return truncated;
+}
+
+const CARRY_OVER_PROMPT: &str = "Summarize the conversation below for an assistant starting a new conversation \
+that continues it. Keep the facts, decisions, names and open questions the new conversation could depend on, in a \
+few short paragraphs, and leave out pleasantries. Reply with the summary alone.";
+
+// the summary is made with the configured model, and kept short since it's sent with every request in the new
+// conversation. the most recent exchanges are summarized once the transcript would pass max_total_chars
+async fn _summarize_conversation(conversation_uuid: uuid::Uuid) -> Result<CarryOver> {
+    let max_chars = 2000;
+    let max_total_chars = 24000;
+
+    let exchanges = _load_exchanges(conversation_uuid).await?;
+    let mut excerpts = vec![];
+    let mut total_chars = 0;
+    for (_, exchange) in exchanges.iter().rev() {
+        let excerpt = format!("\n\nUser: {}\n\nAssistant: {}", truncate(&exchange.user_message, max_chars),
+            truncate(&exchange.assistant_message, max_chars));
+        total_chars += excerpt.chars().count();
+        if total_chars > max_total_chars && !excerpts.is_empty() {
+            break;
+        }
+        excerpts.push(excerpt);
+    }
+    let transcript = excerpts.into_iter().rev().collect::<String>();
+
+    let config = fetch_tokens::background_config(load_config().await?, CARRY_OVER_PROMPT);
+    let summary = fetch_tokens::complete(config, transcript.trim(), vec![]).await
+        .context("Unable to summarize the previous conversation")?;
+    let summary = format!("This conversation continues a previous one. A summary of the previous conversation:\n{}",
+        summary.trim());
+
+    return Ok(CarryOver { conversation_uuid, summary });
+}
+
+#[tauri::command]
+async fn summarize_conversation(conversation_uuid: uuid::Uuid) -> Result<CarryOver, Error> {
+    _summarize_conversation(conversation_uuid).await.map_err(to_serde_err)
+}
+
+async fn _load_carry_over(conversation_uuid: uuid::Uuid) -> Result<Option<CarryOver>> {
+    let conn = &database()?;
 
-    // find the conversation
     let conversation = entity::conversations::Entity::find()
         .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
         .one(conn).await?
         .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
 
-    // load all exchanges in the conversation
-    let exchanges = entity::exchanges::Entity::find()
-        .filter(entity::exchanges::Column::Conversation.eq(conversation.id))
-        .order_by_asc(entity::exchanges::Column::Key)
-        .all(conn).await?
-        .into_iter()
-        .map(|exchange| (exchange.key as usize, Exchange {
-            user_message: exchange.user_message,
-            assistant_message: exchange.assistant_message,
-        }))
-        .collect();
+    let (Some(continued_from), Some(summary)) = (conversation.continued_from, conversation.carry_over_summary)
+    else {
+        return Ok(None);
+    };
 
-    return Ok(exchanges);
+    return Ok(Some(CarryOver { conversation_uuid: uuid::Uuid::from_slice(&continued_from)?, summary }));
 }
 
-#[tauri::command(rename_all = "snake_case")]
-async fn load_exchanges(conversation_uuid: uuid::Uuid) -> Result<Vec<(usize, Exchange)>, Error> {
-    _load_exchanges(conversation_uuid).await.map_err(to_serde_err)
+#[tauri::command]
+async fn load_carry_over(conversation_uuid: uuid::Uuid) -> Result<Option<CarryOver>, Error> {
+    _load_carry_over(conversation_uuid).await.map_err(to_serde_err)
 }
 
-async fn _set_exchanges(
+async fn _load_conversation_settings(conversation_uuid: uuid::Uuid) -> Result<ConversationSettings> {
+    let conn = &database()?;
+
+    let conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(conn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+
+    let Some(settings) = conversation.settings else {
+        return Ok(ConversationSettings::default());
+    };
+    serde_json::from_str(&settings).context("Unable to parse conversation settings")
+}
+
+#[tauri::command]
+async fn load_conversation_settings(conversation_uuid: uuid::Uuid) -> Result<ConversationSettings, Error> {
+    _load_conversation_settings(conversation_uuid).await.map_err(to_serde_err)
+}
+
+async fn _save_conversation_settings(
     conversation_uuid: uuid::Uuid,
-    exchanges: Vec<(usize, Exchange)>
-) -> Result<Option<uuid::Uuid>> {
+    mut settings: ConversationSettings
+) -> Result<()> {
+    // stored expanded so it doesn't depend on the home directory when used
+    if let Some(workspace) = &mut settings.workspace {
+        let path = expand_home(workspace.trim())?;
+        if !path.is_dir() {
+            bail!("{} is not a directory.", path.display());
+        }
+        *workspace = path.display().to_string();
+    }
+
     let txn = initiate_transaction().await?;
 
     let conversation = entity::conversations::Entity::find()
         .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
-        .one(&txn).await?;
-    let Some(conversation) = conversation else {
-        // add conversation if doesn't exist (i.e. another window deleted it
-        // when the current window still had it loaded and expected it to exist
-        return Ok(Some(_add_conversation(exchanges, txn).await?));
-    };
+        .one(&txn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+    // the icon and title are only changed by set_conversation_icon and retitling, the caller's copy may be stale
+    let stored_settings = conversation.settings.as_deref()
+        .and_then(|settings| serde_json::from_str::<ConversationSettings>(settings).ok())
+        .unwrap_or_default();
+    settings.icon = stored_settings.icon;
+    settings.title = stored_settings.title;
 
-    let old_exchanges = entity::exchanges::Entity::find()
-        .filter(entity::exchanges::Column::Conversation.eq(conversation.id))
-        .all(&txn).await?;
+    let mut conversation = conversation.into_active_model();
+    conversation.settings = Set(Some(serde_json::to_string(&settings)?));
+    conversation.update(&txn).await?;
 
-    let exchanges = add_exchanges(conversation.id, exchanges, &txn).await?;
-    let first_exchange = exchanges.get(0).ok_or(anyhow!("Conversation cannot be set empty."))?;
+    txn.commit().await?;
+
+    return Ok(());
+}
+
+async fn _set_conversation_icon(conversation_uuid: uuid::Uuid, icon: Option<String>) -> Result<()> {
+    if icon.as_deref().is_some_and(|icon| !CONVERSATION_ICONS.contains(&icon)) {
+        bail!("Unknown conversation icon.");
+    }
+
+    let txn = initiate_transaction().await?;
+
+    // only the icon changes, so a settings save from another window isn't undone
+    let conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(&txn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+    let mut settings = conversation.settings.as_deref()
+        .map(serde_json::from_str::<ConversationSettings>)
+        .transpose()?
+        .unwrap_or_default();
+    settings.icon = icon;
 
     let mut conversation = conversation.into_active_model();
-    conversation.first_exchange = Set(first_exchange.id);
-    conversation.last_updated = Set(chrono::Utc::now().timestamp());
+    conversation.settings = Set(Some(serde_json::to_string(&settings)?));
     conversation.update(&txn).await?;
 
-    futures::future::join_all(old_exchanges.into_iter()
-        .map(entity::exchanges::Model::into_active_model)
-        .map(|exchange| entity::exchanges::Entity::delete(exchange).exec(&txn))
-    ).await.into_iter().collect::<Result<Vec<_>, _>>()?;
+    txn.commit().await?;
 
+    return Ok(());
+}
+
+#[tauri::command]
+async fn set_conversation_icon(conversation_uuid: uuid::Uuid, icon: Option<String>) -> Result<(), Error> {
+    _set_conversation_icon(conversation_uuid, icon).await.map_err(to_serde_err)
+}
+
+#[tauri::command]
+async fn save_conversation_settings(
+    conversation_uuid: uuid::Uuid,
+    settings: ConversationSettings
+) -> Result<(), Error> {
+    _save_conversation_settings(conversation_uuid, settings).await.map_err(to_serde_err)
+}
+
+pub async fn _set_exchanges(
+    window: Option<&str>,
+    conversation_uuid: uuid::Uuid,
+    exchanges: Vec<(usize, Exchange)>
+) -> Result<Option<uuid::Uuid>> {
+    let txn = initiate_window_transaction(window).await?;
+    let new_uuid = conversations::set_exchanges(&txn, conversation_uuid, exchanges).await?;
     txn.commit().await?;
+    summaries::schedule(new_uuid.unwrap_or(conversation_uuid));
 
-    return Ok(None);
+    return Ok(new_uuid);
 }
 
-#[tauri::command(rename_all = "snake_case")]
+#[tauri::command]
 async fn set_exchanges(
+    window: tauri::Window,
     conversation_uuid: uuid::Uuid,
     exchanges: Vec<(usize, Exchange)>
 ) -> Result<Option<uuid::Uuid>, Error> {
-    _set_exchanges(conversation_uuid, exchanges).await.map_err(to_serde_err)
+    _set_exchanges(Some(window.label()), conversation_uuid, exchanges).await
+        .inspect_err(|error| telemetry::record_error(error, ErrorCategory::Database, None))
+        .map_err(to_serde_err)
 }
 
-fn watch_file(app: tauri::AppHandle, event_name: &'static str, file: &Path) -> Result<()> {
-    let (sender, recv) = std::sync::mpsc::channel::<Result<notify::Event, notify::Error>>();
+// the webview's clipboard api needs focus and permissions, so copying goes through tauri
+#[tauri::command]
+async fn copy_to_clipboard(app: tauri::AppHandle, text: String) -> Result<(), Error> {
+    app.clipboard_manager().write_text(text).map_err(|error| to_serde_err(error.into()))
+}
 
-    let emit = move || app.emit_all(event_name, ())
-        .unwrap_or_else(|error| eprintln!("Error triggering {event_name}: {error}"));
+// the webview's document title doesn't carry over to the native window
+#[tauri::command]
+async fn set_window_title(window: tauri::Window, title: String) -> Result<(), Error> {
+    window.set_title(&title).map_err(|error| to_serde_err(error.into()))
+}
 
-    std::thread::spawn(move || loop {
-        let event= match recv.recv() {
-            Ok(Ok(event)) => event,
-            Ok(Err(error)) => {
-                eprintln!("Error listening for {event_name}: {error}");
-                emit();
-                continue;
-            },
-            // this means the recv is closed, should never happen
-            Err(_) => {
-                eprintln!("Watcher disconnected!");
-                // not breaking will result in an infinite loop
-                break;
-            }
-        };
-        match event.kind {
-            notify::EventKind::Create(_)
-            | notify::EventKind::Modify(_)
-            | notify::EventKind::Remove(_) => emit(),
-            // ignore miscellaneous events
-            _ => continue
-        }
-    });
+async fn _request_attention(window: tauri::Window) -> Result<()> {
+    if !window.is_focused()? {
+        window.request_user_attention(Some(tauri::UserAttentionType::Informational))?;
+    }
 
-    let mut watcher = RecommendedWatcher::new(sender, Default::default())?;
-    watcher.watch(file, RecursiveMode::Recursive)?;
-    std::mem::forget(watcher);
+    return Ok(());
+}
 
-    Ok(())
+// tauri 1 has no taskbar progress, so finished responses are signalled this way instead
+#[tauri::command]
+async fn request_attention(window: tauri::Window) -> Result<(), Error> {
+    _request_attention(window).await.map_err(to_serde_err)
 }
 
 async fn watch_config_and_conversations(app: tauri::AppHandle) -> Result<()> {
-    watch_file(app.clone(), "config_updated", &config_dir().await?.join("config.json"))?;
-    watch_file(app, "conversations_updated", &config_dir().await?.join("conversations.db"))
+    watcher::watch(&app, &config_dir().await?.join("config.json"), "config_updated")?;
+    watcher::watch(&app, &config_dir().await?.join("conversations.db"), "conversations_updated")?;
+    // in WAL mode writes land in the -wal file until they're checkpointed into the database. while an encrypted
+    // database is locked the file may not exist yet, and is watched once it does
+    watcher::watch(&app, &config_dir().await?.join("conversations.db-wal"), "conversations_updated")
+}
+
+// registers every command in the shared list, so one missing here fails to compile
+macro_rules! handler {
+    ($($name:ident($($(#[$($attribute:tt)*])* $argument:ident: $type:ty),*) -> $output:ty;)*) => {
+        tauri::generate_handler![$($name),*]
+    };
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let _log_guard = logs::init().await?;
     if !&config_dir().await?.join("config.json").exists() {
-        save_config(Config::default()).await?;
+        _save_config(None, Config::default()).await?;
     }
+    legacy::upgrade_legacy_config().await?;
 
-    let conn = CONN.as_ref().map_err(Deref::deref)?;
-    Migrator::up(conn, None).await?;
+    // an encrypted database is opened by the unlock command once the passphrase is entered
+    if !database_is_encrypted(&config_dir().await?.join("conversations.db")) {
+        open_database(None).await?;
+    }
+
+    // the read-only api's commands print their output and exit without opening a window
+    if let Some(output) = api::run_command(&std::env::args().skip(1).collect::<Vec<_>>()).await? {
+        println!("{output}");
+        return Ok(());
+    }
 
     tauri::Builder::default()
         .setup(|app| {
+            tauri::async_runtime::spawn(export::run_export_scheduler());
+            summaries::spawn_worker();
             let app = app.handle();
             futures::executor::block_on(watch_config_and_conversations(app)).map_err(Into::into)
         })
-        .invoke_handler(tauri::generate_handler![
-            add_conversation,
-            build_token_stream,
-            delete_conversation,
-            load_config,
-            load_conversations,
-            load_exchanges,
-            save_config,
-            set_exchanges
-        ])
+        .invoke_handler(commands::with_commands!(handler))
         .run(tauri::generate_context!())
         .map_err(Into::into)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // like connect_to_database, so writers wait out each other's locks the way the app's windows do
+    async fn connect(db_path: &Path) -> sea_orm::DatabaseConnection {
+        let mut options = ConnectOptions::new(format!("sqlite://{}?mode=rwc", db_path.display()));
+        options.sqlx_logging(false).map_sqlx_sqlite_opts(|options| options
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(std::time::Duration::from_secs(5))
+            .foreign_keys(true));
+        Database::connect(options).await.unwrap()
+    }
+
+    // two windows appending to the same conversation at once, each reading it and saving it with one more exchange
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn concurrent_writes_are_kept() {
+        const WRITES: usize = 20;
+        let db_path = std::env::temp_dir().join(format!("llm-playground-{}.db", uuid::Uuid::new_v4()));
+        let conn = connect(&db_path).await;
+        Migrator::up(&conn, None).await.unwrap();
+
+        let txn = begin_immediate(&conn).await.unwrap();
+        let first_exchange = Exchange { user_message: "first".into(), ..Default::default() };
+        let conversation_uuid = conversations::add_conversation(&txn, vec![(0, first_exchange)], None,
+            Default::default(), None).await.unwrap();
+        txn.commit().await.unwrap();
+
+        let windows = (0..2).map(|window| {
+            let db_path = db_path.clone();
+            tokio::spawn(async move {
+                let conn = connect(&db_path).await;
+                for write in 0..WRITES {
+                    let txn = begin_immediate(&conn).await.unwrap();
+                    let mut exchanges = conversations::load_exchanges(&txn, conversation_uuid).await.unwrap();
+                    let key = exchanges.last().map_or(0, |(key, _)| key + 1);
+                    let user_message = format!("{window}:{write}");
+                    exchanges.push((key, Exchange { user_message, ..Default::default() }));
+                    conversations::set_exchanges(&txn, conversation_uuid, exchanges).await.unwrap();
+                    txn.commit().await.unwrap();
+                }
+                conn.close().await.unwrap();
+            })
+        }).collect::<Vec<_>>();
+        for window in futures::future::join_all(windows).await {
+            window.unwrap();
+        }
+
+        let exchanges = conversations::load_exchanges(&conn, conversation_uuid).await.unwrap();
+        conn.close().await.unwrap();
+        for suffix in ["db", "db-wal", "db-shm"] {
+            let _ = std::fs::remove_file(db_path.with_extension(suffix));
+        }
+
+        assert_eq!(exchanges.len(), 1 + 2 * WRITES);
+        for window in 0..2 {
+            for write in 0..WRITES {
+                let user_message = format!("{window}:{write}");
+                assert!(exchanges.iter().any(|(_, exchange)| exchange.user_message == user_message));
+            }
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use common::Usage;
+
+struct ModelPrice {
+    // dollars per million tokens
+    input: f64,
+    output: f64
+}
+
+// list prices, matched by the longest prefix like the limits table
+const MODEL_PRICES: [(&str, ModelPrice); 20] = [
+    ("gpt-3.5-turbo", ModelPrice { input: 0.5, output: 1.5 }),
+    ("gpt-4", ModelPrice { input: 30.0, output: 60.0 }),
+    ("gpt-4-turbo", ModelPrice { input: 10.0, output: 30.0 }),
+    ("gpt-4o", ModelPrice { input: 2.5, output: 10.0 }),
+    ("gpt-4o-mini", ModelPrice { input: 0.15, output: 0.6 }),
+    ("o1", ModelPrice { input: 15.0, output: 60.0 }),
+    ("o1-mini", ModelPrice { input: 1.1, output: 4.4 }),
+    ("o3-mini", ModelPrice { input: 1.1, output: 4.4 }),
+    ("claude-3-haiku", ModelPrice { input: 0.25, output: 1.25 }),
+    ("claude-3-sonnet", ModelPrice { input: 3.0, output: 15.0 }),
+    ("claude-3-opus", ModelPrice { input: 15.0, output: 75.0 }),
+    ("claude-3-5-haiku", ModelPrice { input: 0.8, output: 4.0 }),
+    ("claude-3-5-sonnet", ModelPrice { input: 3.0, output: 15.0 }),
+    ("claude-3-7-sonnet", ModelPrice { input: 3.0, output: 15.0 }),
+    ("gemini-1.0-pro", ModelPrice { input: 0.5, output: 1.5 }),
+    ("gemini-1.5-flash", ModelPrice { input: 0.075, output: 0.3 }),
+    ("gemini-1.5-pro", ModelPrice { input: 1.25, output: 5.0 }),
+    ("gemini-2.0-flash", ModelPrice { input: 0.1, output: 0.4 }),
+    ("gemini-2.5-flash", ModelPrice { input: 0.3, output: 2.5 }),
+    ("gemini-2.5-pro", ModelPrice { input: 1.25, output: 10.0 })
+];
+
+fn model_price(model: &str) -> Option<&'static ModelPrice> {
+    MODEL_PRICES.iter()
+        .filter(|(prefix, _)| model.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.len())
+        .map(|(_, price)| price)
+}
+
+// None rather than a partial cost if either token count wasn't reported
+pub fn cost(model: &str, usage: &Usage) -> Option<f64> {
+    let price = model_price(model)?;
+    let (prompt_tokens, completion_tokens) = (usage.prompt_tokens?, usage.completion_tokens?);
+    return Some((prompt_tokens as f64 * price.input + completion_tokens as f64 * price.output) / 1_000_000.0);
+}
+
+// called once a response finishes, the cost is then saved with the exchange
+#[tauri::command]
+pub async fn estimate_cost(model: String, usage: Usage) -> Option<f64> {
+    cost(&model, &usage)
+}
@@ -0,0 +1,117 @@
+use anyhow::{anyhow, bail, Context, Result};
+use common::{to_serde_err, APIKey, Config};
+use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+use serde_error::Error;
+use crate::{config_dir, database, database_is_encrypted};
+
+const SERVICE: &str = "llm-playground";
+// config.json holds one of these prefixes and the secret's name in place of the key itself
+const KEYCHAIN_PREFIX: &str = "keychain:";
+const DATABASE_PREFIX: &str = "database:";
+
+fn entry(name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, name).context("Unable to access the OS keychain")
+}
+
+pub fn is_reference(key: &str) -> bool {
+    key.starts_with(KEYCHAIN_PREFIX) || key.starts_with(DATABASE_PREFIX)
+}
+
+// without a keychain, e.g. linux without a secret service, keys can only be kept in an encrypted database
+async fn store_in_database(name: &str, secret: &str) -> Result<()> {
+    if !database_is_encrypted(&config_dir().await?.join("conversations.db")) {
+        bail!("The database isn't encrypted.");
+    }
+
+    entity::secrets::ActiveModel {
+        name: Set(name.to_string()),
+        secret: Set(secret.to_string())
+    }.insert(&database()?).await?;
+    return Ok(());
+}
+
+// returns the reference to save in the config
+pub async fn store(secret: &str) -> Result<String> {
+    let name = uuid::Uuid::new_v4().to_string();
+    let stored = entry(&name)?.set_password(secret).context("Unable to store the key in the OS keychain");
+    match stored {
+        Ok(()) => Ok(format!("{KEYCHAIN_PREFIX}{name}")),
+        Err(error) => match store_in_database(&name, secret).await {
+            Ok(()) => Ok(format!("{DATABASE_PREFIX}{name}")),
+            Err(_) => Err(error)
+        }
+    }
+}
+
+// keys that aren't references, i.e. blank or kept in plaintext, are returned as is
+pub async fn get(reference: &str) -> Result<String> {
+    if let Some(name) = reference.strip_prefix(KEYCHAIN_PREFIX) {
+        return entry(name)?.get_password().context("Unable to read the key from the OS keychain");
+    }
+    if let Some(name) = reference.strip_prefix(DATABASE_PREFIX) {
+        let secret = entity::secrets::Entity::find_by_id(name)
+            .one(&database()?)
+            .await?
+            .ok_or(anyhow!("The key is missing from the database."))?;
+        return Ok(secret.secret);
+    }
+
+    return Ok(reference.to_string());
+}
+
+pub async fn delete(reference: &str) -> Result<()> {
+    if let Some(name) = reference.strip_prefix(KEYCHAIN_PREFIX) {
+        match entry(name)?.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => (),
+            Err(error) => return Err(error).context("Unable to remove the key from the OS keychain")
+        }
+    }
+    if let Some(name) = reference.strip_prefix(DATABASE_PREFIX) {
+        entity::secrets::Entity::delete_by_id(name).exec(&database()?).await?;
+    }
+
+    return Ok(());
+}
+
+pub async fn resolve_api_key(api_key: &APIKey) -> Result<APIKey> {
+    Ok(APIKey { key: get(&api_key.key).await?, ..api_key.clone() })
+}
+
+// moves plaintext keys out of config.json, returns whether any were moved.
+// the keys stay in plaintext if there is nowhere safer to put them
+pub async fn migrate_plaintext_keys(config: &mut Config) -> bool {
+    let mut migrated = false;
+    let plaintext_keys = config.api_keys.iter_mut()
+        .filter(|api_key| !api_key.key.is_empty() && !is_reference(&api_key.key));
+    for api_key in plaintext_keys {
+        match store(&api_key.key).await {
+            Ok(reference) => {
+                api_key.key = reference;
+                migrated = true;
+            },
+            Err(error) => {
+                tracing::warn!("Keeping API keys in config.json: {error:?}");
+                break;
+            }
+        }
+    }
+
+    return migrated;
+}
+
+// removes the stored secrets of keys no longer in the config
+pub async fn delete_removed(saved_config: &Config, config: &Config) -> Result<()> {
+    let references = saved_config.api_keys.iter()
+        .map(|api_key| &api_key.key)
+        .filter(|key| is_reference(key) && !config.api_keys.iter().any(|api_key| &api_key.key == *key));
+    for reference in references {
+        delete(reference).await?;
+    }
+
+    return Ok(());
+}
+
+#[tauri::command]
+pub async fn store_secret(secret: String) -> Result<String, Error> {
+    store(&secret).await.map_err(to_serde_err)
+}
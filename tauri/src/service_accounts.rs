@@ -0,0 +1,112 @@
+use std::{collections::HashMap, sync::Mutex, time::{Duration, Instant}};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::expand_home;
+
+// the access tokens vertex ai accepts, https://developers.google.com/identity/protocols/oauth2/service-account
+const SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const DEFAULT_TOKEN_URI: &str = "https://oauth2.googleapis.com/token";
+// google caps assertions at an hour
+const ASSERTION_LIFETIME: u64 = 3600;
+// tokens are refreshed this long before they expire, so one can't expire mid-request
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+lazy_static::lazy_static! {
+    // access tokens by the service account's email and key id, with when they expire
+    static ref TOKENS: Mutex<HashMap<(String, String), (String, Instant)>> = Mutex::new(HashMap::new());
+}
+
+// the fields used from the json key file the google cloud console downloads
+#[derive(Deserialize)]
+struct ServiceAccount {
+    #[serde(rename = "type")]
+    account_type: String,
+    client_email: String,
+    private_key_id: String,
+    private_key: String,
+    token_uri: Option<String>
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: u64,
+    exp: u64
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: u64
+}
+
+// whether a key is a service account's json key rather than an access token
+pub fn is_service_account(key: &str) -> bool {
+    key.trim_start().starts_with('{')
+}
+
+// a key ending in .json is the path of the key file, read on every request so a replaced file is picked up
+pub async fn read_key_file(key: &str) -> Result<Option<String>> {
+    let key = key.trim();
+    if !key.to_lowercase().ends_with(".json") {
+        return Ok(None);
+    }
+
+    let path = expand_home(key)?;
+    let contents = tokio::fs::read_to_string(&path).await
+        .with_context(|| format!("Unable to read the service account key file {}", path.display()))?;
+    return Ok(Some(contents));
+}
+
+async fn exchange(service_account: &ServiceAccount) -> Result<(String, Instant)> {
+    let token_uri = service_account.token_uri.as_deref().unwrap_or(DEFAULT_TOKEN_URI);
+    let iat = chrono::Utc::now().timestamp().max(0) as u64;
+    let claims = Claims { iss: &service_account.client_email, scope: SCOPE, aud: token_uri, iat,
+        exp: iat + ASSERTION_LIFETIME };
+
+    let mut header = jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256);
+    header.kid = Some(service_account.private_key_id.clone());
+    let key = jsonwebtoken::EncodingKey::from_rsa_pem(service_account.private_key.as_bytes())
+        .context("The service account's private key isn't a valid RSA key")?;
+    let assertion = jsonwebtoken::encode(&header, &claims, &key)?;
+
+    let requested_at = Instant::now();
+    let response = reqwest::Client::new().post(token_uri)
+        .form(&[("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"), ("assertion", assertion.as_str())])
+        .send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        bail!("Unable to exchange the service account key for an access token ({status}): {body}");
+    }
+
+    let token = serde_json::from_str::<TokenResponse>(&response.text().await?)?;
+    let expires_at = requested_at + Duration::from_secs(token.expires_in).saturating_sub(EXPIRY_MARGIN);
+    return Ok((token.access_token, expires_at));
+}
+
+// an access token for the service account's json key, cached until it's about to expire
+pub async fn access_token(key: &str) -> Result<String> {
+    let service_account = serde_json::from_str::<ServiceAccount>(key)
+        .context("The Vertex AI key isn't a valid service account JSON key")?;
+    if service_account.account_type != "service_account" {
+        bail!("The Vertex AI key is a {} key rather than a service account key.", service_account.account_type);
+    }
+
+    let cache_key = (service_account.client_email.clone(), service_account.private_key_id.clone());
+    let cached = TOKENS.lock().ok()
+        .and_then(|tokens| tokens.get(&cache_key).cloned())
+        .filter(|(_, expires_at)| Instant::now() < *expires_at);
+    if let Some((token, _)) = cached {
+        return Ok(token);
+    }
+
+    let (token, expires_at) = exchange(&service_account).await?;
+    if let Ok(mut tokens) = TOKENS.lock() {
+        tokens.insert(cache_key, (token.clone(), expires_at));
+    }
+
+    return Ok(token);
+}
@@ -0,0 +1,67 @@
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use common::{to_serde_err, Config};
+use serde_error::Error;
+use tauri::api::dialog::blocking::FileDialogBuilder;
+use crate::{load_config, secrets};
+
+// the saved settings, with the keys themselves only if include_keys. returns the path written to, None if the
+// dialog is canceled
+async fn _export_settings(include_keys: bool) -> Result<Option<PathBuf>> {
+    let mut config = load_config().await?;
+    for api_key in config.api_keys.iter_mut() {
+        // keychain references mean nothing on another machine
+        api_key.key = match include_keys {
+            true => secrets::get(&api_key.key).await?,
+            false => String::new()
+        };
+    }
+
+    let path = tokio::task::spawn_blocking(|| FileDialogBuilder::new()
+        .set_file_name("llm-playground-settings.json")
+        .add_filter("Settings", &["json"])
+        .save_file()).await?;
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    tokio::fs::write(&path, serde_json::to_string_pretty(&config)?).await.context("Error writing settings")?;
+    return Ok(Some(path));
+}
+
+#[tauri::command]
+pub async fn export_settings(include_keys: bool) -> Result<Option<String>, Error> {
+    _export_settings(include_keys).await
+        .map(|path| path.map(|path| path.display().to_string()))
+        .map_err(to_serde_err)
+}
+
+// returns the imported settings for the settings page to apply, None if the dialog is canceled.
+// keys exported without their key keep the key of the current config's key of the same name and provider
+async fn _import_settings(config: Config) -> Result<Option<Config>> {
+    let path = tokio::task::spawn_blocking(|| FileDialogBuilder::new()
+        .add_filter("Settings", &["json"])
+        .pick_file()).await?;
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let imported_config = tokio::fs::read_to_string(&path).await.context("Error reading settings")?;
+    let mut imported_config = serde_json::from_str::<Config>(&imported_config).context("Not a settings file")?;
+    for imported_key in imported_config.api_keys.iter_mut().filter(|api_key| api_key.key.is_empty()) {
+        let current_key = config.api_keys.iter().find(|api_key|
+            api_key.name == imported_key.name && api_key.provider == imported_key.provider);
+        if let Some(current_key) = current_key {
+            imported_key.key = current_key.key.clone();
+        }
+    }
+    imported_config.dedupe_key_names();
+    secrets::migrate_plaintext_keys(&mut imported_config).await;
+
+    return Ok(Some(imported_config));
+}
+
+#[tauri::command]
+pub async fn import_settings(config: Config) -> Result<Option<Config>, Error> {
+    _import_settings(config).await.map_err(to_serde_err)
+}
@@ -0,0 +1,237 @@
+use anyhow::{anyhow, Result};
+use common::{estimate_tokens, Config, Exchange};
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc::UnboundedSender;
+use crate::{conversations, database, fetch_tokens, initiate_transaction, limits, load_config};
+
+// older exchanges are summarized once a conversation fills this fraction of the model's context window
+const SUMMARIZE_AT: f64 = 0.5;
+// the most recent exchanges, up to this fraction of the context window, are kept in full
+const RECENT: f64 = 0.25;
+const MIN_RECENT_EXCHANGES: usize = 2;
+// each summarize request is kept to this fraction of the context window, so a conversation that first needs a
+// summary long after passing SUMMARIZE_AT is summarized in parts
+const SUMMARIZE_REQUEST: f64 = 0.5;
+
+const SUMMARIZE_PROMPT: &str = "Summarize the conversation below for an assistant continuing it. Keep every fact, \
+decision, name and open question the rest of the conversation could depend on, and leave out pleasantries. \
+Reply with the summary alone.";
+
+lazy_static::lazy_static! {
+    // conversations waiting to be summarized, one at a time so a burst of saves doesn't send a burst of requests
+    static ref QUEUE: std::sync::Mutex<Option<UnboundedSender<uuid::Uuid>>> = std::sync::Mutex::new(None);
+}
+
+fn exchange_tokens(exchange: &Exchange) -> usize {
+    estimate_tokens(&exchange.user_message) + estimate_tokens(&exchange.assistant_message)
+}
+
+fn summary_key(conversation_uuid: uuid::Uuid) -> Vec<u8> {
+    conversation_uuid.as_bytes().to_vec()
+}
+
+// exchanges edited in place keep their ids, so the summary also records a hash of the text it covers
+fn content_hash(exchanges: &[(i32, Exchange)]) -> String {
+    let mut hasher = Sha256::new();
+    for (_, exchange) in exchanges {
+        for message in [&exchange.user_message, &exchange.assistant_message] {
+            hasher.update((message.len() as u64).to_le_bytes());
+            hasher.update(message.as_bytes());
+        }
+    }
+    hasher.finalize().iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// cuts text to about max_tokens, for an exchange too long to fit in a summarize request by itself
+fn truncate_to_tokens(text: &str, max_tokens: usize) -> String {
+    if estimate_tokens(text) <= max_tokens {
+        return text.into();
+    }
+    text.chars().take(max_tokens * 4).collect::<String>() + "..."
+}
+
+fn parse_exchange_ids(exchange_ids: &str) -> Vec<i32> {
+    exchange_ids.split(',').filter_map(|id| id.parse().ok()).collect()
+}
+
+// ordered by key, with their ids
+async fn load_exchanges(conn: &impl ConnectionTrait, conversation_uuid: uuid::Uuid)
+-> Result<Vec<(i32, Exchange)>> {
+    let conversation = entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(conn).await?
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))?;
+
+    let exchanges = entity::exchanges::Entity::find()
+        .filter(entity::exchanges::Column::Conversation.eq(conversation.id))
+        .order_by_asc(entity::exchanges::Column::Key)
+        .all(conn).await?;
+    return Ok(exchanges.into_iter()
+        .map(|exchange| (exchange.id, conversations::exchange_from_model(exchange).1))
+        .collect());
+}
+
+// the saved summary, if the exchanges it covers are still the conversation's first and unchanged
+async fn current_summary(
+    conn: &impl ConnectionTrait,
+    conversation_uuid: uuid::Uuid,
+    exchanges: &[(i32, Exchange)]
+) -> Result<Option<(String, usize)>> {
+    let Some(saved) = entity::summaries::Entity::find_by_id(summary_key(conversation_uuid)).one(conn).await? else {
+        return Ok(None);
+    };
+
+    let exchange_ids = parse_exchange_ids(&saved.exchange_ids);
+    let is_current = exchange_ids.len() <= exchanges.len()
+        && exchanges.iter().zip(&exchange_ids).all(|((id, _), summarized_id)| id == summarized_id)
+        && content_hash(&exchanges[..exchange_ids.len()]) == saved.content_hash;
+    return Ok(is_current.then_some((saved.summary, exchange_ids.len())));
+}
+
+async fn summarize(conversation_uuid: uuid::Uuid) -> Result<()> {
+    let config = fetch_tokens::background_config(load_config().await?, SUMMARIZE_PROMPT);
+    let Some(context_window) = limits::context_window(&config.model).filter(|_| config.summarize_long_conversations)
+    else {
+        return Ok(());
+    };
+
+    let exchanges = load_exchanges(&database()?, conversation_uuid).await?;
+    let total = exchanges.iter().map(|(_, exchange)| exchange_tokens(exchange)).sum::<usize>();
+    if (total as f64) < f64::from(context_window) * SUMMARIZE_AT {
+        return Ok(());
+    }
+
+    // everything but the most recent exchanges
+    let recent_budget = (f64::from(context_window) * RECENT) as usize;
+    let mut recent_tokens = 0;
+    let recent = exchanges.iter().rev()
+        .take_while(|(_, exchange)| {
+            recent_tokens += exchange_tokens(exchange);
+            recent_tokens <= recent_budget
+        })
+        .count()
+        .max(MIN_RECENT_EXCHANGES);
+    let summarized = exchanges.len().saturating_sub(recent);
+
+    // a current summary is extended with the exchanges since rather than redone
+    let (previous_summary, start) = current_summary(&database()?, conversation_uuid, &exchanges).await?
+        .filter(|(_, count)| *count <= summarized)
+        .map_or((None, 0), |(summary, count)| (Some(summary), count));
+    if start >= summarized {
+        return Ok(());
+    }
+
+    // the exchanges are summarized in parts that fit a request, each part extending the summary of the last
+    let budget = ((f64::from(context_window) * SUMMARIZE_REQUEST) as usize).saturating_sub(config.max_tokens as usize)
+        .max(1);
+    let mut summary = previous_summary;
+    let mut next = start;
+    while next < summarized {
+        let mut transcript = summary.as_ref()
+            .map(|summary| format!("Summary of the conversation so far:\n{summary}\n\nThe conversation continues:"))
+            .unwrap_or_default();
+        let part_start = next;
+        while next < summarized {
+            let exchange = &exchanges[next].1;
+            let remaining = budget.saturating_sub(estimate_tokens(&transcript));
+            if next > part_start && exchange_tokens(exchange) > remaining {
+                break;
+            }
+            // an exchange too long for a request alone is cut, half for each message
+            transcript += &format!("\n\nUser: {}\n\nAssistant: {}",
+                truncate_to_tokens(&exchange.user_message, remaining / 2),
+                truncate_to_tokens(&exchange.assistant_message, remaining / 2));
+            next += 1;
+        }
+        summary = Some(fetch_tokens::complete(config.clone(), transcript.trim(), vec![]).await?.trim().into());
+    }
+    let Some(summary) = summary else {
+        return Ok(());
+    };
+
+    let exchange_ids = exchanges[..summarized].iter()
+        .map(|(id, _)| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let txn = initiate_transaction().await?;
+    entity::summaries::Entity::delete_by_id(summary_key(conversation_uuid)).exec(&txn).await?;
+    entity::summaries::ActiveModel {
+        conversation_uuid: Set(summary_key(conversation_uuid)),
+        summary: Set(summary),
+        exchange_ids: Set(exchange_ids),
+        content_hash: Set(content_hash(&exchanges[..summarized]))
+    }.insert(&txn).await?;
+    txn.commit().await?;
+
+    tracing::info!(%conversation_uuid, summarized, "Summarized conversation");
+    return Ok(());
+}
+
+pub fn spawn_worker() {
+    let (sender, mut recv) = tokio::sync::mpsc::unbounded_channel::<uuid::Uuid>();
+    if let Ok(mut queue) = QUEUE.lock() {
+        *queue = Some(sender);
+    }
+
+    tauri::async_runtime::spawn(async move {
+        while let Some(conversation_uuid) = recv.recv().await {
+            if let Err(error) = summarize(conversation_uuid).await {
+                tracing::warn!(%conversation_uuid, "Error summarizing conversation: {error}");
+            }
+        }
+    });
+}
+
+// checks whether the conversation needs its summary updated, after its exchanges are saved
+pub fn schedule(conversation_uuid: uuid::Uuid) {
+    if let Some(sender) = QUEUE.lock().ok().as_ref().and_then(|queue| queue.as_ref()) {
+        let _ = sender.send(conversation_uuid);
+    }
+}
+
+async fn _condense(conversation_uuid: uuid::Uuid, config: &Config, exchanges: &[Exchange], prompt: &str)
+-> Result<Option<(String, usize)>> {
+    let Some(context_window) = limits::context_window(&config.model).filter(|_| config.summarize_long_conversations)
+    else {
+        return Ok(None);
+    };
+
+    let needed = estimate_tokens(&config.system_prompt) + estimate_tokens(prompt) + config.max_tokens as usize
+        + exchanges.iter().map(exchange_tokens).sum::<usize>();
+    if needed <= context_window as usize {
+        return Ok(None);
+    }
+
+    let conn = &database()?;
+    let saved_exchanges = load_exchanges(conn, conversation_uuid).await?;
+    return Ok(current_summary(conn, conversation_uuid, &saved_exchanges).await?
+        .filter(|(_, summarized)| *summarized <= exchanges.len()));
+}
+
+// when the whole conversation wouldn't fit in the context window, its summary is added to the system prompt in
+// place of the exchanges it covers. returns the exchanges to send and how many the summary replaced
+pub async fn condense(
+    conversation_uuid: Option<uuid::Uuid>,
+    config: &mut Config,
+    mut exchanges: Vec<Exchange>,
+    prompt: &str
+) -> (Vec<Exchange>, usize) {
+    let Some(conversation_uuid) = conversation_uuid else {
+        return (exchanges, 0);
+    };
+
+    match _condense(conversation_uuid, config, &exchanges, prompt).await {
+        Ok(Some((summary, summarized))) => {
+            config.system_prompt = format!("{}\n\nA summary of the conversation's earlier exchanges:\n{summary}",
+                config.system_prompt).trim().into();
+            (exchanges.split_off(summarized), summarized)
+        },
+        Ok(None) => (exchanges, 0),
+        Err(error) => {
+            // the whole conversation is sent as before
+            tracing::warn!(%conversation_uuid, "Error loading the conversation's summary: {error}");
+            (exchanges, 0)
+        }
+    }
+}
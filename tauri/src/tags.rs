@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use anyhow::{anyhow, bail, Result};
+use common::to_serde_err;
+use sea_orm::{ActiveModelTrait, ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter, QueryOrder, Set};
+use serde_error::Error;
+use crate::{database, initiate_transaction};
+
+async fn find_conversation_id(conn: &impl ConnectionTrait, conversation_uuid: uuid::Uuid) -> Result<i32> {
+    entity::conversations::Entity::find()
+        .filter(entity::conversations::Column::Uuid.eq(conversation_uuid))
+        .one(conn).await?
+        .map(|conversation| conversation.id)
+        .ok_or(anyhow!("Conversation with uuid {} not found", conversation_uuid))
+}
+
+// every conversation's tags by its id, sorted by name
+pub async fn tags_by_conversation(conn: &impl ConnectionTrait) -> Result<HashMap<i32, Vec<String>>> {
+    let mut tags = HashMap::<i32, Vec<String>>::new();
+    let conversation_tags = entity::conversation_tags::Entity::find()
+        .find_also_related(entity::tags::Entity)
+        .order_by_asc(entity::tags::Column::Name)
+        .all(conn).await?;
+    for (conversation_tag, tag) in conversation_tags {
+        if let Some(tag) = tag {
+            tags.entry(conversation_tag.conversation).or_default().push(tag.name);
+        }
+    }
+
+    return Ok(tags);
+}
+
+pub async fn add_tag(conn: &impl ConnectionTrait, conversation_uuid: uuid::Uuid, name: &str) -> Result<()> {
+    let name = name.trim();
+    if name.is_empty() {
+        bail!("Tags cannot be empty.");
+    }
+
+    let conversation = find_conversation_id(conn, conversation_uuid).await?;
+    let tag = match entity::tags::Entity::find().filter(entity::tags::Column::Name.eq(name)).one(conn).await? {
+        Some(tag) => tag,
+        None => entity::tags::ActiveModel { name: Set(name.into()), ..Default::default() }.insert(conn).await?
+    };
+
+    let already_tagged = entity::conversation_tags::Entity::find_by_id((conversation, tag.id)).one(conn).await?;
+    if already_tagged.is_none() {
+        entity::conversation_tags::ActiveModel { conversation: Set(conversation), tag: Set(tag.id) }
+            .insert(conn).await?;
+    }
+
+    return Ok(());
+}
+
+// tags no conversation has anymore are deleted so they aren't offered again
+pub async fn remove_tag(conn: &impl ConnectionTrait, conversation_uuid: uuid::Uuid, name: &str) -> Result<()> {
+    let conversation = find_conversation_id(conn, conversation_uuid).await?;
+    let Some(tag) = entity::tags::Entity::find().filter(entity::tags::Column::Name.eq(name)).one(conn).await? else {
+        return Ok(());
+    };
+
+    entity::conversation_tags::Entity::delete_by_id((conversation, tag.id)).exec(conn).await?;
+    let still_used = entity::conversation_tags::Entity::find()
+        .filter(entity::conversation_tags::Column::Tag.eq(tag.id))
+        .one(conn).await?
+        .is_some();
+    if !still_used {
+        entity::tags::Entity::delete_by_id(tag.id).exec(conn).await?;
+    }
+
+    return Ok(());
+}
+
+pub async fn list_tags(conn: &impl ConnectionTrait) -> Result<Vec<String>> {
+    let tags = entity::tags::Entity::find()
+        .order_by_asc(entity::tags::Column::Name)
+        .all(conn).await?
+        .into_iter()
+        .map(|tag| tag.name)
+        .collect();
+
+    return Ok(tags);
+}
+
+async fn _add_conversation_tag(conversation_uuid: uuid::Uuid, tag: String) -> Result<()> {
+    let txn = initiate_transaction().await?;
+    add_tag(&txn, conversation_uuid, &tag).await?;
+    txn.commit().await?;
+
+    return Ok(());
+}
+
+#[tauri::command]
+pub async fn add_conversation_tag(conversation_uuid: uuid::Uuid, tag: String) -> Result<(), Error> {
+    _add_conversation_tag(conversation_uuid, tag).await.map_err(to_serde_err)
+}
+
+async fn _remove_conversation_tag(conversation_uuid: uuid::Uuid, tag: String) -> Result<()> {
+    let txn = initiate_transaction().await?;
+    remove_tag(&txn, conversation_uuid, &tag).await?;
+    txn.commit().await?;
+
+    return Ok(());
+}
+
+#[tauri::command]
+pub async fn remove_conversation_tag(conversation_uuid: uuid::Uuid, tag: String) -> Result<(), Error> {
+    _remove_conversation_tag(conversation_uuid, tag).await.map_err(to_serde_err)
+}
+
+async fn _load_tags() -> Result<Vec<String>> {
+    list_tags(&database()?).await
+}
+
+#[tauri::command]
+pub async fn load_tags() -> Result<Vec<String>, Error> {
+    _load_tags().await.map_err(to_serde_err)
+}
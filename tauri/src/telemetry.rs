@@ -0,0 +1,95 @@
+use std::{path::PathBuf, sync::Mutex};
+use anyhow::{Context, Result};
+use common::{to_serde_err, ErrorCategory, ErrorCount, Provider, ProviderError};
+use serde_error::Error;
+
+lazy_static::lazy_static! {
+    // serializes the read-modify-write of the counts file
+    static ref LOCK: Mutex<()> = Mutex::new(());
+}
+
+fn error_counts_path() -> Result<PathBuf> {
+    let config_dir = dirs::config_dir()
+        .context("Unable to find the config directory")?
+        .join("llm-playground");
+    std::fs::create_dir_all(&config_dir).context("Error creating config directory")?;
+    return Ok(config_dir.join("error_counts.json"));
+}
+
+fn read_error_counts() -> Result<Vec<ErrorCount>> {
+    match std::fs::read_to_string(error_counts_path()?) {
+        Ok(error_counts) => serde_json::from_str(&error_counts).context("Unable to parse error counts"),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(error) => Err(error.into())
+    }
+}
+
+// fallback is the category of errors that aren't recognized, e.g. parse errors for a response stream
+pub fn categorize(error: &anyhow::Error, fallback: ErrorCategory) -> ErrorCategory {
+    if let Some(error) = error.downcast_ref::<ProviderError>() {
+        return match error {
+            ProviderError::RateLimited { .. } => ErrorCategory::RateLimited,
+            ProviderError::Overloaded { .. } => ErrorCategory::Overloaded,
+            _ => ErrorCategory::Provider
+        };
+    }
+    if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        return if error.is_timeout() { ErrorCategory::Timeout } else { ErrorCategory::Network };
+    }
+    if error.downcast_ref::<serde_json::Error>().is_some() {
+        return ErrorCategory::Parse;
+    }
+    if error.downcast_ref::<sea_orm::DbErr>().is_some() {
+        return ErrorCategory::Database;
+    }
+
+    return fallback;
+}
+
+fn _record_error(error: &anyhow::Error, fallback: ErrorCategory, provider: Option<&Provider>) -> Result<()> {
+    let _lock = LOCK.lock().map_err(|_| anyhow::anyhow!("The error counts lock is poisoned"))?;
+    let category = categorize(error, fallback);
+    let mut error_counts = read_error_counts()?;
+
+    let error_count = match error_counts.iter().position(|error_count| error_count.category == category) {
+        Some(index) => &mut error_counts[index],
+        None => {
+            error_counts.push(ErrorCount {
+                category,
+                count: 0,
+                last_occurred: chrono::Utc::now(),
+                last_message: String::new(),
+                provider: None
+            });
+            error_counts.last_mut().expect("an error count was just pushed")
+        }
+    };
+    error_count.count += 1;
+    error_count.last_occurred = chrono::Utc::now();
+    error_count.last_message = error.to_string();
+    error_count.provider = provider.map(Provider::to_string);
+
+    std::fs::write(error_counts_path()?, serde_json::to_string(&error_counts)?)?;
+    return Ok(());
+}
+
+// failing to record an error shouldn't fail whatever hit it
+pub fn record_error(error: &anyhow::Error, fallback: ErrorCategory, provider: Option<&Provider>) {
+    if let Err(recording_error) = _record_error(error, fallback, provider) {
+        tracing::error!("Error recording error: {recording_error}");
+    }
+}
+
+#[tauri::command]
+pub async fn load_error_counts() -> Result<Vec<ErrorCount>, Error> {
+    read_error_counts().map_err(to_serde_err)
+}
+
+#[tauri::command]
+pub async fn clear_error_counts() -> Result<(), Error> {
+    let _lock = LOCK.lock().map_err(|_| to_serde_err(anyhow::anyhow!("The error counts lock is poisoned")))?;
+    match std::fs::remove_file(error_counts_path().map_err(to_serde_err)?) {
+        Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(Error::new(&error)),
+        _ => Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use anyhow::{bail, Result};
+use common::{to_serde_err, Config, Conversation, ErrorCategory, RetitleProgress};
+use serde_error::Error;
+use crate::{conversations, database, fetch_tokens, load_config, telemetry::record_error};
+use crate::{_load_conversations, _load_exchanges};
+
+const TITLE_SYSTEM_PROMPT: &str = "You write short, descriptive titles for conversations. Reply with only the \
+    title, at most six words, without quotes or a trailing period.";
+
+// enough of the first exchange to tell what the conversation is about
+const EXCERPT_CHARS: usize = 2000;
+const TITLE_MAX_TOKENS: u32 = 32;
+const MAX_TITLE_CHARS: usize = 80;
+
+// titles are generated one at a time with this pause in between to stay under providers' rate limits
+const RETITLE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+lazy_static::lazy_static! {
+    // only one retitling runs at a time, even with several windows open
+    static ref RETITLING: AtomicBool = AtomicBool::new(false);
+}
+
+// the user's model and key, without anything that would make a one-line reply long or expensive
+fn title_config(config: Config) -> Config {
+    Config { max_tokens: TITLE_MAX_TOKENS, ..fetch_tokens::background_config(config, TITLE_SYSTEM_PROMPT) }
+}
+
+fn excerpt(message: &str) -> String {
+    message.chars().take(EXCERPT_CHARS).collect()
+}
+
+fn clean_title(title: &str) -> Option<String> {
+    let title = title.trim().lines().next()?
+        .trim_matches(|char: char| char == '"' || char == '\'' || char == '*' || char.is_whitespace())
+        .trim_end_matches('.')
+        .chars()
+        .take(MAX_TITLE_CHARS)
+        .collect::<String>();
+    (!title.is_empty()).then_some(title)
+}
+
+async fn retitle(config: &Config, conversation_uuid: uuid::Uuid) -> Result<()> {
+    let Some((_, first_exchange)) = _load_exchanges(conversation_uuid).await?.into_iter().next() else {
+        bail!("Conversation with uuid {} is empty", conversation_uuid);
+    };
+
+    let prompt = format!("Title this conversation:\n\nUser: {}\n\nAssistant: {}",
+        excerpt(&first_exchange.user_message), excerpt(&first_exchange.assistant_message));
+    let title = fetch_tokens::complete(config.clone(), &prompt, vec![]).await?;
+    let Some(title) = clean_title(&title) else {
+        bail!("The model returned an empty title");
+    };
+
+    let conn = &database()?;
+    conversations::set_generated_title(conn, conversation_uuid, title).await
+}
+
+async fn retitle_all(window: tauri::Window, config: Config, conversations: Vec<Conversation>) {
+    let cancel = std::sync::Arc::new(tokio::sync::Notify::new());
+    let cancel_listener_id = window.listen("cancel_retitle", {
+        let cancel = cancel.clone();
+        move |_| cancel.notify_one()
+    });
+
+    let mut progress = RetitleProgress { total: conversations.len(), ..Default::default() };
+    for conversation in conversations {
+        let retitled = tokio::select! {
+            retitled = retitle(&config, conversation.uuid) => retitled,
+            _ = cancel.notified() => break
+        };
+        match retitled {
+            Ok(()) => progress.retitled += 1,
+            Err(error) => {
+                record_error(&error, ErrorCategory::Other, None);
+                progress.failed += 1;
+                progress.last_error = Some(error.to_string());
+            }
+        }
+        if let Err(error) = window.emit("retitle_progress", &progress) {
+            tracing::error!("{error}");
+        }
+
+        if !progress.is_done() {
+            tokio::select! {
+                _ = tokio::time::sleep(RETITLE_INTERVAL) => (),
+                _ = cancel.notified() => break
+            }
+        }
+    }
+
+    // a canceled run is reported as done with the rest left untitled
+    if !progress.is_done() {
+        progress.total = progress.retitled + progress.failed;
+        if let Err(error) = window.emit("retitle_progress", &progress) {
+            tracing::error!("{error}");
+        }
+    }
+
+    window.unlisten(cancel_listener_id);
+    RETITLING.store(false, Ordering::SeqCst);
+}
+
+async fn start_retitling(window: tauri::Window) -> Result<usize> {
+    let config = title_config(load_config().await?);
+    let conversations = _load_conversations().await?.into_iter()
+        .filter(|conversation| conversation.generated_title.is_none())
+        .collect::<Vec<_>>();
+
+    let total = conversations.len();
+    if total > 0 {
+        tokio::spawn(retitle_all(window, config, conversations));
+    }
+
+    return Ok(total);
+}
+
+async fn _retitle_conversations(window: tauri::Window) -> Result<usize> {
+    if RETITLING.swap(true, Ordering::SeqCst) {
+        bail!("Conversations are already being retitled.");
+    }
+
+    // otherwise retitle_all clears it once it's done
+    let started = start_retitling(window).await;
+    if !matches!(started, Ok(total) if total > 0) {
+        RETITLING.store(false, Ordering::SeqCst);
+    }
+
+    return started;
+}
+
+// titles conversations that are still titled by their first prompt in the background, progress is emitted
+// as "retitle_progress" and a "cancel_retitle" event stops it. returns how many will be retitled
+#[tauri::command]
+pub async fn retitle_conversations(window: tauri::Window) -> Result<usize, Error> {
+    _retitle_conversations(window).await.map_err(to_serde_err)
+}
@@ -0,0 +1,31 @@
+use std::ops::Deref;
+use anyhow::Result;
+use common::to_serde_err;
+use serde_error::Error;
+use tiktoken_rs::{tokenizer::{get_tokenizer, Tokenizer}, CoreBPE};
+
+lazy_static::lazy_static! {
+    // building the encodings takes a while, so each is built once on first use
+    static ref CL100K_BASE: Result<CoreBPE> = tiktoken_rs::cl100k_base();
+    static ref O200K_BASE: Result<CoreBPE> = tiktoken_rs::o200k_base();
+}
+
+// other providers' tokenizers aren't public, cl100k_base is a close enough approximation for them
+fn encoding(model: &str) -> Result<&'static CoreBPE> {
+    let encoding = match get_tokenizer(model) {
+        Some(Tokenizer::O200kBase) => &*O200K_BASE,
+        _ => &*CL100K_BASE
+    };
+    return Ok(encoding.as_ref().map_err(Deref::deref)?);
+}
+
+fn _count_tokens(texts: Vec<String>, model: &str) -> Result<Vec<usize>> {
+    let encoding = encoding(model)?;
+    return Ok(texts.iter().map(|text| encoding.encode_with_special_tokens(text).len()).collect());
+}
+
+// counts several texts in one call so the prompt and the conversation so far can be counted together
+#[tauri::command]
+pub async fn count_tokens(texts: Vec<String>, model: String) -> Result<Vec<usize>, Error> {
+    _count_tokens(texts, &model).map_err(to_serde_err)
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use anyhow::Result;
+use common::{to_serde_err, Provider, Usage, UsagePeriod, UsageTotal};
+use sea_orm::{ActiveModelTrait, EntityTrait, QueryOrder, Set};
+use serde_error::Error;
+use crate::{database, pricing};
+
+async fn _record_usage(provider: &Provider, model: &str, usage: &Usage) -> Result<()> {
+    entity::usage::ActiveModel {
+        timestamp: Set(chrono::Utc::now().timestamp()),
+        provider: Set(provider.to_string()),
+        model: Set(model.to_string()),
+        prompt_tokens: Set(usage.prompt_tokens.map(|prompt_tokens| prompt_tokens as i64)),
+        completion_tokens: Set(usage.completion_tokens.map(|completion_tokens| completion_tokens as i64)),
+        cost: Set(pricing::cost(model, usage)),
+        ..Default::default()
+    }.insert(&database()?).await?;
+
+    return Ok(());
+}
+
+// failing to record usage shouldn't fail the response
+pub async fn record_usage(provider: &Provider, model: &str, usage: &Usage) {
+    if let Err(error) = _record_usage(provider, model, usage).await {
+        tracing::error!("Error recording usage: {error}");
+    }
+}
+
+fn period_label(period: UsagePeriod, timestamp: i64) -> String {
+    let time = chrono::DateTime::from_timestamp(timestamp, 0).unwrap_or_default().with_timezone(&chrono::Local);
+    let format = match period {
+        UsagePeriod::Day => "%Y-%m-%d",
+        // iso weeks, so a week spanning new year's is counted once
+        UsagePeriod::Week => "%G-W%V",
+        UsagePeriod::Month => "%Y-%m"
+    };
+    time.format(format).to_string()
+}
+
+// most recent period first, then the most expensive model
+async fn _load_usage(period: UsagePeriod) -> Result<Vec<UsageTotal>> {
+    let rows = entity::usage::Entity::find()
+        .order_by_asc(entity::usage::Column::Timestamp)
+        .all(&database()?)
+        .await?;
+
+    let mut totals = HashMap::<(String, String, String), UsageTotal>::new();
+    for row in rows {
+        let period = period_label(period, row.timestamp);
+        let total = totals.entry((period.clone(), row.provider.clone(), row.model.clone()))
+            .or_insert_with(|| UsageTotal { period, provider: row.provider, model: row.model, ..Default::default() });
+        total.requests += 1;
+        total.prompt_tokens += row.prompt_tokens.unwrap_or_default() as u64;
+        total.completion_tokens += row.completion_tokens.unwrap_or_default() as u64;
+        total.cost += row.cost.unwrap_or_default();
+    }
+
+    let mut totals = totals.into_values().collect::<Vec<_>>();
+    totals.sort_by(|a, b| b.period.cmp(&a.period).then(b.cost.total_cmp(&a.cost)));
+    return Ok(totals);
+}
+
+#[tauri::command]
+pub async fn load_usage(period: UsagePeriod) -> Result<Vec<UsageTotal>, Error> {
+    _load_usage(period).await.map_err(to_serde_err)
+}
@@ -0,0 +1,181 @@
+use std::{collections::HashMap, path::{Path, PathBuf}, sync::{mpsc, Arc, Mutex}, time::{Duration, Instant}};
+use anyhow::{anyhow, Result};
+use common::{ChangeOrigin, FileChange, FileChangeKind};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use tauri::Manager;
+
+lazy_static::lazy_static! {
+    // one watcher per path, each emitting every event registered for that path
+    static ref WATCHES: Mutex<HashMap<PathBuf, Arc<Mutex<Vec<String>>>>> = Mutex::new(HashMap::new());
+    // when this process last wrote each path, and for which window
+    static ref OWN_WRITES: Mutex<HashMap<PathBuf, (Instant, Option<String>)>> = Mutex::new(HashMap::new());
+}
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+// events less than DEBOUNCE apart are coalesced into one, for at most MAX_BURST
+const DEBOUNCE: Duration = Duration::from_millis(250);
+const MAX_BURST: Duration = Duration::from_secs(1);
+// changes starting this soon after a marked write are attributed to this process
+const OWN_WRITE_WINDOW: Duration = Duration::from_secs(2);
+
+// called before writing path, so listeners can tell the change apart from another process's, and the window
+// that made it apart from the others
+pub fn mark_own_write(path: &Path, window: Option<&str>) {
+    if let Ok(mut own_writes) = OWN_WRITES.lock() {
+        own_writes.insert(path.to_path_buf(), (Instant::now(), window.map(str::to_string)));
+    }
+}
+
+fn origin(path: &Path, changed_at: Instant) -> ChangeOrigin {
+    let written = OWN_WRITES.lock().ok().and_then(|own_writes| own_writes.get(path).cloned());
+    match written {
+        Some((written_at, window)) if changed_at.saturating_duration_since(written_at) < OWN_WRITE_WINDOW =>
+            ChangeOrigin::App { window },
+        _ => ChangeOrigin::External
+    }
+}
+
+type WatcherSender = mpsc::Sender<Result<notify::Event, notify::Error>>;
+
+fn create_watcher(path: &Path, sender: WatcherSender) -> Result<RecommendedWatcher> {
+    let mut watcher = RecommendedWatcher::new(sender, Default::default())?;
+    watcher.watch(path, RecursiveMode::Recursive)?;
+    Ok(watcher)
+}
+
+fn emit(
+    app: &tauri::AppHandle,
+    event_names: &Mutex<Vec<String>>,
+    path: &Path,
+    kind: FileChangeKind,
+    origin: ChangeOrigin
+) {
+    let change = FileChange { path: path.display().to_string(), kind, origin };
+    let Ok(event_names) = event_names.lock() else { return };
+    for event_name in event_names.iter() {
+        app.emit_all(event_name, &change)
+            .unwrap_or_else(|error| tracing::error!("Error triggering {event_name}: {error}"));
+    }
+}
+
+// the change an event amounts to, if any, and whether the watch has to be re-created
+fn classify(path: &Path, event: Result<notify::Event, notify::Error>) -> (Option<FileChangeKind>, bool) {
+    match event {
+        Ok(event) => match event.kind {
+            notify::EventKind::Create(_) | notify::EventKind::Modify(_) => (Some(FileChangeKind::Modified), false),
+            // the watch is lost along with the path, e.g. when it's replaced
+            notify::EventKind::Remove(_) => (Some(FileChangeKind::Removed), true),
+            // ignore miscellaneous events
+            _ => (None, false)
+        },
+        Err(error) => {
+            tracing::error!("Error watching {}: {error}", path.display());
+            (None, true)
+        }
+    }
+}
+
+fn rewatch(path: &Path, sender: &WatcherSender, backoff: &mut Duration) -> RecommendedWatcher {
+    loop {
+        std::thread::sleep(*backoff);
+        *backoff = (*backoff * 2).min(MAX_BACKOFF);
+        match create_watcher(path, sender.clone()) {
+            Ok(watcher) => return watcher,
+            Err(error) => tracing::error!("Error re-watching {}: {error}", path.display())
+        }
+    }
+}
+
+// the watcher is re-created with backoff when it errors or the path is removed,
+// otherwise live updates would silently stop for the rest of the session
+fn run(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    event_names: Arc<Mutex<Vec<String>>>,
+    watcher: Option<RecommendedWatcher>,
+    sender: WatcherSender,
+    recv: mpsc::Receiver<Result<notify::Event, notify::Error>>
+) {
+    let mut backoff = Duration::from_secs(1);
+    // a path that didn't exist yet is watched once it's created
+    let mut watcher = match watcher {
+        Some(watcher) => watcher,
+        None => {
+            let watcher = rewatch(&path, &sender, &mut backoff);
+            emit(&app, &event_names, &path, FileChangeKind::Reconnected, ChangeOrigin::External);
+            watcher
+        }
+    };
+    // the sender held for re-creating watchers keeps recv from ever disconnecting
+    while let Ok(event) = recv.recv() {
+        let changed_at = Instant::now();
+        let (mut kind, mut reconnect) = classify(&path, event);
+
+        // a save is usually several events, e.g. truncating then writing, so the burst is emitted once
+        while !reconnect {
+            let remaining = MAX_BURST.saturating_sub(changed_at.elapsed());
+            if remaining.is_zero() {
+                break;
+            }
+            match recv.recv_timeout(DEBOUNCE.min(remaining)) {
+                Ok(event) => {
+                    let (next_kind, next_reconnect) = classify(&path, event);
+                    // a removal outweighs the modifications before it
+                    kind = match (kind, next_kind) {
+                        (Some(FileChangeKind::Removed), _) | (_, None) => kind,
+                        _ => next_kind
+                    };
+                    reconnect = next_reconnect;
+                },
+                Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                Err(mpsc::RecvTimeoutError::Timeout) => break
+            }
+        }
+
+        if let Some(kind) = kind {
+            if kind == FileChangeKind::Modified {
+                backoff = Duration::from_secs(1);
+            }
+            emit(&app, &event_names, &path, kind, origin(&path, changed_at));
+        }
+        if !reconnect {
+            continue;
+        }
+
+        drop(watcher);
+        watcher = rewatch(&path, &sender, &mut backoff);
+        emit(&app, &event_names, &path, FileChangeKind::Reconnected, ChangeOrigin::External);
+    }
+}
+
+// emit event_name with a FileChange payload whenever path changes, watching an already watched path
+// adds event_name to the events emitted for it
+pub fn watch(app: &tauri::AppHandle, path: &Path, event_name: &str) -> Result<()> {
+    let mut watches = WATCHES.lock().map_err(|_| anyhow!("The watcher registry is poisoned"))?;
+    if let Some(event_names) = watches.get(path) {
+        let mut event_names = event_names.lock().map_err(|_| anyhow!("The watcher registry is poisoned"))?;
+        if !event_names.iter().any(|name| name == event_name) {
+            event_names.push(event_name.to_string());
+        }
+        return Ok(());
+    }
+
+    let (sender, recv) = mpsc::channel();
+    let watcher = match create_watcher(path, sender.clone()) {
+        Ok(watcher) => Some(watcher),
+        // e.g. the database's write-ahead log while the database is locked
+        Err(error) if !path.exists() => {
+            tracing::info!("{} doesn't exist yet, watching it once it's created: {error}", path.display());
+            None
+        },
+        Err(error) => return Err(error)
+    };
+    let event_names = Arc::new(Mutex::new(vec![event_name.to_string()]));
+    watches.insert(path.to_path_buf(), event_names.clone());
+
+    let app = app.clone();
+    let path = path.to_path_buf();
+    std::thread::spawn(move || run(app, path, event_names, watcher, sender, recv));
+
+    Ok(())
+}
@@ -0,0 +1,40 @@
+// javascript the wasm calls into, loaded after the scripts in vendor
+
+mermaid.initialize({
+  startOnLoad: false,
+  theme: "dark",
+  // no click handlers or scripts in diagrams
+  securityLevel: "strict",
+  // html labels are drawn in foreignObjects, which the sanitizer's svg profile removes
+  flowchart: { htmlLabels: false }
+});
+
+// renders mermaid or graphviz source to a sanitized svg string, used by ui/src/render.rs.
+// dot's URL and href attributes would otherwise put javascript: links in the page
+window.renderDiagram = async (language, source, id) => {
+  const svg = language === "mermaid"
+    ? (await mermaid.render(id, source)).svg
+    : (await Viz.instance()).renderString(source, { format: "svg" });
+  return DOMPurify.sanitize(svg, { USE_PROFILES: { svg: true, svgFilters: true } });
+};
+
+// highlights code to html, guessing the language when it's missing or unknown
+window.highlightCode = (code, language) => {
+  const result = language && hljs.getLanguage(language)
+    ? hljs.highlight(code, { language, ignoreIllegals: true })
+    : hljs.highlightAuto(code);
+  return result.value;
+};
+
+// the clipboard api only accepts png images, so other formats are converted through a canvas
+window.copyImage = async (dataUrl) => {
+  const image = new Image();
+  image.src = dataUrl;
+  await image.decode();
+  const canvas = document.createElement("canvas");
+  canvas.width = image.naturalWidth;
+  canvas.height = image.naturalHeight;
+  canvas.getContext("2d").drawImage(image, 0, 0);
+  const blob = await new Promise((resolve) => canvas.toBlob(resolve, "image/png"));
+  await navigator.clipboard.write([new ClipboardItem({ "image/png": blob })]);
+};
@@ -1,63 +1,241 @@
 use proc_macro::TokenStream;
 use quote::quote;
+use commands::camel_case;
 use syn::{ItemFn, FnArg, parse_macro_input, Pat};
 
+// types passed through macro_rules arrive wrapped in invisible groups
+fn ungroup(ty: &syn::Type) -> &syn::Type {
+    match ty {
+        syn::Type::Group(group) => ungroup(&group.elem),
+        syn::Type::Paren(paren) => ungroup(&paren.elem),
+        _ => ty
+    }
+}
+
+// the T in a return type of Result<T>
+fn result_argument(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(path) = ungroup(ty) else {
+        return None;
+    };
+    let segment = path.path.segments.last().filter(|segment| segment.ident == "Result")?;
+    let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+
+    arguments.args.iter().find_map(|argument| match argument {
+        syn::GenericArgument::Type(ty) => Some(ungroup(ty)),
+        _ => None
+    })
+}
+
+// the T in a return type of Result<impl Stream<Item = Result<T>>>
+fn stream_item(return_type: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::ImplTrait(impl_trait) = result_argument(return_type)? else {
+        return None;
+    };
+
+    let item = impl_trait.bounds.iter().find_map(|bound| {
+        let syn::TypeParamBound::Trait(bound) = bound else {
+            return None;
+        };
+        let segment = bound.path.segments.last().filter(|segment| segment.ident == "Stream")?;
+        let syn::PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+            return None;
+        };
+
+        arguments.args.iter().find_map(|argument| match argument {
+            syn::GenericArgument::AssocType(item) if item.ident == "Item" => Some(&item.ty),
+            _ => None
+        })
+    })?;
+
+    Some(result_argument(item).expect("A stream's items must be a Result."))
+}
+
+// how an argument is sent to the backend
+struct Argument {
+    ident: syn::Ident,
+    key: String,
+    optional: bool
+}
+
+// #[name = "..."] sends the argument under that key rather than its name in camelCase, and #[optional] leaves it
+// out when None. the attributes are the macro's, so they're removed from the generated function
+fn parse_argument(argument: &mut FnArg) -> Argument {
+    let FnArg::Typed(argument) = argument else {
+        panic!("Command can't be a method.");
+    };
+
+    let Pat::Ident(pattern) = &*argument.pat else {
+        panic!("Parameters must be an identifier rather than pattern.");
+    };
+
+    let mut parsed = Argument {
+        ident: pattern.ident.clone(),
+        key: camel_case(&pattern.ident.to_string()),
+        optional: false
+    };
+    argument.attrs.retain(|attribute| {
+        if attribute.path().is_ident("optional") {
+            parsed.optional = true;
+        } else if attribute.path().is_ident("name") {
+            let syn::Meta::NameValue(syn::MetaNameValue {
+                value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(name), .. }), ..
+            }) = &attribute.meta else {
+                panic!("Arguments are renamed with #[name = \"...\"].");
+            };
+            parsed.key = name.value();
+        } else {
+            return true;
+        }
+
+        false
+    });
+
+    return parsed;
+}
+
+// a command returning Result<impl Stream<Item = Result<T>>> takes a stream_id, and the backend emits
+// Result<Option<Vec<T>>, common::StreamError> on <command>:<stream_id> until Ok(None) ends the stream.
+// the events are listened to before the command is invoked so none are missed
 #[proc_macro_attribute]
 pub fn command(_attr: TokenStream, item: TokenStream) -> TokenStream {
     let input_function = parse_macro_input!(item as ItemFn);
+    let attributes = input_function.attrs;
     let function_name = input_function.sig.ident;
     let visibility = input_function.vis;
-    let arguments = input_function.sig.inputs;
+    let mut arguments = input_function.sig.inputs;
     let return_type = input_function.sig.output;
 
     let syn::ReturnType::Type(_, return_type) = return_type else {
         panic!("Return type must be specified.");
     };
 
-    let insert_statements = arguments
+    // arguments keeps the signature, without the macro's attributes
+    let parsed_arguments = arguments.iter_mut().map(parse_argument).collect::<Vec<_>>();
+    let insert_statements = parsed_arguments
         .iter()
-        .map(|argument| {
-            let FnArg::Typed(argument) = argument else {
-                panic!("Command can't be a method.");
+        .map(|Argument { ident, key, optional }| {
+            let insert = quote! {
+                arguments_map.insert(#key.to_string(), value);
             };
-
-            let Pat::Ident(argument) = *argument.pat.clone() else {
-                panic!("Parameters must be an identifier rather than pattern.");
+            let insert = if *optional {
+                quote! {
+                    if !value.is_null() {
+                        #insert
+                    }
+                }
+            } else {
+                insert
             };
 
-            let argument = argument.ident;
             quote! {
-                arguments_map.insert(stringify!(#argument).to_string(), serde_json::to_value(#argument)
-                    .context(format!("Error serializing arguments to {function_name}"))?);
+                let value = serde_json::to_value(#ident)
+                    .context(format!("Error serializing arguments to {function_name}"))?;
+                #insert
             }
         })
         .collect::<Vec<_>>();
 
+    let invoke_command = quote! {
+        #[wasm_bindgen]
+        extern "C" {
+            #[wasm_bindgen(catch, js_namespace = ["window", "__TAURI__", "tauri"])]
+            async fn invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+        }
+
+        let function_name = stringify!(#function_name);
+
+        // not run until awaited, after any listener is in place
+        let invoke_command = async move {
+            let mut arguments_map = serde_json::Map::new();
+            #(#insert_statements)*
+            let arguments_jsvalue = JsValue::from_serde(&serde_json::Value::Object(arguments_map))
+                .context(format!("Error serializing arguments to {function_name}"))?;
+
+            invoke(function_name, arguments_jsvalue).await
+                .map_err(|error| JsValue::into_serde::<serde_error::Error>(&error).ok()
+                    .map(anyhow::Error::from)
+                    .unwrap_or(anyhow!("Error invoking {function_name}")))
+        };
+    };
+
+    let Some(item) = stream_item(&return_type) else {
+        return TokenStream::from(quote! {
+            #(#attributes)*
+            #visibility async fn #function_name(#arguments) -> #return_type {
+                use anyhow::{anyhow, Context};
+                use gloo_utils::format::JsValueSerdeExt;
+                use wasm_bindgen::{JsValue, prelude::*};
+
+                #invoke_command
+
+                Ok(JsValue::into_serde(&invoke_command.await?)?)
+            }
+        });
+    };
+
+    if !parsed_arguments.iter().any(|argument| argument.ident == "stream_id") {
+        panic!("A streaming command must take a stream_id.");
+    }
+
     return TokenStream::from(quote! {
+        #(#attributes)*
         #visibility async fn #function_name(#arguments) -> #return_type {
             use anyhow::{anyhow, Context};
             use gloo_utils::format::JsValueSerdeExt;
-            use wasm_bindgen::{JsValue, prelude::*};
+            use wasm_bindgen::{JsCast, JsValue, prelude::*};
 
             #[wasm_bindgen]
             extern "C" {
-                #[wasm_bindgen(catch, js_namespace = ["window", "__TAURI__", "tauri"])]
-                async fn invoke(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+                #[wasm_bindgen(catch, js_namespace = ["window", "__TAURI__", "event"])]
+                async fn listen(event: &str, handler: &Closure<dyn Fn(JsValue)>) -> Result<JsValue, JsValue>;
             }
 
-            let function_name = stringify!(#function_name);
+            let event_name = format!("{}:{}", stringify!(#function_name), stream_id);
+            #invoke_command
 
-            let mut arguments_map = serde_json::Map::new();
-            #(#insert_statements)*
-            let arguments_jsvalue = JsValue::from_serde(&serde_json::Value::Object(arguments_map))
-                .context(format!("Error serializing arguments to {function_name}"))?;
+            let (sender, recv) = tokio::sync::mpsc::unbounded_channel::<anyhow::Result<#item>>();
+            let close = std::sync::Arc::new(tokio::sync::Notify::new());
 
-            match invoke(function_name, arguments_jsvalue).await {
-                Ok(result) => Ok(JsValue::into_serde(&result)?),
-                Err(error) => Err(JsValue::into_serde::<serde_error::Error>(&error).ok()
-                    .map(anyhow::Error::from)
-                    .unwrap_or(anyhow!("Error invoking {function_name}")))
+            let on_event = {
+                let close = close.clone();
+                Closure::<dyn Fn(JsValue)>::new(move |event: JsValue| {
+                    let payload = JsValue::into_serde::<serde_json::Value>(&event)
+                        .ok()
+                        .and_then(|mut event| serde_json::from_value::<Result<Option<Vec<#item>>, common::StreamError>>(
+                            event["payload"].take()).ok());
+                    match payload {
+                        Some(Ok(Some(items))) => for item in items {
+                            drop(sender.send(Ok(item)));
+                        },
+                        Some(Ok(None)) => close.notify_one(),
+                        Some(Err(error)) => drop(sender.send(Err(error.into()))),
+                        None => drop(sender.send(Err(anyhow!("Unable to deserialize {function_name} event."))))
+                    }
+                })
+            };
+
+            let unlisten = listen(&event_name, &on_event).await
+                .ok()
+                .and_then(|unlisten| unlisten.dyn_into::<js_sys::Function>().ok())
+                .ok_or(anyhow!("Error listening for {function_name} events"))?;
+
+            wasm_bindgen_futures::spawn_local({
+                let close = close.clone();
+                async move {
+                    close.notified().await;
+                    let _ = unlisten.call0(&JsValue::null());
+                    drop(on_event);     // move on_event into this closure to keep it alive, and the sender with it
+                }
+            });
+
+            if let Err(error) = invoke_command.await {
+                close.notify_one();
+                return Err(error);
             }
+
+            Ok(tokio_stream::wrappers::UnboundedReceiverStream::new(recv))
         }
     });
-}
\ No newline at end of file
+}
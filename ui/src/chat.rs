@@ -1,29 +1,77 @@
 use std::{collections::HashMap, sync::Arc, time::Duration};
-use anyhow::{anyhow, bail, Result};
-use common::{Config, Exchange};
-use futures::{FutureExt, join, stream, Stream};
+use anyhow::{anyhow, Result};
+use common::{estimate_tokens, redact, CarryOver, Config, ConversationSettings, Exchange, GuardrailFinding};
+use common::{ContextSummary, ConversationParameters, MessageCounter, ProviderError, RetryStatus};
+use common::{with_attachments, Attachment, RateLimit, ResponseChunk, ResponseMetadata, SendKey, Snippet};
+use common::StreamNotice;
+use futures::{FutureExt, join, Stream};
 use gloo_utils::format::JsValueSerdeExt;
 use leptos::{*, leptos_dom::log};
-use tokio_stream::{StreamExt, wrappers::UnboundedReceiverStream};
+use tokio_stream::StreamExt;
 use wasm_bindgen::{JsValue, prelude::*};
-use crate::commands::{add_conversation, delete_conversation, load_exchanges};
-use crate::util::{button, conversation_uuid, get_conversation_uuid_untracked, listen, update_textarea_height};
-use crate::util::{set_conversation_uuid, set_conversation_uuid_untracked, ErrorMessage, Menu};
+use crate::commands::{add_conversation, attach_files, count_tokens, delete_conversation, load_carry_over};
+use crate::commands::{build_token_stream, estimate_cost, load_draft, load_exchanges, request_attention, save_draft};
+use crate::commands::{load_conversation_settings, preview_request, save_conversation_settings, scan_prompt};
+use crate::commands::{load_conversation, save_conversation_parameters, set_window_title, summarize_conversation};
+use crate::render::{is_renderable, Images, Rendered, Streamed};
+use crate::util::{button, conversation_uuid, get_conversation_uuid_untracked, listen, sleep, update_textarea_height};
+use crate::util::{format_cost, set_conversation_uuid, set_conversation_uuid_untracked, CopyLink, ErrorMessage, Menu};
+use crate::util::emit;
 
 lazy_static::lazy_static! {
     // anyhow! macro doesn't work if there is a static variable named "error" in the namespace
     pub static ref signal_pair: (ReadSignal<String>, WriteSignal<String>) = create_signal("".into());
     pub static ref set_error: WriteSignal<String> = signal_pair.1;
+    // the conversation this window just saved for the first time
+    static ref added_conversation: std::sync::Mutex<Option<uuid::Uuid>> = std::sync::Mutex::new(None);
 }
 
-async fn sleep(duration: Duration) {
-    let (send, recv) = tokio::sync::oneshot::channel();
+#[component]
+fn Counter(content: Signal<String>, counter: Signal<MessageCounter>) -> impl IntoView {
+    let characters = move || content.with(|content| content.chars().count());
+    let words = move || content.with(|content| content.split_whitespace().count());
+    let over_limit = move || counter.with(|counter|
+        counter.character_limit.is_some_and(|limit| characters() > limit)
+            || counter.word_limit.is_some_and(|limit| words() > limit));
+
+    let describe = |count: usize, limit: Option<usize>, unit: &str| match limit {
+        Some(limit) => format!("{count}/{limit} {unit}"),
+        None => format!("{count} {unit}")
+    };
+
+    view! {
+        <p class="mt-1 text-right text-[0.75em]"
+            class=("text-[#777788]", move || !over_limit())
+            class=("text-red-400", over_limit)
+            style:display=move || (!counter().enabled).then(|| "None")
+        >
+            {move || describe(characters(), counter().character_limit, "characters")}
+            " · "
+            {move || describe(words(), counter().word_limit, "words")}
+        </p>
+    }
+}
 
-    set_timeout(move || {
-        let _ = send.send(());
-    }, duration);
+// the "/" starting the word before the caret and what's typed after it, as a byte offset and text
+fn slash_query(text: &str, caret: usize) -> Option<(usize, &str)> {
+    let before = text.get(..caret)?;
+    let slash = before.rfind('/')?;
+    let query = &before[slash + 1..];
+    let starts_word = before[..slash].chars().next_back().map_or(true, char::is_whitespace);
+    (starts_word && !query.contains(char::is_whitespace)).then_some((slash, query))
+}
 
-    recv.await.unwrap_or_else(|error| log!("Unable to sleep: {error}"));
+// textareas count the caret in utf-16 code units, strings are indexed by bytes
+fn caret_offset(message_box: &web_sys::HtmlTextAreaElement, text: &str) -> Option<usize> {
+    let caret = message_box.selection_start().ok()?? as usize;
+    let mut units = 0;
+    for (offset, char) in text.char_indices() {
+        if units >= caret {
+            return Some(offset);
+        }
+        units += char.len_utf16();
+    }
+    Some(text.len())
 }
 
 #[component]
@@ -34,11 +82,62 @@ fn MessageBox(
     placeholder: Option<String>,
     content: Signal<String>,
     set_content: SignalSetter<String>,
+    #[prop(optional)]
+    counter: Option<Signal<MessageCounter>>,
+    // notified when the send key is pressed
+    #[prop(optional)]
+    send: Option<(Signal<SendKey>, Trigger)>,
+    // offered by name after typing "/"
+    #[prop(optional)]
+    snippets: Option<Signal<Vec<Snippet>>>
 ) -> impl IntoView {
     let class = format!("{} flex-none w-full min-h-[2em] px-2 pt-1 pb-2 border border-[#303038]
         bg-[#222222] text-[0.9em] overflow-hidden resize-none", class);
     let message_box = view! {
-        <textarea id=id.clone() rows=rows class=class type="text" placeholder=placeholder></textarea>
+        <textarea id=id.clone() rows=rows class=class type="text" placeholder=placeholder spellcheck="true">
+        </textarea>
+    };
+
+    // the position of the "/" and the text after it while a snippet is being chosen
+    let snippet_query = create_rw_signal(None::<(usize, String)>);
+    let highlighted = create_rw_signal(0_usize);
+    let matching_snippets = move || match (snippets, snippet_query()) {
+        (Some(snippets), Some((_, query))) => snippets.with(|snippets| snippets.iter()
+            .filter(|snippet| snippet.name.to_lowercase().contains(&query.to_lowercase()))
+            .cloned()
+            .collect::<Vec<_>>()),
+        _ => vec![]
+    };
+
+    let update_snippet_query = {
+        let message_box = message_box.clone();
+        move || {
+            let text = message_box.value();
+            let query = caret_offset(&message_box, &text)
+                .and_then(|caret| slash_query(&text, caret))
+                .map(|(slash, query)| (slash, query.to_string()));
+            snippet_query.set(query);
+            highlighted.set(0);
+        }
+    };
+
+    // replaces the "/" and the text after it with the snippet's body
+    let insert_snippet = {
+        let message_box = message_box.clone();
+        move |snippet: Snippet| {
+            let Some((slash, query)) = snippet_query.get_untracked() else {
+                return;
+            };
+            let text = message_box.value();
+            let (Some(before), Some(after)) = (text.get(..slash), text.get(slash + 1 + query.len()..)) else {
+                return;
+            };
+
+            let caret = (before.encode_utf16().count() + snippet.body.encode_utf16().count()) as u32;
+            snippet_query.set(None);
+            set_content(format!("{before}{}{after}", snippet.body));
+            let _ = message_box.set_selection_range(caret, caret);
+        }
     };
 
     let on_input = Closure::<dyn Fn(web_sys::Event) + 'static>::new({
@@ -46,11 +145,77 @@ fn MessageBox(
         move |event| {
             set_content(event_target_value(&event));
             update_textarea_height(&message_box);
+            if snippets.is_some() {
+                update_snippet_query();
+            }
         }
     });
     message_box.set_oninput(Some(on_input.as_ref().unchecked_ref()));
     std::mem::forget(on_input);
 
+    if send.is_some() || snippets.is_some() {
+        let on_keydown = Closure::<dyn Fn(web_sys::KeyboardEvent) + 'static>::new({
+            let insert_snippet = insert_snippet.clone();
+            move |event: web_sys::KeyboardEvent| {
+                // enter confirms the composition rather than sending while typing with an IME
+                if event.is_composing() {
+                    return;
+                }
+
+                let matching = untrack(matching_snippets);
+                if !matching.is_empty() {
+                    let handled = match event.key().as_str() {
+                        "ArrowDown" => {
+                            highlighted.update(|highlighted| *highlighted = (*highlighted + 1).min(matching.len() - 1));
+                            true
+                        },
+                        "ArrowUp" => {
+                            highlighted.update(|highlighted| *highlighted = highlighted.saturating_sub(1));
+                            true
+                        },
+                        "Enter" | "Tab" => {
+                            let index = highlighted.get_untracked().min(matching.len() - 1);
+                            insert_snippet(matching[index].clone());
+                            true
+                        },
+                        "Escape" => {
+                            snippet_query.set(None);
+                            true
+                        },
+                        _ => false
+                    };
+                    if handled {
+                        event.prevent_default();
+                        return;
+                    }
+                }
+
+                let Some((send_key, send)) = send else {
+                    return;
+                };
+                if event.key() != "Enter" {
+                    return;
+                }
+                let sends = match send_key.get_untracked() {
+                    SendKey::Enter => !event.shift_key(),
+                    SendKey::ShiftEnter => event.shift_key()
+                };
+                if sends {
+                    event.prevent_default();
+                    send.notify();
+                }
+            }
+        });
+        message_box.set_onkeydown(Some(on_keydown.as_ref().unchecked_ref()));
+        std::mem::forget(on_keydown);
+    }
+
+    if snippets.is_some() {
+        let on_blur = Closure::<dyn Fn() + 'static>::new(move || snippet_query.set(None));
+        message_box.set_onblur(Some(on_blur.as_ref().unchecked_ref()));
+        std::mem::forget(on_blur);
+    }
+
     // this is because value=content entry in the view macro below does not work
     create_effect({
         let message_box = message_box.clone();
@@ -61,14 +226,83 @@ fn MessageBox(
         })
     });
 
-    return message_box;
+    // mousedown rather than click so the textarea keeps focus
+    let snippet_menu = snippets.map(|_| view! {
+        <div class="relative">
+            <div class="absolute z-10 top-0 left-0 flex flex-col max-w-[60%] max-h-[30vh] overflow-y-auto
+                    bg-[#181a1b] border border-[#33333A] text-[0.85em]"
+                    style:display=move || matching_snippets().is_empty().then(|| "None")>
+                {move || matching_snippets().into_iter().enumerate().map(|(index, snippet)| {
+                    let insert_snippet = insert_snippet.clone();
+                    let preview = snippet.body.lines().next().unwrap_or_default().to_string();
+                    view! {
+                        <a class="flex gap-2 px-2 py-1 cursor-pointer hover:bg-[#2A2A2A]"
+                            class=("bg-[#2A2A2A]", move || highlighted() == index)
+                            on:mousedown=move |event| {
+                                event.prevent_default();
+                                insert_snippet(snippet.clone());
+                            }>
+                            <span class="text-[#AAAABB]">{format!("/{}", snippet.name)}</span>
+                            <span class="truncate text-[#777788]">{preview}</span>
+                        </a>
+                    }
+                }).collect_view()}
+            </div>
+        </div>
+    });
+
+    view! {
+        {message_box}
+        {snippet_menu}
+        {counter.map(|counter| view! { <Counter content counter /> })}
+    }
+}
+
+// a one line summary of the details recorded with a response
+fn describe_metadata(metadata: &ResponseMetadata, cost: Option<f64>) -> String {
+    let mut details = vec![];
+    if let Some(seed) = metadata.seed {
+        details.push(format!("seed {seed}"));
+    }
+    if let Some(model) = &metadata.model {
+        details.push(model.clone());
+    }
+    if let Some(system_fingerprint) = &metadata.system_fingerprint {
+        details.push(system_fingerprint.clone());
+    }
+    if let Some(prompt_tokens) = metadata.usage.prompt_tokens {
+        details.push(format!("{prompt_tokens} prompt tokens"));
+    }
+    if let Some(completion_tokens) = metadata.usage.completion_tokens {
+        details.push(format!("{completion_tokens} completion tokens"));
+    }
+    if let Some(cost) = cost {
+        details.push(format_cost(cost));
+    }
+
+    return details.join(" · ");
+}
+
+// the model's thinking, collapsed by default and kept out of the response text
+#[component]
+fn Reasoning(reasoning: Signal<String>) -> impl IntoView {
+    view! {
+        <details class="mt-[12px] text-[0.85em] text-[#777788]"
+                style:display=move || reasoning.with(String::is_empty).then(|| "None")>
+            <summary class="cursor-pointer hover:text-[#AAAABB]">"reasoning"</summary>
+            <p class="mt-1 pl-2 border-l border-[#33333A] whitespace-pre-wrap">{reasoning}</p>
+        </details>
+    }
 }
 
 #[component]
 fn ExchangeComponent(
+    counter: Signal<MessageCounter>,
     key: usize,
     exchange: RwSignal<Exchange>,
     exchanges: RwSignal<Vec<(usize, RwSignal<Exchange>)>>,
+    resubmit_from: RwSignal<Option<usize>>,
+    streaming: RwSignal<bool>
 ) -> impl IntoView {
     let (user_message, set_user_message) = create_slice(
         exchange, 
@@ -77,10 +311,31 @@ fn ExchangeComponent(
     );
     let (assistant_message, set_assistant_message) = create_slice(
         exchange, 
+        // shown trimmed as before, the cleanup settings only change what's saved
         |exchange| exchange.assistant_message.trim().to_string(),
         |exchange, assistant_message| exchange.assistant_message = assistant_message
     );
 
+    let metadata = move || exchange.with(|exchange| describe_metadata(&exchange.metadata, exchange.cost));
+    let images = Signal::derive(move || exchange.with(|exchange| exchange.metadata.images.clone()));
+    let reasoning = Signal::derive(move || exchange.with(|exchange| exchange.metadata.reasoning.clone()));
+    let stopped_reason = move || exchange.with(|exchange| exchange.metadata.stopped_reason());
+    // which model answered, useful once the model is switched partway through a conversation
+    let badge = move || exchange.with(|exchange| match (&exchange.model, &exchange.provider) {
+        (Some(model), Some(provider)) => Some(format!("{model} · {provider}")),
+        (model, provider) => model.clone().or(provider.clone())
+    });
+
+    let rendered = create_rw_signal(false);
+    let renderable = move || assistant_message.with(|message| is_renderable(message));
+    let on_toggle_rendered = move |_| {
+        rendered.update(|rendered| *rendered = !*rendered);
+        // the textarea's height can't be measured while it's hidden
+        if let Ok(message_box) = get_message_box_by_id(2*key + 1) {
+            update_textarea_height(&message_box);
+        }
+    };
+
     let on_delete = move || {
         exchanges.update(|exchanges| {
             exchanges.retain(|(_key, _)| key != *_key);
@@ -92,7 +347,7 @@ fn ExchangeComponent(
                 let exchanges = exchanges.iter()
                     .map(|(key, exchange)| (*key, exchange.get_untracked()))
                     .collect::<Vec<_>>();
-                spawn_local(set_exchanges(exchanges));
+                spawn_local(set_exchanges(exchanges, NewConversation::default()));
             }
         })};
 
@@ -102,9 +357,33 @@ fn ExchangeComponent(
                 class="absolute top-[-10px] right-[10px] text-[1.5rem] text-[#AAAABB]"
             >"-"</button>
             <MessageBox id=format!("message-box-{}", 2*key) rows=1 class="".into()
-                placeholder=None content=user_message set_content=set_user_message />
-            <MessageBox id=format!("message-box-{}", 2*key + 1) rows=1 placeholder=None
-                class="mt-[12px]".into() content=assistant_message set_content=set_assistant_message />
+                placeholder=None content=user_message set_content=set_user_message counter />
+            <Reasoning reasoning />
+            <div class="flex flex-col" style:display=move || rendered().then(|| "None")>
+                <MessageBox id=format!("message-box-{}", 2*key + 1) rows=1 placeholder=None
+                    class="mt-[12px]".into() content=assistant_message set_content=set_assistant_message />
+            </div>
+            <div class="mt-[12px]" style:display=move || (!rendered()).then(|| "None")>
+                <Rendered message=assistant_message />
+            </div>
+            <Images images />
+            <p class="mt-1 text-[0.8em] text-yellow-500"
+                    style:display=move || stopped_reason().is_none().then(|| "None")>
+                {move || stopped_reason().map(|reason| format!("stopped: {reason}"))}
+            </p>
+            <div class="flex gap-2 mt-1 text-[0.75em] text-[#777788]">
+                <span class="px-1 border border-[#33333A] rounded"
+                    style:display=move || badge().is_none().then(|| "None")
+                >{badge}</span>
+                <CopyLink text=assistant_message />
+                <a class="cursor-pointer hover:text-[#AAAABB]" on:click=move |_| resubmit_from.set(Some(key))
+                    style:display=move || streaming().then(|| "None")
+                >"resubmit from here"</a>
+                <a class="cursor-pointer hover:text-[#AAAABB]" on:click=on_toggle_rendered
+                    style:display=move || (!renderable()).then(|| "None")
+                >{move || if rendered() { "edit" } else { "render" }}</a>
+                <p class="ml-auto">{metadata}</p>
+            </div>
         </div>
     }
 }
@@ -118,9 +397,11 @@ fn get_message_box_by_id(id: usize) -> Result<web_sys::HtmlTextAreaElement> {
 
 #[component]
 fn Exchanges(
+    counter: Signal<MessageCounter>,
     new_exchange: RwSignal<Exchange>,
     exchanges: RwSignal<Vec<(usize, RwSignal<Exchange>)>>,
     update_heights: Arc<tokio::sync::Notify>,
+    resubmit_from: RwSignal<Option<usize>>,
     response_textbox: HtmlElement<html::P>,
     streaming: RwSignal<bool>
 ) -> impl IntoView {
@@ -143,6 +424,7 @@ fn Exchanges(
     window().set_onresize(Some(on_resize.as_ref().unchecked_ref()));
     std::mem::forget(on_resize);
 
+    let streamed_reasoning = Signal::derive(move || new_exchange.with(|exchange| exchange.metadata.reasoning.clone()));
     let margin_top = move |key| exchanges().get(0).and_then(|(_key, _)| (key != *_key).then(|| "12px"));
     view! {
         <div class="flex flex-col">
@@ -150,7 +432,7 @@ fn Exchanges(
                 key=|(key, _)| *key
                 children=move |(key, exchange)| view! {
                     <div style:margin-top=move || margin_top(key)>
-                        <ExchangeComponent key exchange exchanges />
+                        <ExchangeComponent counter key exchange exchanges resubmit_from streaming />
                     </div>
                 } />
         </div>
@@ -158,67 +440,28 @@ fn Exchanges(
             style:margin-top=move || (!exchanges().is_empty()).then(|| "12px")
             style:display=move || (!streaming()).then(|| "None")
         >{move || new_exchange().user_message}</p>
+        <div style:display=move || (!streaming()).then(|| "None")>
+            <Reasoning reasoning=streamed_reasoning />
+        </div>
         {response_textbox}
     }
 }
 
-fn deserialize_event(event: JsValue) -> Result<Option<String>> {
-    let mut parsed_event = JsValue::into_serde::<serde_json::Map<String, serde_json::Value>>(&event)?;
-    let Some(serde_json::Value::Object(mut payload)) = parsed_event.remove("payload") else {
-        bail!("Unable to deserialize token.");
+// count down the wait the provider asked for, stopping if the error message is replaced
+async fn show_retry_countdown(error: ProviderError) {
+    let Some(seconds) = error.retry_after() else {
+        set_error(error.to_string());
+        return;
     };
 
-    if let Some(token) = payload.remove("Ok") {
-        if token.is_null() {
-            return Ok(None);    // signals end of response
-        }
-
-        if let Some(token) = token.as_str() {
-            return Ok(Some(token.into()));
+    for remaining in (0..=seconds).rev() {
+        let message = error.clone().with_retry_after(Some(remaining)).to_string();
+        set_error(message.clone());
+        sleep(Duration::from_secs(1)).await;
+        if signal_pair.0.get_untracked() != message {
+            break;
         }
-    } else if let Some(error) = payload.remove("Err") {
-        if let Ok(error) = serde_json::from_value::<serde_error::Error>(error) {
-            return Err(error.into());
-        }
-    }
-
-    bail!("Unable to deserialize token.");
-}
-
-async fn build_token_stream(prompt: &str, config: Config, exchanges: Vec<Exchange>)
--> Result<Box<dyn Stream<Item = Result<String>> + Unpin>> {
-    let canceled = crate::commands::build_token_stream(prompt, config, exchanges).await?;
-    if canceled {
-        // the cancel button was clicked before the token stream could be built
-        return Ok(Box::new(stream::empty()));
     }
-
-    let (sender, recv) = tokio::sync::mpsc::unbounded_channel();
-    let close = std::sync::Arc::new(tokio::sync::Notify::new());
-
-    let on_token = {
-        let close = close.clone();
-        Closure::new(move |event: JsValue| {
-            match deserialize_event(event) {
-                Ok(Some(token)) => drop(sender.send(Ok(token))),
-                Ok(None) => close.notify_one(),
-                Err(error) => drop(sender.send(Err(error)))
-            }
-        })
-    };
-
-    let unlisten = listen("token", &on_token).await
-        .map_err(|_| anyhow!("Error listening for tokens"))?
-        .dyn_into::<js_sys::Function>()
-        .map_err(|_| anyhow!("Error listening for tokens"))?;
-
-    spawn_local(async move {
-        close.notified().await;
-        let _ = unlisten.call0(&JsValue::null());
-        drop(on_token);     // move on_tokens into this closure to keep it alive
-    });
-
-    return Ok(Box::new(UnboundedReceiverStream::new(recv)));
 }
 
 const TOLERANCE: i32 = 5;
@@ -242,18 +485,25 @@ fn calculate_visibility(
     (visible_height as f64)/(exchanges_div.client_height() as f64)
 }
 
+// with multiple candidates the first also streams into exchange, which drives the autoscroll.
+// nothing is scrolled while another conversation is open
 async fn collect_tokens(
     exchange: RwSignal<Exchange>,
+    candidates: RwSignal<Vec<Exchange>>,
     exchanges_div: &web_sys::HtmlDivElement,
     response_textbox: &web_sys::HtmlParagraphElement,
-    mut token_stream: impl Stream<Item = Result<String>> + Unpin,
+    mut token_stream: impl Stream<Item = Result<ResponseChunk>> + Unpin,
+    is_open: impl Fn() -> bool
 ) {
     let mut visibility = calculate_visibility(exchanges_div, response_textbox);
-    while let Some(token) = token_stream.next().await {
-        let token = match token {
-            Ok(token) => token,
+    while let Some(chunk) = token_stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
             Err(error) => {
-                set_error(error.to_string());
+                match error.downcast::<ProviderError>() {
+                    Ok(error) => spawn_local(show_retry_countdown(error)),
+                    Err(error) => set_error(error.to_string())
+                }
                 break;
             }
         };
@@ -264,7 +514,19 @@ async fn collect_tokens(
             (TOLERANCE as f64)/(exchanges_div.client_height() as f64)
         );
 
-        exchange.update(|exchange| exchange.assistant_message.push_str(&token));
+        let append = |exchange: &mut Exchange| {
+            exchange.assistant_message.push_str(&chunk.tokens);
+            exchange.metadata.merge(chunk.metadata.clone());
+        };
+        candidates.update(|candidates| if let Some(candidate) = candidates.get_mut(chunk.candidate) {
+            append(candidate);
+        });
+        if chunk.candidate == 0 {
+            exchange.update(|exchange| append(exchange));
+        }
+        if !is_open() {
+            continue;
+        }
 
         let x = (response_textbox.scroll_height() as f64)/(exchanges_div.client_height() as f64);
         if x < 0.75 {
@@ -291,14 +553,33 @@ async fn collect_tokens(
     }
 }
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(catch, js_namespace = ["window", "__TAURI__", "event"])]
-    async fn emit(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+// saved with a conversation when it's first added to the database
+#[derive(Clone, Default)]
+struct NewConversation {
+    carry_over: Option<CarryOver>,
+    settings: ConversationSettings,
+    parameters: ConversationParameters
+}
+
+// a response in progress, kept by its stream id so it keeps streaming while another conversation is open
+#[derive(Clone)]
+struct ActiveStream {
+    stream_id: uuid::Uuid,
+    // the conversation it's for, see open_conversation in Chat
+    conversation: uuid::Uuid,
+    new_exchange: RwSignal<Exchange>,
+    candidates: RwSignal<Vec<Exchange>>,
+    // exchanges the response replaces once it's saved
+    replaced: Vec<(usize, RwSignal<Exchange>)>
+}
+
+// a conversation has at most one response in progress
+fn open_stream(streams: &HashMap<uuid::Uuid, ActiveStream>, conversation: uuid::Uuid) -> Option<&ActiveStream> {
+    streams.values().find(|stream| stream.conversation == conversation)
 }
 
 // update this conversation's exchanges in the conversation history database
-async fn set_exchanges(exchanges: Vec<(usize, Exchange)>) {
+async fn set_exchanges(exchanges: Vec<(usize, Exchange)>, new_conversation: NewConversation) {
     if exchanges.is_empty() {
         set_error("A conversation cannot be empty.".into());
     }
@@ -314,132 +595,1203 @@ async fn set_exchanges(exchanges: Vec<(usize, Exchange)>) {
             _ => ()
         }
     } else {
-        match add_conversation(exchanges).await {
-            Ok(uuid) => set_conversation_uuid(Some(uuid)),
+        let NewConversation { carry_over, settings, parameters } = new_conversation;
+        match add_conversation(exchanges, carry_over, settings, parameters).await {
+            Ok(uuid) => {
+                if let Ok(mut added_conversation) = added_conversation.lock() {
+                    *added_conversation = Some(uuid);
+                }
+                set_conversation_uuid(Some(uuid));
+            },
             Err(error) => set_error(error.to_string()),
         }
     }
 }
 
+// saves a response that finished while another conversation was open, the first candidate if there are several
+async fn save_in_background(
+    uuid: Option<uuid::Uuid>,
+    replaced: Vec<usize>,
+    exchange: Exchange,
+    new_conversation: NewConversation
+) -> Result<()> {
+    let Some(uuid) = uuid else {
+        let NewConversation { carry_over, settings, parameters } = new_conversation;
+        add_conversation(vec![(0, exchange)], carry_over, settings, parameters).await?;
+        return Ok(());
+    };
+
+    let mut exchanges = load_exchanges(uuid).await?;
+    exchanges.retain(|(key, _)| !replaced.contains(key));
+    let max_key = exchanges.iter().map(|(key, _)| *key + 1).max().unwrap_or(0);
+    exchanges.push((max_key, exchange));
+    crate::commands::set_exchanges(uuid, exchanges).await?;
+    return Ok(());
+}
+
+async fn store_draft(conversation_uuid: Option<uuid::Uuid>, draft: String) {
+    if let Err(error) = save_draft(conversation_uuid, draft).await {
+        set_error(error.to_string());
+    }
+}
+
+// a failed estimate just leaves the cost unknown
+async fn with_cost(mut exchange: Exchange, model: String) -> Exchange {
+    exchange.cost = estimate_cost(model, exchange.metadata.usage.clone()).await.ok().flatten();
+    return exchange;
+}
+
+// append a finished exchange to the conversation and save it
+fn add_exchange(
+    exchanges: RwSignal<Vec<(usize, RwSignal<Exchange>)>>,
+    exchange: Exchange,
+    new_conversation: NewConversation
+) {
+    exchanges.update(|exchanges| {
+        let max_key = exchanges.into_iter().map(|(key, _)| *key + 1).max().unwrap_or(0);
+        exchanges.push((max_key, create_rw_signal(exchange)));
+        let exchanges = exchanges.iter()
+            .map(|(key, exchange)| (*key, exchange.get_untracked()))
+            .collect::<Vec<_>>();
+        // update this conversation's exchanges to the database
+        spawn_local(set_exchanges(exchanges, new_conversation));
+    });
+}
+
+// languages offered for spellcheck and font selection, by BCP 47 tag
+const LANGUAGES: [(&str, &str); 13] = [
+    ("en", "English"), ("es", "Español"), ("fr", "Français"), ("de", "Deutsch"), ("pt", "Português"),
+    ("ru", "Русский"), ("hi", "हिन्दी"), ("zh", "中文"), ("ja", "日本語"), ("ko", "한국어"),
+    ("ar", "العربية"), ("he", "עברית"), ("fa", "فارسی")
+];
+
+#[component]
+fn LanguageSelect(conversation_settings: RwSignal<ConversationSettings>) -> impl IntoView {
+    let on_change = move |event| {
+        let language = Some(event_target_value(&event)).filter(|language| !language.is_empty());
+        conversation_settings.update(|settings| settings.language = language);
+
+        // a new conversation's settings are saved when it's added
+        if let Some(uuid) = get_conversation_uuid_untracked() {
+            spawn_local(async move {
+                if let Err(error) = save_conversation_settings(uuid, conversation_settings.get_untracked()).await {
+                    set_error(error.to_string());
+                }
+            });
+        }
+    };
+
+    let language_select = view! {
+        <select class="mr-4 md:mr-8 px-1 bg-[#222222] border border-[#33333A] text-[#AAAABB]"
+                on:change=on_change>
+            <option value="">"Language"</option>
+            {LANGUAGES.iter().map(|&(tag, name)| view! { <option value=tag>{name}</option> }).collect_view()}
+        </select>
+    };
+
+    create_effect({
+        let language_select = language_select.clone();
+        // this is different from setting the select's value html attribute, which will not work
+        move |_| language_select.set_value(&conversation_settings().language.unwrap_or_default())
+    });
+
+    return language_select;
+}
+
+#[component]
+fn WorkspaceInput(conversation_settings: RwSignal<ConversationSettings>) -> impl IntoView {
+    let on_change = move |event| {
+        let workspace = Some(event_target_value(&event)).filter(|workspace| !workspace.trim().is_empty());
+        conversation_settings.update(|settings| settings.workspace = workspace);
+
+        // a new conversation's settings are saved when it's added
+        if let Some(uuid) = get_conversation_uuid_untracked() {
+            spawn_local(async move {
+                if let Err(error) = save_conversation_settings(uuid, conversation_settings.get_untracked()).await {
+                    set_error(error.to_string());
+                }
+            });
+        }
+    };
+
+    let workspace_input = view! {
+        <input type="text" placeholder="Workspace folder" on:change=on_change
+            class="mr-4 md:mr-8 px-1 w-[12em] bg-[#222222] border border-[#33333A] text-[#AAAABB]" />
+    };
+
+    create_effect({
+        let workspace_input = workspace_input.clone();
+        // this is different from setting the input's value html attribute, which will not work
+        move |_| workspace_input.set_value(&conversation_settings().workspace.unwrap_or_default())
+    });
+
+    return workspace_input;
+}
+
+// applied to the next request only
+#[derive(Clone, Copy, PartialEq)]
+enum LengthPreset {
+    Short,
+    Normal,
+    Detailed
+}
+
+impl LengthPreset {
+    fn label(self) -> &'static str {
+        match self {
+            LengthPreset::Short => "short",
+            LengthPreset::Normal => "normal",
+            LengthPreset::Detailed => "detailed"
+        }
+    }
+
+    // normal leaves the settings as they are
+    fn apply(self, config: &mut Config) {
+        let (max_tokens, instruction) = match self {
+            LengthPreset::Short => (config.max_tokens.min(256), "Keep the response brief, a few sentences at most."),
+            LengthPreset::Normal => return,
+            LengthPreset::Detailed => (config.max_tokens.max(4096), "Respond in detail, covering the topic thoroughly.")
+        };
+        config.max_tokens = max_tokens;
+        config.system_prompt = format!("{}\n\n{instruction}", config.system_prompt).trim().into();
+    }
+}
+
+#[component]
+fn LengthPresets(length_preset: RwSignal<LengthPreset>, streaming: RwSignal<bool>) -> impl IntoView {
+    view! {
+        <div class="flex gap-1 mr-4 md:mr-8 text-[0.8em]" style:display=move || streaming().then(|| "None")>
+            {[LengthPreset::Short, LengthPreset::Normal, LengthPreset::Detailed].into_iter().map(|preset| view! {
+                <button class="px-2 rounded-full border border-[#33333A] text-[#AAAABB]"
+                    class=("bg-[#33333A]", move || length_preset() == preset)
+                    on:click=move |_| length_preset.set(preset)
+                >{preset.label()}</button>
+            }).collect_view()}
+        </div>
+    }
+}
+
 #[component]
 fn Buttons(
+    attachments: RwSignal<Vec<Attachment>>,
+    carry_over: RwSignal<Option<CarryOver>>,
+    candidates: RwSignal<Vec<Exchange>>,
     config: RwSignal<Config>,
+    continue_from: RwSignal<Option<uuid::Uuid>>,
+    conversation_settings: RwSignal<ConversationSettings>,
     exchanges: RwSignal<Vec<(usize, RwSignal<Exchange>)>>,
     exchanges_div: HtmlElement<html::Div>,
+    findings: RwSignal<Option<(String, Vec<GuardrailFinding>)>>,
+    length_preset: RwSignal<LengthPreset>,
     menu: RwSignal<Menu>,
     new_exchange: RwSignal<Exchange>,
+    parameters: RwSignal<Option<ConversationParameters>>,
+    prefill: RwSignal<String>,
+    preview: RwSignal<Option<String>>,
     prompt: RwSignal<String>,
+    queued: RwSignal<Vec<String>>,
+    resubmit_from: RwSignal<Option<usize>>,
     response_textbox: HtmlElement<html::P>,
+    send_trigger: Trigger,
+    open_conversation: Memo<uuid::Uuid>,
     streaming: RwSignal<bool>,
+    streams: RwSignal<HashMap<uuid::Uuid, ActiveStream>>
 ) -> impl IntoView {
     let exchanges_div = std::rc::Rc::new(exchanges_div);
     let response_textbox = std::rc::Rc::new(response_textbox);
+    // exchanges taken out to be regenerated
+    let replaced = create_rw_signal(Vec::<(usize, RwSignal<Exchange>)>::new());
+    // whether the prompt is being sent despite the scanner's findings
+    let sent_anyway = store_value(false);
+
+    let request_config = move || {
+        let mut config = config.get_untracked();
+        if let Some(parameters) = parameters.get_untracked() {
+            parameters.apply(&mut config);
+        }
+        config.prefill = prefill.get_untracked();
+        length_preset.get_untracked().apply(&mut config);
+        if let Some(carry_over) = carry_over.get_untracked() {
+            config.system_prompt = format!("{}\n\n{}", config.system_prompt, carry_over.summary)
+                .trim()
+                .into();
+        }
+        config
+    };
 
-    let on_submit = move |_| {
+    // body is the previewed request, possibly edited. replayed is a saved prompt sent again as it was, with its
+    // attachments already in it, leaving the prompt being written, its attachments and the prefill as they are
+    let submit = move |body: Option<String>, replayed: Option<String>| {
         let is_scrollbar_bottom = is_scrollbar_bottom(&exchanges_div);
         // let is_scrollbar_bottom = (height_hidden - exchanges_div.scroll_top()).abs() < TOLERANCE;
 
-        streaming.set(true);
         set_error("".to_string());
-        let _prompt = prompt();
-        prompt.set("".to_string());
+        // the backend scans the prompt again, unless it was confirmed, previewed or already sent once
+        let confirmed = body.is_some() || replayed.is_some() || sent_anyway.get_value();
+        sent_anyway.set_value(false);
+        let (message, _prompt, _attachments, sent_prefill) = match replayed {
+            Some(message) => (message, String::new(), vec![], String::new()),
+            None => {
+                let _prompt = prompt();
+                prompt.set("".to_string());
+                let _attachments = attachments.get_untracked();
+                attachments.set(vec![]);
+                // the response continues the prefill, which anthropic sends without trailing whitespace
+                let sent_prefill = prefill.get_untracked();
+                prefill.set("".into());
+                (with_attachments(&_prompt, &_attachments), _prompt, _attachments, sent_prefill)
+            }
+        };
+        let mut _config = request_config();
+        _config.prefill = sent_prefill.clone();
+        _config.scan_prompts &= !confirmed;
         let _exchanges = exchanges.get_untracked()
             .iter()
             .map(|(_, exchange)| exchange.get_untracked())
             .collect::<Vec<_>>();
+        // saved with the response, even if it finishes after switching conversations
+        let new_conversation = NewConversation {
+            carry_over: carry_over.get_untracked(),
+            settings: conversation_settings.get_untracked(),
+            parameters: parameters.get_untracked()
+                .unwrap_or_else(|| ConversationParameters::from_config(&config.get_untracked()))
+        };
 
-        new_exchange.set(Exchange {
-            user_message: _prompt.clone(),
-            assistant_message: "".to_string()
-        });
+        // candidates are copies of it, so they're attributed too
+        let _new_exchange = Exchange {
+            user_message: message.clone(),
+            assistant_message: sent_prefill.trim_end().to_string(),
+            model: Some(_config.model.clone()),
+            provider: _config.request_api_key().map(|api_key| api_key.provider.to_string()),
+            ..Default::default()
+        };
+        // a single candidate is streamed into new_exchange alone
+        let candidate_count = config.get_untracked().candidates;
+        let conversation = open_conversation.get_untracked();
+        let stream = ActiveStream {
+            // a fresh id per response, so canceling can't reach a stream that already ended
+            stream_id: uuid::Uuid::new_v4(),
+            conversation,
+            candidates: create_rw_signal(if candidate_count > 1 {
+                vec![_new_exchange.clone(); candidate_count]
+            } else {
+                vec![]
+            }),
+            new_exchange: create_rw_signal(_new_exchange),
+            replaced: replaced.get_untracked()
+        };
+        replaced.set(vec![]);
+        let uuid = get_conversation_uuid_untracked();
+        streams.update(|streams| drop(streams.insert(stream.stream_id, stream.clone())));
+        let is_open = move || open_conversation.get_untracked() == conversation;
 
         if is_scrollbar_bottom {
             exchanges_div.set_scroll_top(exchanges_div.scroll_height() - exchanges_div.client_height());
         }
 
+        // costs are estimated for the model the request is sent to
+        let model = _config.model.clone();
+        length_preset.set(LengthPreset::Normal);
+
         let exchanges_div = exchanges_div.clone();
         let response_textbox = response_textbox.clone();
         spawn_local(async move {
-            match build_token_stream(&_prompt, config.get_untracked(), _exchanges).await {
+            match build_token_stream(stream.stream_id, uuid, &message, _config, _exchanges, body).await {
                 Ok(token_stream) => collect_tokens(
-                    new_exchange,
+                    stream.new_exchange,
+                    stream.candidates,
                     exchanges_div.as_ref(),
                     response_textbox.as_ref(),
-                    token_stream
+                    token_stream,
+                    is_open
                 ).await,
                 Err(error) => set_error(error.to_string())
             }
 
             let scroll_top = exchanges_div.scroll_top();
 
-            let _new_exchange = new_exchange.get_untracked();
-            if _new_exchange.assistant_message.is_empty() {     // whether canceled before response
-                prompt.set(_prompt);
+            let mut _new_exchange = with_cost(stream.new_exchange.get_untracked(), model.clone()).await;
+            let costed_candidates = futures::future::join_all(stream.candidates.get_untracked().into_iter()
+                .map(|candidate| with_cost(candidate, model.clone()))).await;
+            _new_exchange.assistant_message = config.get_untracked().output_cleanup
+                .apply(&_new_exchange.assistant_message);
+            // an empty response the provider stopped, e.g. for safety, is kept to show why
+            let is_empty = |exchange: &Exchange| exchange.assistant_message == sent_prefill.trim_end()
+                && exchange.metadata.images.is_empty() && exchange.metadata.stopped_reason().is_none();
+            // whether canceled before response
+            let canceled = is_empty(&_new_exchange) && costed_candidates.iter().all(is_empty);
+
+            if !is_open() {
+                streams.update(|streams| drop(streams.remove(&stream.stream_id)));
+                // a canceled response leaves the saved conversation as it was
+                if !canceled {
+                    let replaced = stream.replaced.iter().map(|(key, _)| *key).collect();
+                    if let Err(error) = save_in_background(uuid, replaced, _new_exchange, new_conversation).await {
+                        set_error(error.to_string());
+                    }
+                }
+                return;
+            }
+
+            if canceled {
+                if stream.replaced.is_empty() {
+                    prompt.set(_prompt);
+                    attachments.set(_attachments);
+                    prefill.set(sent_prefill);
+                } else {
+                    // a canceled regeneration or resubmission keeps the exchanges it was replacing
+                    exchanges.update(|exchanges| exchanges.extend(stream.replaced));
+                }
+                candidates.set(vec![]);
+            } else if costed_candidates.is_empty() {
+                add_exchange(exchanges, _new_exchange, new_conversation);
             } else {
-                exchanges.update(|exchanges| {
-                    let max_key = exchanges.into_iter().map(|(key, _)| *key + 1).max().unwrap_or(0);
-                    exchanges.push((max_key, create_rw_signal(_new_exchange)));
-                    let exchanges = exchanges.iter()
-                        .map(|(key, exchange)| (*key, exchange.get_untracked()))
-                        .collect::<Vec<_>>();
-                    // update this conversation's exchanges to the database
-                    spawn_local(set_exchanges(exchanges));
-                });
-                new_exchange.set(Exchange::default());
+                candidates.set(costed_candidates);
             }
+            // multiple candidates wait for one to be kept
+            new_exchange.set(Exchange::default());
 
-            streaming.set(false);
+            streams.update(|streams| drop(streams.remove(&stream.stream_id)));
 
             sleep(Duration::from_millis(25)).await;     // don't know why this is necessary
             exchanges_div.set_scroll_top(scroll_top);
         });
     };
 
+    // replays the last prompt, its response is replaced once the new one is saved
+    let on_regenerate = {
+        let submit = submit.clone();
+        move |_| {
+            let Some(last) = exchanges.get_untracked().last().copied() else {
+                return;
+            };
+
+            exchanges.update(|exchanges| drop(exchanges.pop()));
+            replaced.set(vec![last]);
+            submit(None, Some(last.1.with_untracked(|exchange| exchange.user_message.clone())));
+        }
+    };
+
+    // truncates the conversation at an earlier exchange and sends its prompt, possibly edited, again
+    create_effect({
+        let submit = submit.clone();
+        move |_| {
+            let Some(key) = resubmit_from() else {
+                return;
+            };
+            resubmit_from.set(None);
+            if streaming.get_untracked() {
+                return;
+            }
+
+            let _exchanges = exchanges.get_untracked();
+            let Some(position) = _exchanges.iter().position(|(_key, _)| *_key == key) else {
+                return;
+            };
+            let (kept, truncated) = _exchanges.split_at(position);
+            exchanges.set(kept.to_vec());
+            replaced.set(truncated.to_vec());
+            submit(None, Some(truncated[0].1.with_untracked(|exchange| exchange.user_message.clone())));
+        }
+    });
+
+    // shows the request for review first if previews are enabled
+    let proceed = {
+        let submit = submit.clone();
+        move || {
+            if !config.get_untracked().preview_requests {
+                submit(None, None);
+                return;
+            }
+
+            set_error("".to_string());
+            let _exchanges = exchanges.get_untracked()
+                .iter()
+                .map(|(_, exchange)| exchange.get_untracked())
+                .collect::<Vec<_>>();
+            spawn_local(async move {
+                let message = with_attachments(&prompt.get_untracked(), &attachments.get_untracked());
+                let uuid = get_conversation_uuid_untracked();
+                match preview_request(uuid, &message, request_config(), _exchanges).await {
+                    Ok(body) => preview.set(Some(body)),
+                    Err(error) => set_error(error.to_string())
+                }
+            });
+        }
+    };
+
+    let send = {
+        let proceed = proceed.clone();
+        move || {
+            if !config.get_untracked().scan_prompts {
+                proceed();
+                return;
+            }
+
+            set_error("".to_string());
+            let proceed = proceed.clone();
+            spawn_local(async move {
+                // scanned as it's sent, with the attachments' text
+                let message = with_attachments(&prompt.get_untracked(), &attachments.get_untracked());
+                match scan_prompt(message.clone(), config.get_untracked()).await {
+                    Ok(found) if found.is_empty() => proceed(),
+                    Ok(found) => findings.set(Some((message, found))),
+                    Err(error) => set_error(error.to_string())
+                }
+            });
+        }
+    };
+
+    let on_submit = {
+        let send = send.clone();
+        move |_| send()
+    };
+
+    // prompts queued during a response are sent one by one as each finishes, waiting while the prompt box
+    // holds a restored prompt or candidates are waiting to be picked
+    create_effect(move |_| {
+        if streaming() || !candidates.with(Vec::is_empty) || !prompt.with_untracked(String::is_empty) {
+            return;
+        }
+        let Some(next) = queued.with_untracked(|queued| queued.first().cloned()) else {
+            return;
+        };
+
+        queued.update(|queued| drop(queued.remove(0)));
+        prompt.set(next);
+        send();
+    });
+
+    let queue = move || {
+        let queued_prompt = prompt.get_untracked();
+        if !queued_prompt.trim().is_empty() {
+            queued.update(|queued| queued.push(queued_prompt));
+            prompt.set("".into());
+        }
+    };
+    let on_queue = move |_| queue();
+
+    // the send key submits, or queues during a response
+    create_effect({
+        let send = send.clone();
+        move |previous: Option<()>| {
+            send_trigger.track();
+            // the first run only subscribes
+            if previous.is_none() {
+                return;
+            }
+
+            if streaming.get_untracked() {
+                queue();
+            } else if preview.with_untracked(Option::is_none) && findings.with_untracked(Option::is_none)
+                    && candidates.with_untracked(Vec::is_empty) && !prompt.with_untracked(String::is_empty) {
+                send();
+            }
+        }
+    });
+
+    let on_send_anyway = {
+        let proceed = proceed.clone();
+        move |_| {
+            findings.set(None);
+            sent_anyway.set_value(true);
+            proceed();
+        }
+    };
+
+    let on_redact = move |_| {
+        if let Some((scanned, found)) = findings.get_untracked() {
+            prompt.update(|prompt| *prompt = redact(prompt, &scanned, &found));
+            attachments.update(|attachments| attachments.iter_mut()
+                .flat_map(|attachment| attachment.chunks.iter_mut())
+                .for_each(|chunk| *chunk = redact(chunk, &scanned, &found)));
+            findings.set(None);
+            proceed();
+        }
+    };
+
+    let on_send = move |_| {
+        if let Some(body) = preview.get_untracked() {
+            preview.set(None);
+            submit(Some(body), None);
+        }
+    };
+
     let on_cancel = move |_| spawn_local(async move {
-        if let Err(_) = emit("cancel", JsValue::null()).await {
+        let stream_id = streams.with_untracked(|streams| open_stream(streams, open_conversation.get_untracked())
+            .map(|stream| stream.stream_id));
+        let Some(stream_id) = stream_id else {
+            return;
+        };
+        if let Err(_) = emit(&format!("cancel:{stream_id}"), JsValue::null()).await {
             set_error("Unable to cancel stream.".into());
         }
     });
 
     view! {
         <button class=button() + "mr-4 md:mr-8"
-            on:click=move |_| set_conversation_uuid(None)
+            on:click=move |_| {
+                continue_from.set(None);
+                set_conversation_uuid(None);
+            }
             style:display=move || streaming().then(|| "None")
         >"New"</button>
+        <LengthPresets length_preset streaming />
         <button class=button() on:click=on_submit
-            style:display=move || streaming().then(|| "None")
+            style:display=move || (streaming() || preview().is_some() || findings().is_some()
+                || !candidates.with(Vec::is_empty)).then(|| "None")
         >"Submit"</button>
+        <button class=button() + "ml-4 md:ml-8" on:click=on_regenerate
+            style:display=move || (streaming() || preview().is_some() || findings().is_some()
+                || !candidates.with(Vec::is_empty) || exchanges.with(Vec::is_empty)).then(|| "None")
+        >"Regenerate"</button>
+        <button class=button() on:click=on_queue
+            style:display=move || (!streaming()).then(|| "None")
+        >"Queue"</button>
+        <button class=button() + "mr-4 md:mr-8" on:click=move |_| findings.set(None)
+            style:display=move || findings().is_none().then(|| "None")
+        >"Edit"</button>
+        <button class=button() + "mr-4 md:mr-8" on:click=on_redact
+            style:display=move || findings().is_none().then(|| "None")
+        >"Redact"</button>
+        <button class=button() on:click=on_send_anyway
+            style:display=move || findings().is_none().then(|| "None")
+        >"Send anyway"</button>
+        <button class=button() + "mr-4 md:mr-8" on:click=move |_| preview.set(None)
+            style:display=move || preview().is_none().then(|| "None")
+        >"Discard"</button>
+        <button class=button() on:click=on_send
+            style:display=move || preview().is_none().then(|| "None")
+        >"Send"</button>
         <div class="flex ml-auto">
             <button class=button() + "mr-4 md:mr-8" on:click=on_cancel
                 style:display=move || (!streaming()).then(|| "None")
             >"Cancel"</button>
+            <WorkspaceInput conversation_settings />
+            <LanguageSelect conversation_settings />
+            <button class=button() + "mr-4 md:mr-8" on:click=move |_| menu.set(Menu::EditLog)
+                style:display=move || (streaming() || conversation_uuid().is_none()).then(|| "None")
+            >"Log"</button>
             <button class=button() on:click=move |_| menu.set(Menu::Menu)>"Menu"</button>
         </div>
     }
 }
 
+// the start of the response, which the model continues from
 #[component]
-pub fn Chat(config: RwSignal<Config>, menu: RwSignal<Menu>) -> impl IntoView {
-    let error = signal_pair.0;
-    let exchanges = create_rw_signal(Vec::<(usize, RwSignal<common::Exchange>)>::new());
-    let new_exchange = create_rw_signal(Exchange::default());
-    let prompt = create_rw_signal("".to_string());
-    let streaming = create_rw_signal(false);
+fn PrefillInput(prefill: RwSignal<String>) -> impl IntoView {
+    let expanded = create_rw_signal(false);
 
-    create_effect(move |_| {
-        let Some(uuid) = conversation_uuid() else {
-            exchanges.set(vec![]);
+    view! {
+        <div class="flex flex-col text-[0.9em]">
+            <a class="mr-auto text-blue-600 cursor-pointer"
+                on:click=move |_| expanded.update(|expanded| *expanded = !*expanded)
+            >{move || if expanded() { "Hide prefill" } else { "Prefill response" }}</a>
+            <div class="flex flex-col mt-1" style:display=move || (!expanded()).then(|| "None")>
+                <MessageBox id="prefill-box".into() rows=1 class="".into()
+                    placeholder=Some("The response starts with this".into())
+                    content=prefill.into() set_content=prefill.into() />
+            </div>
+        </div>
+    }
+}
+
+// overrides the system prompt in Settings for this conversation only, saved with its parameters
+#[component]
+fn SystemPromptPanel(config: RwSignal<Config>, parameters: RwSignal<Option<ConversationParameters>>) -> impl IntoView {
+    let expanded = create_rw_signal(false);
+    let revision = store_value(0_usize);
+
+    let system_prompt = Signal::derive(move || parameters()
+        .map(|parameters| parameters.system_prompt)
+        .unwrap_or_else(|| config.with(|config| config.system_prompt.clone())));
+    let set_system_prompt = SignalSetter::map(move |system_prompt: String| {
+        parameters.update(|parameters| parameters
+            .get_or_insert_with(|| ConversationParameters::from_config(&config.get_untracked()))
+            .system_prompt = system_prompt);
+
+        // a new conversation's parameters are saved when it's added
+        let Some(uuid) = get_conversation_uuid_untracked() else {
             return;
         };
-
+        revision.update_value(|revision| *revision += 1);
+        let current_revision = revision.get_value();
         spawn_local(async move {
-            let new_exchanges = match load_exchanges(uuid).await {
-                Ok(exchanges) => exchanges,
-                Err(error) => {
+            // saved once typing pauses
+            sleep(Duration::from_millis(500)).await;
+            if revision.get_value() != current_revision {
+                return;
+            }
+            let Some(_parameters) = parameters.get_untracked() else {
+                return;
+            };
+            if let Err(error) = save_conversation_parameters(uuid, _parameters).await {
+                set_error(error.to_string());
+            }
+        });
+    });
+
+    view! {
+        <div class="flex-none mb-2 text-[0.9em]">
+            <a class="text-blue-600 cursor-pointer" on:click=move |_| expanded.update(|expanded| *expanded = !*expanded)
+            >{move || if expanded() { "Hide system prompt" } else { "System prompt" }}</a>
+            <div class="flex flex-col mt-1" style:display=move || (!expanded()).then(|| "None")>
+                <MessageBox id="conversation-system-prompt".into() rows=2 class="".into()
+                    placeholder=Some("Used instead of the system prompt in Settings for this conversation".into())
+                    content=system_prompt set_content=set_system_prompt />
+            </div>
+        </div>
+    }
+}
+
+// parallel candidates side by side, the kept one is added to the conversation and the rest are discarded
+#[component]
+fn CandidatePicker(
+    candidates: RwSignal<Vec<Exchange>>,
+    carry_over: RwSignal<Option<CarryOver>>,
+    config: RwSignal<Config>,
+    conversation_settings: RwSignal<ConversationSettings>,
+    exchanges: RwSignal<Vec<(usize, RwSignal<Exchange>)>>,
+    parameters: RwSignal<Option<ConversationParameters>>,
+    streaming: RwSignal<bool>
+) -> impl IntoView {
+    let on_keep = move |index: usize| {
+        let Some(mut candidate) = candidates.with_untracked(|candidates| candidates.get(index).cloned()) else {
+            return;
+        };
+        candidate.assistant_message = config.get_untracked().output_cleanup.apply(&candidate.assistant_message);
+        candidates.set(vec![]);
+        add_exchange(exchanges, candidate, NewConversation {
+            carry_over: carry_over.get_untracked(),
+            settings: conversation_settings.get_untracked(),
+            parameters: parameters.get_untracked()
+                .unwrap_or_else(|| ConversationParameters::from_config(&config.get_untracked()))
+        });
+    };
+
+    // pending candidates don't carry over to another conversation
+    create_effect(move |_| {
+        conversation_uuid();
+        if !streaming.get_untracked() {
+            candidates.set(vec![]);
+        }
+    });
+
+    let candidate_message = move |index: usize| candidates.with(|candidates|
+        candidates.get(index).map(|candidate| candidate.assistant_message.clone()).unwrap_or_default());
+
+    view! {
+        <div class="grid gap-4 mt-[12px]"
+                style:grid-template-columns=move || format!("repeat({}, minmax(0, 1fr))", candidates().len())
+                style:display=move || candidates.with(Vec::is_empty).then(|| "None")>
+            <For each=move || 0..candidates().len()
+                key=|&index| index
+                children=move |index| view! {
+                    <div class="flex flex-col">
+                        <p class="px-2 py-1 min-h-[2em] bg-[#222222] border border-[#303038] text-[0.9em]
+                            whitespace-pre-wrap">{move || candidate_message(index)}</p>
+                        <button class=button() + "mt-2 w-[max-content]" on:click=move |_| on_keep(index)
+                            style:display=move || streaming().then(|| "None")
+                        >"Keep this one"</button>
+                    </div>
+                } />
+        </div>
+    }
+}
+
+// prompts waiting for the current response to finish
+#[component]
+fn QueuedPrompts(queued: RwSignal<Vec<String>>) -> impl IntoView {
+    let on_remove = move |index: usize| queued.update(|queued| if index < queued.len() {
+        queued.remove(index);
+    });
+
+    view! {
+        <div class="flex flex-col items-end gap-2 mb-4" style:display=move || queued.with(Vec::is_empty).then(|| "None")>
+            {move || queued().into_iter().enumerate().map(|(index, queued_prompt)| view! {
+                <div class="flex items-center max-w-[80%] px-2 py-1 rounded-lg border border-dashed border-[#33333A]
+                        text-[0.9em] text-[#AAAABB]">
+                    <p class="truncate">{queued_prompt}</p>
+                    <button class="ml-2 text-[#777788] hover:text-[#AAAABB]" on:click=move |_| on_remove(index)
+                    >"×"</button>
+                </div>
+            }).collect_view()}
+        </div>
+    }
+}
+
+// files whose text goes in with the next prompt, shown as chips that can be removed
+#[component]
+fn Attachments(attachments: RwSignal<Vec<Attachment>>, streaming: RwSignal<bool>) -> impl IntoView {
+    let on_attach = move |_| {
+        set_error("".into());
+        spawn_local(async move {
+            match attach_files().await {
+                Ok(attached) => attachments.update(|attachments| attachments.extend(attached)),
+                Err(error) => set_error(error.to_string())
+            }
+        });
+    };
+
+    let on_remove = move |index: usize| attachments.update(|attachments| if index < attachments.len() {
+        attachments.remove(index);
+    });
+
+    view! {
+        <div class="flex flex-wrap items-center gap-2 mb-2 text-[0.9em]">
+            {move || attachments().into_iter().enumerate().map(|(index, attachment)| view! {
+                <div class="flex items-center max-w-[50%] px-2 py-[2px] rounded-lg border border-[#33333A]
+                        text-[#AAAABB]">
+                    <p class="truncate"
+                        title=attachment.truncated.then(|| "Only the beginning of this file is included")
+                    >{attachment.name}{attachment.truncated.then(|| " (truncated)")}</p>
+                    <button class="ml-2 text-[#777788] hover:text-[#AAAABB]" on:click=move |_| on_remove(index)
+                    >"×"</button>
+                </div>
+            }).collect_view()}
+            <a class="text-blue-600 cursor-pointer" on:click=on_attach style:display=move || streaming().then(|| "None")
+            >"Attach files"</a>
+        </div>
+    }
+}
+
+// what the scanner flagged, with the matches masked so they aren't shown in full
+#[component]
+fn GuardrailWarning(findings: RwSignal<Option<(String, Vec<GuardrailFinding>)>>) -> impl IntoView {
+    let describe = move |scanned: &str, finding: &GuardrailFinding| {
+        let matched = scanned.get(finding.start..finding.end).unwrap_or_default();
+        format!("{}: {}…", finding.rule, matched.chars().take(4).collect::<String>())
+    };
+
+    view! {
+        <div class="mb-2 text-[0.9em] text-yellow-500" style:display=move || findings().is_none().then(|| "None")>
+            <p>"This prompt may contain sensitive information:"</p>
+            {move || findings().map(|(scanned, found)| found.iter()
+                .map(|finding| view!(<p class="ml-4">{describe(&scanned, finding)}</p>))
+                .collect_view())}
+        </div>
+    }
+}
+
+// counts the text once it stops changing for a moment, falling back to the estimate if the tokenizer fails
+fn count_tokens_debounced(text: Signal<String>, model: Signal<String>, count: RwSignal<Option<(usize, bool)>>) {
+    let revision = store_value(0_usize);
+    create_effect(move |_| {
+        let (_text, _model) = (text(), model());
+        revision.update_value(|revision| *revision += 1);
+        let current_revision = revision.get_value();
+
+        spawn_local(async move {
+            sleep(Duration::from_millis(150)).await;
+            if revision.get_value() != current_revision {
+                return;
+            }
+
+            let counted = match count_tokens(vec![_text.clone()], _model).await {
+                Ok(counts) => counts.first().map(|&tokens| (tokens, true)),
+                Err(_) => None
+            };
+            // a later change may have been counted first
+            if revision.get_value() == current_revision {
+                count.set(Some(counted.unwrap_or((estimate_tokens(&_text), false))));
+            }
+        });
+    });
+}
+
+// tokens in the prompt being written and in what's sent along with it
+#[component]
+fn TokenCount(
+    attachments: RwSignal<Vec<Attachment>>,
+    config: RwSignal<Config>,
+    exchanges: RwSignal<Vec<(usize, RwSignal<Exchange>)>>,
+    parameters: RwSignal<Option<ConversationParameters>>,
+    prompt: RwSignal<String>
+) -> impl IntoView {
+    let prompt_tokens = create_rw_signal(None);
+    let context_tokens = create_rw_signal(None);
+
+    let model = Signal::derive(move || parameters()
+        .map(|parameters| parameters.model)
+        .unwrap_or_else(|| config.with(|config| config.model.clone())));
+    let message = Signal::derive(move || attachments.with(|attachments| prompt.with(|prompt|
+        with_attachments(prompt, attachments))));
+    let context = Signal::derive(move || {
+        let system_prompt = parameters()
+            .map(|parameters| parameters.system_prompt)
+            .unwrap_or_else(|| config.with(|config| config.system_prompt.clone()));
+        exchanges().iter().fold(system_prompt, |context, (_, exchange)| exchange.with(|exchange|
+            format!("{context}\n{}\n{}", exchange.user_message, exchange.assistant_message)))
+    });
+    count_tokens_debounced(message, model, prompt_tokens);
+    count_tokens_debounced(context, model, context_tokens);
+
+    let describe = |count: Option<(usize, bool)>, unit: &str| match count {
+        Some((tokens, true)) => format!("{tokens} {unit}"),
+        Some((tokens, false)) => format!("~{tokens} {unit}"),
+        None => "".into()
+    };
+
+    view! {
+        <p class="mt-1 text-right text-[0.75em] text-[#777788]">
+            {move || describe(prompt_tokens(), "tokens")}
+            " · "
+            {move || describe(context_tokens(), "tokens in context")}
+        </p>
+    }
+}
+
+// the token estimate only counts the text in the body, not its structure
+fn estimate_body_tokens(body: &str) -> Option<usize> {
+    fn text(value: &serde_json::Value) -> String {
+        match value {
+            serde_json::Value::String(string) => string.clone(),
+            serde_json::Value::Array(values) => values.iter().map(text).collect(),
+            serde_json::Value::Object(map) => map.iter()
+                .filter(|(key, _)| !["model", "role"].contains(&key.as_str()))
+                .map(|(_, value)| text(value))
+                .collect(),
+            _ => "".into()
+        }
+    }
+
+    let body = serde_json::from_str::<serde_json::Value>(body).ok()?;
+    return Some(estimate_tokens(&text(&body)));
+}
+
+// the request body about to be sent, editable before sending
+#[component]
+fn RequestPreview(preview: RwSignal<Option<String>>) -> impl IntoView {
+    let (content, set_content) = create_slice(
+        preview,
+        |preview| preview.clone().unwrap_or_default(),
+        |preview, content| *preview = Some(content)
+    );
+
+    let tokens = move || match content.with(|content| estimate_body_tokens(content)) {
+        Some(tokens) => format!("~{tokens} tokens"),
+        None => "Invalid JSON".into()
+    };
+
+    view! {
+        <div class="flex-none md:mx-[14.5vw] mb-4 max-h-[40vh] overflow-y-auto"
+                style:display=move || preview().is_none().then(|| "None")>
+            <div class="flex flex-col">
+                <MessageBox id="request-preview".into() rows=8 class="font-mono".into() placeholder=None
+                    content=content set_content=set_content />
+                <p class="mt-1 text-right text-[0.75em] text-[#777788]">{tokens}</p>
+            </div>
+        </div>
+    }
+}
+
+fn describe_rate_limit(rate_limit: &RateLimit) -> String {
+    let describe = |remaining: Option<u64>, limit: Option<u64>, reset: &Option<String>, unit: &str| {
+        let mut description = match (remaining, limit) {
+            (Some(remaining), Some(limit)) => format!("{remaining}/{limit} {unit}"),
+            (Some(remaining), None) => format!("{remaining} {unit}"),
+            _ => return None
+        };
+        if let Some(reset) = reset {
+            description += &format!(" (resets in {reset})");
+        }
+        Some(description)
+    };
+
+    let quotas = [
+        describe(rate_limit.remaining_requests, rate_limit.limit_requests, &rate_limit.reset_requests, "requests"),
+        describe(rate_limit.remaining_tokens, rate_limit.limit_tokens, &rate_limit.reset_tokens, "tokens")
+    ];
+    format!("{} remaining: {}", rate_limit.provider, quotas.into_iter().flatten().collect::<Vec<_>>().join(", "))
+}
+
+// a notice about a response with the conversation the response is for, while its stream is active
+fn parse_stream_notice<T: serde::de::DeserializeOwned>(
+    event: &JsValue,
+    streams: RwSignal<HashMap<uuid::Uuid, ActiveStream>>
+) -> Option<(uuid::Uuid, T)> {
+    let notice = JsValue::into_serde::<serde_json::Value>(event)
+        .ok()
+        .and_then(|mut event| serde_json::from_value::<StreamNotice<T>>(event["payload"].take()).ok())?;
+    let conversation = streams.with_untracked(|streams| streams.get(&notice.stream_id)
+        .map(|stream| stream.conversation))?;
+    Some((conversation, notice.notice))
+}
+
+// remaining quota from the open conversation's last response, shown once it's running low
+#[component]
+fn RateLimitStatus(
+    open_conversation: Memo<uuid::Uuid>,
+    streams: RwSignal<HashMap<uuid::Uuid, ActiveStream>>
+) -> impl IntoView {
+    let rate_limit = create_rw_signal(None::<(uuid::Uuid, RateLimit)>);
+
+    spawn_local(async move {
+        let on_rate_limit = Closure::new(move |event: JsValue| {
+            if let Some(notice) = parse_stream_notice(&event, streams) {
+                rate_limit.set(Some(notice));
+            }
+        });
+
+        if let Err(_) = listen("rate_limit", &on_rate_limit).await {
+            set_error("Error listening for rate limit updates".into());
+        }
+
+        // keep on_rate_limit alive forever
+        std::mem::forget(on_rate_limit);
+    });
+
+    let low_rate_limit = move || rate_limit()
+        .filter(|(conversation, rate_limit)| *conversation == open_conversation() && rate_limit.is_low())
+        .map(|(_, rate_limit)| rate_limit);
+
+    view! {
+        <p class="mb-2 text-[0.9em] text-yellow-500" style:display=move || low_rate_limit().is_none().then(|| "None")
+        >{move || low_rate_limit().as_ref().map(describe_rate_limit)}</p>
+    }
+}
+
+fn describe_retry_status(retry_status: &RetryStatus) -> String {
+    format!("{}, retrying in {}s (attempt {} of {})", retry_status.reason, retry_status.delay_ms.div_ceil(1000),
+        retry_status.attempt, retry_status.attempts)
+}
+
+// shown while the open conversation's failed request waits to be sent again, until the response starts
+#[component]
+fn RetryNotice(
+    candidates: RwSignal<Vec<Exchange>>,
+    new_exchange: RwSignal<Exchange>,
+    open_conversation: Memo<uuid::Uuid>,
+    streaming: RwSignal<bool>,
+    streams: RwSignal<HashMap<uuid::Uuid, ActiveStream>>
+) -> impl IntoView {
+    // by the conversation whose request is retried
+    let retries = create_rw_signal(HashMap::<uuid::Uuid, RetryStatus>::new());
+
+    spawn_local(async move {
+        let on_retrying = Closure::new(move |event: JsValue| {
+            if let Some((conversation, retry_status)) = parse_stream_notice(&event, streams) {
+                retries.update(|retries| drop(retries.insert(conversation, retry_status)));
+            }
+        });
+
+        if let Err(_) = listen("retrying", &on_retrying).await {
+            set_error("Error listening for retries".into());
+        }
+
+        // keep the listener alive forever
+        std::mem::forget(on_retrying);
+    });
+
+    // the response has started once tokens are streamed into it, the open conversation's since it's the one
+    // streamed into new_exchange and candidates
+    create_effect(move |_| {
+        new_exchange.track();
+        candidates.track();
+        let conversation = open_conversation.get_untracked();
+        if retries.with_untracked(|retries| retries.contains_key(&conversation)) {
+            retries.update(|retries| drop(retries.remove(&conversation)));
+        }
+    });
+
+    // and a retry is over once its stream ends
+    create_effect(move |_| {
+        let active = streams.with(|streams| streams.values().map(|stream| stream.conversation).collect::<Vec<_>>());
+        if retries.with_untracked(|retries| retries.keys().any(|conversation| !active.contains(conversation))) {
+            retries.update(|retries| retries.retain(|conversation, _| active.contains(conversation)));
+        }
+    });
+
+    let retry_status = move || retries.with(|retries| retries.get(&open_conversation()).cloned())
+        .filter(|_| streaming());
+
+    view! {
+        <p class="mb-2 text-[0.9em] text-yellow-500" style:display=move || retry_status().is_none().then(|| "None")
+        >{move || retry_status().as_ref().map(describe_retry_status)}</p>
+    }
+}
+
+// shown once a long conversation's first exchanges were sent as their summary, until the next request
+#[component]
+fn SummaryNotice(streaming: RwSignal<bool>, streams: RwSignal<HashMap<uuid::Uuid, ActiveStream>>) -> impl IntoView {
+    let summarized = create_rw_signal(None::<ContextSummary>);
+
+    spawn_local(async move {
+        let on_summarized = Closure::new(move |event: JsValue| {
+            // a summary for a response streaming in the background is only shown with its conversation
+            if let Some((_, summary)) = parse_stream_notice::<ContextSummary>(&event, streams)
+                    .filter(|(conversation, _)| Some(*conversation) == get_conversation_uuid_untracked()) {
+                summarized.set(Some(summary));
+            }
+        });
+
+        if let Err(_) = listen("context_summarized", &on_summarized).await {
+            set_error("Error listening for context summaries".into());
+        }
+
+        // keep the listener alive forever
+        std::mem::forget(on_summarized);
+    });
+
+    // the summary was sent for one request, so it's cleared by the next one or by switching conversations
+    create_effect(move |previous: Option<(bool, Option<uuid::Uuid>)>| {
+        let current = (streaming(), conversation_uuid());
+        if previous.is_some_and(|previous| current.1 != previous.1 || current.0 && !previous.0) {
+            summarized.set(None);
+        }
+        current
+    });
+
+    let notice = move || summarized().filter(|summary| Some(summary.conversation_uuid) == conversation_uuid());
+
+    view! {
+        <p class="mb-2 text-[0.9em] text-gray-500" style:display=move || notice().is_none().then(|| "None")>
+            {move || notice().map(|summary| format!("The first {} exchanges were sent as a summary to fit the \
+                model's context window.", summary.summarized))}
+        </p>
+    }
+}
+
+// the window's title in tauri.conf.json, used while no conversation is open
+const WINDOW_TITLE: &str = "Chat Playground";
+// titles are the first prompt, which can be long
+const MAX_WINDOW_TITLE_CHARS: usize = 80;
+// drafts are saved once typing pauses for this long
+const DRAFT_DELAY: Duration = Duration::from_millis(500);
+
+#[component]
+pub fn Chat(
+    config: RwSignal<Config>,
+    continue_from: RwSignal<Option<uuid::Uuid>>,
+    menu: RwSignal<Menu>
+) -> impl IntoView {
+    let error = signal_pair.0;
+    let carry_over = create_rw_signal(None::<CarryOver>);
+    // the previous conversation's summary is requested from the model when continuing it
+    let summarizing = create_rw_signal(false);
+    let counter = Signal::derive(move || config.with(|config| config.message_counter.clone()));
+    let conversation_settings = create_rw_signal(ConversationSettings::default());
+    let parameters = create_rw_signal(None::<ConversationParameters>);
+    let exchanges = create_rw_signal(Vec::<(usize, RwSignal<common::Exchange>)>::new());
+    let new_exchange = create_rw_signal(Exchange::default());
+    let candidates = create_rw_signal(Vec::<Exchange>::new());
+    let length_preset = create_rw_signal(LengthPreset::Normal);
+    let queued = create_rw_signal(Vec::<String>::new());
+    let resubmit_from = create_rw_signal(None::<usize>);
+    let attachments = create_rw_signal(Vec::<Attachment>::new());
+    let prefill = create_rw_signal("".to_string());
+    let send_key = Signal::derive(move || config.with(|config| config.send_key));
+    let send_trigger = create_trigger();
+    let findings = create_rw_signal(None::<(String, Vec<GuardrailFinding>)>);
+    let preview = create_rw_signal(None::<String>);
+    let prompt = create_rw_signal("".to_string());
+    // responses in progress by stream id
+    let streams = create_rw_signal(HashMap::<uuid::Uuid, ActiveStream>::new());
+    // conversations that haven't been saved yet share the None uuid, so each new one gets an id of its own
+    let unsaved_conversation = create_rw_signal(uuid::Uuid::new_v4());
+    create_effect(move |previous_uuid: Option<Option<uuid::Uuid>>| {
+        let uuid = conversation_uuid();
+        if uuid.is_none() && previous_uuid.is_some_and(|previous_uuid| previous_uuid.is_some()) {
+            unsaved_conversation.set(uuid::Uuid::new_v4());
+        }
+        uuid
+    });
+    // identifies the open conversation among the streams
+    let open_conversation = create_memo(move |_| conversation_uuid().unwrap_or_else(|| unsaved_conversation()));
+    // whether the open conversation is streaming
+    let streaming = create_rw_signal(false);
+
+    create_effect(move |_| {
+        let is_streaming = streams.with(|streams| open_stream(streams, open_conversation()).is_some());
+        if streaming.get_untracked() != is_streaming {
+            streaming.set(is_streaming);
+        }
+    });
+
+    // the open conversation's response is shown as it streams,
+    // a response or candidates from the conversation switched away from are cleared
+    create_effect(move |previous_conversation: Option<uuid::Uuid>| {
+        let conversation = open_conversation();
+        match streams.with(|streams| open_stream(streams, conversation).cloned()) {
+            Some(stream) => {
+                new_exchange.set(stream.new_exchange.get());
+                candidates.set(stream.candidates.get());
+            },
+            None => if previous_conversation.is_some_and(|previous| previous != conversation) {
+                new_exchange.set(Exchange::default());
+                candidates.set(vec![]);
+            }
+        }
+        conversation
+    });
+
+    // the conversation the prompt box holds a draft of
+    let draft_owner = store_value(None::<uuid::Uuid>);
+    // the prompt is saved as a draft shortly after typing stops
+    create_effect(move |previous: Option<()>| {
+        let draft = prompt();
+        // the first run only subscribes
+        if previous.is_none() {
+            return;
+        }
+
+        let owner = draft_owner.get_value();
+        spawn_local(async move {
+            sleep(DRAFT_DELAY).await;
+            // unless typing continued or the conversation was switched, which saves it right away
+            if draft_owner.get_value() == owner && prompt.with_untracked(|prompt| *prompt == draft) {
+                store_draft(owner, draft).await;
+            }
+        });
+    });
+
+    create_effect(move |previous_uuid: Option<Option<uuid::Uuid>>| {
+        let uuid = conversation_uuid();
+        // the prompt box moves with a conversation saved for the first time,
+        // e.g. when the next prompt is typed while the first response streams
+        let is_added = previous_uuid == Some(None) && uuid.is_some()
+            && added_conversation.lock().ok().and_then(|mut added_conversation| added_conversation.take()) == uuid;
+        if previous_uuid != Some(uuid) {
+            let previous_owner = draft_owner.get_value();
+            draft_owner.set_value(uuid);
+            let draft = prompt.get_untracked();
+            spawn_local(async move {
+                if is_added {
+                    store_draft(None, "".into()).await;
+                    store_draft(uuid, draft).await;
+                    return;
+                }
+                if previous_uuid.is_some() {
+                    store_draft(previous_owner, draft).await;
+                }
+                match load_draft(uuid).await {
+                    // unless the conversation was switched again while loading
+                    Ok(draft) => if draft_owner.get_value() == uuid {
+                        prompt.set(draft);
+                    },
+                    Err(error) => set_error(error.to_string())
+                }
+            });
+        }
+
+        let Some(uuid) = uuid else {
+            exchanges.set(vec![]);
+            return uuid;
+        };
+
+        spawn_local(async move {
+            let mut new_exchanges = match load_exchanges(uuid).await {
+                Ok(exchanges) => exchanges,
+                Err(error) => {
                     set_error(error.to_string());
                     return;
                 }
             };
+            // exchanges being regenerated stay saved until the response replacing them is
+            if let Some(replaced) = streams.with_untracked(|streams| open_stream(streams, uuid)
+                    .map(|stream| stream.replaced.iter().map(|(key, _)| *key).collect::<Vec<_>>())) {
+                new_exchanges.retain(|(key, _)| !replaced.contains(key));
+            }
             let key_to_exchange = exchanges.get_untracked().into_iter().collect::<HashMap<_, _>>();
             let synchronized_exchanges = new_exchanges.into_iter()
                 .map(|(key, new_exchange)| (key, key_to_exchange.get(&key)
@@ -451,8 +1803,178 @@ pub fn Chat(config: RwSignal<Config>, menu: RwSignal<Menu>) -> impl IntoView {
                 .collect();
             exchanges.set(synchronized_exchanges);
         });
+        Some(uuid)
+    });
+
+    // a saved conversation keeps the context it was started with,
+    // a new one continuing from a previous conversation pulls in its summary
+    create_effect(move |_| {
+        let (uuid, continue_from) = (conversation_uuid(), continue_from());
+        spawn_local(async move {
+            let loaded_carry_over = match (uuid, continue_from) {
+                (Some(uuid), _) => load_carry_over(uuid).await,
+                (None, Some(previous_uuid)) => {
+                    summarizing.set(true);
+                    let carry_over = summarize_conversation(previous_uuid).await.map(Some);
+                    summarizing.set(false);
+                    carry_over
+                },
+                (None, None) => Ok(None)
+            };
+            match loaded_carry_over {
+                Ok(loaded_carry_over) => carry_over.set(loaded_carry_over),
+                Err(error) => set_error(error.to_string())
+            }
+        });
+    });
+
+    create_effect(move |_| {
+        let Some(uuid) = conversation_uuid() else {
+            conversation_settings.set(ConversationSettings::default());
+            return;
+        };
+
+        spawn_local(async move {
+            match load_conversation_settings(uuid).await {
+                Ok(settings) => conversation_settings.set(settings),
+                Err(error) => set_error(error.to_string())
+            }
+        });
     });
 
+    // a loaded conversation continues with the parameters it was started with
+    create_effect(move |_| {
+        let Some(uuid) = conversation_uuid() else {
+            parameters.set(None);
+            return;
+        };
+
+        spawn_local(async move {
+            match load_conversation(uuid).await {
+                Ok(conversation) => parameters.set(conversation.parameters),
+                Err(error) => set_error(error.to_string())
+            }
+        });
+    });
+
+    // reloaded when the history changes, e.g. when the conversation's icon is set
+    let conversations_updated = create_trigger();
+    spawn_local(async move {
+        let on_update = Closure::new(move |_| conversations_updated.notify());
+
+        if let Err(_) = listen("conversations_updated", &on_update).await {
+            set_error("Error listening for conversation history updates".into());
+        }
+
+        // keep on_update alive forever
+        std::mem::forget(on_update);
+    });
+
+    let conversation_title = create_rw_signal(None::<String>);
+    create_effect(move |_| {
+        conversations_updated.track();
+        let Some(uuid) = conversation_uuid() else {
+            conversation_title.set(None);
+            return;
+        };
+
+        spawn_local(async move {
+            match load_conversation(uuid).await {
+                Ok(conversation) => conversation_title.set(Some(conversation.display_title())),
+                Err(error) => set_error(error.to_string())
+            }
+        });
+    });
+
+    // the window title shows which conversation is open and whether a response is streaming
+    create_effect(move |_| {
+        let title = conversation_title()
+            .map(|title| title.chars().take(MAX_WINDOW_TITLE_CHARS).collect())
+            .unwrap_or(WINDOW_TITLE.to_string());
+        let title = if streaming() { format!("{title} (responding…)") } else { title };
+        spawn_local(async move {
+            if let Err(error) = set_window_title(title).await {
+                set_error(error.to_string());
+            }
+        });
+    });
+
+    // a response finishing in the background flashes the taskbar entry or bounces the dock icon
+    create_effect(move |previous_count: Option<usize>| {
+        let count = streams.with(HashMap::len);
+        if previous_count.is_some_and(|previous_count| count < previous_count) {
+            spawn_local(async move {
+                if let Err(error) = request_attention().await {
+                    set_error(error.to_string());
+                }
+            });
+        }
+        count
+    });
+
+    // only shown when the saved parameters differ from the current settings
+    let overridden_parameters = move || parameters()
+        .filter(|parameters| config.with(|config| parameters != &ConversationParameters::from_config(config)));
+
+    // the total of the costs that could be estimated
+    let conversation_cost = move || exchanges.with(|exchanges| exchanges.iter()
+        .filter_map(|(_, exchange)| exchange.with(|exchange| exchange.cost))
+        .reduce(|total, cost| total + cost));
+
+    let on_use_current_settings = move |_| {
+        let Some(uuid) = get_conversation_uuid_untracked() else {
+            return;
+        };
+
+        let current_parameters = ConversationParameters::from_config(&config.get_untracked());
+        parameters.set(Some(current_parameters.clone()));
+        spawn_local(async move {
+            if let Err(error) = save_conversation_parameters(uuid, current_parameters).await {
+                set_error(error.to_string());
+            }
+        });
+    };
+
+    // the snapshot the conversation started on and the one that answered last, if they differ,
+    // since providers update what an alias like gpt-4o points to without notice
+    let snapshot_change = move || exchanges.with(|exchanges| {
+        let mut snapshots = exchanges.iter()
+            .filter_map(|(_, exchange)| exchange.with(|exchange| exchange.metadata.model.clone()));
+        let first = snapshots.next()?;
+        let last = snapshots.last()?;
+        (first != last).then_some((first, last))
+    });
+
+    // later requests go to the snapshot the conversation started on
+    let on_pin_snapshot = move |_| {
+        let (Some(uuid), Some((first, _))) = (get_conversation_uuid_untracked(), snapshot_change()) else {
+            return;
+        };
+
+        let mut pinned_parameters = parameters.get_untracked()
+            .unwrap_or_else(|| ConversationParameters::from_config(&config.get_untracked()));
+        pinned_parameters.model = first;
+        parameters.set(Some(pinned_parameters.clone()));
+        spawn_local(async move {
+            if let Err(error) = save_conversation_parameters(uuid, pinned_parameters).await {
+                set_error(error.to_string());
+            }
+        });
+    };
+
+    let language = move || conversation_settings.with(|settings| settings.language.clone());
+    // right-to-left scripts
+    let direction = move || language()
+        .filter(|language| ["ar", "fa", "he", "ur"].contains(&language.split('-').next().unwrap_or_default()))
+        .map(|_| "rtl");
+
+    let on_view_previous = move |_| {
+        if let Some(carry_over) = carry_over.get_untracked() {
+            continue_from.set(None);
+            set_conversation_uuid(Some(carry_over.conversation_uuid));
+        }
+    };
+
     let update_heights = Arc::new(tokio::sync::Notify::new());
     create_effect({
         let update_heights = Arc::clone(&update_heights);
@@ -463,35 +1985,101 @@ pub fn Chat(config: RwSignal<Config>, menu: RwSignal<Menu>) -> impl IntoView {
         format!("{} {}", classes, (exchanges().is_empty() && !streaming()).then(|| "mb-auto")
             .unwrap_or("mt-auto mb-4 md:mb-8"));
 
+    // with smoothing the response is revealed a few characters per frame, catching up faster the further
+    // it falls behind, independent of how the tokens were chunked
+    let revealed = create_rw_signal(0);
+    create_effect(move |_| {
+        if !streaming() || !config.with_untracked(|config| config.smooth_streaming) {
+            return;
+        }
+
+        revealed.set(0);
+        spawn_local(async move {
+            while streaming.get_untracked() {
+                sleep(Duration::from_millis(16)).await;
+                let total = new_exchange.with_untracked(|exchange| exchange.assistant_message.chars().count());
+                revealed.update(|revealed| {
+                    let backlog = total.saturating_sub(*revealed);
+                    *revealed += backlog.min(2 + backlog/10);
+                });
+            }
+        });
+    });
+
+    let response_text = move || new_exchange.with(|exchange| if config.with(|config| config.smooth_streaming) {
+        exchange.assistant_message.chars().take(revealed()).collect()
+    } else {
+        exchange.assistant_message.clone()
+    });
+
     let response_textbox = view! {
-        <p style:display=move || (!streaming()).then(|| "None")
+        <p style:display=move || (!streaming() || !candidates.with(Vec::is_empty)).then(|| "None")
             class="mt-[12px] px-2 py-1 min-h-[2em] bg-[#222222] border border-[#303038] text-[0.9em]"
-        >{move || new_exchange().assistant_message}</p>
+        ><Streamed message=Signal::derive(response_text) /></p>
     };
 
     let exchanges_div = view! {
         <div id="exchanges" class="mb-4 md:mx-[15vw] overflow-y-auto"
-                style:display=move || (exchanges().is_empty() && !streaming()).then(|| "None")>
-            <Exchanges new_exchange exchanges update_heights
+                style:display=move || (exchanges().is_empty() && !streaming() && candidates.with(Vec::is_empty))
+                    .then(|| "None")>
+            <Exchanges counter new_exchange exchanges update_heights resubmit_from
                 response_textbox=response_textbox.clone() streaming />
+            <CandidatePicker candidates carry_over config conversation_settings exchanges parameters streaming />
         </div>
     };
 
     view! {
         <div class="flex flex-col md:w-[80vw] md:mx-auto h-full p-4 md:py-[5vh] overflow-y-hidden"
+                lang=language dir=direction
                 style:display=move || (menu.get() != Menu::Chat).then(|| "None")>
             <h1 class="hidden md:block mb-6 text-[2em] font-serif">"LLM Playground"</h1>
             <ErrorMessage error />
+            <RateLimitStatus open_conversation streams />
+            <RetryNotice candidates new_exchange open_conversation streaming streams />
+            <SummaryNotice streaming streams />
+            <SystemPromptPanel config parameters />
+            <p class="mb-2 text-[0.9em]" style:display=move || (!summarizing()).then(|| "None")>
+                "Summarizing the previous conversation…"
+            </p>
+            <p class="mb-2 text-[0.9em]" style:display=move || carry_over().is_none().then(|| "None")>
+                <a class="text-blue-600 cursor-pointer" on:click=on_view_previous
+                >"Continued from a previous conversation"</a>
+            </p>
+            <p class="mb-2 text-[0.9em]" style:display=move || overridden_parameters().is_none().then(|| "None")>
+                {move || overridden_parameters().map(|parameters|
+                    format!("Using this conversation's settings ({}, temperature {}). ",
+                        parameters.model, parameters.temperature))}
+                <a class="text-blue-600 cursor-pointer" on:click=on_use_current_settings>"Use current settings"</a>
+            </p>
+            <p class="mb-2 text-[0.9em] text-yellow-500"
+                    style:display=move || snapshot_change().is_none().then(|| "None")>
+                {move || snapshot_change().map(|(first, last)|
+                    format!("This conversation started on {first} but the latest response came from {last}. "))}
+                <a class="text-blue-600 cursor-pointer" on:click=on_pin_snapshot>"Pin the original snapshot"</a>
+            </p>
+            <p class="mb-2 text-[0.9em] text-[#777788]"
+                    style:display=move || conversation_cost().is_none().then(|| "None")>
+                {move || conversation_cost().map(|cost| format!("Conversation cost: {}", format_cost(cost)))}
+            </p>
             {exchanges_div.clone()}
+            <div class="flex-none md:mx-[14.5vw]"><QueuedPrompts queued /></div>
             <div class=move || bottom_if_not_empty("flex-none md:mx-[14.5vw] max-h-[50vh] overflow-y-auto")>
                 <div class="flex flex-col">     // scrolling breaks without this useless div
+                    <Attachments attachments streaming />
                     <MessageBox id="prompt-box".into() rows=2 class="".into()
                         placeholder=Some("Enter a prompt here.".into())
-                        content=prompt.into() set_content=prompt.into() />
+                        content=prompt.into() set_content=prompt.into() counter send=(send_key, send_trigger)
+                        snippets=Signal::derive(move || config.with(|config| config.snippets.clone())) />
+                    <TokenCount attachments config exchanges parameters prompt />
+                    <PrefillInput prefill />
                 </div>
             </div>
+            <RequestPreview preview />
+            <div class="flex-none md:mx-[14.5vw]"><GuardrailWarning findings /></div>
             <div class="flex-none md:mx-[10vw] flex md:mx-8">
-                <Buttons config exchanges exchanges_div menu new_exchange prompt response_textbox streaming />
+                <Buttons attachments candidates carry_over config continue_from conversation_settings exchanges
+                    exchanges_div findings length_preset menu new_exchange parameters prefill preview prompt queued
+                    open_conversation resubmit_from response_textbox send_trigger streaming streams />
             </div>
         </div>
     }
@@ -1,33 +1,19 @@
 use anyhow::Result;
-use common::{Config, Conversation, Exchange};
-
-#[macros::command]
-pub async fn add_conversation(exchanges: Vec<(usize, Exchange)>) -> Result<uuid::Uuid> {}
-
-#[macros::command]
-pub async fn build_token_stream(
-    prompt: &str,
-    config: Config,
-    exchanges: Vec<Exchange>
-) -> Result<bool> {}
-
-#[macros::command]
-pub async fn delete_conversation(conversation_uuid: uuid::Uuid) -> Result<()> {}
-
-#[macros::command]
-pub async fn load_config() -> Result<Config> {}
-
-#[macros::command]
-pub async fn load_conversations() -> Result<Vec<Conversation>> {}
-
-#[macros::command]
-pub async fn load_exchanges(conversation_uuid: uuid::Uuid) -> Result<Vec<(usize, Exchange)>> {}
-
-#[macros::command]
-pub async fn save_config(config: Config) -> Result<()> {}
-
-#[macros::command]
-pub async fn set_exchanges(
-    conversation_uuid: uuid::Uuid,
-    exchanges: Vec<(usize, Exchange)>
-) -> Result<Option<uuid::Uuid>> {}
\ No newline at end of file
+use common::{APIKey, CarryOver, Config, Conversation, ConversationParameters, ConversationSettings, EditLogEntry};
+use common::{Attachment, ConversationFormat, EncryptionStatus, Exchange, Folder};
+use common::{ErrorCount, ExportFilter, ExportLayout, GuardrailFinding, ImportSummary, Usage, UsagePeriod, UsageTotal};
+use common::{ConversationRecord, ConversationSummary, ResponseChunk};
+use futures::Stream;
+
+// some commands are for external tools rather than the ui, so their stubs go unused
+macro_rules! stubs {
+    ($($name:ident($($(#[$($attribute:tt)*])* $argument:ident: $type:ty),*) -> $output:ty;)*) => {
+        $(
+            #[macros::command]
+            #[allow(dead_code)]
+            pub async fn $name($($(#[$($attribute)*])* $argument: $type),*) -> Result<$output> {}
+        )*
+    };
+}
+
+::commands::with_commands!(stubs);
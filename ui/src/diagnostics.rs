@@ -0,0 +1,77 @@
+use common::ErrorCount;
+use leptos::*;
+use crate::commands::{clear_error_counts, load_error_counts};
+use crate::util::{button, ErrorMessage, Menu};
+
+lazy_static::lazy_static! {
+    // anyhow! macro doesn't work if there is a static variable named "error" in the namespace
+    pub static ref signal_pair: (ReadSignal<String>, WriteSignal<String>) = create_signal("".into());
+    pub static ref set_error: WriteSignal<String> = signal_pair.1;
+}
+
+// counts of the errors this install has run into, recorded locally by the backend
+#[component]
+pub fn Diagnostics(menu: RwSignal<Menu>) -> impl IntoView {
+    let error = signal_pair.0;
+    let error_counts = create_rw_signal(Vec::<ErrorCount>::new());
+
+    // reload whenever the page is opened
+    create_effect(move |_| {
+        if menu() != Menu::Diagnostics {
+            return;
+        }
+
+        spawn_local(async move {
+            match load_error_counts().await {
+                Ok(mut loaded_error_counts) => {
+                    loaded_error_counts.sort_by_key(|error_count| std::cmp::Reverse(error_count.count));
+                    error_counts.set(loaded_error_counts);
+                },
+                Err(error) => set_error(error.to_string())
+            }
+        });
+    });
+
+    let on_clear = move |_| spawn_local(async move {
+        match clear_error_counts().await {
+            Ok(()) => error_counts.set(vec![]),
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    let local_formatted_time = |error_count: &ErrorCount| error_count.last_occurred
+        .with_timezone(&chrono::Local)
+        .format("%m-%d-%Y %H:%M:%S")
+        .to_string();
+
+    view! {
+        <div class="relative flex flex-col items-center mx-auto md:w-[max-content] md:min-w-[60vw]
+                    h-full px-[5vw] py-[5vh] overflow-y-hidden"
+                style:display=move || (menu.get() != Menu::Diagnostics).then(|| "None")>
+            <div class="flex w-full">
+                <button class=button() + "mr-auto" on:click=move |_| menu.set(Menu::Menu)>"Back"</button>
+                <button class=button() on:click=on_clear
+                    style:display=move || error_counts.with(Vec::is_empty).then(|| "None")
+                >"Clear"</button>
+            </div>
+            <h1 class="text-[1.25em]">"Diagnostics"</h1>
+            <div class="w-full mt-2"><ErrorMessage error /></div>
+            <p class="w-full mt-[10vh] mr-auto"
+                style:display=move || (!error_counts().is_empty()).then(|| "None")
+            >"No errors recorded."</p>
+            <div class="grid grid-cols-[repeat(4,max-content)] gap-x-[4vw] gap-y-[4vh] my-[10vh] w-full
+                    overflow-y-auto justify-center items-start text-[0.925em]">
+                {move || error_counts().into_iter().map(|error_count| view! {
+                    <p>{error_count.category.to_string()}</p>
+                    <p>{error_count.count}</p>
+                    <p class="text-[0.9em]">
+                        {local_formatted_time(&error_count)}
+                        {error_count.provider.clone().map(|provider| format!(" · {provider}"))}
+                    </p>
+                    <p class="truncate w-[30vw] text-[0.9em] text-[#777788]" title=error_count.last_message.clone()
+                    >{error_count.last_message.clone()}</p>
+                }).collect_view()}
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,80 @@
+use common::EditLogEntry;
+use leptos::*;
+use crate::commands::{load_edit_log, restore_edit};
+use crate::util::{button, conversation_uuid, get_conversation_uuid_untracked, set_conversation_uuid};
+use crate::util::{ErrorMessage, Menu};
+
+lazy_static::lazy_static! {
+    // anyhow! macro doesn't work if there is a static variable named "error" in the namespace
+    pub static ref signal_pair: (ReadSignal<String>, WriteSignal<String>) = create_signal("".into());
+    pub static ref set_error: WriteSignal<String> = signal_pair.1;
+}
+
+#[component]
+pub fn EditLog(menu: RwSignal<Menu>) -> impl IntoView {
+    let error = signal_pair.0;
+    let entries = create_rw_signal(Vec::<EditLogEntry>::new());
+
+    // reload whenever the log is opened since the conversation may have changed since
+    create_effect(move |_| {
+        let (Some(uuid), Menu::EditLog) = (conversation_uuid(), menu()) else {
+            return;
+        };
+
+        spawn_local(async move {
+            match load_edit_log(uuid).await {
+                Ok(loaded_entries) => entries.set(loaded_entries.into_iter().rev().collect()),
+                Err(error) => set_error(error.to_string())
+            }
+        });
+    });
+
+    let on_restore = move |edit_id| spawn_local(async move {
+        let Some(uuid) = get_conversation_uuid_untracked() else {
+            return;
+        };
+
+        match restore_edit(uuid, edit_id).await {
+            // a new conversation is created if another window deleted this one
+            Ok(new_uuid) => {
+                set_error("".into());
+                // setting the uuid reloads the conversation's exchanges
+                set_conversation_uuid(Some(new_uuid.unwrap_or(uuid)));
+                menu.set(Menu::Chat);
+            },
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    let local_formatted_time = |entry: &EditLogEntry| entry.timestamp
+        .with_timezone(&chrono::Local)
+        .format("%m-%d-%Y %H:%M:%S")
+        .to_string();
+
+    let description = |entry: &EditLogEntry|
+        format!("{} exchange {}: {}", entry.edit, entry.key, entry.exchange.user_message);
+
+    view! {
+        <div class="relative flex flex-col items-center mx-auto md:w-[max-content] md:min-w-[60vw]
+                    h-full px-[5vw] py-[5vh] overflow-y-hidden"
+                style:display=move || (menu.get() != Menu::EditLog).then(|| "None")>
+            <button class=button() + "mr-auto" on:click=move |_| menu.set(Menu::Chat)>"Back"</button>
+            <h1 class="text-[1.25em]">"Edit log"</h1>
+            <div class="w-full mt-2"><ErrorMessage error /></div>
+            <p class="w-full mt-[10vh] mr-auto"
+                style:display=move || (!entries().is_empty()).then(|| "None")
+            >"No edits logged."</p>
+            <div class="grid grid-cols-[repeat(3,max-content)] gap-[5vh] my-[10vh] w-full
+                    overflow-y-auto justify-center items-center text-[0.925em]">
+                <For each=entries
+                    key=|entry| entry.id
+                    children=move |entry| view! {
+                        <p class="text-[0.9em]">{local_formatted_time(&entry)}</p>
+                        <p class="truncate w-[45vw]">{description(&entry)}</p>
+                        <a class="text-blue-600 cursor-pointer" on:click=move |_| on_restore(entry.id)
+                        >"restore"</a>
+                    } />
+            </div>
+        </div>
+    }
+}
@@ -1,8 +1,17 @@
-use common::Conversation;
+use std::collections::HashSet;
+use common::{Conversation, ConversationFormat, ExportFilter, ExportLayout, Folder, ImportSummary, RetitleProgress};
+use common::{Provider, CONVERSATION_ICONS};
 use leptos::*;
+use strum::VariantNames;
 use wasm_bindgen::prelude::*;
-use crate::commands::delete_conversation;
-use crate::util::{button, listen, set_conversation_uuid, ErrorMessage, Menu};
+use crate::commands::{add_conversation_tag, add_folder, delete_conversation, duplicate_conversation};
+use crate::commands::{export_conversation, export_filtered_conversations, find_duplicate_conversations};
+use crate::commands::{import_chatgpt, load_archived_conversations, load_folders, load_tags, merge_conversations};
+use crate::commands::{remove_conversation_tag, remove_folder, restore_conversation, retitle_conversations};
+use crate::commands::{set_conversation_archived, set_conversation_folder, set_conversation_icon, set_folder_name};
+use crate::commands::toggle_pin;
+use crate::util::{button, emit, format_cost, input, is_app_write, is_other_window_write, listen, sleep};
+use crate::util::{set_conversation_uuid, ErrorMessage, Menu};
 
 lazy_static::lazy_static! {
     // anyhow! macro doesn't work if there is a static variable named "error" in the namespace
@@ -10,8 +19,13 @@ lazy_static::lazy_static! {
     pub static ref set_error: WriteSignal<String> = signal_pair.1;
 }
 
-async fn load_conversations(conversations: RwSignal<Vec<RwSignal<Conversation>>>) {
-    let new_conversations = match crate::commands::load_conversations().await {
+async fn load_conversations(conversations: RwSignal<Vec<RwSignal<Conversation>>>, archived: bool) {
+    let new_conversations = if archived {
+        load_archived_conversations().await
+    } else {
+        crate::commands::load_conversations().await
+    };
+    let new_conversations = match new_conversations {
         Ok(conversations) => conversations,
         Err(error) => {
             set_error(error.to_string());
@@ -37,16 +51,455 @@ async fn load_conversations(conversations: RwSignal<Vec<RwSignal<Conversation>>>
     });
 }
 
+// groups of near-duplicate conversations, each merged into or deduplicated to its most recent
+#[component]
+fn Duplicates(duplicates: RwSignal<Option<Vec<Vec<Conversation>>>>) -> impl IntoView {
+    let on_merge = move |group: Vec<Conversation>| spawn_local(async move {
+        let Some((keep, merge)) = group.split_first() else {
+            return;
+        };
+
+        let merge_uuids = merge.iter().map(|conversation| conversation.uuid).collect();
+        match merge_conversations(keep.uuid, merge_uuids).await {
+            Ok(()) => duplicates.update(|duplicates| {
+                duplicates.as_mut().map(|duplicates| duplicates.retain(|_group| _group[0].uuid != keep.uuid));
+            }),
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    let on_delete_others = move |group: Vec<Conversation>| spawn_local(async move {
+        for conversation in group.iter().skip(1) {
+            if let Err(error) = delete_conversation(conversation.uuid).await {
+                set_error(error.to_string());
+                return;
+            }
+        }
+
+        duplicates.update(|duplicates| {
+            duplicates.as_mut().map(|duplicates| duplicates.retain(|_group| _group[0].uuid != group[0].uuid));
+        });
+    });
+
+    let local_formatted_time = |conversation: &Conversation| conversation.last_updated
+        .with_timezone(&chrono::Local)
+        .format("%m-%d-%Y")
+        .to_string();
+
+    view! {
+        <div class="w-full mt-[5vh] overflow-y-auto text-[0.925em]"
+                style:display=move || duplicates().is_none().then(|| "None")>
+            <p style:display=move || (!duplicates().unwrap_or_default().is_empty()).then(|| "None")
+            >"No duplicate conversations found."</p>
+            <For each=move || duplicates().unwrap_or_default()
+                key=|group| group[0].uuid
+                children=move |group| view! {
+                    <div class="flex flex-col gap-1 mb-[3vh]">
+                        {group.iter().map(|conversation| view! {
+                            <p class="truncate w-[60vw]">
+                                {local_formatted_time(conversation)}" "{conversation.display_title()}
+                            </p>
+                        }).collect_view()}
+                        <div class="flex gap-4">
+                            <a class="text-blue-600 cursor-pointer" on:click={
+                                let group = group.clone();
+                                move |_| on_merge(group.clone())
+                            }>"merge into most recent"</a>
+                            <a class="text-blue-600 cursor-pointer"
+                                on:click=move |_| on_delete_others(group.clone())
+                            >"keep most recent only"</a>
+                        </div>
+                    </div>
+                } />
+        </div>
+    }
+}
+
+// exports the conversations last updated within a date range, optionally with a tag or a provider's responses
+#[component]
+fn Export(show_export: RwSignal<bool>, tags: RwSignal<Vec<String>>) -> impl IntoView {
+    let filter = create_rw_signal(ExportFilter::default());
+    let layout = create_rw_signal(ExportLayout::Combined);
+    let directory = create_rw_signal("".to_string());
+    let exported_path = create_rw_signal(None::<String>);
+
+    // date inputs are empty or formatted as YYYY-MM-DD
+    let parse_date = |event| chrono::NaiveDate::parse_from_str(&event_target_value(&event), "%Y-%m-%d").ok();
+    let on_since_input = move |event| filter.update(|filter| filter.since = parse_date(event));
+    let on_until_input = move |event| filter.update(|filter| filter.until = parse_date(event));
+    let on_tag_change = move |event| filter.update(|filter| filter.tag = Some(event_target_value(&event))
+        .filter(|tag| !tag.is_empty()));
+    let on_provider_change = move |event| filter.update(|filter| filter.provider =
+        Provider::from_name(&event_target_value(&event)));
+    let on_layout_change = move |event| layout.set(match &event_target_value(&event) as &str {
+        "PerConversation" => ExportLayout::PerConversation,
+        _ => ExportLayout::Combined
+    });
+
+    let on_export = move |_| spawn_local(async move {
+        set_error("".into());
+        exported_path.set(None);
+        let result = export_filtered_conversations(
+            directory.get_untracked(),
+            filter.get_untracked(),
+            layout.get_untracked()
+        ).await;
+        match result {
+            Ok(path) => exported_path.set(Some(path)),
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    view! {
+        <div class="grid grid-cols-[repeat(2,max-content)] gap-2 items-center mt-[5vh] text-[0.925em]"
+                style:display=move || (!show_export()).then(|| "None")>
+            <label>"From:"</label>
+            <input type="date" class=input() + "px-1" on:input=on_since_input />
+            <label>"To:"</label>
+            <input type="date" class=input() + "px-1" on:input=on_until_input />
+            <label>"Tag:"</label>
+            <select class="px-1 bg-[#222222] border border-[#33333A] text-[#AAAABB]" on:change=on_tag_change>
+                <option value="">"Any"</option>
+                {move || tags().into_iter()
+                    .map(|tag| view! { <option value=tag.clone()>{tag}</option> })
+                    .collect_view()}
+            </select>
+            <label>"Provider:"</label>
+            <select class="px-1 bg-[#222222] border border-[#33333A] text-[#AAAABB]" on:change=on_provider_change>
+                <option value="">"Any"</option>
+                {Provider::VARIANTS.iter()
+                    .map(|&provider| view! { <option value=provider>{provider}</option> })
+                    .collect_view()}
+            </select>
+            <label>"As:"</label>
+            <select class="px-1 bg-[#222222] border border-[#33333A] text-[#AAAABB]" on:change=on_layout_change>
+                <option value="Combined">"One combined file"</option>
+                <option value="PerConversation">"One file per conversation"</option>
+            </select>
+            <label>"Directory:"</label>
+            <input type="text" class=input() + "px-1" placeholder="~/llm-playground-exports"
+                on:input=move |event| directory.set(event_target_value(&event)) />
+            <button class=button() + "w-[max-content]" on:click=on_export>"Export"</button>
+            <p class="text-[0.9em]">{move || exported_path().map(|path| format!("Exported to {path}"))}</p>
+        </div>
+    }
+}
+
+fn describe_retitle_progress(progress: &RetitleProgress) -> String {
+    if progress.total == 0 {
+        return "Every conversation already has a generated title.".into();
+    }
+
+    let mut description = format!("Titled {} of {} conversations", progress.retitled, progress.total);
+    if progress.failed > 0 {
+        description += &format!(", {} failed", progress.failed);
+    }
+    if let Some(last_error) = &progress.last_error {
+        description += &format!(" (last error: {last_error})");
+    }
+    description
+}
+
+// titles conversations still titled by their first prompt in the background, clicking again stops it
+#[component]
+fn Retitle(progress: RwSignal<Option<RetitleProgress>>) -> impl IntoView {
+    let retitling = move || progress().is_some_and(|progress| !progress.is_done());
+
+    spawn_local(async move {
+        let on_progress = Closure::new(move |event: JsValue| {
+            let payload = JsValue::into_serde::<serde_json::Value>(&event)
+                .ok()
+                .and_then(|mut event| serde_json::from_value::<RetitleProgress>(event["payload"].take()).ok());
+            if payload.is_some() {
+                progress.set(payload);
+            }
+        });
+
+        if let Err(_) = listen("retitle_progress", &on_progress).await {
+            set_error("Error listening for retitling progress".into());
+        }
+
+        // keep on_progress alive forever
+        std::mem::forget(on_progress);
+    });
+
+    let on_click = move |_| spawn_local(async move {
+        set_error("".into());
+        if progress.with_untracked(|progress| progress.as_ref().is_some_and(|progress| !progress.is_done())) {
+            if let Err(_) = emit("cancel_retitle", JsValue::null()).await {
+                set_error("Error stopping the retitling".into());
+            }
+            return;
+        }
+
+        match retitle_conversations().await {
+            Ok(total) => progress.set(Some(RetitleProgress { total, ..Default::default() })),
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    view! {
+        <button class=button() + "mr-4" on:click=on_click>
+            {move || if retitling() { "Stop titling" } else { "Generate titles" }}
+        </button>
+    }
+}
+
+// a palette of icons to mark the conversation with, choosing the current one again clears it
 #[component]
-pub fn History(menu: RwSignal<Menu>) -> impl IntoView {
+fn IconPicker(conversation: RwSignal<Conversation>) -> impl IntoView {
+    let open = create_rw_signal(false);
+
+    let on_pick = move |icon: &'static str| {
+        open.set(false);
+        let (uuid, current_icon) = conversation
+            .with_untracked(|conversation| (conversation.uuid, conversation.icon.clone()));
+        let icon = Some(icon.to_string()).filter(|icon| Some(icon) != current_icon.as_ref());
+        // shown right away, the history reloads once it's saved
+        conversation.update(|conversation| conversation.icon = icon.clone());
+        spawn_local(async move {
+            if let Err(error) = set_conversation_icon(uuid, icon).await {
+                set_error(error.to_string());
+            }
+        });
+    };
+
+    view! {
+        <div class="relative">
+            <a class="cursor-pointer text-[#777788] hover:text-[#AAAABB]" title="Set icon"
+                on:click=move |_| open.update(|open| *open = !*open)
+            >{move || conversation().icon.unwrap_or("☆".into())}</a>
+            <div class="absolute z-10 top-full left-0 grid grid-cols-4 gap-1 p-1 bg-[#181a1b] border border-[#33333A]"
+                    style:display=move || (!open()).then(|| "None")>
+                {CONVERSATION_ICONS.iter().map(|&icon| view! {
+                    <a class="px-1 cursor-pointer hover:bg-[#2A2A2A]"
+                        class=("bg-[#2A2A2A]", move || conversation().icon.as_deref() == Some(icon))
+                        on:click=move |_| on_pick(icon)
+                    >{icon}</a>
+                }).collect_view()}
+            </div>
+        </div>
+    }
+}
+
+// the conversation's tags, removed with their "×" and added from the "+" input, which suggests existing tags
+#[component]
+fn TagEditor(conversation: RwSignal<Conversation>) -> impl IntoView {
+    let adding = create_rw_signal(false);
+
+    let on_remove = move |tag: String| {
+        let uuid = conversation.with_untracked(|conversation| conversation.uuid);
+        // shown right away, the history reloads once it's saved
+        conversation.update(|conversation| conversation.tags.retain(|_tag| *_tag != tag));
+        spawn_local(async move {
+            if let Err(error) = remove_conversation_tag(uuid, tag).await {
+                set_error(error.to_string());
+            }
+        });
+    };
+
+    let on_keydown = move |event: web_sys::KeyboardEvent| match event.key().as_str() {
+        "Enter" => {
+            let tag = event_target_value(&event).trim().to_string();
+            adding.set(false);
+            if tag.is_empty() || conversation.with_untracked(|conversation| conversation.tags.contains(&tag)) {
+                return;
+            }
+
+            let uuid = conversation.with_untracked(|conversation| conversation.uuid);
+            conversation.update(|conversation| {
+                conversation.tags.push(tag.clone());
+                conversation.tags.sort();
+            });
+            spawn_local(async move {
+                if let Err(error) = add_conversation_tag(uuid, tag).await {
+                    set_error(error.to_string());
+                }
+            });
+        },
+        "Escape" => adding.set(false),
+        _ => ()
+    };
+
+    view! {
+        <div class="flex flex-wrap gap-1 max-w-[20vw] text-[0.85em]">
+            <For each=move || conversation().tags
+                key=|tag| tag.clone()
+                children=move |tag| view! {
+                    <span class="px-1 border border-[#33333A] text-[#AAAABB]">
+                        {tag.clone()}
+                        <a class="ml-1 cursor-pointer text-[#777788] hover:text-[#AAAABB]"
+                            on:click=move |_| on_remove(tag.clone())>"×"</a>
+                    </span>
+                } />
+            <a class="cursor-pointer text-[#777788] hover:text-[#AAAABB]" title="Add tag"
+                style:display=move || adding().then(|| "None")
+                on:click=move |_| adding.set(true)
+            >"+"</a>
+            {move || adding().then(|| view! {
+                <input type="text" list="history-tags" class=input() + "px-1 w-[8em]" autofocus=true
+                    on:keydown=on_keydown on:blur=move |_| adding.set(false) />
+            })}
+        </div>
+    }
+}
+
+// a folder or conversation in the history's tree, with how deeply it's nested
+#[derive(Clone)]
+enum HistoryRow {
+    Section(&'static str),
+    Folder(Folder, usize),
+    Conversation(RwSignal<Conversation>, usize)
+}
+
+impl HistoryRow {
+    // rows are rebuilt rather than updated when a folder is renamed or a conversation moves
+    fn key(&self) -> String {
+        match self {
+            HistoryRow::Section(name) => format!("section-{name}"),
+            HistoryRow::Folder(folder, depth) => format!("folder-{}-{depth}-{}", folder.id, folder.name),
+            HistoryRow::Conversation(conversation, depth) => format!("{}-{depth}", conversation.get_untracked().uuid)
+        }
+    }
+}
+
+// each level lists its folders before its conversations
+fn push_history_rows(
+    rows: &mut Vec<HistoryRow>,
+    parent: Option<i32>,
+    depth: usize,
+    folders: &[Folder],
+    conversations: &[(Option<i32>, RwSignal<Conversation>)],
+    collapsed: &HashSet<i32>
+) {
+    for folder in folders.iter().filter(|folder| folder.parent == parent) {
+        rows.push(HistoryRow::Folder(folder.clone(), depth));
+        if !collapsed.contains(&folder.id) {
+            push_history_rows(rows, Some(folder.id), depth + 1, folders, conversations, collapsed);
+        }
+    }
+    rows.extend(conversations.iter()
+        .filter(|(folder, _)| *folder == parent)
+        .map(|(_, conversation)| HistoryRow::Conversation(*conversation, depth)));
+}
+
+// pinned conversations are listed above the folders and left out of them
+fn history_rows(
+    folders: &[Folder],
+    conversations: Vec<RwSignal<Conversation>>,
+    collapsed: &HashSet<i32>
+) -> Vec<HistoryRow> {
+    let (pinned, conversations): (Vec<_>, Vec<_>) = conversations.into_iter()
+        .partition(|conversation| conversation.with(|conversation| conversation.pinned));
+
+    // conversations in a folder deleted from another window are shown at the top level
+    let folder_ids = folders.iter().map(|folder| folder.id).collect::<HashSet<_>>();
+    let conversations = conversations.into_iter()
+        .map(|conversation| (conversation.with(|conversation| conversation.folder)
+            .filter(|folder| folder_ids.contains(folder)), conversation))
+        .collect::<Vec<_>>();
+
+    let mut rows = vec![];
+    if !pinned.is_empty() {
+        rows.push(HistoryRow::Section("Pinned"));
+        rows.extend(pinned.into_iter().map(|conversation| HistoryRow::Conversation(conversation, 0)));
+        rows.push(HistoryRow::Section("All conversations"));
+    }
+    push_history_rows(&mut rows, None, 0, folders, &conversations, collapsed);
+    rows
+}
+
+#[component]
+pub fn History(continue_from: RwSignal<Option<uuid::Uuid>>, menu: RwSignal<Menu>) -> impl IntoView {
     let error = signal_pair.0;
     let conversations = create_rw_signal(Vec::<RwSignal<Conversation>>::new());
+    let duplicates = create_rw_signal(None);
+    let show_export = create_rw_signal(false);
+    let retitle_progress = create_rw_signal(None::<RetitleProgress>);
+    // None shows conversations from every workspace
+    let workspace = create_rw_signal(None::<String>);
+    // None shows conversations with any or no tags
+    let tag = create_rw_signal(None::<String>);
+    let tags = create_rw_signal(Vec::<String>::new());
+    let folders = create_rw_signal(Vec::<Folder>::new());
+    let collapsed = create_rw_signal(HashSet::<i32>::new());
+    let dragged = create_rw_signal(None::<uuid::Uuid>);
+    // Some(None) is the top level
+    let drop_target = create_rw_signal(None::<Option<i32>>);
+
+    let workspaces = move || {
+        let mut workspaces = conversations.with(|conversations| conversations.iter()
+            .filter_map(|conversation| conversation.with(|conversation| conversation.workspace.clone()))
+            .collect::<Vec<_>>());
+        workspaces.sort();
+        workspaces.dedup();
+        workspaces
+    };
+    let filtered_conversations = move || {
+        let (workspace, tag) = (workspace(), tag());
+        conversations().into_iter()
+            .filter(|conversation| conversation.with(|conversation|
+                workspace.as_ref().map_or(true, |workspace| conversation.workspace.as_ref() == Some(workspace))
+                    && tag.as_ref().map_or(true, |tag| conversation.tags.contains(tag))))
+            .collect::<Vec<_>>()
+    };
+    let rows = move || folders.with(|folders| collapsed.with(|collapsed|
+        history_rows(folders, filtered_conversations(), collapsed)));
+
+    // the archived conversations are listed instead of the rest
+    let archived_view = create_rw_signal(false);
+
+    // bursts of updates are coalesced into one reload at most every 250ms
+    let reload = std::rc::Rc::new(tokio::sync::Notify::new());
+    reload.notify_one();
+    spawn_local({
+        let reload = reload.clone();
+        async move {
+            loop {
+                reload.notified().await;
+                load_conversations(conversations, archived_view.get_untracked()).await;
+                match load_tags().await {
+                    Ok(loaded_tags) => tags.set(loaded_tags),
+                    Err(error) => set_error(error.to_string())
+                }
+                match load_folders().await {
+                    Ok(loaded_folders) => folders.set(loaded_folders),
+                    Err(error) => set_error(error.to_string())
+                }
+                sleep(std::time::Duration::from_millis(250)).await;
+            }
+        }
+    });
+
+    create_effect({
+        let reload = reload.clone();
+        move |_| {
+            archived_view.track();
+            reload.notify_one();
+        }
+    });
 
-    spawn_local(load_conversations(conversations));
+    // this window's own writes, e.g. saving each response, are caught up on once the history is opened
+    let stale = create_rw_signal(false);
+    create_effect({
+        let reload = reload.clone();
+        move |_| if menu() == Menu::History && stale.get_untracked() {
+            stale.set(false);
+            reload.notify_one();
+        }
+    });
 
     spawn_local(async move {
         // listen for when the user/another window/this window changes the conversation history
-        let on_update = Closure::new(move |_| spawn_local(load_conversations(conversations)));
+        let on_update = Closure::new(move |event: JsValue| {
+            // the app's own writes, e.g. a response being saved, are loaded once the history is shown,
+            // other windows' writes are loaded right away to keep the windows in sync
+            if is_app_write(&event) && !is_other_window_write(&event) && menu.get_untracked() != Menu::History {
+                stale.set(true);
+            } else {
+                reload.notify_one();
+            }
+        });
 
         if let Err(_) = listen("conversations_updated", &on_update).await {
             set_error("Error listening for conversation history updates".into());
@@ -57,16 +510,241 @@ pub fn History(menu: RwSignal<Menu>) -> impl IntoView {
     });
 
     let on_load = move |uuid| {
+        continue_from.set(None);
         set_conversation_uuid(uuid);
         menu.set(Menu::Chat);
     };
 
+    // start a new conversation with a summary of this one in its system prompt
+    let on_continue = move |uuid| {
+        continue_from.set(Some(uuid));
+        set_conversation_uuid(None);
+        menu.set(Menu::Chat);
+    };
+
+    // the last deleted conversation, restorable from the toast for 10 seconds
+    let deleted = create_rw_signal(None::<uuid::Uuid>);
+
     let on_delete = move |uuid| spawn_local(async move {
         if let Err(error) = delete_conversation(uuid).await {
             set_error(error.to_string());
+            return;
+        }
+
+        deleted.set(Some(uuid));
+        sleep(std::time::Duration::from_secs(10)).await;
+        // unless another conversation was deleted since
+        if deleted.get_untracked() == Some(uuid) {
+            deleted.set(None);
+        }
+    });
+
+    let on_undo = move |_| {
+        let Some(uuid) = deleted.get_untracked() else {
+            return;
+        };
+        deleted.set(None);
+        spawn_local(async move {
+            if let Err(error) = restore_conversation(uuid).await {
+                set_error(error.to_string());
+            }
+        });
+    };
+
+    // the copy shows up once the history reloads
+    let on_duplicate = move |uuid| spawn_local(async move {
+        if let Err(error) = duplicate_conversation(uuid).await {
+            set_error(error.to_string());
+        }
+    });
+
+    // the path is chosen in a save dialog, nothing is written if it's canceled
+    let on_export = move |uuid| spawn_local(async move {
+        if let Err(error) = export_conversation(uuid, ConversationFormat::Markdown).await {
+            set_error(error.to_string());
+        }
+    });
+
+    // the conversation leaves the list once the history reloads
+    let on_toggle_archived = move |conversation: Conversation| spawn_local(async move {
+        if let Err(error) = set_conversation_archived(conversation.uuid, !conversation.archived).await {
+            set_error(error.to_string());
+        }
+    });
+
+    let on_toggle_pin = move |uuid| spawn_local(async move {
+        if let Err(error) = toggle_pin(uuid).await {
+            set_error(error.to_string());
         }
     });
 
+    // folders are changed in the database and shown once the history reloads
+    let on_new_folder = move |parent: Option<i32>| {
+        let Some(name) = window().prompt_with_message("Folder name:").ok().flatten() else {
+            return;
+        };
+        spawn_local(async move {
+            if let Err(error) = add_folder(name, parent).await {
+                set_error(error.to_string());
+            }
+        });
+    };
+
+    let on_rename_folder = move |folder: Folder| {
+        let Some(name) = window().prompt_with_message_and_default("Folder name:", &folder.name).ok().flatten() else {
+            return;
+        };
+        spawn_local(async move {
+            if let Err(error) = set_folder_name(folder.id, name).await {
+                set_error(error.to_string());
+            }
+        });
+    };
+
+    let on_delete_folder = move |id: i32| {
+        let message = "Delete this folder and its subfolders? Their conversations move to the top level.";
+        if !window().confirm_with_message(message).unwrap_or(false) {
+            return;
+        }
+        spawn_local(async move {
+            if let Err(error) = remove_folder(id).await {
+                set_error(error.to_string());
+            }
+        });
+    };
+
+    let on_toggle_folder = move |id: i32| collapsed.update(|collapsed| if !collapsed.remove(&id) {
+        collapsed.insert(id);
+    });
+
+    let on_drop = move |folder: Option<i32>| {
+        drop_target.set(None);
+        let Some(uuid) = dragged.get_untracked() else {
+            return;
+        };
+        dragged.set(None);
+
+        // shown right away, the history reloads once it's saved
+        let conversation = conversations.with_untracked(|conversations| conversations.iter()
+            .find(|conversation| conversation.with_untracked(|conversation| conversation.uuid == uuid))
+            .copied());
+        if let Some(conversation) = conversation {
+            conversation.update(|conversation| conversation.folder = folder);
+        }
+        spawn_local(async move {
+            if let Err(error) = set_conversation_folder(uuid, folder).await {
+                set_error(error.to_string());
+            }
+        });
+    };
+
+    let import_summary = create_rw_signal(None::<ImportSummary>);
+    // the history reloads once the imported conversations are saved
+    let on_import = move |_| spawn_local(async move {
+        match import_chatgpt().await {
+            Ok(Some(summary)) => import_summary.set(Some(summary)),
+            Ok(None) => (),
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    let on_find_duplicates = move |_| {
+        if duplicates.get_untracked().is_some() {
+            duplicates.set(None);
+            return;
+        }
+
+        spawn_local(async move {
+            match find_duplicate_conversations().await {
+                Ok(found_duplicates) => duplicates.set(Some(found_duplicates)),
+                Err(error) => set_error(error.to_string())
+            }
+        });
+    };
+
+    // keyboard navigation, the selection follows the filtered list
+    let selected = create_rw_signal(None::<uuid::Uuid>);
+    let type_ahead = create_rw_signal((String::new(), 0.0));
+
+    let select = move |uuid: uuid::Uuid| {
+        selected.set(Some(uuid));
+        if let Some(element) = document().get_element_by_id(&format!("conversation-{uuid}")) {
+            element.scroll_into_view_with_bool(false);
+        }
+    };
+
+    let on_keydown = move |event: web_sys::KeyboardEvent| {
+        if menu.get_untracked() != Menu::History || event.ctrl_key() || event.meta_key() || event.alt_key() {
+            return;
+        }
+        // leave typing in the export and workspace fields alone
+        let tag_name = event_target::<web_sys::Element>(&event).tag_name();
+        if ["INPUT", "SELECT", "TEXTAREA"].contains(&tag_name.as_str()) {
+            return;
+        }
+
+        // in the order shown, skipping conversations in collapsed folders
+        let uuids = rows().into_iter()
+            .filter_map(|row| match row {
+                HistoryRow::Conversation(conversation, _) => Some(conversation.get_untracked().uuid),
+                HistoryRow::Section(_) | HistoryRow::Folder(..) => None
+            })
+            .collect::<Vec<_>>();
+        let position = selected.get_untracked().and_then(|uuid| uuids.iter().position(|_uuid| *_uuid == uuid));
+        match event.key().as_str() {
+            "ArrowDown" => {
+                event.prevent_default();
+                let next = position.map(|position| (position + 1).min(uuids.len().saturating_sub(1))).unwrap_or(0);
+                if let Some(&uuid) = uuids.get(next) {
+                    select(uuid);
+                }
+            },
+            "ArrowUp" => {
+                event.prevent_default();
+                let previous = position.map(|position| position.saturating_sub(1)).unwrap_or(0);
+                if let Some(&uuid) = uuids.get(previous) {
+                    select(uuid);
+                }
+            },
+            "Enter" => if let Some(uuid) = selected.get_untracked().filter(|_| position.is_some()) {
+                on_load(Some(uuid));
+            },
+            "Delete" => if let Some(uuid) = selected.get_untracked().filter(|_| position.is_some()) {
+                if window().confirm_with_message("Delete this conversation?").unwrap_or(false) {
+                    // keep a selection in place of the deleted conversation
+                    let neighbour = position.and_then(|position| uuids.get(position + 1)
+                        .or_else(|| uuids.get(position.checked_sub(1)?)));
+                    selected.set(neighbour.copied());
+                    on_delete(uuid);
+                }
+            },
+            // type-ahead: jump to the first title starting with what was typed in the last second
+            key if key.chars().count() == 1 => {
+                let now = js_sys::Date::now();
+                let (mut typed, last_typed) = type_ahead.get_untracked();
+                if now - last_typed > 1000.0 {
+                    typed.clear();
+                }
+                typed += &key.to_lowercase();
+                type_ahead.set((typed.clone(), now));
+
+                let found = filtered_conversations().into_iter().find(|conversation| conversation
+                    .with_untracked(|conversation| conversation.generated_title.as_ref().unwrap_or(&conversation.title)
+                        .to_lowercase()
+                        .starts_with(&typed)));
+                if let Some(conversation) = found {
+                    select(conversation.get_untracked().uuid);
+                }
+            },
+            _ => ()
+        }
+    };
+    let keydown_handle = window_event_listener(ev::keydown, on_keydown);
+    on_cleanup(move || keydown_handle.remove());
+
+    let is_selected = move |conversation: RwSignal<Conversation>|
+        selected() == Some(conversation.with_untracked(|conversation| conversation.uuid));
+
     let local_formatted_time = |conversation: Conversation| conversation.last_updated
         .with_timezone(&chrono::Local)
         .format("%m-%d-%Y")
@@ -76,26 +754,143 @@ pub fn History(menu: RwSignal<Menu>) -> impl IntoView {
         <div class="relative flex flex-col items-center mx-auto md:w-[max-content] md:min-w-[60vw]
                     h-full px-[5vw] py-[5vh] overflow-y-hidden"
                 style:display=move || (menu.get() != Menu::History).then(|| "None")>
-            <button class=button() + "mr-auto" on:click=move |_| menu.set(Menu::Menu)>"Back"</button>
-            <h1 class="text-[1.25em]">"History"</h1>
+            <div class="flex w-full">
+                <button class=button() + "mr-auto" on:click=move |_| menu.set(Menu::Menu)>"Back"</button>
+                <button class=button() + "mr-4" on:click=move |_| on_new_folder(None)>"New folder"</button>
+                <Retitle progress=retitle_progress />
+                <button class=button() + "mr-4" on:click=on_import>"Import…"</button>
+                <button class=button() + "mr-4"
+                        on:click=move |_| archived_view.update(|archived| *archived = !*archived)>
+                    {move || if archived_view() { "Hide archived" } else { "Archived" }}
+                </button>
+                <button class=button() + "mr-4" on:click=move |_| show_export.update(|show| *show = !*show)>
+                    {move || if show_export() { "Hide export" } else { "Export" }}
+                </button>
+                <button class=button() on:click=on_find_duplicates>
+                    {move || duplicates().map(|_| "Hide duplicates").unwrap_or("Find duplicates")}
+                </button>
+            </div>
+            <h1 class="text-[1.25em]">{move || if archived_view() { "Archived" } else { "History" }}</h1>
             <div class="w-full mt-2"><ErrorMessage error /></div>
+            <p class="w-full mt-2 text-[0.9em]">{move || retitle_progress().as_ref().map(describe_retitle_progress)}</p>
+            <p class="w-full mt-2 text-[0.9em]">{move || import_summary().as_ref().map(ImportSummary::describe)}</p>
+            <Export show_export tags />
+            <Duplicates duplicates />
+            <select class="mt-[5vh] mr-auto px-1 bg-[#222222] border border-[#33333A] text-[#AAAABB] text-[0.925em]"
+                    on:change=move |event| workspace.set(Some(event_target_value(&event))
+                        .filter(|workspace| !workspace.is_empty()))
+                    style:display=move || workspaces().is_empty().then(|| "None")>
+                <option value="">"All workspaces"</option>
+                {move || workspaces().into_iter()
+                    .map(|workspace| view! { <option value=workspace.clone()>{workspace}</option> })
+                    .collect_view()}
+            </select>
+            <select class="mt-2 mr-auto px-1 bg-[#222222] border border-[#33333A] text-[#AAAABB] text-[0.925em]"
+                    on:change=move |event| tag.set(Some(event_target_value(&event)).filter(|tag| !tag.is_empty()))
+                    style:display=move || tags().is_empty().then(|| "None")>
+                <option value="">"All tags"</option>
+                {move || tags().into_iter()
+                    .map(|tag| view! { <option value=tag.clone()>{tag}</option> })
+                    .collect_view()}
+            </select>
+            <datalist id="history-tags">
+                {move || tags().into_iter().map(|tag| view! { <option value=tag /> }).collect_view()}
+            </datalist>
             <p class="w-full mt-[10vh] mr-auto"
                 style:display=move || (!conversations().is_empty()).then(|| "None")
-            >"No conversations saved."</p>
-            <div class="grid grid-cols-[repeat(3,max-content)] gap-[5vh] my-[10vh] w-full
+            >{move || if archived_view() { "No archived conversations." } else { "No conversations saved." }}</p>
+            <div class="grid grid-cols-[repeat(10,max-content)] gap-[5vh] my-[10vh] w-full
                     overflow-y-auto justify-center items-center text-[0.925em]">
-                <For each=conversations
-                    key=|conversation| conversation.get_untracked().uuid
-                    children=move |conversation| view! {
-                        <p class="text-[0.9em]">{move || local_formatted_time(conversation())}</p>
-                        <a class="truncate w-[45vw] text-blue-600 cursor-pointer"
-                            on:click=move |_| on_load(Some(conversation.get_untracked().uuid))
-                        >{move || conversation().title}</a>
-                        <a class="text-blue-600 cursor-pointer"
-                            on:click=move |_| on_delete(conversation.get_untracked().uuid)
-                        >"delete"</a>
+                <p class="col-span-10 px-2 py-1 border border-dashed border-[#33333A] text-[#777788]"
+                    class=("bg-[#2A2A2A]", move || drop_target() == Some(None))
+                    style:display=move || dragged().is_none().then(|| "None")
+                    on:dragover=move |event| {
+                        event.prevent_default();
+                        drop_target.set(Some(None));
+                    }
+                    on:dragleave=move |_| drop_target.set(None)
+                    on:drop=move |event| {
+                        event.prevent_default();
+                        on_drop(None);
+                    }
+                >"Drop here to move out of folders"</p>
+                <For each=rows
+                    key=HistoryRow::key
+                    children=move |row| match row {
+                        HistoryRow::Section(name) => view! {
+                            <p class="col-span-10 text-[#AAAABB] border-b border-[#33333A]">{name}</p>
+                        }.into_view(),
+                        HistoryRow::Folder(folder, depth) => {
+                            let (id, name) = (folder.id, folder.name.clone());
+                            view! {
+                                <div class="col-span-10 flex gap-4 items-center"
+                                    style:padding-left=format!("{}em", 1.5 * depth as f64)
+                                    class=("bg-[#2A2A2A]", move || drop_target() == Some(Some(id)))
+                                    on:dragover=move |event| {
+                                        event.prevent_default();
+                                        drop_target.set(Some(Some(id)));
+                                    }
+                                    on:dragleave=move |_| drop_target.set(None)
+                                    on:drop=move |event| {
+                                        event.prevent_default();
+                                        on_drop(Some(id));
+                                    }>
+                                    <a class="cursor-pointer text-[#AAAABB]" on:click=move |_| on_toggle_folder(id)>
+                                        {move || if collapsed().contains(&id) { "▸ " } else { "▾ " }}
+                                        {name}
+                                    </a>
+                                    <a class="text-[0.85em] text-[#777788] hover:text-[#AAAABB] cursor-pointer"
+                                        on:click=move |_| on_new_folder(Some(id))>"new subfolder"</a>
+                                    <a class="text-[0.85em] text-[#777788] hover:text-[#AAAABB] cursor-pointer"
+                                        on:click=move |_| on_rename_folder(folder.clone())>"rename"</a>
+                                    <a class="text-[0.85em] text-[#777788] hover:text-[#AAAABB] cursor-pointer"
+                                        on:click=move |_| on_delete_folder(id)>"delete"</a>
+                                </div>
+                            }.into_view()
+                        },
+                        HistoryRow::Conversation(conversation, depth) => view! {
+                            <p class="text-[0.9em]" style:padding-left=format!("{}em", 1.5 * depth as f64)>
+                                {move || local_formatted_time(conversation())}
+                                {move || conversation().cost.map(|cost| format!(" · {}", format_cost(cost)))}
+                            </p>
+                            <IconPicker conversation />
+                            <a class="truncate w-[35vw] text-blue-600 cursor-pointer" draggable="true"
+                                id=move || format!("conversation-{}", conversation().uuid)
+                                class=("bg-[#2A2A2A]", move || is_selected(conversation))
+                                on:click=move |_| on_load(Some(conversation.get_untracked().uuid))
+                                on:dragstart=move |_| dragged.set(Some(conversation.get_untracked().uuid))
+                                on:dragend=move |_| {
+                                    dragged.set(None);
+                                    drop_target.set(None);
+                                }
+                            >{move || conversation().display_title()}</a>
+                            <TagEditor conversation />
+                            <a class="text-blue-600 cursor-pointer"
+                                on:click=move |_| on_toggle_pin(conversation.get_untracked().uuid)
+                            >{move || if conversation().pinned { "unpin" } else { "pin" }}</a>
+                            <a class="text-blue-600 cursor-pointer"
+                                on:click=move |_| on_continue(conversation.get_untracked().uuid)
+                            >"continue"</a>
+                            <a class="text-blue-600 cursor-pointer"
+                                on:click=move |_| on_duplicate(conversation.get_untracked().uuid)
+                            >"duplicate"</a>
+                            <a class="text-blue-600 cursor-pointer"
+                                on:click=move |_| on_toggle_archived(conversation.get_untracked())
+                            >{move || if conversation().archived { "unarchive" } else { "archive" }}</a>
+                            <a class="text-blue-600 cursor-pointer"
+                                on:click=move |_| on_export(conversation.get_untracked().uuid)
+                            >"export"</a>
+                            <a class="text-blue-600 cursor-pointer"
+                                on:click=move |_| on_delete(conversation.get_untracked().uuid)
+                            >"delete"</a>
+                        }.into_view()
                     } />
             </div>
+            <div class="absolute bottom-[5vh] flex gap-4 px-4 py-2 bg-[#222222] border border-[#33333A] text-[0.925em]"
+                    style:display=move || deleted().is_none().then(|| "None")>
+                "Conversation deleted."
+                <a class="text-blue-600 cursor-pointer" on:click=on_undo>"Undo"</a>
+            </div>
         </div>
     }
 }
\ No newline at end of file
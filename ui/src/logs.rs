@@ -0,0 +1,52 @@
+use leptos::*;
+use crate::commands::tail_logs;
+use crate::util::{button, ErrorMessage, Menu};
+
+lazy_static::lazy_static! {
+    // anyhow! macro doesn't work if there is a static variable named "error" in the namespace
+    pub static ref signal_pair: (ReadSignal<String>, WriteSignal<String>) = create_signal("".into());
+    pub static ref set_error: WriteSignal<String> = signal_pair.1;
+}
+
+const TAIL_LINES: usize = 500;
+
+// the end of the backend's log files, which are kept under <config-dir>/logs
+#[component]
+pub fn Logs(menu: RwSignal<Menu>) -> impl IntoView {
+    let error = signal_pair.0;
+    let lines = create_rw_signal(Vec::<String>::new());
+
+    let load_lines = move || spawn_local(async move {
+        match tail_logs(TAIL_LINES).await {
+            Ok(tail) => {
+                lines.set(tail);
+                set_error("".into());
+            },
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    // reload whenever the page is opened
+    create_effect(move |_| {
+        if menu() == Menu::Logs {
+            load_lines();
+        }
+    });
+
+    view! {
+        <div class="relative flex flex-col items-center mx-auto md:w-[80vw] h-full px-[5vw] py-[5vh] overflow-y-hidden"
+                style:display=move || (menu.get() != Menu::Logs).then(|| "None")>
+            <div class="flex w-full">
+                <button class=button() + "mr-auto" on:click=move |_| menu.set(Menu::Menu)>"Back"</button>
+                <button class=button() on:click=move |_| load_lines()>"Refresh"</button>
+            </div>
+            <h1 class="text-[1.25em]">"Logs"</h1>
+            <div class="w-full mt-2"><ErrorMessage error /></div>
+            <p class="w-full mt-[10vh] mr-auto" style:display=move || (!lines().is_empty()).then(|| "None")
+            >"Nothing logged yet."</p>
+            <pre class="w-full mt-[4vh] overflow-auto text-[0.8em] text-[#AAAABB] whitespace-pre-wrap"
+                style:display=move || lines().is_empty().then(|| "None")
+            >{move || lines().join("\n")}</pre>
+        </div>
+    }
+}
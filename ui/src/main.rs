@@ -1,14 +1,27 @@
 use leptos::*;
 use crate::chat::Chat;
-use crate::util::{button, Menu};
+use crate::diagnostics::Diagnostics;
+use crate::edit_log::EditLog;
+use crate::util::{button, ErrorMessage, Menu};
 use crate::history::History;
+use crate::logs::Logs;
 use crate::settings::Settings;
+use crate::switcher::QuickSwitcher;
+use crate::unlock::Unlock;
+use crate::usage::Usage;
 
 mod chat;
 mod commands;
+mod diagnostics;
+mod edit_log;
 mod util;
 mod history;
+mod logs;
+mod render;
 mod settings;
+mod switcher;
+mod unlock;
+mod usage;
 
 #[component]
 pub fn Menu(menu: RwSignal<Menu>) -> impl IntoView {
@@ -28,6 +41,15 @@ pub fn Menu(menu: RwSignal<Menu>) -> impl IntoView {
                 <button class=button() + "md:py-[6px]" on:click=move |_| menu.set(Menu::Settings)>
                     "Settings"
                 </button>
+                <button class=button() + "md:py-[6px]" on:click=move |_| menu.set(Menu::Usage)>
+                    "Usage"
+                </button>
+                <button class=button() + "md:py-[6px]" on:click=move |_| menu.set(Menu::Diagnostics)>
+                    "Diagnostics"
+                </button>
+                <button class=button() + "md:py-[6px]" on:click=move |_| menu.set(Menu::Logs)>
+                    "Logs"
+                </button>
             </div>
         </div>
     }
@@ -37,18 +59,44 @@ pub fn Menu(menu: RwSignal<Menu>) -> impl IntoView {
 fn App() -> impl IntoView {
     let conversation_uuid = create_rw_signal(None);
     let config = create_rw_signal(common::Config::default());
+    let continue_from = create_rw_signal(None);
     let menu = create_rw_signal(Menu::Chat);
+    // None until the backend says whether the database needs a passphrase
+    let locked = create_rw_signal(None);
+    // shown in place of the app when that can't be found out
+    let (status_error, set_status_error) = create_signal(String::new());
 
     match crate::util::_conversation_uuid.write() {
         Ok(mut _conversation_uuid) => *_conversation_uuid = conversation_uuid,
         Err(error) => eprintln!("{error}")      // this is unreachable so not handling error
     }
 
-    view! {
-        <Chat config menu />
-        <Menu menu />
-        <History menu />
-        <Settings active_config=config menu />
+    spawn_local(async move {
+        match crate::commands::load_encryption_status().await {
+            Ok(status) => locked.set(Some(status.locked)),
+            Err(error) => set_status_error(format!("Unable to open the database: {error}"))
+        }
+    });
+
+    // everything else loads from the database, so it's only mounted once the database is unlocked
+    move || match locked() {
+        Some(false) => view! {
+            <Chat config continue_from menu />
+            <Menu menu />
+            <History continue_from menu />
+            <Settings active_config=config menu />
+            <EditLog menu />
+            <Diagnostics menu />
+            <Logs menu />
+            <Usage menu />
+            <QuickSwitcher continue_from menu />
+        }.into_view(),
+        Some(true) => view! { <Unlock locked /> }.into_view(),
+        None => view! {
+            <div class="flex items-center justify-center h-full px-[5vw]">
+                <ErrorMessage error=status_error />
+            </div>
+        }.into_view()
     }
 }
 
@@ -0,0 +1,492 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use leptos::*;
+use serde_json::Value;
+use wasm_bindgen::{JsValue, prelude::*};
+use crate::commands::{load_image, save_image};
+use crate::util::CopyLink;
+
+#[wasm_bindgen]
+extern "C" {
+    // defined in index.html
+    #[wasm_bindgen(catch, js_namespace = window, js_name = renderDiagram)]
+    async fn render_diagram(language: &str, source: &str, id: &str) -> Result<JsValue, JsValue>;
+    #[wasm_bindgen(catch, js_namespace = window, js_name = copyImage)]
+    async fn copy_image(data_url: &str) -> Result<JsValue, JsValue>;
+    #[wasm_bindgen(catch, js_namespace = window, js_name = highlightCode)]
+    fn highlight_code(code: &str, language: &str) -> Result<String, JsValue>;
+}
+
+// a piece of a message, split on fenced code blocks
+#[derive(Clone, Debug, PartialEq)]
+pub enum Block {
+    Text(String),
+    Code { language: String, code: String },
+    // rows of cells, the first being the header
+    Table(Vec<Vec<String>>)
+}
+
+fn is_table_row(line: &str) -> bool {
+    let line = line.trim();
+    line.len() > 1 && line.starts_with('|') && line.ends_with('|')
+}
+
+fn parse_table_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    line[1..line.len() - 1].split('|').map(|cell| cell.trim().to_string()).collect()
+}
+
+// e.g. |---|:---:|
+fn is_separator_row(line: &str) -> bool {
+    is_table_row(line) && parse_table_row(line).iter()
+        .all(|cell| !cell.is_empty() && cell.chars().all(|c| matches!(c, '-' | ':')))
+}
+
+// split markdown tables out of text
+fn parse_tables(text: String) -> Vec<Block> {
+    let lines = text.split_inclusive('\n').collect::<Vec<_>>();
+    let mut blocks = vec![];
+    let mut preceding_text = String::new();
+    let mut i = 0;
+    while i < lines.len() {
+        if is_table_row(lines[i]) && lines.get(i + 1).is_some_and(|line| is_separator_row(line)) {
+            if !preceding_text.trim().is_empty() {
+                blocks.push(Block::Text(std::mem::take(&mut preceding_text)));
+            }
+            preceding_text.clear();
+
+            let mut rows = vec![parse_table_row(lines[i])];
+            i += 2;
+            while i < lines.len() && is_table_row(lines[i]) {
+                rows.push(parse_table_row(lines[i]));
+                i += 1;
+            }
+            blocks.push(Block::Table(rows));
+        } else {
+            preceding_text.push_str(lines[i]);
+            i += 1;
+        }
+    }
+
+    if !preceding_text.trim().is_empty() {
+        blocks.push(Block::Text(preceding_text));
+    }
+
+    return blocks;
+}
+
+// parse comma or tab separated values, with double quoted fields
+fn parse_delimited(code: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = vec![];
+    for line in code.lines().filter(|line| !line.trim().is_empty()) {
+        let mut row = vec![];
+        let mut cell = String::new();
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    cell.push('"');
+                    chars.next();
+                },
+                '"' => quoted = !quoted,
+                c if c == delimiter && !quoted => row.push(std::mem::take(&mut cell).trim().to_string()),
+                c => cell.push(c)
+            }
+        }
+        row.push(cell.trim().to_string());
+        rows.push(row);
+    }
+
+    return rows;
+}
+
+pub fn parse_blocks(message: &str) -> Vec<Block> {
+    // a message that is entirely json is treated as a json block
+    let trimmed = message.trim();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+            && serde_json::from_str::<Value>(trimmed).is_ok() {
+        return vec![Block::Code { language: "json".into(), code: trimmed.into() }];
+    }
+
+    return parse_fenced_blocks(message, parse_tables);
+}
+
+// only fenced code blocks are split out, cheap enough to run on every streamed chunk
+pub fn parse_code_blocks(message: &str) -> Vec<Block> {
+    parse_fenced_blocks(message, |text| vec![Block::Text(text)])
+}
+
+// parse_text splits the text between code blocks
+fn parse_fenced_blocks(message: &str, parse_text: impl Fn(String) -> Vec<Block>) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut text = String::new();
+    let mut code: Option<(String, String)> = None;
+    for line in message.split_inclusive('\n') {
+        let fence = line.trim_start().strip_prefix("```");
+        match (fence, code.take()) {
+            (Some(language), None) => {
+                let preceding_text = std::mem::take(&mut text);
+                if !preceding_text.trim().is_empty() {
+                    blocks.extend(parse_text(preceding_text));
+                }
+                code = Some((language.trim().to_lowercase(), String::new()));
+            },
+            (Some(_), Some((language, code))) => blocks.push(Block::Code { language, code }),
+            (None, Some((language, mut code_lines))) => {
+                code_lines.push_str(line);
+                code = Some((language, code_lines));
+            },
+            (None, None) => text.push_str(line)
+        }
+    }
+
+    // an unterminated block is still being streamed
+    if let Some((language, code)) = code {
+        blocks.push(Block::Code { language, code });
+    }
+    if !text.trim().is_empty() {
+        blocks.extend(parse_text(text));
+    }
+
+    return blocks;
+}
+
+// whether rendering the message shows anything beyond the raw text
+pub fn is_renderable(message: &str) -> bool {
+    parse_blocks(message).iter().any(|block| !matches!(block, Block::Text(_)))
+}
+
+// the JSONPath of an object's key or array's index, with keys that aren't identifiers in brackets
+fn child_path(path: &str, key: Option<&str>, index: usize) -> String {
+    match key {
+        Some(key) if key.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_alphanumeric() || c == '_') => format!("{path}.{key}"),
+        Some(key) => format!("{path}[{}]", Value::from(key)),
+        None => format!("{path}[{index}]")
+    }
+}
+
+// hovering an entry's key or index shows a link copying its path
+fn json_view(value: &Value, path: &str) -> View {
+    let entries = match value {
+        Value::Object(object) => object.iter()
+            .map(|(key, value)| (Some(key.clone()), value.clone()))
+            .collect::<Vec<_>>(),
+        Value::Array(array) => array.iter().map(|value| (None, value.clone())).collect(),
+        Value::String(string) => return view! {
+            <span class="text-green-400">{format!("{string:?}")}</span>
+        }.into_view(),
+        Value::Null => return view!(<span class="text-[#777788]">"null"</span>).into_view(),
+        value => return view!(<span class="text-blue-400">{value.to_string()}</span>).into_view()
+    };
+
+    let summary = match value {
+        Value::Object(_) => format!("{{{} keys}}", entries.len()),
+        _ => format!("[{} items]", entries.len())
+    };
+
+    view! {
+        <details open=true class="inline-block align-top">
+            <summary class="cursor-pointer text-[#AAAABB]">{summary}</summary>
+            <div class="pl-4 border-l border-[#33333A]">
+                {entries.into_iter().enumerate().map(|(index, (key, value))| {
+                    let path = child_path(path, key.as_deref(), index);
+                    let text = Signal::derive({
+                        let path = path.clone();
+                        move || path.clone()
+                    });
+                    let label = match key {
+                        Some(key) => view!(<span class="text-[#AAAABB]">{format!("{key:?}")}</span>),
+                        None => view!(<span class="text-[#777788]">{index}</span>)
+                    };
+                    view! {
+                        <div>
+                            <span class="group" title=path.clone()>
+                                {label}
+                                <span class="hidden group-hover:inline mx-1 text-[0.8em] text-[#777788]">
+                                    <CopyLink text />
+                                </span>
+                                ": "
+                            </span>
+                            {json_view(&value, &path)}
+                        </div>
+                    }
+                }).collect_view()}
+            </div>
+        </details>
+    }.into_view()
+}
+
+#[component]
+pub fn JsonViewer(value: Value) -> impl IntoView {
+    view! {
+        <div class="px-2 py-1 overflow-x-auto font-mono text-[0.85em]">{json_view(&value, "$")}</div>
+    }
+}
+
+// cells that are both numbers compare as numbers, otherwise as text ignoring case
+fn compare_cells(cell: &str, other_cell: &str) -> std::cmp::Ordering {
+    match (cell.trim().parse::<f64>(), other_cell.trim().parse::<f64>()) {
+        (Ok(number), Ok(other_number)) => number.total_cmp(&other_number),
+        _ => cell.to_lowercase().cmp(&other_cell.to_lowercase())
+    }
+}
+
+// quoted where a cell has a comma, quote or line break, per RFC 4180
+fn to_csv(rows: &[Vec<String>]) -> String {
+    rows.iter()
+        .map(|row| row.iter()
+            .map(|cell| match cell.contains([',', '"', '\n', '\r']) {
+                true => format!("\"{}\"", cell.replace('"', "\"\"")),
+                false => cell.clone()
+            })
+            .collect::<Vec<_>>()
+            .join(","))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// clicking a column's header sorts by it ascending, then descending, then back to the original order
+#[component]
+pub fn Table(rows: Vec<Vec<String>>) -> impl IntoView {
+    let mut rows = rows.into_iter();
+    let header = rows.next().unwrap_or_default();
+    let rows = rows.collect::<Vec<_>>();
+    // the column sorted by and whether it's ascending
+    let sort = create_rw_signal(None::<(usize, bool)>);
+
+    let sorted_rows = {
+        let rows = rows.clone();
+        move || {
+            let mut rows = rows.clone();
+            if let Some((column, ascending)) = sort() {
+                rows.sort_by(|row, other_row| {
+                    let ordering = compare_cells(row.get(column).map_or("", String::as_str),
+                        other_row.get(column).map_or("", String::as_str));
+                    if ascending { ordering } else { ordering.reverse() }
+                });
+            }
+            rows
+        }
+    };
+    let text = Signal::derive({
+        let header = header.clone();
+        let sorted_rows = sorted_rows.clone();
+        move || to_csv(&[vec![header.clone()], sorted_rows()].concat())
+    });
+
+    let on_sort = move |column| sort.update(|sort| *sort = match *sort {
+        Some((sorted_column, true)) if sorted_column == column => Some((column, false)),
+        Some((sorted_column, false)) if sorted_column == column => None,
+        _ => Some((column, true))
+    });
+    let indicator = move |column| match sort() {
+        Some((sorted_column, true)) if sorted_column == column => " ▲",
+        Some((sorted_column, false)) if sorted_column == column => " ▼",
+        _ => ""
+    };
+
+    view! {
+        <div class="overflow-x-auto">
+            <div class="flex justify-end gap-2 text-[0.75em] text-[#777788]">
+                <p>"csv"</p>
+                <CopyLink text />
+            </div>
+            <table class="border-collapse text-[0.9em]">
+                <thead>
+                    <tr>{header.into_iter().enumerate().map(|(column, cell)| view! {
+                        <th class="px-2 py-1 border border-[#33333A] text-left cursor-pointer select-none"
+                            on:click=move |_| on_sort(column)>{cell}{move || indicator(column)}</th>
+                    }).collect_view()}</tr>
+                </thead>
+                <tbody>
+                    {move || sorted_rows().into_iter().map(|row| view! {
+                        <tr>{row.into_iter().map(|cell| view! {
+                            <td class="px-2 py-1 border border-[#33333A]">{cell}</td>
+                        }).collect_view()}</tr>
+                    }).collect_view()}
+                </tbody>
+            </table>
+        </div>
+    }
+}
+
+// highlighted by highlight.js, which guesses the language when it's missing or unknown
+#[component]
+pub fn CodeBlock(language: String, code: String) -> impl IntoView {
+    let text = Signal::derive({
+        let code = code.clone();
+        move || code.clone()
+    });
+    let code = match highlight_code(&code, &language) {
+        Ok(highlighted) => view!(<code inner_html=highlighted></code>),
+        // falls back to plain text if highlight.js failed to load
+        Err(_) => view!(<code>{code}</code>)
+    };
+
+    view! {
+        <div class="relative">
+            <div class="absolute top-1 right-2 flex gap-2 text-[0.75em] text-[#777788]">
+                <p>{language}</p>
+                <CopyLink text />
+            </div>
+            <pre class="px-2 py-1 overflow-x-auto bg-[#1C1C1C] border border-[#303038] text-[0.85em]">{code}</pre>
+        </div>
+    }
+}
+
+// mermaid needs a unique id for each diagram it renders
+static DIAGRAM_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[component]
+pub fn Diagram(language: String, source: String) -> impl IntoView {
+    let svg = create_rw_signal(None::<String>);
+    let error = create_rw_signal(None::<String>);
+
+    spawn_local({
+        let (language, source) = (language.clone(), source.clone());
+        async move {
+            let id = format!("diagram-{}", DIAGRAM_COUNT.fetch_add(1, Ordering::Relaxed));
+            match render_diagram(&language, &source, &id).await.map(|svg| svg.as_string()) {
+                Ok(Some(rendered_svg)) => svg.set(Some(rendered_svg)),
+                Ok(None) => error.set(Some("Unable to render diagram.".into())),
+                Err(rendering_error) => error.set(Some(rendering_error.as_string()
+                    .unwrap_or("Unable to render diagram.".into())))
+            }
+        }
+    });
+
+    // the source is shown instead while rendering, if it failed, or when toggled
+    let show_source = create_rw_signal(false);
+    let showing_source = move || show_source() || svg().is_none();
+    let text = Signal::derive({
+        let source = source.clone();
+        move || source.clone()
+    });
+
+    view! {
+        <div class="relative">
+            <div class="absolute top-1 right-2 flex gap-2 text-[0.75em] text-[#777788]">
+                <p>{language}</p>
+                <a class="cursor-pointer hover:text-[#AAAABB]" style:display=move || svg().is_none().then(|| "None")
+                    on:click=move |_| show_source.update(|show_source| *show_source = !*show_source)>
+                    {move || if show_source() { "preview" } else { "source" }}
+                </a>
+                <CopyLink text />
+            </div>
+            <div class="overflow-x-auto pt-5" inner_html=move || svg().unwrap_or_default()
+                style:display=move || showing_source().then(|| "None")></div>
+            <div style:display=move || (!showing_source()).then(|| "None")>
+                <pre class="px-2 py-1 overflow-x-auto bg-[#1C1C1C] border border-[#303038] text-[0.85em]"
+                ><code>{source}</code></pre>
+                <p class="mt-1 text-red-400 text-[0.9em]">{error}</p>
+            </div>
+        </div>
+    }
+}
+
+// an image stored in the config dir, with actions to save it to downloads or copy it
+#[component]
+fn Image(name: String) -> impl IntoView {
+    let data_url = create_rw_signal(None::<String>);
+    let status = create_rw_signal(String::new());
+
+    spawn_local({
+        let name = name.clone();
+        async move {
+            match load_image(name).await {
+                Ok(loaded_data_url) => data_url.set(Some(loaded_data_url)),
+                Err(error) => status.set(error.to_string())
+            }
+        }
+    });
+
+    let on_save = {
+        let name = name.clone();
+        move |_| spawn_local({
+            let name = name.clone();
+            async move {
+                match save_image(name).await {
+                    Ok(path) => status.set(format!("Saved to {path}")),
+                    Err(error) => status.set(error.to_string())
+                }
+            }
+        })
+    };
+
+    let on_copy = move |_| spawn_local(async move {
+        let Some(data_url) = data_url.get_untracked() else {
+            return;
+        };
+        match copy_image(&data_url).await {
+            Ok(_) => status.set("Copied".into()),
+            Err(error) => status.set(error.as_string().unwrap_or("Unable to copy image.".into()))
+        }
+    });
+
+    view! {
+        <div class="flex flex-col items-start">
+            <img class="max-w-full max-h-[32rem]" src=move || data_url().unwrap_or_default()
+                style:display=move || data_url().is_none().then(|| "None") />
+            <div class="flex gap-2 mt-1 text-[0.75em] text-[#777788]">
+                <a class="cursor-pointer hover:text-[#AAAABB]" on:click=on_save>"save"</a>
+                <a class="cursor-pointer hover:text-[#AAAABB]" on:click=on_copy>"copy"</a>
+                <p>{status}</p>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+pub fn Images(images: Signal<Vec<String>>) -> impl IntoView {
+    view! {
+        <div class="flex flex-col gap-2 mt-[12px]" style:display=move || images.with(Vec::is_empty).then(|| "None")>
+            <For each=images key=|name| name.clone() let:name>
+                <Image name />
+            </For>
+        </div>
+    }
+}
+
+fn block_view(block: Block) -> View {
+    match block {
+        Block::Text(text) => view!(<p class="whitespace-pre-wrap">{text.trim().to_string()}</p>).into_view(),
+        Block::Table(rows) => view!(<Table rows />).into_view(),
+        Block::Code { language, code } if language == "csv" =>
+            view!(<Table rows=parse_delimited(&code, ',') />).into_view(),
+        Block::Code { language, code } if ["mermaid", "dot", "graphviz"].contains(&language.as_str()) =>
+            view!(<Diagram language source=code />).into_view(),
+        Block::Code { language, code } if language == "tsv" =>
+            view!(<Table rows=parse_delimited(&code, '\t') />).into_view(),
+        Block::Code { language, code } => match serde_json::from_str::<Value>(&code) {
+            Ok(value) if language == "json" || language.is_empty() => view!(<JsonViewer value />).into_view(),
+            _ => view!(<CodeBlock language code />).into_view()
+        }
+    }
+}
+
+// read-only view of a message with its structured blocks rendered
+#[component]
+pub fn Rendered(message: Signal<String>) -> impl IntoView {
+    view! {
+        <div class="flex flex-col gap-2 px-2 pt-1 pb-2 border border-[#303038] bg-[#222222] text-[0.9em]">
+            {move || message.with(|message| parse_blocks(message))
+                .into_iter()
+                .map(block_view)
+                .collect_view()}
+        </div>
+    }
+}
+
+// a response while it streams, highlighting code as it arrives but leaving everything else as text
+#[component]
+pub fn Streamed(message: Signal<String>) -> impl IntoView {
+    move || message.with(|message| parse_code_blocks(message))
+        .into_iter()
+        .map(|block| match block {
+            Block::Code { language, code } => view!(<CodeBlock language code />).into_view(),
+            Block::Text(text) => view!(<span class="whitespace-pre-wrap">{text}</span>).into_view(),
+            Block::Table(_) => ().into_view()
+        })
+        .collect_view()
+}
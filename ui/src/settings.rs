@@ -1,9 +1,12 @@
-use common::{APIKey, Config, Provider};
+use common::{APIKey, Config, KeyRoute, Provider, ReasoningEffort, ResponseFormat, SafetyThreshold, SendKey, Snippet};
+use common::{ConversationFormat, ExportCadence, ExportLayout};
 use leptos::*;
 use strum::VariantNames;
 use wasm_bindgen::prelude::*;
-use crate::commands::{load_config, save_config};
-use crate::util::{button, listen, update_textarea_height, ErrorMessage, Menu};
+use crate::commands::{copy_to_clipboard, encrypt_database, export_all, export_settings, import_all, import_env_keys};
+use crate::commands::{import_settings, list_models, load_config, load_encryption_status, load_last_request};
+use crate::commands::{save_config, store_secret, validate_api_key, validate_request};
+use crate::util::{button, input, is_own_write, listen, sleep, update_textarea_height, ErrorMessage, Menu};
 
 lazy_static::lazy_static! {
     // anyhow! macro doesn't work if there is a static variable named "error" in the namespace
@@ -11,16 +14,13 @@ lazy_static::lazy_static! {
     pub static ref set_error: WriteSignal<String> = signal_pair.1;
 }
 
-fn input() -> String {
-    " bg-[#222222] h-[2em] border border-[#33333A] text-[0.9em] ".into()
-}
-
 #[component]
 fn SystemPromptInput(config: RwSignal<Config>, menu: RwSignal<Menu>) -> impl IntoView {
     let class = "flex-none w-full min-h-[2em] px-2 pt-1 pb-2 border border-[#303038]
         bg-[#222222] text-[0.9em] overflow-hidden resize-none";
     let system_prompt_input = view! {
-        <textarea rows=2 class=class type="text"></textarea>
+        <textarea rows=2 class=class type="text"
+            placeholder="{{today}}, {{model}}, and {{conversation_title}} are filled in when sending"></textarea>
     };
 
     let on_input = Closure::<dyn Fn(web_sys::Event) + 'static>::new({
@@ -80,57 +80,864 @@ fn TemperatureSlider(config: RwSignal<Config>) -> impl IntoView {
         }
     });
 
-    return view! {
-        <label>"Temperature:"</label>
-        <div class="flex items-center">
-            {temperature_slider}
-            <span class="mx-2">"|"</span>
-            <span>{move || config().temperature.to_string()}</span>
+    return view! {
+        <label>"Temperature:"</label>
+        <div class="flex items-center">
+            {temperature_slider}
+            <span class="mx-2">"|"</span>
+            <span>{move || config().temperature.to_string()}</span>
+        </div>
+    };
+}
+
+#[component]
+fn MaxTokensInput(max_tokens: RwSignal<String>) -> impl IntoView {
+    let on_input = move |event| max_tokens.set(event_target_value(&event));
+
+    let max_tokens_input = view!(<input type="text" on:input=on_input class=input() + "px-2 py-1" />);
+
+    create_effect({
+        let max_tokens_input = max_tokens_input.clone();
+        move |_| max_tokens.with(|max_tokens|
+            if &max_tokens_input.value() != max_tokens {
+                max_tokens_input.set_value(max_tokens);
+            }
+        )
+    });
+
+    view! {
+        <label>"Max tokens:"</label>
+        {max_tokens_input}
+    }
+}
+
+#[component]
+fn ModelInput(config: RwSignal<Config>) -> impl IntoView {
+    let on_input = move |event| config.update(|config|
+        config.model = event_target_value(&event));
+
+    let model_input = view! {
+        <input type="text" list="model-catalog" placeholder="Search or enter a model" on:input=on_input
+            class=input() + "px-2 py-1" />
+    };
+
+    create_effect({
+        let model_input = model_input.clone();
+        move |_| {
+            let config = config();
+            if model_input.value() != config.model {
+                model_input.set_value(&config.model);
+            }
+        }
+    });
+
+    // the selected key's live catalog, models that aren't listed can still be typed in
+    let models = create_rw_signal(Vec::<String>::new());
+    let api_key = create_memo(move |_| config.with(|config| config.api_keys.get(config.api_key?).cloned()));
+    create_effect(move |_| {
+        let Some(requested_key) = api_key() else {
+            models.set(vec![]);
+            return;
+        };
+
+        spawn_local(async move {
+            // a failed listing only loses the suggestions
+            let listed_models = list_models(requested_key.clone()).await.unwrap_or_default();
+            // the key may have changed while this one was listed
+            if api_key.get_untracked().as_ref() == Some(&requested_key) {
+                models.set(listed_models);
+            }
+        });
+    });
+
+    view! {
+        <label>"Model:"</label>
+        {model_input}
+        <datalist id="model-catalog">
+            <For each=models key=|model| model.clone() children=|model| view! { <option value=model /> } />
+        </datalist>
+    }
+}
+
+#[component]
+fn Checkbox(
+    label: &'static str,
+    checked: Signal<bool>,
+    set_checked: SignalSetter<bool>
+) -> impl IntoView {
+    let checkbox = view! {
+        <input type="checkbox" class="accent-blue-900"
+            on:change=move |event| set_checked(event_target_checked(&event)) />
+    };
+
+    create_effect({
+        let checkbox = checkbox.clone();
+        // this is different from setting the input's checked html attribute, which will not work
+        move |_| checkbox.set_checked(checked())
+    });
+
+    view! {
+        <div class="flex items-center">
+            {checkbox}
+            <label class="ml-2">{label}</label>
+        </div>
+    }
+}
+
+#[component]
+fn OutputCleanupInput(config: RwSignal<Config>) -> impl IntoView {
+    let (trim, set_trim) = create_slice(
+        config,
+        |config| config.output_cleanup.trim,
+        |config, trim| config.output_cleanup.trim = trim
+    );
+    let (collapse_blank_lines, set_collapse_blank_lines) = create_slice(
+        config,
+        |config| config.output_cleanup.collapse_blank_lines,
+        |config, collapse| config.output_cleanup.collapse_blank_lines = collapse
+    );
+    let (strip_invisible, set_strip_invisible) = create_slice(
+        config,
+        |config| config.output_cleanup.strip_invisible,
+        |config, strip| config.output_cleanup.strip_invisible = strip
+    );
+
+    view! {
+        <label>"Response cleanup:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <Checkbox label="Trim whitespace" checked=trim set_checked=set_trim />
+            <Checkbox label="Collapse blank lines" checked=collapse_blank_lines
+                set_checked=set_collapse_blank_lines />
+            <Checkbox label="Strip invisible characters" checked=strip_invisible
+                set_checked=set_strip_invisible />
+        </div>
+    }
+}
+
+#[component]
+fn GoogleToolsInput(config: RwSignal<Config>) -> impl IntoView {
+    let (code_execution, set_code_execution) = create_slice(
+        config,
+        |config| config.google_tools.code_execution,
+        |config, code_execution| config.google_tools.code_execution = code_execution
+    );
+    let (search_grounding, set_search_grounding) = create_slice(
+        config,
+        |config| config.google_tools.search_grounding,
+        |config, search_grounding| config.google_tools.search_grounding = search_grounding
+    );
+
+    view! {
+        <label>"Gemini tools:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <Checkbox label="Code execution" checked=code_execution set_checked=set_code_execution />
+            <Checkbox label="Google Search grounding" checked=search_grounding
+                set_checked=set_search_grounding />
+        </div>
+    }
+}
+
+#[component]
+fn ProviderDefaultsInput(config: RwSignal<Config>) -> impl IntoView {
+    let on_suffix_input = move |event| config.update(|config|
+        config.provider_defaults.anthropic_system_suffix = event_target_value(&event));
+    let on_threshold_change = move |event| {
+        let Ok(threshold) = event_target_value(&event).parse::<SafetyThreshold>() else {
+            set_error("Invalid safety threshold.".into());
+            return;
+        };
+        config.update(|config| config.provider_defaults.google_safety_threshold = threshold);
+    };
+
+    let suffix_input = view! {
+        <input type="text" on:input=on_suffix_input placeholder="Appended to the system prompt"
+            class=input() + "ml-2 px-1" />
+    };
+    let threshold_select = view! {
+        <select class="ml-2 px-1 bg-[#222222] border border-[#33333A] text-[#AAAABB]" on:change=on_threshold_change>
+            {SafetyThreshold::VARIANTS.iter().map(|&threshold| view! {
+                <option value=threshold>{threshold.replace('_', " ").to_lowercase()}</option>
+            }).collect_view()}
+        </select>
+    };
+
+    create_effect({
+        let suffix_input = suffix_input.clone();
+        let threshold_select = threshold_select.clone();
+        move |_| config.with(|config| {
+            if suffix_input.value() != config.provider_defaults.anthropic_system_suffix {
+                suffix_input.set_value(&config.provider_defaults.anthropic_system_suffix);
+            }
+            // this is different from setting the select's value html attribute, which will not work
+            threshold_select.set_value(&config.provider_defaults.google_safety_threshold.to_string());
+        })
+    });
+
+    view! {
+        <label>"Provider defaults:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <div class="flex items-center">"Anthropic system suffix:"{suffix_input}</div>
+            <div class="flex items-center">"Gemini safety:"{threshold_select}</div>
+        </div>
+    }
+}
+
+#[component]
+fn ResponseFormatInput(config: RwSignal<Config>) -> impl IntoView {
+    let on_change = move |event| config.update(|config| {
+        config.response_format = match &event_target_value(&event) as &str {
+            "JsonObject" => ResponseFormat::JsonObject,
+            // keep an existing schema when reselected
+            "JsonSchema" => match &config.response_format {
+                ResponseFormat::JsonSchema(schema) => ResponseFormat::JsonSchema(schema.clone()),
+                _ => ResponseFormat::JsonSchema("{\n  \"type\": \"object\"\n}".into())
+            },
+            _ => ResponseFormat::Text
+        }
+    });
+    let on_schema_input = move |event| config.update(|config| {
+        if let ResponseFormat::JsonSchema(schema) = &mut config.response_format {
+            *schema = event_target_value(&event);
+        }
+    });
+
+    let schema = move || match config().response_format {
+        ResponseFormat::JsonSchema(schema) => Some(schema),
+        _ => None
+    };
+
+    let format_select = view! {
+        <select class="px-1 w-[max-content] bg-[#222222] border border-[#33333A] text-[#AAAABB]" on:change=on_change>
+            <option value="Text">"Text"</option>
+            <option value="JsonObject">"JSON object"</option>
+            <option value="JsonSchema">"JSON schema"</option>
+        </select>
+    };
+    let schema_input = view! {
+        <textarea rows=4 class="px-2 py-1 bg-[#222222] border border-[#303038] font-mono text-[0.9em]"
+            on:input=on_schema_input style:display=move || schema().is_none().then(|| "None")></textarea>
+    };
+
+    create_effect({
+        let format_select = format_select.clone();
+        let schema_input = schema_input.clone();
+        move |_| config.with(|config| {
+            // this is different from setting the select's value html attribute, which will not work
+            format_select.set_value(match config.response_format {
+                ResponseFormat::Text => "Text",
+                ResponseFormat::JsonObject => "JsonObject",
+                ResponseFormat::JsonSchema(_) => "JsonSchema"
+            });
+            if let ResponseFormat::JsonSchema(schema) = &config.response_format {
+                if &schema_input.value() != schema {
+                    schema_input.set_value(schema);
+                }
+            }
+        })
+    });
+
+    view! {
+        <label>"Response format:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            {format_select}
+            {schema_input}
+        </div>
+    }
+}
+
+#[component]
+fn ReasoningInput(config: RwSignal<Config>) -> impl IntoView {
+    let on_effort_change = move |event| config.update(|config|
+        config.reasoning_effort = event_target_value(&event).parse::<ReasoningEffort>().ok());
+
+    // an empty input disables thinking
+    let on_budget_input = move |event| match event_target_value(&event).trim() {
+        "" => config.update(|config| config.thinking_budget = None),
+        budget => match budget.parse::<u32>() {
+            Ok(budget) => config.update(|config| config.thinking_budget = Some(budget)),
+            Err(_) => set_error("The thinking budget must be a non-negative integer.".into())
+        }
+    };
+
+    let effort_select = view! {
+        <select class="ml-2 px-1 bg-[#222222] border border-[#33333A] text-[#AAAABB]" on:change=on_effort_change>
+            <option value="">"default"</option>
+            {ReasoningEffort::VARIANTS.iter()
+                .map(|&effort| view! { <option value=effort>{effort}</option> })
+                .collect_view()}
+        </select>
+    };
+    let budget_input = view! {
+        <input type="text" on:input=on_budget_input placeholder="off" class=input() + "ml-2 px-1 w-[6em]" />
+    };
+
+    create_effect({
+        let effort_select = effort_select.clone();
+        let budget_input = budget_input.clone();
+        move |_| config.with(|config| {
+            // this is different from setting the select's value html attribute, which will not work
+            effort_select.set_value(&config.reasoning_effort.map(|effort| effort.to_string()).unwrap_or_default());
+            let budget = config.thinking_budget.map(|budget| budget.to_string()).unwrap_or_default();
+            if budget_input.value().trim() != budget {
+                budget_input.set_value(&budget);
+            }
+        })
+    });
+
+    view! {
+        <label>"Reasoning:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <div class="flex items-center">"OpenAI effort:"{effort_select}</div>
+            <div class="flex items-center">"Thinking budget:"{budget_input}"tokens"</div>
+        </div>
+    }
+}
+
+const MAX_CANDIDATES: usize = 8;
+
+#[component]
+fn CandidatesInput(config: RwSignal<Config>) -> impl IntoView {
+    let (candidates, set_candidates) = create_slice(
+        config,
+        |config| config.candidates.max(1),
+        |config, candidates| config.candidates = candidates
+    );
+
+    let on_input = move |event| match event_target_value(&event).trim().parse::<usize>() {
+        Ok(value) if (1..=MAX_CANDIDATES).contains(&value) => set_candidates(value),
+        _ => set_error(format!("Candidates must be between 1 and {MAX_CANDIDATES}."))
+    };
+
+    let candidates_input = view!(<input type="text" on:input=on_input class=input() + "px-2 py-1 w-[6em]" />);
+
+    create_effect({
+        let candidates_input = candidates_input.clone();
+        move |_| {
+            let candidates = candidates().to_string();
+            if candidates_input.value().trim() != candidates {
+                candidates_input.set_value(&candidates);
+            }
+        }
+    });
+
+    view! {
+        <label>"Candidates:"</label>
+        {candidates_input}
+    }
+}
+
+#[component]
+fn DeterministicInput(config: RwSignal<Config>) -> impl IntoView {
+    let (deterministic, set_deterministic) = create_slice(
+        config,
+        |config| config.deterministic,
+        |config, deterministic| config.deterministic = deterministic
+    );
+
+    let on_input = move |event| {
+        let Ok(seed) = event_target_value(&event).parse::<u64>() else {
+            set_error("The seed must be a non-negative integer.".into());
+            return;
+        };
+        config.update(|config| config.seed = seed);
+    };
+
+    let seed_input = view!(<input type="text" on:input=on_input class=input() + "ml-2 px-1 w-[8em]" />);
+
+    create_effect({
+        let seed_input = seed_input.clone();
+        move |_| {
+            let seed = config().seed.to_string();
+            if seed_input.value() != seed {
+                seed_input.set_value(&seed);
+            }
+        }
+    });
+
+    view! {
+        <label>"Deterministic:"</label>
+        <div class="flex items-center">
+            <Checkbox label="Temperature 0, seed:" checked=deterministic set_checked=set_deterministic />
+            {seed_input}
+        </div>
+    }
+}
+
+#[component]
+fn RequestChecksInput(config: RwSignal<Config>) -> impl IntoView {
+    let (preview_requests, set_preview_requests) = create_slice(
+        config,
+        |config| config.preview_requests,
+        |config, preview_requests| config.preview_requests = preview_requests
+    );
+
+    let (scan_prompts, set_scan_prompts) = create_slice(
+        config,
+        |config| config.scan_prompts,
+        |config, scan_prompts| config.scan_prompts = scan_prompts
+    );
+
+    view! {
+        <label>"Requests:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <Checkbox label="Preview before sending" checked=preview_requests set_checked=set_preview_requests />
+            <Checkbox label="Warn about secrets and emails" checked=scan_prompts set_checked=set_scan_prompts />
+        </div>
+    }
+}
+
+// summaries are made in the background and only sent once a conversation outgrows the model's context window
+#[component]
+fn LongConversationsInput(config: RwSignal<Config>) -> impl IntoView {
+    let (summarize, set_summarize) = create_slice(
+        config,
+        |config| config.summarize_long_conversations,
+        |config, summarize| config.summarize_long_conversations = summarize
+    );
+
+    view! {
+        <label>"Long conversations:"</label>
+        <div class="text-[0.9em]">
+            <Checkbox label="Send a summary of earlier exchanges when the context window fills" checked=summarize
+                set_checked=set_summarize />
+        </div>
+    }
+}
+
+// for debugging a provider's schema changes, the copied request body can be sent again with curl
+#[component]
+fn CaptureInput(config: RwSignal<Config>) -> impl IntoView {
+    let (capture_requests, set_capture_requests) = create_slice(
+        config,
+        |config| config.capture_requests,
+        |config, capture_requests| config.capture_requests = capture_requests
+    );
+    let status = create_rw_signal(None::<&'static str>);
+
+    let on_copy = move |_| spawn_local(async move {
+        let last_request = match load_last_request().await {
+            Ok(Some(last_request)) => last_request,
+            Ok(None) => {
+                set_error("No request has been captured yet.".into());
+                return;
+            },
+            Err(error) => {
+                set_error(error.to_string());
+                return;
+            }
+        };
+
+        let copied = copy_to_clipboard(last_request).await;
+        status.set(Some(if copied.is_ok() { "copied" } else { "copy failed" }));
+        sleep(std::time::Duration::from_secs(2)).await;
+        status.set(None);
+    });
+
+    view! {
+        <label>"Capture:"</label>
+        <div class="flex gap-2 items-center text-[0.9em]">
+            <Checkbox label="Record raw requests and responses" checked=capture_requests
+                set_checked=set_capture_requests />
+            <button class=button() + "w-[max-content]" on:click=on_copy>"Copy last request"</button>
+            <span class="text-[#777788]">{status}</span>
+        </div>
+    }
+}
+
+#[component]
+fn StreamingInput(config: RwSignal<Config>) -> impl IntoView {
+    let (smooth_streaming, set_smooth_streaming) = create_slice(
+        config,
+        |config| config.smooth_streaming,
+        |config, smooth_streaming| config.smooth_streaming = smooth_streaming
+    );
+
+    let on_interval_input = move |event| match event_target_value(&event).parse::<u64>() {
+        Ok(token_interval_ms) if token_interval_ms > 0 =>
+            config.update(|config| config.token_interval_ms = token_interval_ms),
+        _ => set_error("The interval must be a positive number of milliseconds.".into())
+    };
+
+    let interval_input = view! {
+        <input type="text" on:input=on_interval_input class=input() + "mx-2 px-1 w-[4em]" />
+    };
+
+    create_effect({
+        let interval_input = interval_input.clone();
+        move |_| config.with(|config| {
+            let token_interval_ms = config.token_interval().as_millis().to_string();
+            if interval_input.value() != token_interval_ms {
+                interval_input.set_value(&token_interval_ms);
+            }
+        })
+    });
+
+    view! {
+        <label>"Streaming:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <Checkbox label="Smooth character by character" checked=smooth_streaming set_checked=set_smooth_streaming />
+            // faster models stream more tokens per update rather than more updates
+            <div class="flex items-center">"Update every"{interval_input}"ms"</div>
+        </div>
+    }
+}
+
+#[component]
+fn RetryInput(config: RwSignal<Config>) -> impl IntoView {
+    let on_attempts_input = move |event| match event_target_value(&event).parse::<u32>() {
+        Ok(attempts) if attempts > 0 => config.update(|config| config.retry.attempts = attempts),
+        _ => set_error("The number of attempts must be a positive number.".into())
+    };
+    let on_backoff_input = move |event| match event_target_value(&event).parse::<u64>() {
+        Ok(initial_backoff_ms) => config.update(|config| config.retry.initial_backoff_ms = initial_backoff_ms),
+        _ => set_error("The backoff must be a number of milliseconds.".into())
+    };
+
+    let attempts_input = view! {
+        <input type="text" on:input=on_attempts_input class=input() + "mx-2 px-1 w-[4em]" />
+    };
+    let backoff_input = view! {
+        <input type="text" on:input=on_backoff_input class=input() + "mx-2 px-1 w-[6em]" />
+    };
+
+    create_effect({
+        let attempts_input = attempts_input.clone();
+        let backoff_input = backoff_input.clone();
+        move |_| config.with(|config| {
+            let attempts = config.retry.attempts.to_string();
+            if attempts_input.value() != attempts {
+                attempts_input.set_value(&attempts);
+            }
+            let initial_backoff_ms = config.retry.initial_backoff_ms.to_string();
+            if backoff_input.value() != initial_backoff_ms {
+                backoff_input.set_value(&initial_backoff_ms);
+            }
+        })
+    });
+
+    view! {
+        <label>"Retries:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <div class="flex items-center">"Up to"{attempts_input}"attempts"</div>
+            <div class="flex items-center">"Waiting"{backoff_input}"ms, doubled each retry"</div>
+        </div>
+    }
+}
+
+#[component]
+fn SendKeyInput(config: RwSignal<Config>) -> impl IntoView {
+    let on_change = move |event| config.update(|config| {
+        config.send_key = match event_target_value(&event).as_str() {
+            "Enter" => SendKey::Enter,
+            _ => SendKey::ShiftEnter
+        };
+    });
+
+    let send_key_select = view! {
+        <select class="px-1 w-[max-content] bg-[#222222] border border-[#33333A] text-[#AAAABB]" on:change=on_change>
+            <option value="Enter">"Enter sends, Shift+Enter adds a newline"</option>
+            <option value="ShiftEnter">"Shift+Enter sends, Enter adds a newline"</option>
+        </select>
+    };
+
+    create_effect({
+        let send_key_select = send_key_select.clone();
+        move |_| config.with(|config| {
+            // this is different from setting the select's value html attribute, which will not work
+            send_key_select.set_value(match config.send_key {
+                SendKey::Enter => "Enter",
+                SendKey::ShiftEnter => "ShiftEnter"
+            });
+        })
+    });
+
+    view! {
+        <label>"Send key:"</label>
+        <div class="text-[0.9em]">{send_key_select}</div>
+    }
+}
+
+#[component]
+fn LimitInput(label: &'static str, limit: Signal<Option<usize>>, set_limit: SignalSetter<Option<usize>>)
+-> impl IntoView {
+    // an empty input means no limit
+    let on_input = move |event| match event_target_value(&event).trim() {
+        "" => set_limit(None),
+        value => match value.parse::<usize>() {
+            Ok(value) => set_limit(Some(value)),
+            Err(_) => set_error(format!("The {label} limit must be a non-negative integer."))
+        }
+    };
+
+    let limit_input = view!(<input type="text" on:input=on_input class=input() + "ml-2 px-1 w-[6em]" />);
+
+    create_effect({
+        let limit_input = limit_input.clone();
+        move |_| {
+            let limit = limit().map(|limit| limit.to_string()).unwrap_or_default();
+            if limit_input.value().trim() != limit {
+                limit_input.set_value(&limit);
+            }
+        }
+    });
+
+    view! {
+        <div class="flex items-center">
+            <label>{label}" limit:"</label>
+            {limit_input}
+        </div>
+    }
+}
+
+#[component]
+fn MessageCounterInput(config: RwSignal<Config>) -> impl IntoView {
+    let (enabled, set_enabled) = create_slice(
+        config,
+        |config| config.message_counter.enabled,
+        |config, enabled| config.message_counter.enabled = enabled
+    );
+    let (character_limit, set_character_limit) = create_slice(
+        config,
+        |config| config.message_counter.character_limit,
+        |config, limit| config.message_counter.character_limit = limit
+    );
+    let (word_limit, set_word_limit) = create_slice(
+        config,
+        |config| config.message_counter.word_limit,
+        |config, limit| config.message_counter.word_limit = limit
+    );
+
+    view! {
+        <label>"Message counters:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <Checkbox label="Show counters" checked=enabled set_checked=set_enabled />
+            <LimitInput label="Character" limit=character_limit set_limit=set_character_limit />
+            <LimitInput label="Word" limit=word_limit set_limit=set_word_limit />
+        </div>
+    }
+}
+
+#[component]
+fn TrashInput(config: RwSignal<Config>) -> impl IntoView {
+    let on_input = move |event| match event_target_value(&event).parse::<u32>() {
+        Ok(retention_days) => config.update(|config| config.trash.retention_days = retention_days),
+        _ => set_error("The retention period must be a number of days.".into())
+    };
+
+    let retention_input = view! {
+        <input type="text" on:input=on_input class=input() + "mx-2 px-1 w-[4em]" />
+    };
+
+    create_effect({
+        let retention_input = retention_input.clone();
+        move |_| config.with(|config| {
+            let retention_days = config.trash.retention_days.to_string();
+            if retention_input.value() != retention_days {
+                retention_input.set_value(&retention_days);
+            }
+        })
+    });
+
+    view! {
+        <label>"Trash:"</label>
+        <div class="flex items-center text-[0.9em]">"Keep deleted conversations for"{retention_input}"days"</div>
+    }
+}
+
+// the whole history in one file, to move it to another machine
+#[component]
+fn ArchiveInput() -> impl IntoView {
+    let status = create_rw_signal(String::new());
+
+    let on_export = move |_| spawn_local(async move {
+        match export_all().await {
+            Ok(Some(path)) => status.set(format!("Exported to {path}.")),
+            Ok(None) => (),
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    let on_import = move |_| spawn_local(async move {
+        match import_all().await {
+            Ok(Some(summary)) => status.set(summary.describe()),
+            Ok(None) => (),
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    view! {
+        <label>"Archive:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <div class="flex gap-2">
+                <button class=button() + "w-[max-content]" on:click=on_export>"Export all"</button>
+                <button class=button() + "w-[max-content]" on:click=on_import>"Import"</button>
+            </div>
+            <p style:display=move || status().is_empty().then(|| "None")>{status}</p>
+        </div>
+    }
+}
+
+// the conversations, and the API keys without a keychain to go in, are encrypted with a passphrase asked for at
+// startup
+#[component]
+fn EncryptionInput() -> impl IntoView {
+    let encrypted = create_rw_signal(false);
+    let passphrase = create_rw_signal(String::new());
+    let confirmation = create_rw_signal(String::new());
+
+    spawn_local(async move {
+        match load_encryption_status().await {
+            Ok(status) => encrypted.set(status.encrypted),
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    let on_encrypt = move |_| spawn_local(async move {
+        if passphrase.get_untracked() != confirmation.get_untracked() {
+            set_error("The passphrases don't match.".into());
+            return;
+        }
+
+        match encrypt_database(passphrase.get_untracked()).await {
+            Ok(()) => {
+                encrypted.set(true);
+                passphrase.set(String::new());
+                confirmation.set(String::new());
+                set_error("".into());
+            },
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    view! {
+        <label>"Encryption:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <p style:display=move || (!encrypted()).then(|| "None")>"Encrypted with a passphrase."</p>
+            <div class="flex gap-2" style:display=move || encrypted().then(|| "None")>
+                <input type="password" placeholder="Passphrase" class=input() + "px-1" prop:value=passphrase
+                    on:input=move |event| passphrase.set(event_target_value(&event)) />
+                <input type="password" placeholder="Confirm passphrase" class=input() + "px-1" prop:value=confirmation
+                    on:input=move |event| confirmation.set(event_target_value(&event)) />
+                <button class=button() + "w-[max-content]" on:click=on_encrypt>"Encrypt"</button>
+            </div>
+            <p style:display=move || encrypted().then(|| "None")>"A forgotten passphrase can't be recovered."</p>
         </div>
-    };
+    }
 }
 
+// the settings alone, to sync them across machines. imported settings are applied like any other change
 #[component]
-fn MaxTokensInput(max_tokens: RwSignal<String>) -> impl IntoView {
-    let on_input = move |event| max_tokens.set(event_target_value(&event));
+fn SettingsFileInput(config: RwSignal<Config>, max_tokens: RwSignal<String>) -> impl IntoView {
+    let include_keys = create_rw_signal(false);
+    let status = create_rw_signal(String::new());
 
-    let max_tokens_input = view!(<input type="text" on:input=on_input class=input() + "px-2 py-1" />);
+    let on_export = move |_| spawn_local(async move {
+        match export_settings(include_keys.get_untracked()).await {
+            Ok(Some(path)) => status.set(format!("Exported to {path}.")),
+            Ok(None) => (),
+            Err(error) => set_error(error.to_string())
+        }
+    });
 
-    create_effect({
-        let max_tokens_input = max_tokens_input.clone();
-        move |_| max_tokens.with(|max_tokens|
-            if &max_tokens_input.value() != max_tokens {
-                max_tokens_input.set_value(max_tokens);
-            }
-        )
+    let on_import = move |_| spawn_local(async move {
+        match import_settings(config.get_untracked()).await {
+            Ok(Some(imported_config)) => {
+                max_tokens.set(imported_config.max_tokens.to_string());
+                config.set(imported_config);
+                status.set("Imported, apply to keep the imported settings.".into());
+            },
+            Ok(None) => (),
+            Err(error) => set_error(error.to_string())
+        }
     });
 
     view! {
-        <label>"Max tokens:"</label>
-        {max_tokens_input}
+        <label>"Settings file:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <div class="flex gap-2 items-center">
+                <button class=button() + "w-[max-content]" on:click=on_export>"Export settings"</button>
+                <button class=button() + "w-[max-content]" on:click=on_import>"Import settings"</button>
+                <Checkbox label="Include API keys" checked=include_keys.into()
+                    set_checked=include_keys.write_only().into() />
+            </div>
+            <p style:display=move || status().is_empty().then(|| "None")>{status}</p>
+        </div>
     }
 }
 
 #[component]
-fn ModelInput(config: RwSignal<Config>) -> impl IntoView {
-    let on_input = move |event| config.update(|config|
-        config.model = event_target_value(&event));
+fn ExportScheduleInput(config: RwSignal<Config>) -> impl IntoView {
+    let (enabled, set_enabled) = create_slice(
+        config,
+        |config| config.export_schedule.enabled,
+        |config, enabled| config.export_schedule.enabled = enabled
+    );
+
+    let on_cadence_change = move |event| config.update(|config|
+        config.export_schedule.cadence = match &event_target_value(&event) as &str {
+            "Weekly" => ExportCadence::Weekly,
+            _ => ExportCadence::Daily
+        });
+    let on_format_change = move |event| config.update(|config|
+        config.export_schedule.format = match &event_target_value(&event) as &str {
+            "Markdown" => ConversationFormat::Markdown,
+            _ => ConversationFormat::Json
+        });
+    let on_layout_change = move |event| config.update(|config|
+        config.export_schedule.layout = match &event_target_value(&event) as &str {
+            "PerConversation" => ExportLayout::PerConversation,
+            _ => ExportLayout::Combined
+        });
+    let on_directory_input = move |event| config.update(|config|
+        config.export_schedule.directory = event_target_value(&event));
 
-    let model_input = view!(<input type="text" on:input=on_input class=input() + "px-2 py-1" />);
+    let cadence_select = view! {
+        <select class="mx-2 px-1 bg-[#222222] border border-[#33333A] text-[#AAAABB]" on:change=on_cadence_change>
+            <option value="Daily">"Daily"</option>
+            <option value="Weekly">"Weekly"</option>
+        </select>
+    };
+    let format_select = view! {
+        <select class="mx-2 px-1 bg-[#222222] border border-[#33333A] text-[#AAAABB]" on:change=on_format_change>
+            <option value="Json">"JSON"</option>
+            <option value="Markdown">"Markdown"</option>
+        </select>
+    };
+    let layout_select = view! {
+        <select class="px-1 bg-[#222222] border border-[#33333A] text-[#AAAABB]" on:change=on_layout_change>
+            <option value="Combined">"one combined file"</option>
+            <option value="PerConversation">"one file per conversation"</option>
+        </select>
+    };
+    let directory_input = view! {
+        <input type="text" on:input=on_directory_input placeholder="~/llm-playground-exports"
+            class=input() + "ml-2 px-1" />
+    };
 
     create_effect({
-        let model_input = model_input.clone();
-        move |_| {
-            let config = config();
-            if model_input.value() != config.model {
-                model_input.set_value(&config.model);
+        let cadence_select = cadence_select.clone();
+        let format_select = format_select.clone();
+        let layout_select = layout_select.clone();
+        let directory_input = directory_input.clone();
+        move |_| config.with(|config| {
+            // this is different from setting the select's value html attribute, which will not work
+            cadence_select.set_value(&format!("{:?}", config.export_schedule.cadence));
+            format_select.set_value(&format!("{:?}", config.export_schedule.format));
+            layout_select.set_value(&format!("{:?}", config.export_schedule.layout));
+            if directory_input.value() != config.export_schedule.directory {
+                directory_input.set_value(&config.export_schedule.directory);
             }
-        }
+        })
     });
 
     view! {
-        <label>"Model:"</label>
-        {model_input}
+        <label>"Scheduled export:"</label>
+        <div class="grid grid-cols-1 gap-1 text-[0.9em]">
+            <Checkbox label="Export new conversations" checked=enabled set_checked=set_enabled />
+            <div class="flex items-center">{cadence_select}"since the last export"</div>
+            <div class="flex items-center">"As"{format_select}{layout_select}</div>
+            <div class="flex items-center">"To:"{directory_input}</div>
+        </div>
     }
 }
 
@@ -153,9 +960,37 @@ fn KeyEntry(
         }
     });
 
+    // None until the key is tested, the error says why the provider rejected it
+    let validation = create_rw_signal(None::<Result<(), String>>);
+    let on_test = {
+        let api_key = api_key.clone();
+        move |_| {
+            let api_key = api_key.clone();
+            validation.set(None);
+            spawn_local(async move {
+                let validated = validate_api_key(api_key).await.map_err(|error| error.to_string());
+                validation.set(Some(validated));
+            });
+        }
+    };
+
     view! {
         {key_entry}
         <p class="mx-2">{api_key.name.clone()}</p>
+        <button class="px-[5px] w-[max-content] h-[max-content] border border-[#33333A]
+                bg-[#222222] hover:bg-[#33333A] text-[#AAAABB] text-[0.9em]"
+            on:click=on_test
+        >"Test key"</button>
+        <span class=move || match validation() {
+                Some(Ok(())) => "text-green-400",
+                _ => "text-red-400"
+            }
+            title=move || validation().and_then(Result::err).unwrap_or_default()
+        >{move || match validation() {
+            Some(Ok(())) => "✓",
+            Some(Err(_)) => "✗",
+            None => ""
+        }}</span>
         <button class="px-[5px] w-[max-content] h-[max-content] border border-[#33333A]
                 bg-[#222222] hover:bg-[#33333A] text-[#AAAABB]"
             on:click=move |_| on_remove(&api_key.name)
@@ -212,28 +1047,28 @@ fn ProviderList(new_key: RwSignal<Option<APIKey>>) -> impl IntoView {
 #[component]
 fn BaseUrlInput(new_key: RwSignal<Option<APIKey>>) -> impl IntoView {
     let hidden = move || new_key()
-        .map(|new_key| !matches!(new_key.provider, Provider::OpenAI { .. }))
+        .map(|new_key| new_key.provider.base_url().is_none())
         .unwrap_or_default();
 
     let on_input = move |event| new_key.update(|new_key| {
-        let Some(APIKey { provider: Provider::OpenAI { base_url }, .. }) = new_key else {
+        let Some(base_url) = new_key.as_mut().and_then(|new_key| new_key.provider.base_url_mut()) else {
             return;
         };
         *base_url = event_target_value(&event);
     });
 
     let input = view! {
-        <input class=input() + "px-1" type="text"
+        <input class=input() + "px-1" type="text" placeholder="e.g. http://localhost:8000/v1"
             on:input=on_input style:display=move || hidden().then(|| "None") />
     };
 
     create_effect({
         let input = input.clone();
         move |_| new_key.with(|new_key| {
-            let Some(APIKey { provider: Provider::OpenAI { base_url }, .. }) = new_key else {
+            let Some(base_url) = new_key.as_ref().and_then(|new_key| new_key.provider.base_url()) else {
                 return;
             };
-            input.set_value(&base_url);
+            input.set_value(base_url);
         })
     });
 
@@ -243,6 +1078,115 @@ fn BaseUrlInput(new_key: RwSignal<Option<APIKey>>) -> impl IntoView {
     }
 }
 
+// the deployment requests are routed to, empty to use the model's name
+#[component]
+fn DeploymentInput(new_key: RwSignal<Option<APIKey>>) -> impl IntoView {
+    let hidden = move || !matches!(new_key(), Some(APIKey { provider: Provider::Azure { .. }, .. }));
+
+    let on_input = move |event| new_key.update(|new_key| {
+        let Some(APIKey { provider: Provider::Azure { deployment, .. }, .. }) = new_key else {
+            return;
+        };
+        *deployment = event_target_value(&event);
+    });
+
+    let input = view! {
+        <input class=input() + "px-1" type="text" placeholder="the model's name"
+            on:input=on_input style:display=move || hidden().then(|| "None") />
+    };
+
+    create_effect({
+        let input = input.clone();
+        move |_| new_key.with(|new_key| {
+            let Some(APIKey { provider: Provider::Azure { deployment, .. }, .. }) = new_key else {
+                return;
+            };
+            input.set_value(deployment);
+        })
+    });
+
+    view! {
+        <label style:display=move || hidden().then(|| "None")>"Deployment:"</label>
+        {input}
+    }
+}
+
+#[component]
+fn ApiVersionInput(new_key: RwSignal<Option<APIKey>>) -> impl IntoView {
+    let hidden = move || !matches!(new_key(), Some(APIKey { provider: Provider::Azure { .. }, .. }));
+
+    let on_input = move |event| new_key.update(|new_key| {
+        let Some(APIKey { provider: Provider::Azure { api_version, .. }, .. }) = new_key else {
+            return;
+        };
+        *api_version = event_target_value(&event);
+    });
+
+    let input = view! {
+        <input class=input() + "px-1" type="text"
+            on:input=on_input style:display=move || hidden().then(|| "None") />
+    };
+
+    create_effect({
+        let input = input.clone();
+        move |_| new_key.with(|new_key| {
+            let Some(APIKey { provider: Provider::Azure { api_version, .. }, .. }) = new_key else {
+                return;
+            };
+            input.set_value(api_version);
+        })
+    });
+
+    view! {
+        <label style:display=move || hidden().then(|| "None")>"API version:"</label>
+        {input}
+    }
+}
+
+#[component]
+fn VertexAIInputs(new_key: RwSignal<Option<APIKey>>) -> impl IntoView {
+    let hidden = move || !matches!(new_key(), Some(APIKey { provider: Provider::VertexAI { .. }, .. }));
+
+    let on_project_input = move |event| new_key.update(|new_key| {
+        if let Some(APIKey { provider: Provider::VertexAI { project_id, .. }, .. }) = new_key {
+            *project_id = event_target_value(&event);
+        }
+    });
+    let on_location_input = move |event| new_key.update(|new_key| {
+        if let Some(APIKey { provider: Provider::VertexAI { location, .. }, .. }) = new_key {
+            *location = event_target_value(&event);
+        }
+    });
+
+    let project_input = view! {
+        <input class=input() + "px-1" type="text"
+            on:input=on_project_input style:display=move || hidden().then(|| "None") />
+    };
+    let location_input = view! {
+        <input class=input() + "px-1" type="text"
+            on:input=on_location_input style:display=move || hidden().then(|| "None") />
+    };
+
+    create_effect({
+        let project_input = project_input.clone();
+        let location_input = location_input.clone();
+        move |_| new_key.with(|new_key| {
+            let Some(APIKey { provider: Provider::VertexAI { project_id, location }, .. }) = new_key else {
+                return;
+            };
+            project_input.set_value(project_id);
+            location_input.set_value(location);
+        })
+    });
+
+    view! {
+        <label style:display=move || hidden().then(|| "None")>"Project ID:"</label>
+        {project_input}
+        <label style:display=move || hidden().then(|| "None")>"Location:"</label>
+        {location_input}
+    }
+}
+
 #[component]
 fn KeyInput(new_key: RwSignal<Option<APIKey>>) -> impl IntoView {
     let on_change = move |event| new_key.update(|new_key| {
@@ -253,6 +1197,20 @@ fn KeyInput(new_key: RwSignal<Option<APIKey>>) -> impl IntoView {
             },
             "Anthropic" => new_key.provider = Provider::Anthropic,
             "Google" => new_key.provider = Provider::Google,
+            "Groq" => new_key.provider = Provider::Groq,
+            "XAI" => new_key.provider = Provider::XAI,
+            "Azure" => match new_key.provider {
+                Provider::Azure { .. } => (),
+                _ => new_key.provider = Provider::default_azure()
+            },
+            "VertexAI" => match new_key.provider {
+                Provider::VertexAI { .. } => (),
+                _ => new_key.provider = Provider::default_vertex_ai()
+            },
+            "Ollama" => match new_key.provider {
+                Provider::Ollama { .. } => (),      // don't override existing base_url
+                _ => new_key.provider = Provider::default_ollama()
+            },
             _misc_event => ()
         });
     });
@@ -260,19 +1218,61 @@ fn KeyInput(new_key: RwSignal<Option<APIKey>>) -> impl IntoView {
     let on_input = move |event| new_key.update(|new_key| {
         new_key.as_mut().map(|new_key| new_key.key = event_target_value(&event));
     });
+    let vertex_ai = move || matches!(new_key(), Some(APIKey { provider: Provider::VertexAI { .. }, .. }));
 
     view! {
         <div class="grid grid-cols-[repeat(2,max-content)] gap-2 text-[0.9em]" on:change=on_change
                 style:display=move || new_key().is_none().then(|| "None")>
             <KeyNameInput new_key />
             <label>"Key:"</label>
-            <input type="text" on:input=on_input class=input() + "px-1" />
+            <input type="text" on:input=on_input class=input() + "px-1" placeholder=move || vertex_ai()
+                .then_some("Service account JSON key or its file's path") />
             <BaseUrlInput new_key />
+            <DeploymentInput new_key />
+            <ApiVersionInput new_key />
+            <VertexAIInputs new_key />
             <ProviderList new_key />
         </div>
     }
 }
 
+#[component]
+fn EnvImport(config: RwSignal<Config>) -> impl IntoView {
+    let path = create_rw_signal("~/.env".to_string());
+
+    let on_import = move |_| spawn_local(async move {
+        let api_keys = config.with_untracked(|config| config.api_keys.clone());
+        let imported_keys = match import_env_keys(path.get_untracked(), api_keys).await {
+            Ok(imported_keys) => imported_keys,
+            Err(error) => {
+                set_error(error.to_string());
+                return;
+            }
+        };
+
+        if imported_keys.is_empty() {
+            set_error("No new API keys found in the file.".into());
+            return;
+        }
+
+        config.update(|config| {
+            for imported_key in imported_keys {
+                config.add_api_key(imported_key);
+            }
+        });
+        set_error("".into());
+    });
+
+    view! {
+        <div class="flex items-center text-[0.9em]">
+            <label class="mr-2">".env file:"</label>
+            <input type="text" class=input() + "px-1 mr-2" prop:value=path
+                on:input=move |event| path.set(event_target_value(&event)) />
+            <button class=button() + "w-[max-content]" on:click=on_import>"Import"</button>
+        </div>
+    }
+}
+
 #[component]
 fn KeyList(config: RwSignal<Config>) -> impl IntoView {
     let (api_keys, set_api_keys) = create_slice(
@@ -283,9 +1283,11 @@ fn KeyList(config: RwSignal<Config>) -> impl IntoView {
     let (selected_key, set_selected_key) = create_slice(
         config,
         |config| Some(config.api_keys.get(config.api_key?)?.name.clone()),
-        |config, selected_key: Option<String>|
-            config.api_key = selected_key.and_then(|selected_key|
-                config.api_keys.iter().position(|api_key| api_key.name == selected_key))
+        |config, selected_key: Option<String>| {
+            let api_key = selected_key.and_then(|selected_key|
+                config.api_keys.iter().position(|api_key| api_key.name == selected_key));
+            config.switch_api_key(api_key);
+        }
     );
     let new_key = create_rw_signal(None::<APIKey>);
 
@@ -314,16 +1316,26 @@ fn KeyList(config: RwSignal<Config>) -> impl IntoView {
                 return;
             }
 
-            let mut api_keys = api_keys();
-            if api_keys.iter().any(|api_key| api_key.name == new_api_key.name) {
+            if api_keys.with_untracked(|api_keys| api_keys.iter().any(|api_key| api_key.name == new_api_key.name)) {
                 set_error("New key name must be unique.".into());
                 return;
             }
 
-            new_key.set(None);
-            api_keys.push(new_api_key);
-            set_api_keys(api_keys);
-            set_error("".into());
+            spawn_local(async move {
+                // the config only holds a reference to the key in the OS keychain, or the key itself if there
+                // is no keychain
+                if !new_api_key.key.is_empty() {
+                    if let Ok(reference) = store_secret(new_api_key.key.clone()).await {
+                        new_api_key.key = reference;
+                    }
+                }
+
+                new_key.set(None);
+                let mut api_keys = api_keys.get_untracked();
+                api_keys.push(new_api_key);
+                set_api_keys(api_keys);
+                set_error("".into());
+            });
         } else {
             new_key.set(Some(APIKey::default()));
         }
@@ -332,7 +1344,7 @@ fn KeyList(config: RwSignal<Config>) -> impl IntoView {
     view! {
         <div class="col-span-2 grid grid-cols-1 gap-4">
             <h2 class="text-[1.1em] underline">"API Keys"</h2>
-            <div class="grid grid-cols-[repeat(3,max-content)] gap-2 items-center"
+            <div class="grid grid-cols-[repeat(5,max-content)] gap-2 items-center"
                     style:display=move || new_key().is_some().then(|| "None")
                     on:change=move |event| set_selected_key(Some(event_target_value(&event)))>
                 <For each=api_keys
@@ -342,6 +1354,9 @@ fn KeyList(config: RwSignal<Config>) -> impl IntoView {
                     } />
             </div>
             <KeyInput new_key />
+            <div style:display=move || new_key().is_some().then(|| "None")>
+                <EnvImport config />
+            </div>
             <div class="flex">
                 <button class=button() + "mr-2 w-[max-content]"
                     style:display=move || new_key().is_none().then(|| "None")
@@ -353,6 +1368,183 @@ fn KeyList(config: RwSignal<Config>) -> impl IntoView {
     }
 }
 
+#[component]
+fn KeyRouteEntry(config: RwSignal<Config>, index: usize) -> impl IntoView {
+    let route = move || config.with(|config| config.key_routes.get(index).cloned().unwrap_or_default());
+    let update_route = move |update: &dyn Fn(&mut KeyRoute)| config.update(|config| {
+        config.key_routes.get_mut(index).map(update);
+    });
+
+    let prefix_input = view! {
+        <input type="text" class=input() + "px-1" placeholder="gpt-4o"
+            on:input=move |event| update_route(&|route| route.model_prefix = event_target_value(&event)) />
+    };
+    let key_select = view! {
+        <select class=input() + "px-1"
+            on:change=move |event| update_route(&|route| route.api_key = event_target_value(&event))>
+            <option value="" disabled=true>"Select key"</option>
+            <For each=move || config().api_keys
+                key=|api_key| api_key.name.clone()
+                children=|api_key| view! { <option value=api_key.name.clone()>{api_key.name}</option> } />
+        </select>
+    };
+
+    create_effect({
+        let prefix_input = prefix_input.clone();
+        let key_select = key_select.clone();
+        move |_| {
+            let route = route();
+            // this is different from setting the value html attribute, which will not work
+            if prefix_input.value() != route.model_prefix {
+                prefix_input.set_value(&route.model_prefix);
+            }
+            key_select.set_value(&route.api_key);
+        }
+    });
+
+    let on_remove = move |_| config.update(|config| {
+        if index < config.key_routes.len() {
+            config.key_routes.remove(index);
+        }
+    });
+
+    view! {
+        {prefix_input}
+        {key_select}
+        <button class="px-[5px] w-[max-content] h-[max-content] border border-[#33333A]
+                bg-[#222222] hover:bg-[#33333A] text-[#AAAABB]"
+            on:click=on_remove
+        >"-"</button>
+    }
+}
+
+#[component]
+fn KeyRoutes(config: RwSignal<Config>) -> impl IntoView {
+    let route_count = move || config.with(|config| config.key_routes.len());
+    let on_add = move |_| config.update(|config| config.key_routes.push(KeyRoute::default()));
+
+    view! {
+        <div class="col-span-2 grid grid-cols-1 gap-4">
+            <h2 class="text-[1.1em] underline">"Key Routing"</h2>
+            <p class="text-[0.9em]">"Models starting with a prefix use its key instead of the selected one."</p>
+            <div class="grid grid-cols-[repeat(3,max-content)] gap-2 items-center text-[0.9em]">
+                <For each=move || 0..route_count()
+                    key=|&index| index
+                    children=move |index| view! { <KeyRouteEntry config index /> } />
+            </div>
+            <button class=button() + "w-[max-content]" on:click=on_add>"Add"</button>
+        </div>
+    }
+}
+
+#[component]
+fn SnippetEntry(config: RwSignal<Config>, index: usize) -> impl IntoView {
+    let snippet = move || config.with(|config| config.snippets.get(index).cloned().unwrap_or_default());
+    let update_snippet = move |update: &dyn Fn(&mut Snippet)| config.update(|config| {
+        config.snippets.get_mut(index).map(update);
+    });
+
+    let name_input = view! {
+        <input type="text" class=input() + "px-1" placeholder="summarize"
+            on:input=move |event| update_snippet(&|snippet| snippet.name = event_target_value(&event)) />
+    };
+    let body_input = view! {
+        <textarea rows=2 class="px-1 w-[40vw] bg-[#222222] border border-[#33333A] resize-y"
+            placeholder="Summarize the following in three bullet points:"
+            on:input=move |event| update_snippet(&|snippet| snippet.body = event_target_value(&event))>
+        </textarea>
+    };
+
+    create_effect({
+        let name_input = name_input.clone();
+        let body_input = body_input.clone();
+        move |_| {
+            let snippet = snippet();
+            // this is different from setting the value html attribute, which will not work
+            if name_input.value() != snippet.name {
+                name_input.set_value(&snippet.name);
+            }
+            if body_input.value() != snippet.body {
+                body_input.set_value(&snippet.body);
+            }
+        }
+    });
+
+    let on_remove = move |_| config.update(|config| {
+        if index < config.snippets.len() {
+            config.snippets.remove(index);
+        }
+    });
+
+    view! {
+        {name_input}
+        {body_input}
+        <button class="px-[5px] w-[max-content] h-[max-content] border border-[#33333A]
+                bg-[#222222] hover:bg-[#33333A] text-[#AAAABB]"
+            on:click=on_remove
+        >"-"</button>
+    }
+}
+
+#[component]
+fn Snippets(config: RwSignal<Config>) -> impl IntoView {
+    let snippet_count = move || config.with(|config| config.snippets.len());
+    let on_add = move |_| config.update(|config| config.snippets.push(Snippet::default()));
+
+    view! {
+        <div class="col-span-2 grid grid-cols-1 gap-4">
+            <h2 class="text-[1.1em] underline">"Snippets"</h2>
+            <p class="text-[0.9em]">"Typing \"/\" in the prompt box offers these by name."</p>
+            <div class="grid grid-cols-[repeat(3,max-content)] gap-2 items-start text-[0.9em]">
+                <For each=move || 0..snippet_count()
+                    key=|&index| index
+                    children=move |index| view! { <SnippetEntry config index /> } />
+            </div>
+            <button class=button() + "w-[max-content]" on:click=on_add>"Add"</button>
+        </div>
+    }
+}
+
+fn matches_filter(name: &str, filter: &str) -> bool {
+    name.to_lowercase().contains(&filter.trim().to_lowercase())
+}
+
+// a row of the settings grid, hidden when its name doesn't match the filter
+#[component]
+fn Setting(name: &'static str, filter: RwSignal<String>, children: Children) -> impl IntoView {
+    view! {
+        <div class="contents" style:display=move || (!filter.with(|filter| matches_filter(name, filter))).then(|| "None")>
+            {children()}
+        </div>
+    }
+}
+
+// names lists the section's settings, so that the section hides when none of them match the filter
+#[component]
+fn SettingsSection(
+    title: &'static str,
+    names: &'static [&'static str],
+    filter: RwSignal<String>,
+    children: Children
+) -> impl IntoView {
+    let collapsed = create_rw_signal(false);
+    let hidden = move || filter.with(|filter| !names.iter().any(|name| matches_filter(name, filter)));
+    // a search shows every match, even in collapsed sections
+    let expanded = move || !collapsed() || filter.with(|filter| !filter.trim().is_empty());
+
+    view! {
+        <div class="flex flex-col w-full" style:display=move || hidden().then(|| "None")>
+            <button class="flex mb-4 text-[1.1em] text-left" on:click=move |_| collapsed.update(|collapsed| *collapsed = !*collapsed)>
+                {move || if expanded() { "▾ " } else { "▸ " }}{title}
+            </button>
+            <div class="grid grid-cols-[repeat(2,max-content)] gap-[6vh] items-center pl-4"
+                style:display=move || (!expanded()).then(|| "None")>
+                {children()}
+            </div>
+        </div>
+    }
+}
+
 #[component]
 pub fn Settings(active_config: RwSignal<Config>, menu: RwSignal<Menu>) -> impl IntoView {
     let error = signal_pair.0;
@@ -373,13 +1565,16 @@ pub fn Settings(active_config: RwSignal<Config>, menu: RwSignal<Menu>) -> impl I
     });
 
     spawn_local(async move {
-        // listen for when the user/another window/this window changes the config
-        let on_update = Closure::new(move |_| spawn_local(async move {
-            match load_config().await {
-                Ok(config) => saved_config.set(Some(config)),
-                Err(error) => set_error(error.to_string())
-            }
-        }));
+        // listen for when the user, another window or another process changes the config,
+        // saves from this window are already in saved_config
+        let on_update = Closure::new(move |event: JsValue| if !is_own_write(&event) {
+            spawn_local(async move {
+                match load_config().await {
+                    Ok(config) => saved_config.set(Some(config)),
+                    Err(error) => set_error(error.to_string())
+                }
+            });
+        });
 
         if let Err(_) = listen("config_updated", &on_update).await {
             set_error("Error listening for config updates".into());
@@ -407,6 +1602,8 @@ pub fn Settings(active_config: RwSignal<Config>, menu: RwSignal<Menu>) -> impl I
         };
     };
 
+    let filter = create_rw_signal(String::new());
+
     let on_apply = move |_| {
         let max_tokens = match max_tokens.get_untracked().parse::<u32>() {
             Ok(max_tokens) => max_tokens,
@@ -415,14 +1612,26 @@ pub fn Settings(active_config: RwSignal<Config>, menu: RwSignal<Menu>) -> impl I
                 return;
             }
         };
-        config.update(|config| config.max_tokens = max_tokens);
-        let config = config.get_untracked();
-        active_config.set(config.clone());
+        if let ResponseFormat::JsonSchema(schema) = config.get_untracked().response_format {
+            if let Err(error) = serde_json::from_str::<serde_json::Value>(&schema) {
+                set_error(format!("Invalid JSON schema: {error}"));
+                return;
+            }
+        }
+        let mut applied_config = config.get_untracked();
+        applied_config.max_tokens = max_tokens;
         spawn_local(async move {
-            if let Err(error) = save_config(config.clone()).await {
+            // reject limits the provider would otherwise fail the request over
+            if let Err(error) = validate_request(applied_config.clone()).await {
+                set_error(error.to_string());
+                return;
+            }
+            config.update(|config| config.max_tokens = max_tokens);
+            active_config.set(applied_config.clone());
+            if let Err(error) = save_config(applied_config.clone()).await {
                 set_error(error.to_string());
             } else {
-                saved_config.set(Some(config));
+                saved_config.set(Some(applied_config));
                 set_error("".into());
             }
         });
@@ -435,12 +1644,48 @@ pub fn Settings(active_config: RwSignal<Config>, menu: RwSignal<Menu>) -> impl I
             <button class=button() + "mr-auto" on:click=move |_| menu.set(Menu::Menu)>"Back"</button>
             <h1 class="text-[1.25em]">"Settings"</h1>
             <div class="w-full mt-2"><ErrorMessage error /></div>
-            <div class="grid grid-cols-[repeat(2,max-content)] gap-[6vh] items-center my-auto overflow-y-auto">
-                <SystemPromptInput config menu />
-                <TemperatureSlider config />
-                <MaxTokensInput max_tokens />
-                <ModelInput config />
-                <KeyList config />
+            <input type="text" placeholder="Filter settings" on:input=move |event| filter.set(event_target_value(&event))
+                class=input() + "w-full mt-2 px-2" />
+            <div class="flex flex-col gap-[6vh] w-full my-auto py-[3vh] overflow-y-auto">
+                <SettingsSection title="Generation" filter names=&["System prompt", "Temperature", "Deterministic",
+                        "Max tokens", "Candidates", "Response format", "Reasoning", "Model"]>
+                    <Setting name="System prompt" filter><SystemPromptInput config menu /></Setting>
+                    <Setting name="Temperature" filter><TemperatureSlider config /></Setting>
+                    <Setting name="Deterministic" filter><DeterministicInput config /></Setting>
+                    <Setting name="Max tokens" filter><MaxTokensInput max_tokens /></Setting>
+                    <Setting name="Candidates" filter><CandidatesInput config /></Setting>
+                    <Setting name="Response format" filter><ResponseFormatInput config /></Setting>
+                    <Setting name="Reasoning" filter><ReasoningInput config /></Setting>
+                    <Setting name="Model" filter><ModelInput config /></Setting>
+                </SettingsSection>
+                <SettingsSection title="Requests" filter names=&["Requests", "Streaming", "Response cleanup", "Gemini tools",
+                        "Provider defaults", "Send key", "Retries", "Long conversations", "Capture"]>
+                    <Setting name="Requests" filter><RequestChecksInput config /></Setting>
+                    <Setting name="Send key" filter><SendKeyInput config /></Setting>
+                    <Setting name="Retries" filter><RetryInput config /></Setting>
+                    <Setting name="Streaming" filter><StreamingInput config /></Setting>
+                    <Setting name="Response cleanup" filter><OutputCleanupInput config /></Setting>
+                    <Setting name="Gemini tools" filter><GoogleToolsInput config /></Setting>
+                    <Setting name="Provider defaults" filter><ProviderDefaultsInput config /></Setting>
+                    <Setting name="Long conversations" filter><LongConversationsInput config /></Setting>
+                    <Setting name="Capture" filter><CaptureInput config /></Setting>
+                </SettingsSection>
+                <SettingsSection title="Conversations" filter names=&["Message counters", "Scheduled export",
+                        "Archive", "Trash", "Snippets"]>
+                    <Setting name="Message counters" filter><MessageCounterInput config /></Setting>
+                    <Setting name="Scheduled export" filter><ExportScheduleInput config /></Setting>
+                    <Setting name="Archive" filter><ArchiveInput /></Setting>
+                    <Setting name="Trash" filter><TrashInput config /></Setting>
+                    <Setting name="Snippets" filter><Snippets config /></Setting>
+                </SettingsSection>
+                <SettingsSection title="Keys" filter names=&["API keys", "Key routing", "Encryption"]>
+                    <Setting name="API keys" filter><KeyList config /></Setting>
+                    <Setting name="Key routing" filter><KeyRoutes config /></Setting>
+                    <Setting name="Encryption" filter><EncryptionInput /></Setting>
+                </SettingsSection>
+                <SettingsSection title="Sync" filter names=&["Settings file"]>
+                    <Setting name="Settings file" filter><SettingsFileInput config max_tokens /></Setting>
+                </SettingsSection>
             </div>
             <div class="flex justify-end mb-[4vh] md:mb-[8vh] w-full">
                 <button class=button() + "mr-4" on:click=on_discard
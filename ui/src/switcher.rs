@@ -0,0 +1,139 @@
+use common::Conversation;
+use leptos::*;
+use crate::commands::load_conversations;
+use crate::util::{input, set_conversation_uuid, ErrorMessage, Menu};
+
+lazy_static::lazy_static! {
+    // anyhow! macro doesn't work if there is a static variable named "error" in the namespace
+    pub static ref signal_pair: (ReadSignal<String>, WriteSignal<String>) = create_signal("".into());
+    pub static ref set_error: WriteSignal<String> = signal_pair.1;
+}
+
+const MAX_RESULTS: usize = 10;
+
+// the query's characters must appear in order in the title, the score is the total gap between them
+// so tighter matches rank first, None if it doesn't match
+fn fuzzy_score(title: &str, query: &str) -> Option<usize> {
+    let title = title.to_lowercase();
+    let mut title_chars = title.chars().enumerate();
+    let mut score = 0;
+    let mut last_index = None;
+    for query_char in query.to_lowercase().chars().filter(|char| !char.is_whitespace()) {
+        let (index, _) = title_chars.find(|&(_, title_char)| title_char == query_char)?;
+        score += last_index.map(|last_index| index - last_index - 1).unwrap_or(index);
+        last_index = Some(index);
+    }
+
+    return Some(score);
+}
+
+// the most recent conversations ranked by how well their titles match the query
+fn search(conversations: &[Conversation], query: &str) -> Vec<Conversation> {
+    let mut matches = conversations.iter()
+        .filter_map(|conversation| Some((fuzzy_score(&conversation.title, query)?, conversation)))
+        .collect::<Vec<_>>();
+    // stable so equally good matches stay in order of recency
+    matches.sort_by_key(|(score, _)| *score);
+    matches.into_iter().take(MAX_RESULTS).map(|(_, conversation)| conversation.clone()).collect()
+}
+
+// ctrl+p opens a list of recent conversations searchable by title, switching to the chosen one
+#[component]
+pub fn QuickSwitcher(continue_from: RwSignal<Option<uuid::Uuid>>, menu: RwSignal<Menu>) -> impl IntoView {
+    let error = signal_pair.0;
+    let open = create_rw_signal(false);
+    let conversations = create_rw_signal(Vec::<Conversation>::new());
+    let query = create_rw_signal("".to_string());
+    let selected = create_rw_signal(0);
+
+    let results = move || conversations.with(|conversations| query.with(|query| search(conversations, query)));
+
+    let query_input = view! {
+        <input type="text" placeholder="Search conversations" class=input() + "w-full px-2"
+            on:input=move |event| {
+                query.set(event_target_value(&event));
+                selected.set(0);
+            } />
+    };
+
+    let on_open = {
+        let query_input = query_input.clone();
+        move || {
+            query.set("".into());
+            // this is different from setting the input's value html attribute, which will not work
+            query_input.set_value("");
+            selected.set(0);
+            set_error("".into());
+            open.set(true);
+            let _ = query_input.focus();
+
+            // the history is loaded on every open so it reflects other windows' changes
+            spawn_local(async move {
+                match load_conversations().await {
+                    Ok(loaded_conversations) => conversations.set(loaded_conversations),
+                    Err(error) => set_error(error.to_string())
+                }
+            });
+        }
+    };
+
+    let on_switch = move |uuid: uuid::Uuid| {
+        open.set(false);
+        continue_from.set(None);
+        set_conversation_uuid(Some(uuid));
+        menu.set(Menu::Chat);
+    };
+
+    let toggle_handle = window_event_listener(ev::keydown, move |event| {
+        if (event.ctrl_key() || event.meta_key()) && event.key().eq_ignore_ascii_case("p") {
+            // otherwise the print dialog opens
+            event.prevent_default();
+            if open.get_untracked() {
+                open.set(false);
+            } else {
+                on_open();
+            }
+        }
+    });
+    on_cleanup(move || toggle_handle.remove());
+
+    let on_keydown = move |event: ev::KeyboardEvent| match event.key().as_str() {
+        "ArrowDown" => {
+            event.prevent_default();
+            let result_count = results().len();
+            selected.update(|selected| *selected = (*selected + 1).min(result_count.saturating_sub(1)));
+        },
+        "ArrowUp" => {
+            event.prevent_default();
+            selected.update(|selected| *selected = selected.saturating_sub(1));
+        },
+        "Enter" => if let Some(conversation) = results().get(selected.get_untracked()) {
+            on_switch(conversation.uuid);
+        },
+        "Escape" => open.set(false),
+        _ => ()
+    };
+
+    view! {
+        <div class="fixed inset-0 z-10 flex justify-center items-start pt-[15vh] bg-black/50"
+                style:display=move || (!open()).then(|| "None")
+                on:click=move |_| open.set(false)>
+            <div class="flex flex-col w-[90vw] md:w-[40vw] p-4 bg-[#181a1b] border border-[#33333A]"
+                    on:click=|event| event.stop_propagation() on:keydown=on_keydown>
+                <ErrorMessage error />
+                {query_input}
+                <p class="mt-4 text-[0.9em]" style:display=move || (!results().is_empty()).then(|| "None")
+                >"No matching conversations."</p>
+                <div class="flex flex-col mt-2 text-[0.925em]">
+                    {move || results().into_iter().enumerate().map(|(index, conversation)| view! {
+                        <a class="truncate px-2 py-1 cursor-pointer text-blue-600"
+                            class=("bg-[#2A2A2A]", move || selected() == index)
+                            on:mouseenter=move |_| selected.set(index)
+                            on:click=move |_| on_switch(conversation.uuid)
+                        >{conversation.display_title()}</a>
+                    }).collect_view()}
+                </div>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,36 @@
+use leptos::*;
+use crate::commands::unlock;
+use crate::util::{button, input, ErrorMessage};
+
+lazy_static::lazy_static! {
+    // anyhow! macro doesn't work if there is a static variable named "error" in the namespace
+    pub static ref signal_pair: (ReadSignal<String>, WriteSignal<String>) = create_signal("".into());
+    pub static ref set_error: WriteSignal<String> = signal_pair.1;
+}
+
+// shown in place of the app at startup while the encrypted database is locked
+#[component]
+pub fn Unlock(locked: RwSignal<Option<bool>>) -> impl IntoView {
+    let error = signal_pair.0;
+    let passphrase = create_rw_signal(String::new());
+
+    let on_unlock = move || spawn_local(async move {
+        match unlock(passphrase.get_untracked()).await {
+            Ok(()) => locked.set(Some(false)),
+            Err(error) => set_error(error.to_string())
+        }
+    });
+
+    view! {
+        <div class="flex flex-col items-center justify-center h-full">
+            <h1 class="mb-8 text-[1.25em]">"Unlock"</h1>
+            <ErrorMessage error />
+            <input type="password" placeholder="Passphrase" class=input() + "mb-4 px-1 w-[50vw] md:w-[25vw]"
+                on:input=move |event| passphrase.set(event_target_value(&event))
+                on:keydown=move |event| if event.key() == "Enter" {
+                    on_unlock();
+                } />
+            <button class=button() + "w-[max-content]" on:click=move |_| on_unlock()>"Unlock"</button>
+        </div>
+    }
+}
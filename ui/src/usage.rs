@@ -0,0 +1,75 @@
+use common::{UsagePeriod, UsageTotal};
+use leptos::*;
+use crate::commands::load_usage;
+use crate::util::{button, ErrorMessage, Menu};
+
+lazy_static::lazy_static! {
+    // anyhow! macro doesn't work if there is a static variable named "error" in the namespace
+    pub static ref signal_pair: (ReadSignal<String>, WriteSignal<String>) = create_signal("".into());
+    pub static ref set_error: WriteSignal<String> = signal_pair.1;
+}
+
+// tokens and spend per model, totaled by day, week, or month
+#[component]
+pub fn Usage(menu: RwSignal<Menu>) -> impl IntoView {
+    let error = signal_pair.0;
+    let period = create_rw_signal(UsagePeriod::default());
+    let usage_totals = create_rw_signal(Vec::<UsageTotal>::new());
+
+    // reload whenever the page is opened or the period changes
+    create_effect(move |_| {
+        if menu() != Menu::Usage {
+            return;
+        }
+
+        let period = period();
+        spawn_local(async move {
+            match load_usage(period).await {
+                Ok(loaded_usage_totals) => usage_totals.set(loaded_usage_totals),
+                Err(error) => set_error(error.to_string())
+            }
+        });
+    });
+
+    let period_button = move |button_period: UsagePeriod| view! {
+        <button class=move || button() + if period() == button_period { "bg-[#33333A]" } else { "" }
+            on:click=move |_| period.set(button_period)
+        >{button_period.to_string()}</button>
+    };
+
+    view! {
+        <div class="relative flex flex-col items-center mx-auto md:w-[max-content] md:min-w-[60vw]
+                    h-full px-[5vw] py-[5vh] overflow-y-hidden"
+                style:display=move || (menu.get() != Menu::Usage).then(|| "None")>
+            <div class="flex w-full gap-2">
+                <button class=button() + "mr-auto" on:click=move |_| menu.set(Menu::Menu)>"Back"</button>
+                {period_button(UsagePeriod::Day)}
+                {period_button(UsagePeriod::Week)}
+                {period_button(UsagePeriod::Month)}
+            </div>
+            <h1 class="text-[1.25em]">"Usage"</h1>
+            <div class="w-full mt-2"><ErrorMessage error /></div>
+            <p class="w-full mt-[10vh] mr-auto"
+                style:display=move || (!usage_totals().is_empty()).then(|| "None")
+            >"No usage recorded."</p>
+            <div class="grid grid-cols-[repeat(6,max-content)] gap-x-[4vw] gap-y-[3vh] my-[10vh] w-full
+                    overflow-y-auto justify-center items-start text-[0.925em]"
+                    style:display=move || usage_totals().is_empty().then(|| "None")>
+                <p class="text-[#777788]">"Period"</p>
+                <p class="text-[#777788]">"Model"</p>
+                <p class="text-[#777788]">"Requests"</p>
+                <p class="text-[#777788]">"Prompt tokens"</p>
+                <p class="text-[#777788]">"Completion tokens"</p>
+                <p class="text-[#777788]">"Cost"</p>
+                {move || usage_totals().into_iter().map(|usage_total| view! {
+                    <p>{usage_total.period}</p>
+                    <p title=usage_total.provider>{usage_total.model}</p>
+                    <p>{usage_total.requests}</p>
+                    <p>{usage_total.prompt_tokens}</p>
+                    <p>{usage_total.completion_tokens}</p>
+                    <p>{format!("${:.4}", usage_total.cost)}</p>
+                }).collect_view()}
+            </div>
+        </div>
+    }
+}
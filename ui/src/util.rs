@@ -6,7 +6,11 @@ pub enum Menu {
     Chat,
     Menu,
     History,
-    Settings
+    Settings,
+    EditLog,
+    Diagnostics,
+    Logs,
+    Usage
 }
 
 #[component]
@@ -18,10 +22,46 @@ pub fn ErrorMessage(error: ReadSignal<String>) -> impl IntoView {
     }
 }
 
+// a link copying text to the clipboard, confirming briefly once it's copied
+#[component]
+pub fn CopyLink(text: Signal<String>) -> impl IntoView {
+    let status = create_rw_signal(None::<&'static str>);
+
+    let on_copy = move |_| spawn_local(async move {
+        let copied = crate::commands::copy_to_clipboard(text.get_untracked()).await;
+        status.set(Some(if copied.is_ok() { "copied" } else { "copy failed" }));
+        sleep(std::time::Duration::from_secs(2)).await;
+        status.set(None);
+    });
+
+    view! {
+        <a class="cursor-pointer hover:text-[#AAAABB]" on:click=on_copy>{move || status().unwrap_or("copy")}</a>
+    }
+}
+
+pub async fn sleep(duration: std::time::Duration) {
+    let (send, recv) = tokio::sync::oneshot::channel();
+
+    set_timeout(move || {
+        let _ = send.send(());
+    }, duration);
+
+    recv.await.unwrap_or_else(|error| leptos_dom::log!("Unable to sleep: {error}"));
+}
+
+// small costs keep enough digits to not show as zero
+pub fn format_cost(cost: f64) -> String {
+    if cost < 0.01 { format!("${cost:.4}") } else { format!("${cost:.2}") }
+}
+
 pub fn button() -> String {
     " px-[9px] py-[3px] border border-[#33333A] bg-[#222222] hover:bg-[#2A2A2A] text-[#AAAABB] ".into()
 }
 
+pub fn input() -> String {
+    " bg-[#222222] h-[2em] border border-[#33333A] text-[0.9em] ".into()
+}
+
 #[wasm_bindgen]
 extern "C" {
     #[wasm_bindgen(catch, js_namespace = ["window", "__TAURI__", "event"])]
@@ -29,6 +69,46 @@ extern "C" {
         cmd: &str,
         cb: &Closure<dyn Fn(JsValue)>
     ) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(catch, js_namespace = ["window", "__TAURI__", "event"])]
+    pub async fn emit(cmd: &str, args: JsValue) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(js_namespace = ["window", "__TAURI__", "window"], js_name = getCurrent)]
+    fn current_window() -> JsValue;
+}
+
+fn change_origin(event: &JsValue) -> Option<common::ChangeOrigin> {
+    use gloo_utils::format::JsValueSerdeExt;
+    JsValue::into_serde::<serde_json::Value>(event)
+        .ok()
+        .and_then(|mut event| serde_json::from_value::<common::FileChange>(event["payload"].take()).ok())
+        .map(|change| change.origin)
+}
+
+// whether a file watcher event is for a write made by any window of this app
+pub fn is_app_write(event: &JsValue) -> bool {
+    matches!(change_origin(event), Some(common::ChangeOrigin::App { .. }))
+}
+
+fn current_window_label() -> Option<String> {
+    js_sys::Reflect::get(&current_window(), &"label".into()).ok().and_then(|label| label.as_string())
+}
+
+// whether a file watcher event is for a write made by a window other than this one
+pub fn is_other_window_write(event: &JsValue) -> bool {
+    match change_origin(event) {
+        Some(common::ChangeOrigin::App { window: Some(window) }) => Some(window) != current_window_label(),
+        _ => false
+    }
+}
+
+// whether a file watcher event is for a write made by this window, which already has it.
+// other windows' writes still have to be loaded
+pub fn is_own_write(event: &JsValue) -> bool {
+    match change_origin(event) {
+        Some(common::ChangeOrigin::App { window: Some(window) }) => Some(window) == current_window_label(),
+        _ => false
+    }
 }
 
 lazy_static::lazy_static! {